@@ -0,0 +1,86 @@
+//! Criterion benchmarks of key generation, the matchers and whole searches.
+//!
+//! Run them with `cargo bench`, or a group of them with ex: `cargo bench -- matchers`.
+
+use btc_vanity::keys_and_address::{IncrementalKeys, KeysAndAddress, RngKeySource};
+use btc_vanity::vanity_addr_generator::{SearchEngines, VanityAddrBuilder, VanityMode};
+
+use bitcoin::secp256k1::rand;
+use bitcoin::secp256k1::Secp256k1;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::hint::black_box;
+
+/// A 34 char address, the usual length of a P2PKH address.
+const ADDRESS: &str = "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2";
+
+fn keygen(c: &mut Criterion) {
+    let secp256k1 = Secp256k1::new();
+    let mut group = c.benchmark_group("keygen");
+
+    group.bench_function("generate_random", |b| {
+        b.iter(|| KeysAndAddress::generate_random(&secp256k1))
+    });
+
+    let mut keys = IncrementalKeys::new(&secp256k1, RngKeySource(rand::thread_rng()));
+    let mut candidate = None;
+    group.bench_function("incremental", |b| {
+        b.iter(|| {
+            keys.next_keys_into(&secp256k1, &mut candidate);
+        })
+    });
+
+    group.finish();
+}
+
+fn matchers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("matchers");
+
+    for vanity_mode in [VanityMode::Prefix, VanityMode::Suffix, VanityMode::Anywhere] {
+        for case_sensitive in [true, false] {
+            let name = format!(
+                "{:?}/{}",
+                vanity_mode,
+                match case_sensitive {
+                    true => "case_sensitive",
+                    false => "case_insensitive",
+                }
+            );
+            // Never matches, so anywhere mode checks every position of the address.
+            for len in 1..=8 {
+                let string = "z".repeat(len);
+                group.bench_with_input(BenchmarkId::new(&name, len), &string, |b, string| {
+                    b.iter(|| {
+                        SearchEngines::is_match(
+                            black_box(ADDRESS),
+                            black_box(string),
+                            case_sensitive,
+                            vanity_mode,
+                        )
+                    })
+                });
+            }
+        }
+    }
+
+    group.finish();
+}
+
+fn search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search");
+    // Every sample is a whole search of a few thousand candidates.
+    group.sample_size(20);
+
+    group.bench_function("prefix_2_chars", |b| {
+        b.iter(|| {
+            VanityAddrBuilder::new("ab")
+                .case_insensitive()
+                .generate()
+                .unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, keygen, matchers, search);
+criterion_main!(benches);
@@ -0,0 +1,103 @@
+//! Runs the vanity search in the browser or Node.js.
+//!
+//! wasm32-unknown-unknown has no threads, so the search goes through
+//! `VanityAddr::generate_blocking_single`. Build it and generate the JavaScript bindings with:
+//!
+//! ```bash
+//! cargo build --release --target wasm32-unknown-unknown --no-default-features --example wasm
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/examples/wasm.wasm
+//! ```
+//!
+//! Then from JavaScript:
+//!
+//! ```js
+//! import init, { estimate, generate } from "./pkg/wasm.js";
+//!
+//! await init();
+//! console.log(estimate("Emiv", "prefix", false));
+//! const wallet = generate("Emiv", "prefix", false, 10_000_000);
+//! console.log(wallet.address, wallet.wif);
+//! ```
+#![cfg(target_arch = "wasm32")]
+
+use btc_vanity::chain::BITCOIN;
+use btc_vanity::estimate::estimate_attempts;
+use btc_vanity::vanity_addr_generator::{VanityAddr, VanityMode};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct Wallet {
+    address: String,
+    public_key: String,
+    wif: String,
+}
+
+#[wasm_bindgen]
+impl Wallet {
+    #[wasm_bindgen(getter)]
+    pub fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn public_key(&self) -> String {
+        self.public_key.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn wif(&self) -> String {
+        self.wif.clone()
+    }
+}
+
+fn vanity_mode(mode: &str) -> Result<VanityMode, JsError> {
+    match mode {
+        "prefix" => Ok(VanityMode::Prefix),
+        "suffix" => Ok(VanityMode::Suffix),
+        "anywhere" => Ok(VanityMode::Anywhere),
+        _ => Err(JsError::new(&format!(
+            "unknown vanity mode {mode}, expected prefix, suffix or anywhere"
+        ))),
+    }
+}
+
+/// Returns the expected number of attempts to find the pattern, or an error if it can't be
+/// searched.
+#[wasm_bindgen]
+pub fn estimate(pattern: &str, mode: &str, case_sensitive: bool) -> Result<f64, JsError> {
+    let vanity_mode = vanity_mode(mode)?;
+    let resolved = VanityAddr::plan(
+        pattern,
+        vanity_mode,
+        case_sensitive,
+        BITCOIN.max_pattern_len(false),
+        false,
+    )?;
+    Ok(estimate_attempts(
+        &resolved.pattern,
+        vanity_mode,
+        case_sensitive,
+    ))
+}
+
+/// Searches for a matching address on the calling thread, it gives up after `max_attempts`.
+#[wasm_bindgen]
+pub fn generate(
+    pattern: &str,
+    mode: &str,
+    case_sensitive: bool,
+    max_attempts: u64,
+) -> Result<Wallet, JsError> {
+    let keys_and_address = VanityAddr::generate_blocking_single(
+        pattern,
+        case_sensitive,
+        false,
+        vanity_mode(mode)?,
+        Some(max_attempts),
+    )?;
+    Ok(Wallet {
+        address: keys_and_address.get_comp_address().clone(),
+        public_key: keys_and_address.get_comp_public_key(),
+        wif: keys_and_address.get_wif_private_key(),
+    })
+}
@@ -0,0 +1,10 @@
+//! # btc-vanity FFI
+//!
+//! Builds the C FFI of btc-vanity, see `btc_vanity::ffi`, as a shared library. It is a crate of
+//! its own, so only this crate is built as a `cdylib` and the btc-vanity builds stay rlib only.
+//!
+//! ```txt
+//! $ cargo build --release -p btc-vanity-ffi
+//! ```
+
+pub use btc_vanity::ffi::*;
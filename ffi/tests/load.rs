@@ -0,0 +1,115 @@
+//! Loads the built shared library and calls it through its C symbols, the same way a C program
+//! using `include/btc_vanity.h` does.
+
+use std::ffi::{c_char, c_int, c_void, CStr};
+use std::process::Command;
+use std::ptr;
+use std::sync::Once;
+
+const BTC_VANITY_OK: c_int = 0;
+const BTC_VANITY_INVALID_PATTERN: c_int = 3;
+const BTC_VANITY_MODE_SUFFIX: c_int = 1;
+
+#[repr(C)]
+struct BtcVanityResult {
+    address: *mut c_char,
+    public_key: *mut c_char,
+    private_key_wif: *mut c_char,
+}
+
+type Generate = unsafe extern "C" fn(
+    *const c_char,
+    *const c_char,
+    c_int,
+    bool,
+    u64,
+    *const c_void,
+    *mut BtcVanityResult,
+) -> c_int;
+type FreeResult = unsafe extern "C" fn(*mut BtcVanityResult);
+
+/// Builds the library and loads it from the target directory of the test binary.
+/// Cargo doesn't build a cdylib for the integration tests, so it is built here.
+fn load() -> libloading::Library {
+    static BUILD: Once = Once::new();
+    BUILD.call_once(|| {
+        let mut cargo = Command::new(env!("CARGO"));
+        cargo
+            .args(["build", "--lib", "--manifest-path"])
+            .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"));
+        if !cfg!(debug_assertions) {
+            cargo.arg("--release");
+        }
+        assert!(cargo.status().unwrap().success());
+    });
+
+    let test_binary = std::env::current_exe().unwrap();
+    let target_dir = test_binary.parent().unwrap().parent().unwrap();
+    let path = target_dir.join(libloading::library_filename("btc_vanity_ffi"));
+    unsafe { libloading::Library::new(&path) }
+        .unwrap_or_else(|err| panic!("couldn't load {}: {}", path.display(), err))
+}
+
+#[test]
+fn test_load_and_generate() {
+    let library = load();
+    let generate = unsafe { library.get::<Generate>(b"btc_vanity_generate\0") }.unwrap();
+    let free_result = unsafe { library.get::<FreeResult>(b"btc_vanity_free_result\0") }.unwrap();
+
+    let mut result = BtcVanityResult {
+        address: ptr::null_mut(),
+        public_key: ptr::null_mut(),
+        private_key_wif: ptr::null_mut(),
+    };
+    let status = unsafe {
+        generate(
+            c"bitcoin".as_ptr(),
+            c"e".as_ptr(),
+            BTC_VANITY_MODE_SUFFIX,
+            false,
+            2,
+            ptr::null(),
+            &mut result,
+        )
+    };
+    assert_eq!(status, BTC_VANITY_OK);
+    let address = unsafe { CStr::from_ptr(result.address) }.to_str().unwrap();
+    assert!(address.to_lowercase().ends_with('e'));
+
+    unsafe { free_result(&mut result) };
+    assert!(result.address.is_null());
+
+    let status = unsafe {
+        generate(
+            ptr::null(),
+            c"e0".as_ptr(),
+            BTC_VANITY_MODE_SUFFIX,
+            false,
+            1,
+            ptr::null(),
+            &mut result,
+        )
+    };
+    assert_eq!(status, BTC_VANITY_INVALID_PATTERN);
+}
+
+#[test]
+fn test_load_cancel_handle() {
+    let library = load();
+    let cancel_new =
+        unsafe { library.get::<extern "C" fn() -> *mut c_void>(b"btc_vanity_cancel_new\0") }
+            .unwrap();
+    let cancel =
+        unsafe { library.get::<unsafe extern "C" fn(*const c_void)>(b"btc_vanity_cancel\0") }
+            .unwrap();
+    let cancel_free =
+        unsafe { library.get::<unsafe extern "C" fn(*mut c_void)>(b"btc_vanity_cancel_free\0") }
+            .unwrap();
+
+    let handle = cancel_new();
+    assert!(!handle.is_null());
+    unsafe {
+        cancel(handle);
+        cancel_free(handle);
+    }
+}
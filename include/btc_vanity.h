@@ -0,0 +1,129 @@
+#ifndef BTC_VANITY_H
+#define BTC_VANITY_H
+
+/* Generated with cbindgen from src/ffi.rs, don't edit it by hand. */
+
+#include <stdbool.h>
+#include <stdint.h>
+
+/**
+ * A vanity address is found and written to the result.
+ */
+#define BTC_VANITY_OK 0
+
+/**
+ * A required pointer argument is null.
+ */
+#define BTC_VANITY_NULL_ARGUMENT 1
+
+/**
+ * The chain isn't supported.
+ */
+#define BTC_VANITY_UNSUPPORTED_CHAIN 2
+
+/**
+ * The pattern is empty, isn't valid UTF-8, has a non base58 character or is too long.
+ */
+#define BTC_VANITY_INVALID_PATTERN 3
+
+/**
+ * The mode isn't one of the BTC_VANITY_MODE constants.
+ */
+#define BTC_VANITY_INVALID_MODE 4
+
+/**
+ * Threads is 0.
+ */
+#define BTC_VANITY_INVALID_THREADS 5
+
+/**
+ * The search is cancelled with the cancellation handle.
+ */
+#define BTC_VANITY_CANCELLED 6
+
+/**
+ * The search failed for another reason.
+ */
+#define BTC_VANITY_ERROR 7
+
+/**
+ * The library panicked. The result isn't written.
+ */
+#define BTC_VANITY_PANIC 8
+
+#define BTC_VANITY_MODE_PREFIX 0
+
+#define BTC_VANITY_MODE_SUFFIX 1
+
+#define BTC_VANITY_MODE_ANYWHERE 2
+
+/**
+ * Cancels a running `btc_vanity_generate` from another thread.
+ */
+typedef struct BtcVanityCancel BtcVanityCancel;
+
+/**
+ * A found vanity address. The strings are null terminated and owned by the library,
+ * so they must be released with `btc_vanity_free_result`.
+ */
+typedef struct BtcVanityResult {
+  char *address;
+  char *public_key;
+  char *private_key_wif;
+} BtcVanityResult;
+
+/**
+ * Searches for a vanity address with the pattern and writes it to `out_result`.
+ * Fast mode is disabled, so the caller is responsible for the length of the pattern.
+ * `chain` can be null for bitcoin. `cancel` can be null if the search won't be cancelled.
+ * Returns BTC_VANITY_OK or one of the error status codes, BTC_VANITY_PANIC if the library panics.
+ * `out_result` is only written on success.
+ *
+ * # Safety
+ *
+ * `chain` and `pattern` must be null or valid null terminated strings, `cancel` must be null or
+ * a handle from `btc_vanity_cancel_new` and `out_result` must be null or valid for writes.
+ */
+int btc_vanity_generate(const char *chain,
+                        const char *pattern,
+                        int mode,
+                        bool case_sensitive,
+                        uint64_t threads,
+                        const struct BtcVanityCancel *cancel,
+                        struct BtcVanityResult *out_result);
+
+/**
+ * Releases the strings of a result written by `btc_vanity_generate` and sets them to null,
+ * so freeing the same result twice does nothing.
+ *
+ * # Safety
+ *
+ * `result` must be null or point to a result written by `btc_vanity_generate`.
+ */
+void btc_vanity_free_result(struct BtcVanityResult *result);
+
+/**
+ * Returns a new cancellation handle, which must be released with `btc_vanity_cancel_free`.
+ * Returns null if the library panics.
+ */
+struct BtcVanityCancel *btc_vanity_cancel_new(void);
+
+/**
+ * Cancels the searches using the handle. It can be called from any thread.
+ *
+ * # Safety
+ *
+ * `cancel` must be null or a handle from `btc_vanity_cancel_new` which isn't freed yet.
+ */
+void btc_vanity_cancel(const struct BtcVanityCancel *cancel);
+
+/**
+ * Releases the cancellation handle after the searches using it are finished.
+ *
+ * # Safety
+ *
+ * `cancel` must be null or a handle from `btc_vanity_cancel_new` which isn't freed yet.
+ */
+void btc_vanity_cancel_free(struct BtcVanityCancel *cancel);
+
+#endif /* BTC_VANITY_H */
@@ -0,0 +1,261 @@
+//! # Benchmark Module
+//!
+//! This module is used to measure how fast this machine generates key pairs and checks
+//! them against a vanity pattern, with a growing number of threads.
+//!
+//! # Example Usage
+//!
+//! ```rust
+//! use btc_vanity::bench::{run_benchmark, BenchmarkConfig};
+//! use std::time::Duration;
+//!
+//! let report = run_benchmark(&BenchmarkConfig {
+//!     threads: 2,
+//!     duration: Duration::from_millis(100),
+//!     ..BenchmarkConfig::default()
+//! });
+//!
+//! for run in report.runs {
+//!     println!("{} threads: {:.0} keys/sec", run.threads, run.keygen_keys_per_second);
+//! }
+//! ```
+
+use crate::keys_and_address::{IncrementalKeys, KeysAndAddress, RngKeySource};
+use crate::vanity_addr_generator::{Backend, SearchEngines, VanityAddrBuilder, VanityMode};
+
+use bitcoin::secp256k1::rand::{self, rngs::ThreadRng};
+use bitcoin::secp256k1::{All, Secp256k1};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Settings of a benchmark run.
+pub struct BenchmarkConfig {
+    /// The most threads to measure with. Runs are made with 1, 2, 4, ... threads up to this number.
+    pub threads: u64,
+    /// How long each measurement takes.
+    pub duration: Duration,
+    /// The pattern used to measure match throughput. It is never expected to match.
+    pub pattern: String,
+    /// The vanity mode used to measure match throughput.
+    pub vanity_mode: VanityMode,
+    /// The case sensitivity used to measure match throughput.
+    pub case_sensitive: bool,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        BenchmarkConfig {
+            threads: 16,
+            duration: Duration::from_secs(3),
+            pattern: String::from("zzzz"),
+            vanity_mode: VanityMode::Prefix,
+            case_sensitive: false,
+        }
+    }
+}
+
+/// Measured throughputs for a thread count.
+pub struct BenchmarkRun {
+    pub threads: u64,
+    /// Key pairs and addresses generated per second, each with a new scalar multiplication.
+    pub keygen_keys_per_second: f64,
+    /// Key pairs and addresses generated per second by stepping from the previous key,
+    /// the way the search threads generate them.
+    pub incremental_keys_per_second: f64,
+    /// Key pairs generated and checked against the pattern per second.
+    pub match_keys_per_second: f64,
+}
+
+/// Results of all the runs of a benchmark.
+pub struct BenchmarkReport {
+    pub runs: Vec<BenchmarkRun>,
+}
+
+/// Returns the thread counts to measure with: powers of two below the given
+/// threads and the given threads itself.
+fn thread_counts(threads: u64) -> Vec<u64> {
+    let mut counts = (0..)
+        .map(|i| 1u64 << i)
+        .take_while(|&count| count < threads)
+        .collect::<Vec<_>>();
+    counts.push(threads.max(1));
+    counts
+}
+
+/// Runs `work` on the given number of threads for the given duration and returns how many
+/// times it ran per second across all the threads.
+pub fn measure_throughput<F>(threads: u64, duration: Duration, work: F) -> f64
+where
+    F: Fn(&Secp256k1<All>) + Send + Sync + 'static,
+{
+    measure_throughput_with(
+        threads,
+        duration,
+        |_| (),
+        move |secp256k1, _| work(secp256k1),
+    )
+}
+
+/// Same as `measure_throughput` but every thread creates its own state with `init` first
+/// and passes it to every run of `work`.
+pub fn measure_throughput_with<S, I, F>(threads: u64, duration: Duration, init: I, work: F) -> f64
+where
+    I: Fn(&Secp256k1<All>) -> S + Send + Sync + 'static,
+    F: Fn(&Secp256k1<All>, &mut S) + Send + Sync + 'static,
+{
+    let secp256k1 = Secp256k1::new();
+    let init = Arc::new(init);
+    let work = Arc::new(work);
+    let stop = Arc::new(AtomicBool::new(false));
+    let done = Arc::new(AtomicU64::new(0));
+    let start = Instant::now();
+
+    let handles = (0..threads)
+        .map(|_| {
+            let secp256k1 = secp256k1.clone();
+            let init = Arc::clone(&init);
+            let work = Arc::clone(&work);
+            let stop = Arc::clone(&stop);
+            let done = Arc::clone(&done);
+
+            thread::spawn(move || {
+                let mut state = init(&secp256k1);
+                while !stop.load(Ordering::Relaxed) {
+                    work(&secp256k1, &mut state);
+                    done.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    thread::sleep(duration);
+    stop.store(true, Ordering::Relaxed);
+    for handle in handles {
+        let _ = handle.join();
+    }
+
+    done.load(Ordering::Relaxed) as f64 / start.elapsed().as_secs_f64()
+}
+
+/// The incremental key generator of a benchmark thread and its reused candidate.
+type BenchKeys = (
+    IncrementalKeys<RngKeySource<ThreadRng>>,
+    Option<KeysAndAddress>,
+);
+
+/// Returns the incremental key generator of a benchmark thread.
+fn new_incremental_keys(secp256k1: &Secp256k1<All>) -> BenchKeys {
+    (
+        IncrementalKeys::new(secp256k1, RngKeySource(rand::thread_rng())),
+        None,
+    )
+}
+
+/// Measures how many key pairs and addresses the given threads generate per second
+/// the way the search threads generate them.
+pub fn measure_incremental_keys(threads: u64, duration: Duration) -> f64 {
+    measure_throughput_with(
+        threads,
+        duration,
+        new_incremental_keys,
+        |secp256k1, (keys, candidate)| {
+            let _ = keys.next_keys_into(secp256k1, candidate);
+        },
+    )
+}
+
+/// Measures how many candidates a whole search on the backend tries per second with the given
+/// threads, ex: to compare the rayon backend to the default one. Found addresses don't stop it.
+pub fn measure_search(backend: Backend, threads: u64, duration: Duration, pattern: &str) -> f64 {
+    let attempts = Arc::new(AtomicU64::new(0));
+    let start = Instant::now();
+    let _ = VanityAddrBuilder::new(pattern)
+        .threads(threads)
+        .backend(backend)
+        .case_insensitive()
+        .count(u64::MAX)
+        .timeout(duration)
+        .attempt_counter(Arc::clone(&attempts))
+        .generate_with(|_| ());
+
+    attempts.load(Ordering::Relaxed) as f64 / start.elapsed().as_secs_f64()
+}
+
+/// Measures key generation and match throughput with 1, 2, 4, ... up to `config.threads` threads.
+pub fn run_benchmark(config: &BenchmarkConfig) -> BenchmarkReport {
+    let runs = thread_counts(config.threads)
+        .into_iter()
+        .map(|threads| {
+            let keygen_keys_per_second =
+                measure_throughput(threads, config.duration, |secp256k1| {
+                    let _ = KeysAndAddress::generate_random(secp256k1);
+                });
+
+            let incremental_keys_per_second = measure_incremental_keys(threads, config.duration);
+
+            let pattern = config.pattern.clone();
+            let case_sensitive = config.case_sensitive;
+            let vanity_mode = config.vanity_mode;
+            let match_keys_per_second = measure_throughput_with(
+                threads,
+                config.duration,
+                new_incremental_keys,
+                move |secp256k1, (keys, candidate)| {
+                    let _ = SearchEngines::is_match(
+                        keys.next_keys_into(secp256k1, candidate).get_comp_address(),
+                        &pattern,
+                        case_sensitive,
+                        vanity_mode,
+                    );
+                },
+            );
+
+            BenchmarkRun {
+                threads,
+                keygen_keys_per_second,
+                incremental_keys_per_second,
+                match_keys_per_second,
+            }
+        })
+        .collect();
+
+    BenchmarkReport { runs }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_counts() {
+        assert_eq!(thread_counts(1), vec![1]);
+        assert_eq!(thread_counts(4), vec![1, 2, 4]);
+        assert_eq!(thread_counts(6), vec![1, 2, 4, 6]);
+    }
+
+    #[test]
+    fn test_run_benchmark() {
+        let report = run_benchmark(&BenchmarkConfig {
+            threads: 2,
+            duration: Duration::from_millis(100),
+            ..BenchmarkConfig::default()
+        });
+
+        assert_eq!(report.runs.len(), 2);
+        for run in report.runs {
+            assert!(run.keygen_keys_per_second > 0.0);
+            assert!(run.incremental_keys_per_second > 0.0);
+            assert!(run.match_keys_per_second > 0.0);
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_measure_search_backends() {
+        for backend in [Backend::Threads, Backend::Rayon] {
+            assert!(measure_search(backend, 2, Duration::from_millis(100), "zzzz") > 0.0);
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! # Calibration Cache Module
+//!
+//! This module keeps the measured speed of this machine on the disk, so --estimate and the
+//! difficulty warning don't measure it again on every run. The speeds are kept per chain and
+//! thread count, with the CPU they are measured on:
+//! ```txt
+//! bitcoin\t8\tAMD Ryzen 7 5800X x86_64 16 cores\t4123456.25\t1700000000
+//! ```
+//! The fields are separated with tabs, written as `\t` above. The last one is the unix time of
+//! the measurement.
+//! A speed is measured again if it is older than 30 days or the CPU is changed.
+//!
+//! The cache only saves time, so a missing or corrupted file is never an error: the lines which
+//! can't be read are dropped and the speed is measured again.
+
+use crate::error::BtcVanityError;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A measured speed is used for this long.
+pub const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Name of the cache file in the cache directory.
+const FILE_NAME: &str = "calibration.tsv";
+
+/// A measured speed of this machine.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Calibration {
+    pub chain: String,
+    pub threads: u64,
+    /// The CPU the speed is measured on, see `cpu_id`.
+    pub cpu: String,
+    pub keys_per_second: f64,
+    /// Unix time of the measurement in seconds.
+    pub measured_at: u64,
+}
+
+impl Calibration {
+    /// The speed is written with its full precision, so a cached speed is printed the same as
+    /// when it was measured.
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}",
+            self.chain, self.threads, self.cpu, self.keys_per_second, self.measured_at
+        )
+    }
+
+    /// Returns None if the line isn't a calibration.
+    fn from_line(line: &str) -> Option<Self> {
+        let fields = line.split('\t').collect::<Vec<_>>();
+        let [chain, threads, cpu, keys_per_second, measured_at] = fields[..] else {
+            return None;
+        };
+        let keys_per_second = keys_per_second.parse::<f64>().ok()?;
+        if chain.is_empty() || !keys_per_second.is_finite() || keys_per_second <= 0.0 {
+            return None;
+        }
+
+        Some(Calibration {
+            chain: chain.to_string(),
+            threads: threads.parse().ok()?,
+            cpu: cpu.to_string(),
+            keys_per_second,
+            measured_at: measured_at.parse().ok()?,
+        })
+    }
+}
+
+/// The measured speeds read from the cache file, written back with `save`.
+#[derive(Debug, Default)]
+pub struct CalibrationCache {
+    /// No file is read or written if None.
+    path: Option<PathBuf>,
+    calibrations: Vec<Calibration>,
+    changed: bool,
+}
+
+impl CalibrationCache {
+    /// Reads the cache file. A missing or unreadable file is an empty cache, and the lines which
+    /// can't be read are dropped.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let calibrations = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|data| data.lines().filter_map(Calibration::from_line).collect())
+            .unwrap_or_default();
+
+        CalibrationCache {
+            path,
+            calibrations,
+            changed: false,
+        }
+    }
+
+    /// Returns the cached speed of the chain with the given threads if it is measured on the
+    /// same CPU in the last 30 days.
+    pub fn get(&self, chain: &str, threads: u64, cpu: &str, now: SystemTime) -> Option<f64> {
+        let now = unix_secs(now);
+        self.calibrations
+            .iter()
+            .find(|calibration| calibration.chain == chain && calibration.threads == threads)
+            .filter(|calibration| {
+                calibration.cpu == cpu
+                    && now.saturating_sub(calibration.measured_at) <= MAX_AGE.as_secs()
+            })
+            .map(|calibration| calibration.keys_per_second)
+    }
+
+    /// Adds the calibration, replacing the one of the same chain and thread count.
+    pub fn insert(&mut self, calibration: Calibration) {
+        self.calibrations
+            .retain(|c| c.chain != calibration.chain || c.threads != calibration.threads);
+        self.calibrations.push(calibration);
+        self.changed = true;
+    }
+
+    /// Adds the speed of the chain with the given threads measured on this CPU now.
+    pub fn record(&mut self, chain: &str, threads: u64, keys_per_second: f64) {
+        if !keys_per_second.is_finite() || keys_per_second <= 0.0 {
+            return;
+        }
+        self.insert(Calibration {
+            chain: chain.to_string(),
+            threads,
+            cpu: cpu_id(),
+            keys_per_second,
+            measured_at: unix_secs(SystemTime::now()),
+        });
+    }
+
+    /// Returns the cached speed of the chain with the given threads, or measures and records it
+    /// if it isn't cached, is stale or `recalibrate` is true.
+    /// The returned bool is true if the speed is from the cache.
+    pub fn keys_per_second(
+        &mut self,
+        chain: &str,
+        threads: u64,
+        recalibrate: bool,
+        measure: impl FnOnce() -> f64,
+    ) -> (f64, bool) {
+        if !recalibrate {
+            if let Some(keys_per_second) = self.get(chain, threads, &cpu_id(), SystemTime::now()) {
+                return (keys_per_second, true);
+            }
+        }
+        let keys_per_second = measure();
+        self.record(chain, threads, keys_per_second);
+        (keys_per_second, false)
+    }
+
+    /// Writes the cache file if a calibration is added. The file is replaced at once, so an
+    /// interrupted write doesn't corrupt it.
+    pub fn save(&self) -> Result<(), BtcVanityError> {
+        let Some(path) = self.path.as_ref().filter(|_| self.changed) else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let data = self
+            .calibrations
+            .iter()
+            .map(|calibration| format!("{}\n", calibration.to_line()))
+            .collect::<String>();
+        let temp_path = path.with_extension("tsv.tmp");
+        fs::write(&temp_path, data)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+}
+
+/// Returns the path of the cache file: in BTC_VANITY_CACHE_DIR if it is set, otherwise in the
+/// btc-vanity directory of the user's cache directory.
+/// Returns None if there is no cache directory.
+pub fn calibration_cache_path() -> Option<PathBuf> {
+    let env_dir = |name| std::env::var_os(name).filter(|dir| !dir.is_empty());
+    if let Some(dir) = env_dir("BTC_VANITY_CACHE_DIR") {
+        return Some(PathBuf::from(dir).join(FILE_NAME));
+    }
+
+    let cache_dir = match cfg!(windows) {
+        true => env_dir("LOCALAPPDATA").map(PathBuf::from),
+        false => env_dir("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env_dir("HOME").map(|home| PathBuf::from(home).join(".cache"))),
+    };
+    cache_dir.map(|dir| dir.join("btc-vanity").join(FILE_NAME))
+}
+
+/// Returns an identifier of the CPU of this machine: its model name if it is known,
+/// the architecture and the number of cores.
+pub fn cpu_id() -> String {
+    let model = fs::read_to_string("/proc/cpuinfo")
+        .ok()
+        .and_then(|cpuinfo| {
+            cpuinfo.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                (key.trim() == "model name").then(|| value.trim().to_string())
+            })
+        });
+    let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+
+    format!(
+        "{} {} {} cores",
+        model.unwrap_or_else(|| String::from("unknown")),
+        std::env::consts::ARCH,
+        cores
+    )
+    .replace(['\t', '\n', '\r'], " ")
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CPU: &str = "Test CPU x86_64 8 cores";
+
+    fn calibration(threads: u64, measured_at: u64) -> Calibration {
+        Calibration {
+            chain: String::from("bitcoin"),
+            threads,
+            cpu: String::from(CPU),
+            keys_per_second: 1_000_000.0,
+            measured_at,
+        }
+    }
+
+    fn temp_cache_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!(
+                "btc-vanity-calibration-{}-{}",
+                name,
+                std::process::id()
+            ))
+            .join(FILE_NAME)
+    }
+
+    #[test]
+    fn test_calibration_cache_round_trip() {
+        let path = temp_cache_path("round-trip");
+        let mut cache = CalibrationCache::load(Some(path.clone()));
+        cache.insert(calibration(4, 1_700_000_000));
+        cache.insert(calibration(8, 1_700_000_000));
+        cache.insert(Calibration {
+            keys_per_second: 2_000_000.0,
+            ..calibration(8, 1_700_000_000)
+        });
+        cache.save().unwrap();
+
+        let cache = CalibrationCache::load(Some(path.clone()));
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(cache.calibrations.len(), 2);
+        assert_eq!(cache.get("bitcoin", 4, CPU, now), Some(1_000_000.0));
+        assert_eq!(cache.get("bitcoin", 8, CPU, now), Some(2_000_000.0));
+        assert_eq!(cache.get("bitcoin", 2, CPU, now), None);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_calibration_cache_stale_or_other_cpu() {
+        let mut cache = CalibrationCache::default();
+        cache.insert(calibration(4, 1_700_000_000));
+        let measured_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        assert!(cache
+            .get("bitcoin", 4, CPU, measured_at + MAX_AGE)
+            .is_some());
+        assert!(cache
+            .get(
+                "bitcoin",
+                4,
+                CPU,
+                measured_at + MAX_AGE + Duration::from_secs(1)
+            )
+            .is_none());
+        assert!(cache.get("bitcoin", 4, "Other CPU", measured_at).is_none());
+    }
+
+    #[test]
+    fn test_calibration_cache_skips_corrupted_lines() {
+        let path = temp_cache_path("corrupted");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            &path,
+            format!(
+                "{}\nnot a calibration\nbitcoin\tfour\tcpu\t1.0\t0\nbitcoin\t2\tcpu\tNaN\t0\n\u{0}\u{1}",
+                calibration(4, 1_700_000_000).to_line()
+            ),
+        )
+        .unwrap();
+
+        let cache = CalibrationCache::load(Some(path.clone()));
+        assert_eq!(cache.calibrations, vec![calibration(4, 1_700_000_000)]);
+        assert!(CalibrationCache::load(Some(path.with_extension("missing")))
+            .calibrations
+            .is_empty());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_calibration_cache_measures_once() {
+        let mut cache = CalibrationCache::default();
+        let mut measured = 0;
+
+        for _ in 0..2 {
+            cache.keys_per_second("bitcoin", 4, false, || {
+                measured += 1;
+                500.0
+            });
+        }
+        assert_eq!(measured, 1);
+        assert_eq!(
+            cache.keys_per_second("bitcoin", 4, false, || 0.0),
+            (500.0, true)
+        );
+        assert_eq!(
+            cache.keys_per_second("bitcoin", 4, true, || 600.0),
+            (600.0, false)
+        );
+        // A cache without a path is never written.
+        assert!(cache.save().is_ok());
+    }
+}
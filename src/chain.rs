@@ -0,0 +1,108 @@
+//! # Chain Information Module
+//!
+//! This module describes the chains btc-vanity can generate vanity addresses for,
+//! so both the cli and the library can check what is supported by this build.
+//!
+//! # Example Usage At Your Code
+//! ```rust
+//! use btc_vanity::chain::supported_chains;
+//!
+//! for chain in supported_chains() {
+//!     println!("{} (compiled in: {})", chain.name, chain.compiled_in);
+//! }
+//! ```
+
+use std::ops::RangeInclusive;
+
+/// Characters used in base58 encoded addresses.
+pub const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Capabilities and limits of a chain.
+pub struct ChainInfo {
+    pub name: &'static str,
+    /// Characters a string can contain.
+    pub alphabet: &'static str,
+    /// Longest string accepted while fast mode is enabled.
+    pub fast_mode_max_length: usize,
+    /// Longest string that can fit in an address.
+    pub max_length: usize,
+    /// Lengths an address of the chain can have, including the fixed prefix.
+    pub address_len: RangeInclusive<usize>,
+    /// Characters every address of the chain starts with, which are skipped by prefix matching.
+    pub fixed_prefix: Option<&'static str>,
+    pub supports_case_sensitive: bool,
+    /// Whether a string can match an address in any case, ex: 'emiv' matching "1EmIv...".
+    pub supports_case_insensitive: bool,
+    /// Case sensitivity of the strings searched without -c, --case-sensitive.
+    pub default_case_sensitive: bool,
+    /// Whether this build can generate addresses for the chain.
+    pub compiled_in: bool,
+}
+
+/// Bitcoin compressed P2PKH addresses. Strings are matched after the leading '1'.
+pub const BITCOIN: ChainInfo = ChainInfo {
+    name: "bitcoin",
+    alphabet: BASE58_ALPHABET,
+    fast_mode_max_length: 4,
+    max_length: 33,
+    address_len: 26..=34,
+    fixed_prefix: Some("1"),
+    supports_case_sensitive: true,
+    supports_case_insensitive: true,
+    default_case_sensitive: false,
+    compiled_in: true,
+};
+
+impl ChainInfo {
+    /// Returns the longest string accepted with or without fast mode.
+    pub fn max_pattern_len(&self, fast_mode: bool) -> usize {
+        match fast_mode {
+            true => self.fast_mode_max_length,
+            false => self.max_length,
+        }
+    }
+}
+
+/// Returns all the chains known by btc-vanity. New chains must be added here.
+pub fn supported_chains() -> &'static [ChainInfo] {
+    &[BITCOIN]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base58_alphabet() {
+        assert_eq!(BASE58_ALPHABET.len(), 58);
+        assert!(!BASE58_ALPHABET.contains(['0', 'I', 'O', 'l']));
+    }
+
+    #[test]
+    fn test_supported_chains_are_compiled_in() {
+        let chains = supported_chains();
+        assert_eq!(chains.len(), 1);
+        assert_eq!(chains[0].name, "bitcoin");
+        assert!(chains[0].compiled_in);
+    }
+
+    #[test]
+    fn test_bitcoin_case_sensitivity() {
+        // Base58 addresses are case significant, but any case matches unless -c is given.
+        const {
+            assert!(BITCOIN.supports_case_sensitive);
+            assert!(BITCOIN.supports_case_insensitive);
+            assert!(!BITCOIN.default_case_sensitive);
+        }
+    }
+
+    #[test]
+    fn test_max_pattern_len() {
+        assert_eq!(BITCOIN.max_pattern_len(true), 4);
+        assert_eq!(BITCOIN.max_pattern_len(false), 33);
+        assert_eq!(
+            BITCOIN.max_length,
+            BITCOIN.address_len.end() - BITCOIN.fixed_prefix.unwrap().len()
+        );
+    }
+}
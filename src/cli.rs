@@ -9,6 +9,12 @@
 //! A bitcoin vanity address generator written with the Rust programming language.
 //!
 //! Usage: btc-vanity [OPTIONS] [string]
+//!        btc-vanity [OPTIONS] [string] <COMMAND>
+//!
+//! Commands:
+//! verify          Checks that a private key derives the given address and the address has the pattern.
+//! decrypt-output  Decrypts an output file written with --encrypt-output and prints it.
+//! help            Print this message or the help of the given subcommand(s)
 //!
 //! Arguments:
 //! [string]  String used to match addresses.
@@ -16,18 +22,95 @@
 //! Options:
 //! -i, --input-file <input-file>    File with strings to match addresses with.
 //! Important: Write every string in a separate line.
+//! Use - to read the strings from stdin.
+//!     --input-format <input-format>
+//!                                  Format of the input file. toml reads every string from an [[entry]] table.
+//!                                  [default: toml for .toml files, lines for the rest] [possible values: lines, toml]
+//!     --ignore-unknown-flags       Warns about the unknown flags in the lines of the input file instead of exiting.
+//!     --watch                      Keeps running and searches the lines appended to the input file until Ctrl-C.
+//!                                  Only the lines format is supported.
 //! -f, --force-flags                Use this flag to override the flags in the input file
 //! or use in file to override cli flags for only that string.
 //! Note: Cli -f is stronger than input-file -f.
 //! -o, --output-file <output-file>  Crates a file that contains found wallet/s.
+//!     --max-output-size <max-output-size>
+//!                                  Rotates the output file before a write would make it larger than the given bytes.
+//!                                  The full file is renamed to <name>.1, then <name>.2, ...
+//!     --output-dir <output-dir>    Writes every found wallet to its own file in the given directory.
+//!     --name-template <name-template>
+//!                                  File name template used with --output-dir. Placeholders: {chain}, {pattern}, {n}
+//!                                  [default: {chain}-{pattern}-{n}.txt, or .json with --format json]
+//!     --checkpoint <checkpoint>    Records every completed string of the input file to the given file
+//!                                  and skips the strings already found in it on the next run.
+//!     --summary-file <summary-file>
+//!                                  Writes the summary of the input file run to the given file as JSON.
+//!     --no-dedup                   Searches every line of the input file for its own wallet/s. By default a line with
+//!                                  the same string, mode, case sensitivity and count as an earlier line gets its wallet/s.
+//!     --append                     Appends the found wallet/s to the output file. [default]
+//!     --overwrite                  Empties the output file before the first write of the run.
+//!     --format <format>            Output format of the found wallet/s. json writes one JSON object per line.
+//!                                  [default: text] [possible values: text, json]
+//!     --template <template>        Formats every found wallet as a single line, ex: "{address},{private_key}".
+//!                                  Placeholders: {address}, {private_key}, {private_key_hex}, {public_key}, {pattern},
+//!                                  {mode}, {chain}, {timestamp}, {attempts}, {duration}, {keys_per_second}
+//!     --porcelain                  Prints every found wallet as a line of address<TAB>private_key<TAB>public_key and nothing
+//!                                  else to stdout, the rest goes to stderr. private_key is in WIF, public_key is the compressed
+//!                                  public key in hex. With --no-secrets private_key is empty.
+//!     --stats                      Adds when and how the wallet is found to the output.
+//!     --no-secrets                 Prints only the address and the public key, the private keys are only written
+//!                                  to the output file. Needs --output-file or --output-dir.
+//!     --encrypt-output             Encrypts everything written to the output files with a passphrase.
+//!                                  Needs --output-file or --output-dir. Read it back with decrypt-output.
+//!     --output-password-file <output-password-file>
+//!                                  Reads the passphrase of --encrypt-output from the first line of the file
+//!                                  instead of asking it.
 //! -p, --prefix                     Finds a vanity address which has 'string' prefix. [default]
 //! -s, --suffix                     Finds a vanity address which has 'string' suffix.
 //! -a, --anywhere                   Finds a vanity address which includes 'string' at any part of the address.
-//! -t, --threads <threads>          Number of threads to be used. [default: 16]
-//! -c, --case-sensitive             Use case sensitive comparison to match addresses.
+//! -t, --threads <threads>          Number of threads to be used. 0 uses all the CPU cores.
+//! [default: number of CPU cores]
+//!     --pin-threads                Pins every search thread to its own CPU core. Logs the cores with --log-level debug.
+//! -n, --count <count>              Number of vanity addresses to be found for each string. [default: 1]
+//!     --unique-matches             Skips the wallets whose matched part is already found, so with -n every wallet has
+//!                                  a different match, ex: the distinct capitalizations of the string. Remembers the last 65536 matches.
+//!     --exclude-addresses <exclude-addresses>
+//!                                  Skips the matching addresses listed in the file, one address per line, ex: the ones already found.
+//!     --keep-leading-one           Searches a prefix starting with '1' as it is. By default the '1' is dropped,
+//!                                  because every address already starts with it.
+//!     --random                     Generates -n random wallet/s without searching for a string.
+//!     --timeout <timeout>          Gives up searching a string after the given duration. ex: 30s, 10m, 2h
+//!     --max-attempts <max-attempts>
+//!                                  Gives up searching a string after trying the given number of candidates.
+//!     --max-rate <max-rate>        Tries at most the given number of candidates per second, so the search leaves CPU time
+//!                                  to other work. The threads sleep to keep to it.
+//!     --seed <seed>                Generates the keys deterministically from the given number. Only for tests and demos,
+//!                                  never use the found wallet/s with real funds.
+//!     --entropy-file <entropy-file>
+//!                                  Mixes the content of the file, ex: dice rolls, into every generated key as extra entropy.
+//! -c, --case-sensitive             Use case sensitive comparison to match addresses. Without it any case matches.
 //! -d, --disable-fast               Disables fast mode to find a prefix more than 4 characters.
+//!     --max-len <max-len>          Accepts strings up to the given length in fast mode instead of 4 characters. -d removes the limit.
+//! -q, --quiet                      Disables the progress line printed to stderr while searching.
+//!     --no-color                   Disables the colors in the output. Setting the NO_COLOR environment variable does the same.
+//!     --log-level <log-level>      Logs what the search is doing to stderr. debug also logs the speed every second.
+//!                                  [default: off] [possible values: off, error, warn, info, debug, trace]
+//!     --difficulty-warn <difficulty-warn>
+//!                                  Asks for confirmation before searching a string which needs more attempts than this.
+//!                                  [default: 10000000000]
+//! -y, --yes                        Starts searching without asking for confirmation for hard strings.
+//!     --dry-run                    Validates every string and prints the resolved search and difficulty without searching, as JSON lines with --format json.
+//!     --estimate                   Prints the expected attempts and time to find each string without searching.
+//!     --recalibrate                Measures the speed of this machine again instead of using the cached speed.
+//!     --benchmark                  Measures key generation and match speed with 1, 2, 4, ... up to -t threads.
+//!     --list-chains                Prints the supported chains with their alphabet, length limits and capabilities.
 //! -h, --help                       Print help
 //! -V, --version                    Print version
+//!
+//! Environment variables:
+//! BTC_VANITY_THREADS      Used as -t, --threads when it is not given.
+//! BTC_VANITY_OUTPUT_FILE  Used as -o, --output-file when it is not given.
+//! BTC_VANITY_CACHE_DIR    Directory of the cached speed of this machine, used by --estimate and
+//!                         the difficulty warning.
 //! ```
 //!
 //! # Some Usage Examples
@@ -56,23 +139,54 @@
 
 use clap;
 
+/// The highest thread count accepted by -t, --threads.
+pub const MAX_THREADS: u64 = 1024;
+
 /// Runs the clap app in order to use cli
 pub fn cli() -> clap::Command {
-    clap::Command::new(env!("CARGO_PKG_NAME"))
+    let command = clap::Command::new(env!("CARGO_PKG_NAME"))
         .version(env!("CARGO_PKG_VERSION"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
+        .after_help(
+            "Environment variables:\n  \
+            BTC_VANITY_THREADS      Used as -t, --threads when it is not given.\n  \
+            BTC_VANITY_OUTPUT_FILE  Used as -o, --output-file when it is not given.\n  \
+            BTC_VANITY_CACHE_DIR    Directory of the cached speed of this machine, used by --estimate and\n                          \
+            the difficulty warning.",
+        )
         .arg(
             clap::Arg::new("string")
                 .index(1)
-                .required_unless_present_any(["input-file"])
+                .required_unless_present_any(["input-file", "benchmark", "list-chains", "random"])
                 .help("String used to match addresses."),
         )
         .arg(
             clap::Arg::new("input-file")
                 .short('i')
                 .long("input-file")
-                .required_unless_present_any(["string"])
-                .help("File with strings to match addresses with.\nImportant: Write every string in a separate line.")
+                .required_unless_present_any(["string", "benchmark", "list-chains", "random"])
+                .help("File with strings to match addresses with.\nImportant: Write every string in a separate line.\nUse - to read the strings from stdin.")
+        )
+        .arg(
+            clap::Arg::new("input-format")
+                .long("input-format")
+                .requires("input-file")
+                .value_parser(["lines", "toml"])
+                .help("Format of the input file. toml reads every string from an [[entry]] table.\n[default: toml for .toml files, lines for the rest]")
+        )
+        .arg(
+            clap::Arg::new("ignore-unknown-flags")
+                .long("ignore-unknown-flags")
+                .action(clap::ArgAction::SetTrue)
+                .help("Warns about the unknown flags in the lines of the input file instead of exiting.")
+        )
+        .arg(
+            clap::Arg::new("watch")
+                .long("watch")
+                .requires("input-file")
+                .conflicts_with_all(["estimate", "dry-run"])
+                .action(clap::ArgAction::SetTrue)
+                .help("Keeps running and searches the lines appended to the input file until Ctrl-C.\nOnly the lines format is supported.")
         )
         .arg(
             clap::Arg::new("force-flags")
@@ -87,6 +201,75 @@ pub fn cli() -> clap::Command {
                 .long("output-file")
                 .help("Crates a file that contains found wallet/s."),
         )
+        .arg(
+            clap::Arg::new("max-output-size")
+                .long("max-output-size")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help("Rotates the output file before a write would make it larger than the given bytes.\nThe full file is renamed to <name>.1, then <name>.2, ..."),
+        )
+        .arg(
+            clap::Arg::new("output-dir")
+                .conflicts_with("output-file")
+                .long("output-dir")
+                .help("Writes every found wallet to its own file in the given directory."),
+        )
+        .arg(
+            clap::Arg::new("name-template")
+                .requires("output-dir")
+                .long("name-template")
+                .help("File name template used with --output-dir. Placeholders: {chain}, {pattern}, {n}\n[default: {chain}-{pattern}-{n}.txt, or .json with --format json]"),
+        )
+        .arg(
+            clap::Arg::new("checkpoint")
+                .requires("input-file")
+                .long("checkpoint")
+                .help("Records every completed string of the input file to the given file\nand skips the strings already found in it on the next run."),
+        )
+        .arg(
+            clap::Arg::new("summary-file")
+                .requires("input-file")
+                .long("summary-file")
+                .help("Writes the summary of the input file run to the given file as JSON."),
+        )
+        .arg(
+            clap::Arg::new("no-dedup")
+                .long("no-dedup")
+                .action(clap::ArgAction::SetTrue)
+                .help("Searches every line of the input file for its own wallet/s. By default a line with\nthe same string, mode, case sensitivity and count as an earlier line gets its wallet/s."),
+        )
+        .arg(
+            clap::Arg::new("append")
+                .conflicts_with("overwrite")
+                .long("append")
+                .action(clap::ArgAction::SetTrue)
+                .help("Appends the found wallet/s to the output file. [default]"),
+        )
+        .arg(
+            clap::Arg::new("overwrite")
+                .long("overwrite")
+                .action(clap::ArgAction::SetTrue)
+                .help("Empties the output file before the first write of the run."),
+        )
+        .arg(
+            clap::Arg::new("format")
+                .long("format")
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .help("Output format of the found wallet/s. json writes one JSON object per line."),
+        )
+        .arg(
+            clap::Arg::new("template")
+                .long("template")
+                .conflicts_with("format")
+                .help("Formats every found wallet as a single line, ex: \"{address},{private_key}\".\nPlaceholders: {address}, {private_key}, {private_key_hex}, {public_key}, {pattern},\n{mode}, {chain}, {timestamp}, {attempts}, {duration}, {keys_per_second}"),
+        )
+        .arg(
+            clap::Arg::new("porcelain")
+                .long("porcelain")
+                .conflicts_with_all(["format", "template", "estimate", "dry-run"])
+                .action(clap::ArgAction::SetTrue)
+                .help("Prints every found wallet as a line of address<TAB>private_key<TAB>public_key and nothing\nelse to stdout, the rest goes to stderr. private_key is in WIF, public_key is the compressed\npublic key in hex. With --no-secrets private_key is empty."),
+        )
         .arg(
             clap::Arg::new("prefix")
                 .conflicts_with("suffix")
@@ -116,15 +299,79 @@ pub fn cli() -> clap::Command {
             clap::Arg::new("threads")
                 .short('t')
                 .long("threads")
-                .default_value("16")
-                .help("Number of threads to be used."),
+                .value_parser(clap::value_parser!(u64).range(0..=MAX_THREADS))
+                .help("Number of threads to be used. 0 uses all the CPU cores.\n[default: number of CPU cores]"),
+        )
+        .arg(
+            clap::Arg::new("pin-threads")
+                .long("pin-threads")
+                .action(clap::ArgAction::SetTrue)
+                .help("Pins every search thread to its own CPU core. Logs the cores with --log-level debug."),
+        )
+        .arg(
+            clap::Arg::new("count")
+                .short('n')
+                .long("count")
+                .default_value("1")
+                .help("Number of vanity addresses to be found for each string."),
+        )
+        .arg(
+            clap::Arg::new("unique-matches")
+                .long("unique-matches")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("random")
+                .help("Skips the wallets whose matched part is already found, so with -n every wallet has\na different match, ex: the distinct capitalizations of the string. Remembers the last 65536 matches."),
+        )
+        .arg(
+            clap::Arg::new("exclude-addresses")
+                .long("exclude-addresses")
+                .conflicts_with("random")
+                .help("Skips the matching addresses listed in the file, one address per line, ex: the ones already found."),
+        )
+        .arg(
+            clap::Arg::new("keep-leading-one")
+                .long("keep-leading-one")
+                .action(clap::ArgAction::SetTrue)
+                .help("Searches a prefix starting with '1' as it is. By default the '1' is dropped,\nbecause every address already starts with it."),
+        )
+        .arg(
+            clap::Arg::new("random")
+                .long("random")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["string", "input-file", "estimate", "dry-run"])
+                .help("Generates -n random wallet/s without searching for a string."),
+        )
+        .arg(
+            clap::Arg::new("timeout")
+                .long("timeout")
+                .help("Gives up searching a string after the given duration. ex: 30s, 10m, 2h"),
+        )
+        .arg(
+            clap::Arg::new("max-attempts")
+                .long("max-attempts")
+                .help("Gives up searching a string after trying the given number of candidates."),
+        )
+        .arg(
+            clap::Arg::new("max-rate")
+                .long("max-rate")
+                .help("Tries at most the given number of candidates per second, so the search leaves CPU time\nto other work. The threads sleep to keep to it."),
+        )
+        .arg(
+            clap::Arg::new("seed")
+                .long("seed")
+                .help("Generates the keys deterministically from the given number. Only for tests and demos,\nnever use the found wallet/s with real funds."),
+        )
+        .arg(
+            clap::Arg::new("entropy-file")
+                .long("entropy-file")
+                .help("Mixes the content of the file, ex: dice rolls, into every generated key as extra entropy."),
         )
         .arg(
             clap::Arg::new("case-sensitive")
                 .short('c')
                 .long("case-sensitive")
                 .action(clap::ArgAction::SetTrue)
-                .help("Use case sensitive comparison to match addresses."),
+                .help("Use case sensitive comparison to match addresses. Without it any case matches."),
         )
         .arg(
             clap::Arg::new("disable-fast-mode")
@@ -133,6 +380,100 @@ pub fn cli() -> clap::Command {
                 .action(clap::ArgAction::SetTrue)
                 .help("Disables fast mode to find a prefix more than 4 characters."),
         )
+        .arg(
+            clap::Arg::new("max-len")
+                .long("max-len")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help("Accepts strings up to the given length in fast mode instead of 4 characters. -d removes the limit."),
+        )
+        .arg(
+            clap::Arg::new("stats")
+                .long("stats")
+                .action(clap::ArgAction::SetTrue)
+                .help("Adds when and how the wallet is found to the output."),
+        )
+        .arg(
+            clap::Arg::new("no-secrets")
+                .long("no-secrets")
+                .action(clap::ArgAction::SetTrue)
+                .help("Prints only the address and the public key, the private keys are only written\nto the output file. Needs --output-file or --output-dir."),
+        )
+        .arg(
+            clap::Arg::new("encrypt-output")
+                .long("encrypt-output")
+                .action(clap::ArgAction::SetTrue)
+                .help("Encrypts everything written to the output files with a passphrase.\nNeeds --output-file or --output-dir. Read it back with decrypt-output."),
+        )
+        .arg(
+            clap::Arg::new("output-password-file")
+                .long("output-password-file")
+                .requires("encrypt-output")
+                .help("Reads the passphrase of --encrypt-output from the first line of the file\ninstead of asking it."),
+        )
+        .arg(
+            clap::Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .action(clap::ArgAction::SetTrue)
+                .help("Disables the progress line printed to stderr while searching."),
+        )
+        .arg(
+            clap::Arg::new("no-color")
+                .long("no-color")
+                .action(clap::ArgAction::SetTrue)
+                .help("Disables the colors in the output. Setting the NO_COLOR environment variable does the same."),
+        )
+        .arg(
+            clap::Arg::new("log-level")
+                .long("log-level")
+                .value_parser(["off", "error", "warn", "info", "debug", "trace"])
+                .default_value("off")
+                .help("Logs what the search is doing to stderr. debug also logs the speed every second."),
+        )
+        .arg(
+            clap::Arg::new("difficulty-warn")
+                .long("difficulty-warn")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("10000000000")
+                .help("Asks for confirmation before searching a string which needs more attempts than this."),
+        )
+        .arg(
+            clap::Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .action(clap::ArgAction::SetTrue)
+                .help("Starts searching without asking for confirmation for hard strings."),
+        )
+        .arg(
+            clap::Arg::new("dry-run")
+                .long("dry-run")
+                .action(clap::ArgAction::SetTrue)
+                .help("Validates every string and prints the resolved search and difficulty without searching, as JSON lines with --format json."),
+        )
+        .arg(
+            clap::Arg::new("estimate")
+                .long("estimate")
+                .action(clap::ArgAction::SetTrue)
+                .help("Prints the expected attempts and time to find each string without searching."),
+        )
+        .arg(
+            clap::Arg::new("recalibrate")
+                .long("recalibrate")
+                .action(clap::ArgAction::SetTrue)
+                .help("Measures the speed of this machine again instead of using the cached speed."),
+        )
+        .arg(
+            clap::Arg::new("benchmark")
+                .long("benchmark")
+                .action(clap::ArgAction::SetTrue)
+                .help("Measures key generation and match speed with 1, 2, 4, ... up to -t threads."),
+        )
+        .arg(
+            clap::Arg::new("list-chains")
+                .long("list-chains")
+                .action(clap::ArgAction::SetTrue)
+                .help("Prints the supported chains with their alphabet, length limits and capabilities."),
+        )
         .arg(
             clap::Arg::new("range-min")
                 .long("range-min")
@@ -142,5 +483,80 @@ pub fn cli() -> clap::Command {
             clap::Arg::new("range-max")
                 .long("range-max")
                 .help("Maximum range for private key in hexadecimal format.")
+            );
+
+    // The subcommands don't need the string or the input file.
+    let command = command.subcommand_negates_reqs(true).subcommand(
+        clap::Command::new("verify")
+            .about("Checks that a private key derives the given address and the address has the pattern.")
+            .arg(
+                clap::Arg::new("chain")
+                    .long("chain")
+                    .default_value("bitcoin")
+                    .value_parser(["bitcoin"])
+                    .help("Chain of the wallet."),
+            )
+            .arg(
+                clap::Arg::new("private-key")
+                    .long("private-key")
+                    .required(true)
+                    .help("Private key of the wallet in WIF."),
             )
+            .arg(
+                clap::Arg::new("address")
+                    .long("address")
+                    .required(true)
+                    .help("Address of the wallet."),
+            )
+            .arg(
+                clap::Arg::new("pattern")
+                    .long("pattern")
+                    .help("Also checks that the address has the pattern."),
+            )
+            .arg(
+                clap::Arg::new("mode")
+                    .long("mode")
+                    .requires("pattern")
+                    .default_value("prefix")
+                    .value_parser(["prefix", "suffix", "anywhere"])
+                    .help("Where the pattern must be in the address."),
+            )
+            .arg(
+                clap::Arg::new("case-sensitive")
+                    .short('c')
+                    .long("case-sensitive")
+                    .requires("pattern")
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Use case sensitive comparison to match the pattern."),
+            ),
+    );
+
+    let command = command.subcommand(
+        clap::Command::new("decrypt-output")
+            .about("Decrypts an output file written with --encrypt-output and prints it.")
+            .arg(
+                clap::Arg::new("file")
+                    .required(true)
+                    .help("The encrypted output file."),
+            )
+            .arg(
+                clap::Arg::new("output-password-file")
+                    .long("output-password-file")
+                    .help("Reads the passphrase from the first line of the file instead of asking it."),
+            ),
+    );
+
+    #[cfg(feature = "serve")]
+    let command = command.subcommand(
+        clap::Command::new("serve")
+            .about("Serves vanity address requests over a local HTTP API.")
+            .arg(
+                clap::Arg::new("address")
+                    .long("address")
+                    .default_value("127.0.0.1:3000")
+                    .help("Address the server listens on."),
+            ),
+    );
+
+    command
 }
@@ -3,6 +3,16 @@
 //! This module is used for creating a better stylized outputs for btc-vanity.
 
 use crate::vanity_addr_generator::VanityMode;
+use anstyle::{AnsiColor, Style};
+
+/// Style of the progress messages and the labels of the found wallets.
+pub const DIM: Style = Style::new().dimmed();
+/// Style of the part of the found address which matched the string.
+pub const HIGHLIGHT: Style = Style::new()
+    .bold()
+    .fg_color(Some(anstyle::Color::Ansi(AnsiColor::Green)));
+/// Style of the errors.
+pub const ERROR: Style = Style::new().fg_color(Some(anstyle::Color::Ansi(AnsiColor::Red)));
 
 const VANITY_MODE_STR: [&str; 3] = ["has the prefix", "has the suffix", "has the string"];
 const CASE_SENSITIVITY_STR: [&str; 2] = ["(case sensitive)", "(case sensitivity disabled)"];
@@ -27,3 +37,47 @@ pub fn get_decoration_strings<'a>(
 
     (vanity_mode_str, case_sensitive_str)
 }
+
+/// Returns the text wrapped with the escape codes of the style.
+/// The codes are removed later if the output doesn't support colors.
+pub fn paint(style: Style, text: &str) -> String {
+    format!("{}{}{:#}", style, text, style)
+}
+
+/// Returns the string with a caret line under the invalid character and a suggestion
+/// for the characters which are easily confused with a base58 character.
+///
+/// ```txt
+///   Emi0
+///      ^ did you mean 'o'? note: 'O' is also invalid in base58
+/// ```
+pub fn get_invalid_character_hint(string: &str, ch: char, index: usize) -> String {
+    let suggestion = match ch {
+        '0' => " did you mean 'o'? note: 'O' is also invalid in base58",
+        'O' => " did you mean 'o'? note: '0' is also invalid in base58",
+        'I' => " did you mean 'i' or '1'? note: 'l' is also invalid in base58",
+        'l' => " did you mean 'L' or '1'? note: 'I' is also invalid in base58",
+        _ => "",
+    };
+    format!("  {}\n  {}^{}", string, " ".repeat(index), suggestion)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_invalid_character_hint() {
+        assert_eq!(
+            get_invalid_character_hint("Emi0", '0', 3),
+            "  Emi0\n     ^ did you mean 'o'? note: 'O' is also invalid in base58"
+        );
+        assert_eq!(get_invalid_character_hint("a-b", '-', 1), "  a-b\n   ^");
+    }
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(paint(Style::new(), "text"), "text");
+        assert_eq!(paint(ERROR, "text"), "\x1b[31mtext\x1b[0m");
+    }
+}
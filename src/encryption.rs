@@ -0,0 +1,211 @@
+//! # Output Encryption Module
+//!
+//! This module is used for encrypting the found wallets written to the output files with a passphrase,
+//! so the private keys aren't stored on the disk in cleartext.
+//!
+//! Every write to an encrypted output file is appended as its own line, so encrypted files can be
+//! appended to like the plain ones:
+//! ```txt
+//! btc-vanity-encrypted-v1 <salt> <nonce> <ciphertext>
+//! ```
+//! The fields are in hex. The key is derived from the passphrase and the salt with scrypt and
+//! the text is encrypted with AES-256-GCM. A run uses the same salt for all of its writes,
+//! so the key is derived only once.
+//!
+//! # Example
+//! ```rust,no_run
+//! use btc_vanity::encryption::{decrypt_output, OutputCipher};
+//!
+//! let cipher = OutputCipher::new("passphrase").unwrap();
+//! let line = cipher.encrypt("private_key (wif): ...\n").unwrap();
+//! assert_eq!(decrypt_output(&line, "passphrase").unwrap(), "private_key (wif): ...\n");
+//! ```
+
+use crate::error::BtcVanityError;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use scrypt::Params;
+
+/// The first field of every encrypted line.
+const MAGIC: &str = "btc-vanity-encrypted-v1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Encrypts the text written to the output files with the key derived from a passphrase.
+pub struct OutputCipher {
+    salt: [u8; SALT_LEN],
+    cipher: Aes256Gcm,
+}
+
+impl OutputCipher {
+    /// Derives the key from the passphrase with a new random salt.
+    pub fn new(passphrase: &str) -> Result<Self, BtcVanityError> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::with_salt(passphrase, salt)
+    }
+
+    fn with_salt(passphrase: &str, salt: [u8; SALT_LEN]) -> Result<Self, BtcVanityError> {
+        Ok(OutputCipher {
+            salt,
+            cipher: Aes256Gcm::new(&derive_key(passphrase, &salt)?),
+        })
+    }
+
+    /// Encrypts the text and returns it as an encrypted line ending with a newline.
+    pub fn encrypt(&self, text: &str) -> Result<String, BtcVanityError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, text.as_bytes())
+            .map_err(|_| BtcVanityError::EncryptionFailed)?;
+
+        Ok(format!(
+            "{} {} {} {}\n",
+            MAGIC,
+            to_hex(&self.salt),
+            to_hex(&nonce),
+            to_hex(&ciphertext)
+        ))
+    }
+}
+
+/// Derives the 256 bit AES key from the passphrase and the salt with scrypt.
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>, BtcVanityError> {
+    let params = Params::new(
+        Params::RECOMMENDED_LOG_N,
+        Params::RECOMMENDED_R,
+        Params::RECOMMENDED_P,
+        32,
+    )
+    .map_err(|_| BtcVanityError::EncryptionFailed)?;
+    let mut key = Key::<Aes256Gcm>::default();
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+        .map_err(|_| BtcVanityError::EncryptionFailed)?;
+    Ok(key)
+}
+
+/// Decrypts every line of an encrypted output file and returns the text written to it.
+/// Returns Err naming the line if it isn't an encrypted line, and DecryptionFailed if the
+/// passphrase is wrong or the line is edited.
+pub fn decrypt_output(data: &str, passphrase: &str) -> Result<String, BtcVanityError> {
+    // The lines written by the same run have the same salt.
+    let mut ciphers: Vec<OutputCipher> = Vec::new();
+    let mut text = String::new();
+
+    for (i, line) in data.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid_line = || BtcVanityError::InvalidEncryptedOutput { line: i + 1 };
+        let fields = line.split(' ').collect::<Vec<_>>();
+        let [MAGIC, salt, nonce, ciphertext] = fields[..] else {
+            return Err(invalid_line());
+        };
+        let salt: [u8; SALT_LEN] = from_hex(salt)
+            .and_then(|salt| salt.try_into().ok())
+            .ok_or_else(invalid_line)?;
+        let nonce: [u8; NONCE_LEN] = from_hex(nonce)
+            .and_then(|nonce| nonce.try_into().ok())
+            .ok_or_else(invalid_line)?;
+        let ciphertext = from_hex(ciphertext).ok_or_else(invalid_line)?;
+
+        let cipher = match ciphers.iter().position(|cipher| cipher.salt == salt) {
+            Some(index) => &ciphers[index],
+            None => {
+                ciphers.push(OutputCipher::with_salt(passphrase, salt)?);
+                ciphers.last().unwrap()
+            }
+        };
+        let plaintext = cipher
+            .cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| BtcVanityError::DecryptionFailed { line: i + 1 })?;
+        text.push_str(
+            &String::from_utf8(plaintext)
+                .map_err(|_| BtcVanityError::DecryptionFailed { line: i + 1 })?,
+        );
+    }
+
+    Ok(text)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => {
+                let high = char::from(*high).to_digit(16)?;
+                let low = char::from(*low).to_digit(16)?;
+                Some((high * 16 + low) as u8)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        let cipher = OutputCipher::new("correct horse").unwrap();
+        let mut data = cipher.encrypt("first wallet\n").unwrap();
+        data.push_str(&cipher.encrypt("second wallet\n").unwrap());
+        // A later run appends to the same file with another salt.
+        data.push_str(
+            &OutputCipher::new("correct horse")
+                .unwrap()
+                .encrypt("third wallet\n")
+                .unwrap(),
+        );
+
+        assert!(!data.contains("wallet"));
+        assert_eq!(data.lines().count(), 3);
+        assert_eq!(
+            decrypt_output(&data, "correct horse").unwrap(),
+            "first wallet\nsecond wallet\nthird wallet\n"
+        );
+    }
+
+    #[test]
+    fn test_decrypt_with_wrong_passphrase() {
+        let data = OutputCipher::new("correct horse")
+            .unwrap()
+            .encrypt("wallet\n")
+            .unwrap();
+
+        assert!(matches!(
+            decrypt_output(&data, "battery staple"),
+            Err(BtcVanityError::DecryptionFailed { line: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_invalid_line() {
+        let mut data = OutputCipher::new("correct horse")
+            .unwrap()
+            .encrypt("wallet\n")
+            .unwrap();
+        data.push_str("private_key (wif): L4RMjXo3AWzBuJTv98ZPoLtPtPP71aLwG7xV5pXodxGzWNZmK6Db\n");
+
+        assert!(matches!(
+            decrypt_output(&data, "correct horse"),
+            Err(BtcVanityError::InvalidEncryptedOutput { line: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(to_hex(&[0x00, 0xab, 0x10]), "00ab10");
+        assert_eq!(from_hex("00ab10"), Some(vec![0x00, 0xab, 0x10]));
+        assert_eq!(from_hex("0"), None);
+        assert_eq!(from_hex("zz"), None);
+    }
+}
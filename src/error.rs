@@ -2,41 +2,140 @@ use std::io;
 use thiserror::Error;
 
 /// A unified error type that encapsulates all possible errors in the btc-vanity application.
+/// New variants can be added in minor releases, so matches on it need a wildcard arm.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum BtcVanityError {
     #[error("File error: {0}")]
     FileError(#[from] io::Error),
 
-    #[error("Keys and address error: {0}")]
-    KeysAndAddressError(&'static str),
+    #[error("Keys and address error: the private key is not a valid WIF")]
+    InvalidWif,
 
-    #[error("Vanity address generator error: {0}")]
-    VanityGeneratorError(&'static str),
-}
+    #[error("Keys and address error: the private key is not a mainnet private key")]
+    NotMainnetKey,
 
-impl From<KeysAndAddressError> for BtcVanityError {
-    fn from(keys_and_address_err: KeysAndAddressError) -> Self {
-        BtcVanityError::KeysAndAddressError(keys_and_address_err.0)
-    }
-}
+    #[error("Keys and address error: the private key is not a valid secp256k1 key")]
+    InvalidPrivateKey,
 
-impl From<VanityGeneratorError> for BtcVanityError {
-    fn from(vanity_err: VanityGeneratorError) -> Self {
-        BtcVanityError::VanityGeneratorError(vanity_err.0)
-    }
-}
+    #[error("Keys and address error: invalid private key range, {reason}")]
+    InvalidRange { reason: &'static str },
 
-/// Struct-based error types for backward compatibility or specific contexts.
-#[derive(Error, Debug)]
-#[error("Keys and address error: {0}")]
-pub struct KeysAndAddressError(pub &'static str);
+    #[error("Vanity address generator error: You're asking for too much! The string is {len} chars, the limit is {max}; pass --disable-fast or --max-len.\n\
+        A {chain} string longer than {max} characters can take for a long time to find, only raise the limit if you really want to.")]
+    FastModeEnabled {
+        len: usize,
+        max: usize,
+        chain: &'static str,
+    },
 
-#[derive(Error, Debug)]
-#[error("Vanity address generator error: {0}")]
-pub struct VanityGeneratorError(pub &'static str);
+    #[error("Vanity address generator error: {len} characters is longer than the {max} characters a {chain} address can match.")]
+    PatternTooLong {
+        len: usize,
+        max: usize,
+        chain: &'static str,
+    },
+
+    #[error("Vanity address generator error: no {chain} address can match the string. It fills the whole address, and its value is above the highest address.")]
+    ImpossiblePattern { chain: &'static str },
+
+    #[error("Vanity address generator error: the string is empty. Use random mode to generate a key pair without a pattern.")]
+    EmptyPattern,
+
+    #[error("Vanity address generator error: count must be greater than 0")]
+    InvalidCount,
+
+    #[error("Vanity address generator error: the string has only {unique} distinct matches, can't find {count} unique ones")]
+    TooFewUniqueMatches { count: u64, unique: u64 },
+
+    #[error(
+        "Vanity address generator error: all search threads exited before finding a vanity address"
+    )]
+    SearchThreadsExited,
+
+    #[error("Vanity address generator error: a search thread panicked: {message}")]
+    WorkerPanicked { message: String },
+
+    #[error("Vanity address generator error: vanity address not found within the given range")]
+    NotFoundInRange,
+
+    #[error("Vanity address generator error: search timed out after {attempts} attempts")]
+    TimedOut { attempts: u64 },
+
+    #[error("Vanity address generator error: attempt limit reached after {attempts} attempts")]
+    AttemptLimitReached { attempts: u64 },
+
+    #[error("Vanity address generator error: search cancelled after {attempts} attempts")]
+    Cancelled { attempts: u64 },
+
+    #[error("Vanity address generator error: Your input is not in base58. '{ch}' at index {index} is not a base58 character.")]
+    InvalidCharacter { ch: char, index: usize },
+
+    #[error("Vanity address generator error: threads must be greater than 0")]
+    InvalidThreadCount,
+
+    #[error("Vanity address generator error: max rate must be a number of keys per second greater than 0")]
+    InvalidMaxRate,
+
+    #[error("Verification error: the private key derives {derived}, not {address}")]
+    AddressMismatch { address: String, derived: String },
+
+    #[error("Verification error: {address} doesn't match the pattern '{pattern}'")]
+    PatternMismatch { address: String, pattern: String },
+
+    #[error("Self-check error: the found key pair of {address} is dropped, {reason}. This is a bug or a memory error, please report it.")]
+    SelfCheckFailed { address: String, reason: String },
+
+    #[error("Invalid template: '{placeholder}' is not a placeholder. Placeholders: {{address}}, {{private_key}}, \
+        {{private_key_hex}}, {{public_key}}, {{pattern}}, {{mode}}, {{chain}}, {{timestamp}}, {{attempts}}, {{duration}}, {{keys_per_second}}")]
+    InvalidTemplate { placeholder: String },
+
+    #[error("Input file error: {reason}")]
+    InvalidInputFile { reason: String },
+
+    #[error("Input file error: line {line}: {reason}")]
+    InvalidInputLine { line: usize, reason: String },
+
+    #[error("Input file error: line {line}: unknown flag '{flag}'{}. Use --ignore-unknown-flags to skip unknown flags.",
+        .suggestion.map(|suggestion| format!(", did you mean '{}'?", suggestion)).unwrap_or_default())]
+    UnknownInputFlag {
+        line: usize,
+        flag: String,
+        suggestion: Option<&'static str>,
+    },
+
+    #[error("Input file error: entry {index}: {reason}")]
+    InvalidInputEntry { index: usize, reason: String },
+
+    #[error("Exclusion list error: line {line} is not a base58 address")]
+    InvalidExcludedAddress { line: usize },
+
+    #[error("Encryption error: couldn't encrypt the output")]
+    EncryptionFailed,
+
+    #[error("Decryption error: line {line} can't be decrypted, the passphrase is wrong or the line is edited")]
+    DecryptionFailed { line: usize },
+
+    #[error("Decryption error: line {line} is not an encrypted line")]
+    InvalidEncryptedOutput { line: usize },
+
+    #[error("Invalid passphrase: {reason}")]
+    InvalidPassphrase { reason: &'static str },
+
+    #[error(
+        "--encrypt-output needs --output-file or --output-dir to write the encrypted wallets to"
+    )]
+    EncryptWithoutOutput,
+
+    #[error("--no-secrets needs --output-file or --output-dir to write the private keys to")]
+    NoSecretsWithoutOutput,
+
+    #[error("--watch needs an input file with a string in every line, not stdin or a TOML file")]
+    WatchUnsupportedInput,
+
+    #[error("{flag} needs btc-vanity built with the serde feature to write JSON")]
+    SerdeDisabled { flag: &'static str },
 
-impl From<KeysAndAddressError> for VanityGeneratorError {
-    fn from(keys_and_address_err: KeysAndAddressError) -> Self {
-        VanityGeneratorError(keys_and_address_err.0)
-    }
+    #[error("Invalid value '{value}' for environment variable {name}")]
+    InvalidEnvVar { name: &'static str, value: String },
 }
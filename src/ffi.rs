@@ -0,0 +1,345 @@
+//! # C FFI Module
+//!
+//! This module exposes vanity address generation to C and other languages with a C FFI.
+//! The declarations are in `include/btc_vanity.h`, generated with cbindgen from this module.
+//! The `btc-vanity-ffi` crate in `ffi/` builds it as a shared library.
+//!
+//! # Example Usage In C
+//!
+//! ```c
+//! BtcVanityResult result;
+//! int status = btc_vanity_generate("bitcoin", "Emiv", BTC_VANITY_MODE_PREFIX, false, 8, NULL, &result);
+//! if (status == BTC_VANITY_OK) {
+//!     printf("%s %s\n", result.address, result.private_key_wif);
+//!     btc_vanity_free_result(&result);
+//! }
+//! ```
+
+use crate::chain::BITCOIN;
+use crate::error::BtcVanityError;
+use crate::vanity_addr_generator::{CancellationToken, VanityAddrBuilder, VanityMode};
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// A vanity address is found and written to the result.
+pub const BTC_VANITY_OK: c_int = 0;
+/// A required pointer argument is null.
+pub const BTC_VANITY_NULL_ARGUMENT: c_int = 1;
+/// The chain isn't supported.
+pub const BTC_VANITY_UNSUPPORTED_CHAIN: c_int = 2;
+/// The pattern is empty, isn't valid UTF-8, has a non base58 character or is too long.
+pub const BTC_VANITY_INVALID_PATTERN: c_int = 3;
+/// The mode isn't one of the BTC_VANITY_MODE constants.
+pub const BTC_VANITY_INVALID_MODE: c_int = 4;
+/// Threads is 0.
+pub const BTC_VANITY_INVALID_THREADS: c_int = 5;
+/// The search is cancelled with the cancellation handle.
+pub const BTC_VANITY_CANCELLED: c_int = 6;
+/// The search failed for another reason.
+pub const BTC_VANITY_ERROR: c_int = 7;
+/// The library panicked. The result isn't written.
+pub const BTC_VANITY_PANIC: c_int = 8;
+
+pub const BTC_VANITY_MODE_PREFIX: c_int = 0;
+pub const BTC_VANITY_MODE_SUFFIX: c_int = 1;
+pub const BTC_VANITY_MODE_ANYWHERE: c_int = 2;
+
+/// A found vanity address. The strings are null terminated and owned by the library,
+/// so they must be released with `btc_vanity_free_result`.
+#[repr(C)]
+pub struct BtcVanityResult {
+    pub address: *mut c_char,
+    pub public_key: *mut c_char,
+    pub private_key_wif: *mut c_char,
+}
+
+/// Cancels a running `btc_vanity_generate` from another thread.
+pub struct BtcVanityCancel(CancellationToken);
+
+/// Runs the body of an exported function and returns on_panic if it panics, so a panic never
+/// unwinds into the caller, which would abort its process.
+fn catch_panic<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(on_panic)
+}
+
+/// Returns the status code of the error.
+fn status_code(err: &BtcVanityError) -> c_int {
+    match err {
+        BtcVanityError::InvalidCharacter { .. }
+        | BtcVanityError::PatternTooLong { .. }
+        | BtcVanityError::ImpossiblePattern { .. }
+        | BtcVanityError::EmptyPattern => BTC_VANITY_INVALID_PATTERN,
+        BtcVanityError::InvalidThreadCount => BTC_VANITY_INVALID_THREADS,
+        BtcVanityError::Cancelled { .. } => BTC_VANITY_CANCELLED,
+        _ => BTC_VANITY_ERROR,
+    }
+}
+
+/// Searches for a vanity address with the pattern and writes it to `out_result`.
+/// Fast mode is disabled, so the caller is responsible for the length of the pattern.
+/// `chain` can be null for bitcoin. `cancel` can be null if the search won't be cancelled.
+/// Returns BTC_VANITY_OK or one of the error status codes, BTC_VANITY_PANIC if the library panics.
+/// `out_result` is only written on success.
+///
+/// # Safety
+///
+/// `chain` and `pattern` must be null or valid null terminated strings, `cancel` must be null or
+/// a handle from `btc_vanity_cancel_new` and `out_result` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn btc_vanity_generate(
+    chain: *const c_char,
+    pattern: *const c_char,
+    mode: c_int,
+    case_sensitive: bool,
+    threads: u64,
+    cancel: *const BtcVanityCancel,
+    out_result: *mut BtcVanityResult,
+) -> c_int {
+    catch_panic(BTC_VANITY_PANIC, || {
+        generate(
+            chain,
+            pattern,
+            mode,
+            case_sensitive,
+            threads,
+            cancel,
+            out_result,
+        )
+    })
+}
+
+/// The body of `btc_vanity_generate`, see its safety section.
+unsafe fn generate(
+    chain: *const c_char,
+    pattern: *const c_char,
+    mode: c_int,
+    case_sensitive: bool,
+    threads: u64,
+    cancel: *const BtcVanityCancel,
+    out_result: *mut BtcVanityResult,
+) -> c_int {
+    if pattern.is_null() || out_result.is_null() {
+        return BTC_VANITY_NULL_ARGUMENT;
+    }
+    if !chain.is_null() && CStr::from_ptr(chain).to_bytes() != BITCOIN.name.as_bytes() {
+        return BTC_VANITY_UNSUPPORTED_CHAIN;
+    }
+    let Ok(pattern) = CStr::from_ptr(pattern).to_str() else {
+        return BTC_VANITY_INVALID_PATTERN;
+    };
+    let vanity_mode = match mode {
+        BTC_VANITY_MODE_PREFIX => VanityMode::Prefix,
+        BTC_VANITY_MODE_SUFFIX => VanityMode::Suffix,
+        BTC_VANITY_MODE_ANYWHERE => VanityMode::Anywhere,
+        _ => return BTC_VANITY_INVALID_MODE,
+    };
+
+    let mut builder = VanityAddrBuilder::new(pattern)
+        .threads(threads)
+        .case_sensitive(case_sensitive)
+        .fast_mode(false)
+        .mode(vanity_mode);
+    if let Some(cancel) = cancel.as_ref() {
+        builder = builder.cancellation_token(cancel.0.clone());
+    }
+
+    match builder.generate() {
+        Ok(keys_and_address) => {
+            // Base58 addresses, hex public keys and WIF private keys never contain a null byte.
+            let to_c_string = |string: String| CString::new(string).unwrap().into_raw();
+            out_result.write(BtcVanityResult {
+                address: to_c_string(keys_and_address.get_comp_address().clone()),
+                public_key: to_c_string(keys_and_address.get_comp_public_key()),
+                private_key_wif: to_c_string(keys_and_address.get_wif_private_key()),
+            });
+            BTC_VANITY_OK
+        }
+        Err(err) => status_code(&err),
+    }
+}
+
+/// Releases the strings of a result written by `btc_vanity_generate` and sets them to null,
+/// so freeing the same result twice does nothing.
+///
+/// # Safety
+///
+/// `result` must be null or point to a result written by `btc_vanity_generate`.
+#[no_mangle]
+pub unsafe extern "C" fn btc_vanity_free_result(result: *mut BtcVanityResult) {
+    catch_panic((), || {
+        let Some(result) = result.as_mut() else {
+            return;
+        };
+        for string in [
+            &mut result.address,
+            &mut result.public_key,
+            &mut result.private_key_wif,
+        ] {
+            if !string.is_null() {
+                drop(CString::from_raw(*string));
+                *string = ptr::null_mut();
+            }
+        }
+    })
+}
+
+/// Returns a new cancellation handle, which must be released with `btc_vanity_cancel_free`.
+/// Returns null if the library panics.
+#[no_mangle]
+pub extern "C" fn btc_vanity_cancel_new() -> *mut BtcVanityCancel {
+    catch_panic(ptr::null_mut(), || {
+        Box::into_raw(Box::new(BtcVanityCancel(CancellationToken::new())))
+    })
+}
+
+/// Cancels the searches using the handle. It can be called from any thread.
+///
+/// # Safety
+///
+/// `cancel` must be null or a handle from `btc_vanity_cancel_new` which isn't freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn btc_vanity_cancel(cancel: *const BtcVanityCancel) {
+    catch_panic((), || {
+        if let Some(cancel) = cancel.as_ref() {
+            cancel.0.cancel();
+        }
+    })
+}
+
+/// Releases the cancellation handle after the searches using it are finished.
+///
+/// # Safety
+///
+/// `cancel` must be null or a handle from `btc_vanity_cancel_new` which isn't freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn btc_vanity_cancel_free(cancel: *mut BtcVanityCancel) {
+    catch_panic((), || {
+        if !cancel.is_null() {
+            drop(Box::from_raw(cancel));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_ffi_generate() {
+        let mut result = BtcVanityResult {
+            address: ptr::null_mut(),
+            public_key: ptr::null_mut(),
+            private_key_wif: ptr::null_mut(),
+        };
+        let status = unsafe {
+            btc_vanity_generate(
+                c"bitcoin".as_ptr(),
+                c"e".as_ptr(),
+                BTC_VANITY_MODE_SUFFIX,
+                false,
+                4,
+                ptr::null(),
+                &mut result,
+            )
+        };
+
+        assert_eq!(status, BTC_VANITY_OK);
+        let address = unsafe { CStr::from_ptr(result.address) }.to_str().unwrap();
+        assert!(address.to_lowercase().ends_with('e'));
+        assert!(!result.private_key_wif.is_null());
+
+        unsafe { btc_vanity_free_result(&mut result) };
+        assert!(result.address.is_null());
+    }
+
+    #[test]
+    fn test_ffi_errors() {
+        let mut result = BtcVanityResult {
+            address: ptr::null_mut(),
+            public_key: ptr::null_mut(),
+            private_key_wif: ptr::null_mut(),
+        };
+        let generate = |chain: &CStr, pattern: &CStr, mode, threads, result| unsafe {
+            btc_vanity_generate(
+                chain.as_ptr(),
+                pattern.as_ptr(),
+                mode,
+                false,
+                threads,
+                ptr::null(),
+                result,
+            )
+        };
+
+        assert_eq!(
+            generate(c"bitcoin", c"e", 0, 1, ptr::null_mut()),
+            BTC_VANITY_NULL_ARGUMENT
+        );
+        assert_eq!(
+            generate(c"ethereum", c"e", 0, 1, &mut result),
+            BTC_VANITY_UNSUPPORTED_CHAIN
+        );
+        assert_eq!(
+            generate(c"bitcoin", c"e0", 0, 1, &mut result),
+            BTC_VANITY_INVALID_PATTERN
+        );
+        assert_eq!(
+            generate(c"bitcoin", c"e", 3, 1, &mut result),
+            BTC_VANITY_INVALID_MODE
+        );
+        assert_eq!(
+            generate(c"bitcoin", c"e", 0, 0, &mut result),
+            BTC_VANITY_INVALID_THREADS
+        );
+        assert!(result.address.is_null());
+    }
+
+    #[test]
+    fn test_catch_panic() {
+        assert_eq!(
+            catch_panic(BTC_VANITY_PANIC, || BTC_VANITY_OK),
+            BTC_VANITY_OK
+        );
+        assert_eq!(
+            catch_panic(BTC_VANITY_PANIC, || panic!("search thread panicked")),
+            BTC_VANITY_PANIC
+        );
+    }
+
+    #[test]
+    fn test_ffi_cancel() {
+        let cancel = btc_vanity_cancel_new();
+        let canceller = {
+            let cancel = cancel as usize;
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(100));
+                unsafe { btc_vanity_cancel(cancel as *const BtcVanityCancel) };
+            })
+        };
+
+        let mut result = BtcVanityResult {
+            address: ptr::null_mut(),
+            public_key: ptr::null_mut(),
+            private_key_wif: ptr::null_mut(),
+        };
+        let status = unsafe {
+            btc_vanity_generate(
+                ptr::null(),
+                c"zzzzzzzzzzzzzzzzzzzz".as_ptr(),
+                BTC_VANITY_MODE_PREFIX,
+                true,
+                2,
+                cancel,
+                &mut result,
+            )
+        };
+        canceller.join().unwrap();
+        unsafe { btc_vanity_cancel_free(cancel) };
+
+        assert_eq!(status, BTC_VANITY_CANCELLED);
+    }
+}
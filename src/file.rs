@@ -2,20 +2,32 @@
 //!
 //! This module is used for reading multiple strings and flags from files and writing found vanity wallets to desired destination.
 
+use crate::chain::{supported_chains, BASE58_ALPHABET};
+use crate::cli::MAX_THREADS;
+use crate::encryption::OutputCipher;
 use crate::error::BtcVanityError;
 use crate::vanity_addr_generator::VanityMode;
+use std::collections::HashSet;
 use std::fs::OpenOptions;
-use std::io::Write;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 use std::{fs, io};
 
 /// This struct is used to get set flags for each string input
 /// from the file.
+#[derive(Debug)]
 pub struct FileFlags {
     pub force_flags: bool,
     pub is_case_sensitive: bool,
     pub disable_fast_mode: bool,
     pub output_file_name: Option<String>,
     pub vanity_mode: Option<VanityMode>,
+    /// Thread count of the string, 0 means the number of CPU cores.
+    pub threads: Option<u64>,
+    /// Number of wallets to find for the string.
+    pub count: Option<u64>,
+    /// Tokens of the line which aren't flags, ex: a typo like `--sufix`.
+    pub unknown_flags: Vec<String>,
 }
 
 impl FileFlags {
@@ -29,17 +41,65 @@ impl FileFlags {
             disable_fast_mode: false,
             output_file_name: None,
             vanity_mode: None,
+            threads: None,
+            count: None,
+            unknown_flags: Vec::new(),
         }
     }
 }
 
+/// Format of the input file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+    /// Every line is a string followed by its flags, ex: `Emiv -p -c`
+    Lines,
+    /// Every string is an `[[entry]]` table, see `get_strings_and_flags_from_toml`.
+    Toml,
+}
+
+impl InputFormat {
+    /// Returns Toml for the files with the .toml extension and Lines for the rest.
+    pub fn from_file_name(file_name: &str) -> Self {
+        match Path::new(file_name)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"))
+        {
+            true => InputFormat::Toml,
+            false => InputFormat::Lines,
+        }
+    }
+}
+
+/// Every flag a line of the input file can have.
+const LINE_FLAGS: [&str; 18] = [
+    "-f",
+    "--force-flags",
+    "-c",
+    "--case-sensitive",
+    "-d",
+    "--disable-fast",
+    "-p",
+    "--prefix",
+    "-s",
+    "--suffix",
+    "-a",
+    "--anywhere",
+    "-o",
+    "--output-file",
+    "-t",
+    "--threads",
+    "-n",
+    "--count",
+];
+
 /// Gets all the flags in the line and returns a FileFlags struct.
-/// Each flag must be seperated by space.
-pub fn get_flags(line: &str) -> FileFlags {
+/// Each flag must be seperated by space. Tokens which aren't flags are collected in unknown_flags.
+/// Returns Err with the reason if the value of -t or -n is invalid.
+pub fn get_flags(line: &str) -> Result<FileFlags, String> {
     let args = line.split(' ').collect::<Vec<_>>();
 
     if args.len() == 1 {
-        return FileFlags::use_cli_flags();
+        return Ok(FileFlags::use_cli_flags());
     }
 
     let force_flags = args.contains(&"-f") || args.contains(&"--force-flags");
@@ -67,13 +127,89 @@ pub fn get_flags(line: &str) -> FileFlags {
     let output_file_name = ofn_index
         .and_then(|i| args.get(i + 1))
         .map(ToString::to_string);
+    let threads = match get_number_flag(&args, "-t", "--threads")? {
+        Some(threads) if threads > MAX_THREADS => {
+            return Err(format!("-t must be at most {}", MAX_THREADS))
+        }
+        threads => threads,
+    };
+    let count = match get_number_flag(&args, "-n", "--count")? {
+        Some(0) => return Err(String::from("-n must be greater than 0")),
+        count => count,
+    };
+
+    let mut unknown_flags = Vec::new();
+    // Empty tokens are the extra spaces between the flags.
+    let mut tokens = args[1..].iter().filter(|token| !token.is_empty());
+    while let Some(&token) = tokens.next() {
+        match token {
+            // Skips the value of the flag.
+            "-o" | "--output-file" | "-t" | "--threads" | "-n" | "--count" => {
+                tokens.next();
+            }
+            _ if LINE_FLAGS.contains(&token) => {}
+            _ => unknown_flags.push(token.to_string()),
+        }
+    }
 
-    FileFlags {
+    Ok(FileFlags {
         force_flags,
         is_case_sensitive,
         disable_fast_mode,
         output_file_name,
         vanity_mode,
+        threads,
+        count,
+        unknown_flags,
+    })
+}
+
+/// Returns the line flag closest to the unknown flag, or None if none of them is close enough.
+///
+/// # Example
+/// ```rust
+/// use btc_vanity::file::suggest_line_flag;
+///
+/// assert_eq!(suggest_line_flag("--sufix"), Some("--suffix"));
+/// assert_eq!(suggest_line_flag("-x"), None);
+/// ```
+pub fn suggest_line_flag(flag: &str) -> Option<&'static str> {
+    LINE_FLAGS
+        .iter()
+        .map(|&known| (get_edit_distance(flag, known), known))
+        // A short flag is one edit away from every other short flag, so it is never suggested.
+        .filter(|&(distance, known)| distance <= 2 && distance * 2 < known.len())
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, known)| known)
+}
+
+/// Returns the Levenshtein distance of the strings.
+fn get_edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Gets the number after the flag, ex: 2 for `-t 2`. Returns Ok(None) if the flag isn't set.
+fn get_number_flag(args: &[&str], short: &str, long: &str) -> Result<Option<u64>, String> {
+    let Some(i) = args.iter().position(|&arg| arg == short || arg == long) else {
+        return Ok(None);
+    };
+    match args
+        .get(i + 1)
+        .and_then(|number| number.parse::<u64>().ok())
+    {
+        Some(number) => Ok(Some(number)),
+        None => Err(format!("{} must be followed by a number", args[i])),
     }
 }
 
@@ -85,11 +221,20 @@ pub fn get_flags(line: &str) -> FileFlags {
 /// TALA -a
 /// 3169
 /// test -o test-output.txt
+/// abc -s -n 3 -t 2
 /// ```
 pub fn get_strings_and_flags_from_file(
     file_name: &String,
 ) -> Result<(Vec<String>, Vec<FileFlags>), BtcVanityError> {
-    let data = fs::read_to_string(file_name)?;
+    get_strings_and_flags_from_reader(fs::File::open(file_name)?)
+}
+
+/// Same as `get_strings_and_flags_from_file` but reads the lines from any Read source, like stdin.
+pub fn get_strings_and_flags_from_reader<R: Read>(
+    mut reader: R,
+) -> Result<(Vec<String>, Vec<FileFlags>), BtcVanityError> {
+    let mut data = String::new();
+    reader.read_to_string(&mut data)?;
     let lines: Vec<&str> = data.lines().collect::<Vec<_>>();
     let strings: Vec<_> = lines
         .iter()
@@ -98,51 +243,1027 @@ pub fn get_strings_and_flags_from_file(
             line_split[0].to_string()
         })
         .collect();
-    let flags: Vec<FileFlags> = lines.iter().map(|&string| get_flags(string)).collect();
+    let flags = lines
+        .iter()
+        .enumerate()
+        .map(|(i, &line)| {
+            get_flags(line).map_err(|reason| BtcVanityError::InvalidInputLine {
+                line: i + 1,
+                reason,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
     Ok((strings, flags))
 }
 
+/// Reads the addresses of an exclusion list file, one address per line.
+/// Returns Err with the line number if a line isn't a base58 address.
+pub fn get_addresses_from_file(file_name: &str) -> Result<HashSet<Box<[u8]>>, BtcVanityError> {
+    get_addresses_from_reader(fs::File::open(file_name)?)
+}
+
+/// Same as `get_addresses_from_file` but reads the lines from any Read source.
+/// Empty lines are skipped.
+pub fn get_addresses_from_reader<R: Read>(
+    mut reader: R,
+) -> Result<HashSet<Box<[u8]>>, BtcVanityError> {
+    let mut data = String::new();
+    reader.read_to_string(&mut data)?;
+
+    let mut addresses = HashSet::new();
+    for (i, line) in data.lines().enumerate() {
+        let address = line.trim();
+        if address.is_empty() {
+            continue;
+        }
+        if !address.chars().all(|ch| BASE58_ALPHABET.contains(ch)) {
+            return Err(BtcVanityError::InvalidExcludedAddress { line: i + 1 });
+        }
+        addresses.insert(address.as_bytes().into());
+    }
+
+    Ok(addresses)
+}
+
+/// Follows the lines appended to an input file, used by --watch.
+/// Only the complete lines are read, a line without a newline at the end may still be written.
+pub struct InputFileWatcher {
+    file_name: String,
+    /// Length of the lines already read.
+    offset: u64,
+}
+
+impl InputFileWatcher {
+    /// Starts from the first line of the file.
+    pub fn new(file_name: &str) -> Self {
+        InputFileWatcher {
+            file_name: file_name.to_string(),
+            offset: 0,
+        }
+    }
+
+    /// Returns the complete lines appended to the file since the last call, or no lines if it
+    /// hasn't grown. A missing file has no lines yet, ex: while an editor replaces it.
+    /// If the file gets shorter, it is read from its first line again.
+    pub fn read_new_lines(&mut self) -> Result<Vec<String>, BtcVanityError> {
+        let mut file = match fs::File::open(&self.file_name) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let len = file.metadata()?.len();
+        if len < self.offset {
+            self.offset = 0;
+        }
+        if len == self.offset {
+            return Ok(Vec::new());
+        }
+
+        let mut data = Vec::new();
+        file.seek(SeekFrom::Start(self.offset))?;
+        file.take(len - self.offset).read_to_end(&mut data)?;
+        let Some(end) = data.iter().rposition(|&byte| byte == b'\n') else {
+            return Ok(Vec::new());
+        };
+        self.offset += end as u64 + 1;
+
+        Ok(String::from_utf8_lossy(&data[..end])
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+            .collect())
+    }
+}
+
+/// Gets all strings and the flags from a TOML input file. Every string is an `[[entry]]` table
+/// and only its pattern is required, the missing keys are taken from the cli flags.
+///
+/// Example inputs.toml
+/// ```toml
+/// [[entry]]
+/// pattern = "Emiv"
+/// mode = "prefix"
+/// case_sensitive = true
+///
+/// [[entry]]
+/// pattern = "TALA"
+/// chain = "bitcoin"
+/// mode = "anywhere"
+/// count = 3
+/// threads = 4
+/// output_file = "tala.txt"
+/// ```
+pub fn get_strings_and_flags_from_toml<R: Read>(
+    mut reader: R,
+) -> Result<(Vec<String>, Vec<FileFlags>), BtcVanityError> {
+    let mut data = String::new();
+    reader.read_to_string(&mut data)?;
+    let mut table =
+        data.parse::<toml::Table>()
+            .map_err(|err| BtcVanityError::InvalidInputFile {
+                reason: err.to_string(),
+            })?;
+
+    let entries = match table.remove("entry") {
+        Some(toml::Value::Array(entries)) => entries,
+        Some(_) => {
+            return Err(BtcVanityError::InvalidInputFile {
+                reason: String::from("'entry' must be an array of tables, ex: [[entry]]"),
+            })
+        }
+        None => Vec::new(),
+    };
+    if let Some(key) = table.keys().next() {
+        return Err(BtcVanityError::InvalidInputFile {
+            reason: format!(
+                "unknown key '{}', every string must be in an [[entry]] table",
+                key
+            ),
+        });
+    }
+
+    let mut strings = Vec::with_capacity(entries.len());
+    let mut flags = Vec::with_capacity(entries.len());
+    for (i, entry) in entries.iter().enumerate() {
+        let (string, entry_flags) = entry
+            .as_table()
+            .ok_or_else(|| String::from("entry must be a table"))
+            .and_then(get_toml_entry)
+            .map_err(|reason| BtcVanityError::InvalidInputEntry {
+                index: i + 1,
+                reason,
+            })?;
+        strings.push(string);
+        flags.push(entry_flags);
+    }
+
+    Ok((strings, flags))
+}
+
+/// Gets the pattern and the flags of an `[[entry]]` table.
+/// Returns Err with the reason if a key is unknown or its value is invalid.
+fn get_toml_entry(entry: &toml::Table) -> Result<(String, FileFlags), String> {
+    let mut pattern = None;
+    let mut flags = FileFlags::use_cli_flags();
+    for (key, value) in entry {
+        match key.as_str() {
+            "pattern" => pattern = Some(get_toml_str(key, value)?.to_string()),
+            "chain" => {
+                let chain = get_toml_str(key, value)?;
+                if !supported_chains().iter().any(|info| info.name == chain) {
+                    return Err(format!("'{}' is not a supported chain", chain));
+                }
+            }
+            "mode" => {
+                flags.vanity_mode = Some(match get_toml_str(key, value)? {
+                    "prefix" => VanityMode::Prefix,
+                    "suffix" => VanityMode::Suffix,
+                    "anywhere" => VanityMode::Anywhere,
+                    mode => {
+                        return Err(format!(
+                            "'{}' is not a mode, use prefix, suffix or anywhere",
+                            mode
+                        ))
+                    }
+                })
+            }
+            "case_sensitive" => flags.is_case_sensitive = get_toml_bool(key, value)?,
+            "disable_fast" => flags.disable_fast_mode = get_toml_bool(key, value)?,
+            "force_flags" => flags.force_flags = get_toml_bool(key, value)?,
+            "count" => match get_toml_u64(key, value)? {
+                0 => return Err(String::from("'count' must be greater than 0")),
+                count => flags.count = Some(count),
+            },
+            "threads" => match get_toml_u64(key, value)? {
+                threads if threads > MAX_THREADS => {
+                    return Err(format!("'threads' must be at most {}", MAX_THREADS))
+                }
+                threads => flags.threads = Some(threads),
+            },
+            "output_file" => flags.output_file_name = Some(get_toml_str(key, value)?.to_string()),
+            _ => return Err(format!("unknown key '{}'", key)),
+        }
+    }
+
+    match pattern {
+        Some(pattern) => Ok((pattern, flags)),
+        None => Err(String::from("'pattern' is missing")),
+    }
+}
+
+fn get_toml_str<'a>(key: &str, value: &'a toml::Value) -> Result<&'a str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("'{}' must be a string", key))
+}
+
+fn get_toml_bool(key: &str, value: &toml::Value) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| format!("'{}' must be true or false", key))
+}
+
+fn get_toml_u64(key: &str, value: &toml::Value) -> Result<u64, String> {
+    value
+        .as_integer()
+        .and_then(|number| u64::try_from(number).ok())
+        .ok_or_else(|| format!("'{}' must be a non-negative integer", key))
+}
+
+/// A string of the run saved to the --checkpoint file after it is completed.
+#[derive(Debug, PartialEq)]
+pub struct CheckpointRecord {
+    /// Line number of the string in the input file, starting from 1.
+    pub line: usize,
+    /// Hash of the string and its flags, so an edited line isn't skipped.
+    pub hash: u64,
+    pub status: String,
+    /// Where the result is written to.
+    pub output: String,
+}
+
+impl CheckpointRecord {
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{:016x}\t{}\t{}\n",
+            self.line,
+            self.hash,
+            self.status,
+            self.output.replace(['\t', '\n'], " ")
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let record = CheckpointRecord {
+            line: fields.next()?.parse().ok()?,
+            hash: u64::from_str_radix(fields.next()?, 16).ok()?,
+            status: fields.next()?.to_string(),
+            output: fields.next()?.to_string(),
+        };
+        match fields.next() {
+            None => Some(record),
+            Some(_) => None,
+        }
+    }
+}
+
+/// Returns the 64 bit FNV-1a hash of the text. Unlike the std hashers it is stable
+/// between builds, so checkpoint files can be read by any version.
+pub fn get_checkpoint_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Reads all the records of the checkpoint file. Returns no records if the file doesn't exist.
+/// Only the lines ending with a newline are complete, so a line cut while writing is ignored.
+pub fn read_checkpoint(file_name: &str) -> Result<Vec<CheckpointRecord>, BtcVanityError> {
+    let data = match fs::read_to_string(file_name) {
+        Ok(data) => data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+
+    Ok(data
+        .split_inclusive('\n')
+        .filter_map(|line| line.strip_suffix('\n'))
+        .filter_map(CheckpointRecord::from_line)
+        .collect())
+}
+
+/// Appends the record to the checkpoint file. If the last record was cut while writing,
+/// the new record starts from a new line.
+pub fn append_checkpoint(file_name: &str, record: &CheckpointRecord) -> Result<(), BtcVanityError> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(file_name)?;
+
+    if !ends_with_newline(&mut file)? {
+        file.write_all(b"\n")?;
+    }
+    file.write_all(record.to_line().as_bytes())?;
+    Ok(())
+}
+
+/// Returns true if the file is empty or its last byte is a newline.
+fn ends_with_newline(file: &mut fs::File) -> io::Result<bool> {
+    let mut last_byte = [b'\n'];
+    if file.metadata()?.len() > 0 {
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last_byte)?;
+    }
+    Ok(last_byte[0] == b'\n')
+}
+
+/// Replaces every character that isn't safe in a file name with '_'.
+/// Only ASCII letters, digits, '-' and '_' are kept.
+pub fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect()
+}
+
+/// Expands the {chain}, {pattern} and {n} placeholders of the --name-template.
+/// The pattern is sanitized so it can be used in a file name.
+pub fn expand_name_template(template: &str, chain: &str, pattern: &str, n: u64) -> String {
+    template
+        .replace("{chain}", chain)
+        .replace("{pattern}", &sanitize_file_name(pattern))
+        .replace("{n}", &n.to_string())
+}
+
+/// Creates the output directory if it doesn't exist and returns the path of file_name in it.
+/// If the file already exists a numeric suffix is added, ex: wallet.txt -> wallet-1.txt
+pub fn get_unique_output_path(
+    output_dir: &Path,
+    file_name: &str,
+) -> Result<PathBuf, BtcVanityError> {
+    fs::create_dir_all(output_dir)?;
+
+    let file_path = Path::new(file_name);
+    let stem = file_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = file_path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut path = output_dir.join(file_name);
+    let mut suffix = 1;
+    while path.exists() {
+        path = output_dir.join(format!("{}-{}{}", stem, suffix, extension));
+        suffix += 1;
+    }
+    Ok(path)
+}
+
+/// How write_output_file writes the buffer to the output file.
+pub enum OutputWriter {
+    /// Writes the buffer as it is.
+    Plain,
+    /// Writes the buffer as an encrypted line, see the `encryption` module.
+    Encrypted(Box<OutputCipher>),
+}
+
+impl OutputWriter {
+    /// Returns the buffer as it is written to the file.
+    pub fn encode(&self, buffer: &str) -> Result<String, BtcVanityError> {
+        match self {
+            OutputWriter::Plain => Ok(buffer.to_string()),
+            OutputWriter::Encrypted(cipher) => cipher.encrypt(buffer),
+        }
+    }
+}
+
+/// How write_output_file writes the buffer to the output file.
+pub struct OutputFileOptions<'a> {
+    /// Empties the file before writing if true, otherwise the buffer is appended.
+    pub overwrite: bool,
+    pub writer: &'a OutputWriter,
+    /// The file is rotated before a write which would make it larger than this.
+    pub max_size: Option<u64>,
+}
+
 /// If file already exists appends else creates an output text file and writes all the found wallet details.
+/// If overwrite is set the file is emptied before writing.
+/// With `--format json` every wallet is written as a JSON object in its own line.
+/// With `--encrypt-output` every write is an encrypted line, which can be read back with `decrypt-output`.
+/// With `--max-output-size` the file is rotated before a write which would make it larger than max_size,
+/// see rotate_output_file. A buffer is never split across files.
 ///
 /// Example output.txt
 /// ```txt
-/// Key pair which their address has the prefix: 'Emiv' (case sensitive)
-///
+/// [2024-01-31T12:00:00Z] chain: bitcoin, pattern: 'Emiv', mode: prefix, case sensitive: true, btc-vanity: 1.3.1
+/// private_key (hex): D6DA88575E9D2F81BB747EAA4C52AA1AF6F2FCD9790BF868D0B4F6708310795E
 /// private_key (wif): L4RMjXo3AWzBuJTv98ZPoLtPtPP71aLwG7xV5pXodxGzWNZmK6Db
 /// public_key (compressed): 03a80e3296e19ffd656210aafe1bc2acb5a41c6a9b9361631c68fb7c9dbd416563
 /// address (compressed): 1Emiv6UxeRbAchqLvLEyVXDeL8UkrEUpzd
-///
-/// Key pair which their address has the string: 'TALA' (case sensitivity disabled)
-///
+/// ----------------------------------------
+/// [2024-01-31T12:00:05Z] chain: bitcoin, pattern: 'TALA', mode: anywhere, case sensitive: false, btc-vanity: 1.3.1
+/// private_key (hex): 5D701ACE2B20C1D2108936AD0BB2A8D72EE03109E7CA116769A206465E9AC895
 /// private_key (wif): KzMLndRF3EjgLPnuYsQC31sgcvJKkqX2XoebovRPjdYAp5rYPhHm
 /// public_key (compressed): 02dc0eaebe451bc868ac0a7806f1ccde356c9a0e296217b684ba9095d9a41cb36a
 /// address (compressed): 1G9rGeY13XZoa8CjK3BhaEtqotaLasmbB7
-///
-/// Key pair which their address has the string: '3169' (case sensitivity disabled)
-///
-/// private_key (wif): L44VHVPLhD19TDpVzY3oJU5TXANVNpjVzYSB89giP1mUdPy4WexG
-/// public_key (compressed): 0349604b1459052a9f0bd993c4b03af4faa8e8e08c733dac129c6bcc1cdbae6057
-/// address (compressed): 1PKFWNcazkSKymp3169CSGBsN8gbTGeJDm
-///
-/// Key pair which their address has the string: 'tala' (case sensitivity disabled)
-///
-/// Skipping because of error: Custom Error: Your input is not in base58. Don't include zero: '0', uppercase i: 'I', uppercase o: 'O', lowercase L: 'l', in your input!
+/// ----------------------------------------
+/// [2024-01-31T12:00:05Z] chain: bitcoin, pattern: 'tala', mode: anywhere, case sensitive: false, btc-vanity: 1.3.1
+/// Skipping because of error: Vanity address generator error: Your input is not in base58. 'l' at index 3 is not a base58 character.
+/// ----------------------------------------
 /// ```
-pub fn write_output_file(output_file_name: &String, buffer: &String) -> Result<(), BtcVanityError> {
-    let ofn_len = output_file_name.len();
-    if &output_file_name[ofn_len - 4..ofn_len] != ".txt" {
+pub fn write_output_file(
+    output_file_name: &str,
+    buffer: &str,
+    options: &OutputFileOptions,
+) -> Result<(), BtcVanityError> {
+    let overwrite = options.overwrite;
+    check_output_file_name(output_file_name)?;
+    let buffer = options.writer.encode(buffer)?;
+    if !overwrite {
+        let file_size = fs::metadata(output_file_name).map_or(0, |metadata| metadata.len());
+        if is_rotation_needed(file_size, buffer.len(), options.max_size) {
+            rotate_output_file(output_file_name)?;
+        }
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(!overwrite)
+        .truncate(overwrite)
+        .open(output_file_name)?;
+
+    file.write_all(buffer.as_bytes())?;
+    Ok(())
+}
+
+/// Returns true if writing len bytes would make the file larger than max_size.
+/// An empty file is never rotated, so a write larger than max_size gets a file of its own.
+fn is_rotation_needed(file_size: u64, len: usize, max_size: Option<u64>) -> bool {
+    max_size.is_some_and(|max_size| file_size > 0 && file_size + len as u64 > max_size)
+}
+
+/// Renames the output file to the first free `<name>.N`, starting from `<name>.1`,
+/// so the next write starts a new file. ex: wallets.jsonl -> wallets.jsonl.1, then wallets.jsonl.2
+fn rotate_output_file(output_file_name: &str) -> Result<(), BtcVanityError> {
+    let mut n = 1;
+    loop {
+        let rotated_file_name = format!("{}.{}", output_file_name, n);
+        if !Path::new(&rotated_file_name).exists() {
+            fs::rename(output_file_name, rotated_file_name)?;
+            return Ok(());
+        }
+        n += 1;
+    }
+}
+
+/// Returns Err if the output file isn't a text or a json file.
+fn check_output_file_name(output_file_name: &str) -> Result<(), BtcVanityError> {
+    let is_supported_file = Path::new(output_file_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| matches!(extension, "txt" | "json" | "jsonl"));
+    if !is_supported_file {
         return Err(BtcVanityError::FileError(io::Error::new(
             io::ErrorKind::InvalidInput,
-            "file must be a text file. ex: output.txt",
+            "file must be a text or a json file. ex: output.txt, output.jsonl",
         )));
     }
-    let file_result = OpenOptions::new().append(true).open(output_file_name);
-    let mut file = match file_result {
-        Ok(file) => file,
-        Err(_) => fs::File::create(output_file_name)?,
-    };
-
-    file.write_all(buffer.as_bytes())?;
     Ok(())
 }
+
+/// Appends lines, ex: the JSON objects of the found wallets, to a file which can be read
+/// while the run continues. Every line is written with a single write and synced to the disk
+/// before the next one, so a crash never corrupts the lines before it. A line is appended while
+/// `<name>.lock` is locked, so other btc-vanity processes can append to the same file. The lock
+/// file isn't rotated, so every writer locks the same file after a rotation.
+/// With max_size the file is rotated like in write_output_file.
+pub struct JsonlWriter {
+    lock_file: fs::File,
+    file_name: String,
+    max_size: Option<u64>,
+}
+
+impl JsonlWriter {
+    /// Opens the file to append to, or creates it if it doesn't exist.
+    pub fn open(file_name: &str, max_size: Option<u64>) -> Result<Self, BtcVanityError> {
+        check_output_file_name(file_name)?;
+        open_to_append(file_name)?;
+        let lock_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(format!("{}.lock", file_name))?;
+        Ok(JsonlWriter {
+            lock_file,
+            file_name: file_name.to_string(),
+            max_size,
+        })
+    }
+
+    /// Appends the line, with a newline if it doesn't end with one.
+    /// If the last line is cut by a crash, the line starts from a new line.
+    pub fn append(&mut self, line: &str) -> Result<(), BtcVanityError> {
+        self.lock_file.lock()?;
+        let written = self.append_locked(line);
+        self.lock_file.unlock()?;
+        written
+    }
+
+    fn append_locked(&mut self, line: &str) -> Result<(), BtcVanityError> {
+        // Another writer may have rotated the file since the last line, so the path is opened
+        // again instead of keeping the file open.
+        let mut file = open_to_append(&self.file_name)?;
+        // The line and its newlines.
+        let len = line.len() + 2;
+        if is_rotation_needed(file.metadata()?.len(), len, self.max_size) {
+            rotate_output_file(&self.file_name)?;
+            file = open_to_append(&self.file_name)?;
+        }
+
+        let mut buffer = String::with_capacity(len);
+        if !ends_with_newline(&mut file)? {
+            buffer.push('\n');
+        }
+        buffer.push_str(line);
+        if !line.ends_with('\n') {
+            buffer.push('\n');
+        }
+        file.write_all(buffer.as_bytes())?;
+        Ok(file.sync_data()?)
+    }
+}
+
+fn open_to_append(file_name: &str) -> io::Result<fs::File> {
+    OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_get_strings_and_flags_from_reader() {
+        let input = Cursor::new("Emiv -p -c\nTALA -a\n3169\n");
+        let (strings, flags) = get_strings_and_flags_from_reader(input).unwrap();
+
+        assert_eq!(strings, vec!["Emiv", "TALA", "3169"]);
+        assert!(flags[0].is_case_sensitive);
+        assert!(matches!(flags[0].vanity_mode, Some(VanityMode::Prefix)));
+        assert!(matches!(flags[1].vanity_mode, Some(VanityMode::Anywhere)));
+        assert!(flags[2].vanity_mode.is_none());
+    }
+
+    #[test]
+    fn test_get_flags_threads_and_count() {
+        let flags = get_flags("Emiv -t 2 -n 3").unwrap();
+        assert_eq!(flags.threads, Some(2));
+        assert_eq!(flags.count, Some(3));
+
+        let flags = get_flags("Emiv --threads 0 -p").unwrap();
+        assert_eq!(flags.threads, Some(0));
+        assert!(flags.count.is_none());
+
+        // A line with or without other flags leaves both to the cli flags.
+        for line in ["Emiv", "Emiv -c"] {
+            let flags = get_flags(line).unwrap();
+            assert!(flags.threads.is_none());
+            assert!(flags.count.is_none());
+        }
+
+        assert!(get_flags("Emiv -t").is_err());
+        assert!(get_flags("Emiv -t two").is_err());
+        assert!(get_flags("Emiv -t 1025").is_err());
+        assert!(get_flags("Emiv -n 0").is_err());
+    }
+
+    #[test]
+    fn test_get_flags_unknown_flags() {
+        let flags = get_flags("Emiv -p --sufix -o -x.txt -x  ").unwrap();
+        assert!(matches!(flags.vanity_mode, Some(VanityMode::Prefix)));
+        assert_eq!(flags.output_file_name.as_deref(), Some("-x.txt"));
+        assert_eq!(flags.unknown_flags, vec!["--sufix", "-x"]);
+
+        assert!(get_flags("Emiv -s -c -n 2")
+            .unwrap()
+            .unknown_flags
+            .is_empty());
+    }
+
+    #[test]
+    fn test_suggest_line_flag() {
+        assert_eq!(suggest_line_flag("--sufix"), Some("--suffix"));
+        assert_eq!(
+            suggest_line_flag("--case-sensitve"),
+            Some("--case-sensitive")
+        );
+        assert_eq!(suggest_line_flag("--disable-fast-mode"), None);
+        assert_eq!(suggest_line_flag("-x"), None);
+        assert_eq!(get_edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_invalid_line_names_its_number() {
+        let input = Cursor::new("Emiv\nTALA -n x\n");
+        let err = get_strings_and_flags_from_reader(input).unwrap_err();
+
+        assert!(matches!(
+            err,
+            BtcVanityError::InvalidInputLine { line: 2, .. }
+        ));
+        assert!(err
+            .to_string()
+            .contains("line 2: -n must be followed by a number"));
+    }
+
+    #[test]
+    fn test_get_strings_and_flags_from_toml() {
+        let input = Cursor::new(
+            r#"
+            [[entry]]
+            pattern = "Emiv"
+
+            [[entry]]
+            pattern = "with space"
+            chain = "bitcoin"
+            mode = "suffix"
+            case_sensitive = true
+            count = 3
+            threads = 2
+            output_file = "tala.txt"
+            "#,
+        );
+        let (strings, flags) = get_strings_and_flags_from_toml(input).unwrap();
+
+        assert_eq!(strings, vec!["Emiv", "with space"]);
+        assert!(!flags[0].is_case_sensitive);
+        assert!(flags[0].vanity_mode.is_none());
+        assert!(flags[0].threads.is_none());
+        assert!(flags[0].count.is_none());
+        assert!(flags[0].output_file_name.is_none());
+        assert!(flags[1].is_case_sensitive);
+        assert!(matches!(flags[1].vanity_mode, Some(VanityMode::Suffix)));
+        assert_eq!(flags[1].threads, Some(2));
+        assert_eq!(flags[1].count, Some(3));
+        assert_eq!(flags[1].output_file_name.as_deref(), Some("tala.txt"));
+    }
+
+    #[test]
+    fn test_invalid_toml_entry_names_its_index() {
+        let input = Cursor::new(
+            r#"
+            [[entry]]
+            pattern = "Emiv"
+
+            [[entry]]
+            pattern = "TALA"
+            mode = "middle"
+            "#,
+        );
+        let err = get_strings_and_flags_from_toml(input).unwrap_err();
+
+        assert!(matches!(
+            err,
+            BtcVanityError::InvalidInputEntry { index: 2, .. }
+        ));
+        assert!(err.to_string().contains("entry 2: 'middle' is not a mode"));
+
+        let input = Cursor::new("[[entry]]\nmode = \"prefix\"\n");
+        assert!(matches!(
+            get_strings_and_flags_from_toml(input),
+            Err(BtcVanityError::InvalidInputEntry { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_input_format_from_file_name() {
+        assert_eq!(
+            InputFormat::from_file_name("inputs.toml"),
+            InputFormat::Toml
+        );
+        assert_eq!(
+            InputFormat::from_file_name("inputs.txt"),
+            InputFormat::Lines
+        );
+        assert_eq!(InputFormat::from_file_name("-"), InputFormat::Lines);
+    }
+
+    fn checkpoint_record(line: usize, status: &str) -> CheckpointRecord {
+        CheckpointRecord {
+            line,
+            hash: get_checkpoint_hash(&line.to_string()),
+            status: status.to_string(),
+            output: String::from("wallets.txt"),
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_after_interrupted_run() {
+        let file_name = std::env::temp_dir()
+            .join(format!("btc-vanity-checkpoint-{}.tsv", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let _ = fs::remove_file(&file_name);
+
+        assert!(read_checkpoint(&file_name).unwrap().is_empty());
+        append_checkpoint(&file_name, &checkpoint_record(1, "found")).unwrap();
+        append_checkpoint(&file_name, &checkpoint_record(2, "not-found")).unwrap();
+
+        // The run is killed while writing the third record.
+        let mut file = OpenOptions::new().append(true).open(&file_name).unwrap();
+        file.write_all(b"3\tcbf29ce4").unwrap();
+        assert_eq!(
+            read_checkpoint(&file_name).unwrap(),
+            vec![
+                checkpoint_record(1, "found"),
+                checkpoint_record(2, "not-found")
+            ]
+        );
+
+        // The resumed run appends after the cut line.
+        append_checkpoint(&file_name, &checkpoint_record(3, "found")).unwrap();
+        assert_eq!(
+            read_checkpoint(&file_name).unwrap(),
+            vec![
+                checkpoint_record(1, "found"),
+                checkpoint_record(2, "not-found"),
+                checkpoint_record(3, "found")
+            ]
+        );
+
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[test]
+    fn test_get_checkpoint_hash() {
+        assert_eq!(get_checkpoint_hash(""), 0xcbf29ce484222325);
+        assert_eq!(get_checkpoint_hash("a"), 0xaf63dc4c8601ec8c);
+        assert_ne!(get_checkpoint_hash("Emiv"), get_checkpoint_hash("Emiv "));
+    }
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(sanitize_file_name("Emiv"), "Emiv");
+        assert_eq!(sanitize_file_name("^1a.*[bc]+$"), "_1a___bc___");
+        assert_eq!(sanitize_file_name("../a/b\\c"), "___a_b_c");
+    }
+
+    #[test]
+    fn test_expand_name_template() {
+        assert_eq!(
+            expand_name_template("{chain}-{pattern}-{n}.txt", "bitcoin", "Emiv", 2),
+            "bitcoin-Emiv-2.txt"
+        );
+        assert_eq!(
+            expand_name_template("{pattern}.json", "bitcoin", "(ab|cd)?", 1),
+            "_ab_cd__.json"
+        );
+    }
+
+    #[test]
+    fn test_write_encrypted_output_file() {
+        let output_file = std::env::temp_dir()
+            .join(format!("btc-vanity-encrypted-{}.txt", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let writer = OutputWriter::Encrypted(Box::new(OutputCipher::new("passphrase").unwrap()));
+
+        let first = String::from("first wallet\n");
+        let second = String::from("second wallet\n");
+        let options = |overwrite| OutputFileOptions {
+            overwrite,
+            writer: &writer,
+            max_size: None,
+        };
+        write_output_file(&output_file, &first, &options(true)).unwrap();
+        write_output_file(&output_file, &second, &options(false)).unwrap();
+
+        let data = fs::read_to_string(&output_file).unwrap();
+        fs::remove_file(&output_file).unwrap();
+        assert!(!data.contains("wallet"));
+        assert_eq!(
+            crate::encryption::decrypt_output(&data, "passphrase").unwrap(),
+            "first wallet\nsecond wallet\n"
+        );
+    }
+
+    #[test]
+    fn test_jsonl_writer_interleaved_writers() {
+        let output_file = std::env::temp_dir()
+            .join(format!("btc-vanity-jsonl-{}.jsonl", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let _ = fs::remove_file(&output_file);
+
+        // Long lines make it likely that unlocked writes would interleave.
+        let padding = "x".repeat(16 * 1024);
+        std::thread::scope(|scope| {
+            for writer in 0..8 {
+                let (output_file, padding) = (&output_file, &padding);
+                scope.spawn(move || {
+                    let mut jsonl_writer = JsonlWriter::open(output_file, None).unwrap();
+                    for n in 0..25 {
+                        let line = format!(
+                            "{{\"writer\":{},\"n\":{},\"padding\":\"{}\"}}",
+                            writer, n, padding
+                        );
+                        jsonl_writer.append(&line).unwrap();
+                    }
+                });
+            }
+        });
+
+        let data = fs::read_to_string(&output_file).unwrap();
+        fs::remove_file(&output_file).unwrap();
+        fs::remove_file(format!("{}.lock", output_file)).unwrap();
+        assert_eq!(data.lines().count(), 8 * 25);
+        for line in data.lines() {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(value["padding"], padding.as_str());
+        }
+    }
+
+    #[test]
+    fn test_jsonl_writer_after_crash() {
+        let output_file = std::env::temp_dir()
+            .join(format!(
+                "btc-vanity-jsonl-crash-{}.jsonl",
+                std::process::id()
+            ))
+            .to_string_lossy()
+            .into_owned();
+        let _ = fs::remove_file(&output_file);
+
+        let mut jsonl_writer = JsonlWriter::open(&output_file, None).unwrap();
+        jsonl_writer.append("{\"n\":1}").unwrap();
+        // The run is killed while writing the second line.
+        let mut file = OpenOptions::new().append(true).open(&output_file).unwrap();
+        file.write_all(b"{\"n\":").unwrap();
+        jsonl_writer.append("{\"n\":3}\n").unwrap();
+
+        let data = fs::read_to_string(&output_file).unwrap();
+        fs::remove_file(&output_file).unwrap();
+        fs::remove_file(format!("{}.lock", output_file)).unwrap();
+        assert_eq!(data, "{\"n\":1}\n{\"n\":\n{\"n\":3}\n");
+    }
+
+    #[test]
+    fn test_write_output_file_rotation() {
+        let output_file = std::env::temp_dir()
+            .join(format!("btc-vanity-rotation-{}.txt", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let rotated_file = format!("{}.1", output_file);
+        let _ = fs::remove_file(&rotated_file);
+
+        let first = String::from("private_key (wif): first\naddress (compressed): first\n\n");
+        let second = String::from("private_key (wif): second\naddress (compressed): second\n\n");
+        let options = |overwrite| OutputFileOptions {
+            overwrite,
+            writer: &OutputWriter::Plain,
+            max_size: Some(64),
+        };
+        write_output_file(&output_file, &first, &options(true)).unwrap();
+        write_output_file(&output_file, &second, &options(false)).unwrap();
+
+        let rotated = fs::read_to_string(&rotated_file).unwrap();
+        let data = fs::read_to_string(&output_file).unwrap();
+        fs::remove_file(&rotated_file).unwrap();
+        fs::remove_file(&output_file).unwrap();
+        assert_eq!(rotated, first);
+        assert_eq!(data, second);
+    }
+
+    #[test]
+    fn test_jsonl_writer_rotation() {
+        let output_file = std::env::temp_dir()
+            .join(format!(
+                "btc-vanity-jsonl-rotation-{}.jsonl",
+                std::process::id()
+            ))
+            .to_string_lossy()
+            .into_owned();
+        let rotated_files = [format!("{}.1", output_file), format!("{}.2", output_file)];
+        for file_name in rotated_files.iter().chain([&output_file]) {
+            let _ = fs::remove_file(file_name);
+        }
+
+        // Two lines fit in a file.
+        let mut jsonl_writer = JsonlWriter::open(&output_file, Some(60)).unwrap();
+        for n in 0..5 {
+            jsonl_writer
+                .append(&format!("{{\"n\":{},\"address\":\"1Emiv\"}}", n))
+                .unwrap();
+        }
+
+        let mut lines = Vec::new();
+        for file_name in rotated_files.iter().chain([&output_file]) {
+            let data = fs::read_to_string(file_name).unwrap();
+            fs::remove_file(file_name).unwrap();
+            assert!(data.len() as u64 <= 60);
+            for line in data.lines() {
+                let value: serde_json::Value = serde_json::from_str(line).unwrap();
+                lines.push(value["n"].as_u64().unwrap());
+            }
+        }
+        fs::remove_file(format!("{}.lock", output_file)).unwrap();
+        assert_eq!(lines, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_jsonl_writer_rotation_with_two_writers() {
+        let output_file = std::env::temp_dir()
+            .join(format!(
+                "btc-vanity-jsonl-rotation-writers-{}.jsonl",
+                std::process::id()
+            ))
+            .to_string_lossy()
+            .into_owned();
+        let rotated_file = |n| format!("{}.{}", output_file, n);
+        let _ = fs::remove_file(&output_file);
+        for n in 1..=20 {
+            let _ = fs::remove_file(rotated_file(n));
+        }
+
+        // Two lines fit in a file, so the writers keep rotating the file the other one appends to.
+        let mut jsonl_writers = [
+            JsonlWriter::open(&output_file, Some(60)).unwrap(),
+            JsonlWriter::open(&output_file, Some(60)).unwrap(),
+        ];
+        for n in 0..20 {
+            jsonl_writers[n % 2]
+                .append(&format!("{{\"writer\":{},\"n\":{}}}", n % 2, n))
+                .unwrap();
+        }
+
+        let mut file_names = (1..)
+            .map(rotated_file)
+            .take_while(|file_name| Path::new(file_name).exists())
+            .collect::<Vec<_>>();
+        file_names.push(output_file.clone());
+        // The rotated files are older than the current one, so the lines are in the append order.
+        let mut lines = Vec::new();
+        for file_name in &file_names {
+            let data = fs::read_to_string(file_name).unwrap();
+            fs::remove_file(file_name).unwrap();
+            assert!(data.len() as u64 <= 60);
+            for line in data.lines() {
+                let value: serde_json::Value = serde_json::from_str(line).unwrap();
+                lines.push(value["n"].as_u64().unwrap());
+            }
+        }
+        fs::remove_file(format!("{}.lock", output_file)).unwrap();
+        assert_eq!(lines, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_input_file_watcher() {
+        let input_file = std::env::temp_dir()
+            .join(format!("btc-vanity-watch-{}.txt", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let _ = fs::remove_file(&input_file);
+        let mut watcher = InputFileWatcher::new(&input_file);
+        assert!(watcher.read_new_lines().unwrap().is_empty());
+
+        fs::write(&input_file, "Emiv -p\r\n\nTA").unwrap();
+        assert_eq!(watcher.read_new_lines().unwrap(), vec!["Emiv -p", ""]);
+        assert!(watcher.read_new_lines().unwrap().is_empty());
+
+        let mut file = OpenOptions::new().append(true).open(&input_file).unwrap();
+        file.write_all(b"LA -a\n3169\n").unwrap();
+        assert_eq!(watcher.read_new_lines().unwrap(), vec!["TALA -a", "3169"]);
+
+        // A new file with the same name is read from its first line.
+        fs::write(&input_file, "abc\n").unwrap();
+        assert_eq!(watcher.read_new_lines().unwrap(), vec!["abc"]);
+        fs::remove_file(&input_file).unwrap();
+    }
+
+    #[test]
+    fn test_get_addresses_from_reader() {
+        let addresses = get_addresses_from_reader(Cursor::new(
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH\n\n  1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2 \n",
+        ))
+        .unwrap();
+        assert_eq!(addresses.len(), 2);
+        assert!(addresses.contains(b"1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2".as_slice()));
+
+        assert!(matches!(
+            get_addresses_from_reader(Cursor::new("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH\n0xabc\n")),
+            Err(BtcVanityError::InvalidExcludedAddress { line: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_get_unique_output_path() {
+        let output_dir = std::env::temp_dir()
+            .join(format!("btc-vanity-unique-{}", std::process::id()))
+            .join("wallets");
+
+        let first = get_unique_output_path(&output_dir, "wallet.txt").unwrap();
+        assert_eq!(first, output_dir.join("wallet.txt"));
+        fs::write(&first, "").unwrap();
+
+        let second = get_unique_output_path(&output_dir, "wallet.txt").unwrap();
+        assert_eq!(second, output_dir.join("wallet-1.txt"));
+        fs::write(&second, "").unwrap();
+
+        let third = get_unique_output_path(&output_dir, "wallet.txt").unwrap();
+        assert_eq!(third, output_dir.join("wallet-2.txt"));
+
+        fs::remove_dir_all(output_dir.parent().unwrap()).unwrap();
+    }
+}
@@ -3,19 +3,72 @@
 //! This module is used for getting flags and file names from the cli
 //! and change flags for each string iteration if any other flags set in input file.
 
-use crate::file::{get_strings_and_flags_from_file, FileFlags};
-use crate::vanity_addr_generator::VanityMode;
+use crate::chain::BITCOIN;
+use crate::cli::MAX_THREADS;
+use crate::error::BtcVanityError;
+use crate::file::{
+    get_addresses_from_file, get_flags, get_strings_and_flags_from_file,
+    get_strings_and_flags_from_reader, get_strings_and_flags_from_toml, suggest_line_flag,
+    FileFlags, InputFormat,
+};
+use crate::output::{OutputFormat, OutputTemplate, PORCELAIN_TEMPLATE};
+use crate::vanity_addr_generator::{SearchOptions, VanityMode};
 use clap::ArgMatches;
+use log::LevelFilter;
+use std::collections::HashSet;
+use std::env::{self, VarError};
+use std::fs;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 /// This struct is used to save the cli flags
 pub struct CliFlags {
     threads: u64,
+    count: u64,
+    timeout: Option<Duration>,
+    max_attempts: Option<u64>,
+    max_rate: Option<u64>,
+    seed: Option<u64>,
+    extra_entropy: Option<Vec<u8>>,
+    excluded_addresses: Option<Arc<HashSet<Box<[u8]>>>>,
     strings: Vec<String>,
     flags: Vec<FileFlags>,
+    watch_file: Option<String>,
+    is_ignore_unknown_flags: bool,
     force_flags: bool,
     is_case_sensitive: bool,
     is_fast_disabled: bool,
+    max_len: Option<usize>,
+    is_quiet: bool,
+    is_stats: bool,
+    is_no_secrets: bool,
+    is_porcelain: bool,
+    is_encrypt_output: bool,
+    is_no_color: bool,
+    is_estimate: bool,
+    is_recalibrate: bool,
+    is_dry_run: bool,
+    is_benchmark: bool,
+    is_list_chains: bool,
+    is_random: bool,
+    is_keep_leading_one: bool,
+    is_unique_matches: bool,
+    is_pin_threads: bool,
+    is_overwrite: bool,
+    is_no_dedup: bool,
+    is_yes: bool,
+    difficulty_warn: f64,
+    log_level: LevelFilter,
+    output_format: OutputFormat,
+    template: Option<OutputTemplate>,
     output_file_name: String,
+    max_output_size: Option<u64>,
+    output_dir: Option<String>,
+    checkpoint: Option<String>,
+    summary_file: Option<String>,
+    output_password_file: Option<String>,
+    name_template: String,
     vanity_mode: VanityMode,
 }
 
@@ -27,30 +80,415 @@ impl CliFlags {
     pub fn get_threads(&self) -> u64 {
         self.threads
     }
+
+    pub fn get_count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn get_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn get_max_attempts(&self) -> Option<u64> {
+        self.max_attempts
+    }
+
+    pub fn get_max_rate(&self) -> Option<u64> {
+        self.max_rate
+    }
+
+    pub fn get_seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn get_log_level(&self) -> LevelFilter {
+        self.log_level
+    }
+
+    pub fn get_extra_entropy(&self) -> Option<&[u8]> {
+        self.extra_entropy.as_deref()
+    }
+
+    /// The addresses of the --exclude-addresses file, shared by the searches of every string.
+    pub fn get_excluded_addresses(&self) -> Option<Arc<HashSet<Box<[u8]>>>> {
+        self.excluded_addresses.clone()
+    }
+
+    pub fn get_is_quiet(&self) -> bool {
+        self.is_quiet
+    }
+
+    pub fn get_is_stats(&self) -> bool {
+        self.is_stats
+    }
+
+    pub fn get_is_no_secrets(&self) -> bool {
+        self.is_no_secrets
+    }
+
+    pub fn get_is_porcelain(&self) -> bool {
+        self.is_porcelain
+    }
+
+    pub fn get_is_encrypt_output(&self) -> bool {
+        self.is_encrypt_output
+    }
+
+    pub fn get_output_password_file(&self) -> Option<&String> {
+        self.output_password_file.as_ref()
+    }
+
+    pub fn get_is_no_color(&self) -> bool {
+        self.is_no_color
+    }
+
+    pub fn get_is_estimate(&self) -> bool {
+        self.is_estimate
+    }
+
+    pub fn get_is_recalibrate(&self) -> bool {
+        self.is_recalibrate
+    }
+
+    pub fn get_is_dry_run(&self) -> bool {
+        self.is_dry_run
+    }
+
+    pub fn get_is_benchmark(&self) -> bool {
+        self.is_benchmark
+    }
+
+    pub fn get_is_list_chains(&self) -> bool {
+        self.is_list_chains
+    }
+
+    /// Random key pairs are generated for the only, empty string instead of searching.
+    pub fn get_is_random(&self) -> bool {
+        self.is_random
+    }
+
+    pub fn get_is_keep_leading_one(&self) -> bool {
+        self.is_keep_leading_one
+    }
+
+    /// With -n, every found wallet of a string has a different matched substring.
+    pub fn get_is_unique_matches(&self) -> bool {
+        self.is_unique_matches
+    }
+
+    pub fn get_is_pin_threads(&self) -> bool {
+        self.is_pin_threads
+    }
+
+    pub fn get_is_overwrite(&self) -> bool {
+        self.is_overwrite
+    }
+
+    pub fn get_is_no_dedup(&self) -> bool {
+        self.is_no_dedup
+    }
+
+    pub fn get_is_yes(&self) -> bool {
+        self.is_yes
+    }
+
+    pub fn get_difficulty_warn(&self) -> f64 {
+        self.difficulty_warn
+    }
+
+    pub fn get_output_format(&self) -> OutputFormat {
+        self.output_format
+    }
+
+    pub fn get_template(&self) -> Option<&OutputTemplate> {
+        self.template.as_ref()
+    }
+
+    pub fn get_output_file_name(&self) -> &String {
+        &self.output_file_name
+    }
+
+    pub fn get_max_output_size(&self) -> Option<u64> {
+        self.max_output_size
+    }
+
+    pub fn get_output_dir(&self) -> Option<&String> {
+        self.output_dir.as_ref()
+    }
+
+    pub fn get_checkpoint(&self) -> Option<&String> {
+        self.checkpoint.as_ref()
+    }
+
+    pub fn get_summary_file(&self) -> Option<&String> {
+        self.summary_file.as_ref()
+    }
+
+    pub fn get_name_template(&self) -> &String {
+        &self.name_template
+    }
+
+    /// Returns the input file followed with --watch.
+    pub fn get_watch_file(&self) -> Option<&String> {
+        self.watch_file.as_ref()
+    }
+
+    /// Adds a line appended to the input file while it is watched. The line is checked like the
+    /// lines of the input file at the start. An invalid line is still added with the cli flags,
+    /// so every string keeps its line number, and Err is returned to skip it.
+    pub fn push_input_line(&mut self, line: &str) -> Result<(), BtcVanityError> {
+        let line_number = self.strings.len() + 1;
+        let flags = get_flags(line)
+            .map_err(|reason| BtcVanityError::InvalidInputLine {
+                line: line_number,
+                reason,
+            })
+            .and_then(|flags| match flags.unknown_flags.first() {
+                Some(flag) if !self.is_ignore_unknown_flags => {
+                    Err(BtcVanityError::UnknownInputFlag {
+                        line: line_number,
+                        flag: flag.to_owned(),
+                        suggestion: suggest_line_flag(flag),
+                    })
+                }
+                _ => Ok(flags),
+            });
+
+        self.strings
+            .push(line.split(' ').next().unwrap_or_default().to_string());
+        match flags {
+            Ok(flags) => {
+                self.flags.push(flags);
+                Ok(())
+            }
+            Err(err) => {
+                self.flags.push(FileFlags::use_cli_flags());
+                Err(err)
+            }
+        }
+    }
+
+    /// Returns the line number and the token of every unknown flag in the input file.
+    pub fn get_unknown_input_flags(&self) -> Vec<(usize, &String)> {
+        self.flags
+            .iter()
+            .enumerate()
+            .flat_map(|(i, flags)| flags.unknown_flags.iter().map(move |flag| (i + 1, flag)))
+            .collect()
+    }
+}
+
+/// Returns the given thread count, or the number of CPU cores if it is 0 or not given.
+pub fn resolve_threads(threads: Option<u64>) -> u64 {
+    match threads {
+        Some(threads) if threads > 0 => threads,
+        _ => thread::available_parallelism().map_or(1, |cores| cores.get() as u64),
+    }
 }
 
-/// Gets all the set flags, file names from cli and returns them with CliFlags struct
-pub fn get_cli_flags(matches: ArgMatches) -> CliFlags {
-    let threads = matches
-        .get_one::<String>("threads")
-        .expect("This was unexpected :(. Something went wrong while getting -t or --threads arg")
+/// Returns the value of the environment variable or None if it is not set.
+/// Returns Err naming the variable if it isn't valid unicode.
+fn get_env_var(name: &'static str) -> Result<Option<String>, BtcVanityError> {
+    match env::var(name) {
+        Ok(value) => Ok(Some(value)),
+        Err(VarError::NotPresent) => Ok(None),
+        Err(VarError::NotUnicode(value)) => Err(BtcVanityError::InvalidEnvVar {
+            name,
+            value: value.to_string_lossy().into_owned(),
+        }),
+    }
+}
+
+/// Gets the thread count from BTC_VANITY_THREADS. It must be a number between 0 and MAX_THREADS.
+fn get_env_threads() -> Result<Option<u64>, BtcVanityError> {
+    let name = "BTC_VANITY_THREADS";
+    get_env_var(name)?
+        .map(|value| match value.trim().parse::<u64>() {
+            Ok(threads) if threads <= MAX_THREADS => Ok(threads),
+            _ => Err(BtcVanityError::InvalidEnvVar { name, value }),
+        })
+        .transpose()
+}
+
+/// Gets all the set flags, file names from cli and returns them with CliFlags struct.
+/// Flags which are not set in cli are taken from their BTC_VANITY_* environment variables if set.
+pub fn get_cli_flags(matches: ArgMatches) -> Result<CliFlags, BtcVanityError> {
+    let threads = match matches.get_one::<u64>("threads") {
+        Some(&threads) => threads,
+        None => get_env_threads()?.unwrap_or(0),
+    };
+    let threads = resolve_threads(Some(threads));
+    let count = matches
+        .get_one::<String>("count")
+        .expect("This was unexpected :(. Something went wrong while getting -n or --count arg")
         .trim()
         .parse::<u64>()
-        .expect("Threads must be a number!");
+        .expect("Count must be a number!");
+    let timeout = matches.get_one::<String>("timeout").map(|timeout| {
+        parse_duration(timeout).expect("Timeout must be a duration like 30s, 10m or 2h!")
+    });
+    let max_attempts = matches
+        .get_one::<String>("max-attempts")
+        .map(|max_attempts| {
+            max_attempts
+                .trim()
+                .parse::<u64>()
+                .expect("Max attempts must be a number!")
+        });
+    let max_rate = matches.get_one::<String>("max-rate").map(|max_rate| {
+        max_rate
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .filter(|&max_rate| max_rate > 0)
+            .expect("Max rate must be a number greater than 0!")
+    });
+    let seed = matches
+        .get_one::<String>("seed")
+        .map(|seed| seed.trim().parse::<u64>().expect("Seed must be a number!"));
+    let extra_entropy = matches
+        .get_one::<String>("entropy-file")
+        .map(fs::read)
+        .transpose()?;
+    let excluded_addresses = matches
+        .get_one::<String>("exclude-addresses")
+        .map(|file_name| get_addresses_from_file(file_name).map(Arc::new))
+        .transpose()?;
+    let cli_is_watch = matches.get_flag("watch");
+    let mut cli_watch_file = None;
     let (strings, flags_vec) = match matches.get_one::<String>("string") {
         Some(string) => (vec![string.to_owned()], vec![FileFlags::use_cli_flags()]),
-        None => {
-            let file_name = matches.get_one::<String>("input-file").unwrap();
-            get_strings_and_flags_from_file(file_name).unwrap()
-        }
+        None => match matches.get_one::<String>("input-file") {
+            Some(file_name) => {
+                let input_format = match matches.get_one::<String>("input-format") {
+                    Some(input_format) if input_format == "toml" => InputFormat::Toml,
+                    Some(_) => InputFormat::Lines,
+                    None => InputFormat::from_file_name(file_name),
+                };
+                match (file_name.as_str(), input_format) {
+                    // With --watch the lines are added while searching, see push_input_line.
+                    ("-", _) | (_, InputFormat::Toml) if cli_is_watch => {
+                        return Err(BtcVanityError::WatchUnsupportedInput);
+                    }
+                    _ if cli_is_watch => {
+                        cli_watch_file = Some(file_name.to_string());
+                        (Vec::new(), Vec::new())
+                    }
+                    // "-" reads the strings from stdin, ex: cat inputs.txt | btc-vanity -i -
+                    ("-", InputFormat::Lines) => {
+                        get_strings_and_flags_from_reader(std::io::stdin().lock())?
+                    }
+                    ("-", InputFormat::Toml) => {
+                        get_strings_and_flags_from_toml(std::io::stdin().lock())?
+                    }
+                    (_, InputFormat::Lines) => get_strings_and_flags_from_file(file_name)?,
+                    (_, InputFormat::Toml) => {
+                        get_strings_and_flags_from_toml(fs::File::open(file_name)?)?
+                    }
+                }
+            }
+            // --random runs once with an empty string, which is never matched.
+            None if matches.get_flag("random") => {
+                (vec![String::new()], vec![FileFlags::use_cli_flags()])
+            }
+            // Only --benchmark and --list-chains run without any strings.
+            None => (Vec::new(), Vec::new()),
+        },
     };
 
+    // A typo in the flags of a line would silently search with the cli flags instead.
+    let cli_is_ignore_unknown_flags = matches.get_flag("ignore-unknown-flags");
+    if !cli_is_ignore_unknown_flags {
+        let unknown_flag = flags_vec
+            .iter()
+            .enumerate()
+            .find_map(|(i, flags)| flags.unknown_flags.first().map(|flag| (i + 1, flag)));
+        if let Some((line, flag)) = unknown_flag {
+            return Err(BtcVanityError::UnknownInputFlag {
+                line,
+                flag: flag.to_owned(),
+                suggestion: suggest_line_flag(flag),
+            });
+        }
+    }
+
     let cli_force_flags = matches.get_flag("force-flags");
-    let cli_is_case_sensitive = matches.get_flag("case-sensitive");
+    // Without -c the strings use the default case sensitivity of the chain.
+    let cli_is_case_sensitive =
+        matches.get_flag("case-sensitive") || BITCOIN.default_case_sensitive;
     let cli_is_fast_disabled = matches.get_flag("disable-fast-mode");
+    let cli_max_len = matches.get_one::<u64>("max-len").map(|&n| n as usize);
+    let cli_is_quiet = matches.get_flag("quiet");
+    let cli_is_stats = matches.get_flag("stats");
+    let cli_is_no_secrets = matches.get_flag("no-secrets");
+    let cli_is_porcelain = matches.get_flag("porcelain");
+    let cli_is_encrypt_output = matches.get_flag("encrypt-output");
+    let cli_is_no_color = matches.get_flag("no-color");
+    let cli_is_estimate = matches.get_flag("estimate");
+    let cli_is_recalibrate = matches.get_flag("recalibrate");
+    let cli_is_dry_run = matches.get_flag("dry-run");
+    let cli_is_benchmark = matches.get_flag("benchmark");
+    let cli_is_list_chains = matches.get_flag("list-chains");
+    let cli_is_random = matches.get_flag("random");
+    let cli_is_keep_leading_one = matches.get_flag("keep-leading-one");
+    let cli_is_unique_matches = matches.get_flag("unique-matches");
+    let cli_is_pin_threads = matches.get_flag("pin-threads");
+    let cli_is_overwrite = matches.get_flag("overwrite");
+    let cli_is_no_dedup = matches.get_flag("no-dedup");
+    let cli_is_yes = matches.get_flag("yes");
+    let cli_difficulty_warn = matches
+        .get_one::<f64>("difficulty-warn")
+        .copied()
+        .unwrap_or(f64::INFINITY);
+    let cli_log_level = matches
+        .get_one::<String>("log-level")
+        .and_then(|log_level| log_level.parse().ok())
+        .unwrap_or(LevelFilter::Off);
+    let cli_output_format = match matches.get_one::<String>("format").map(String::as_str) {
+        Some("json") => OutputFormat::Json,
+        _ => OutputFormat::Text,
+    };
+    #[cfg(not(feature = "serde"))]
+    if cli_output_format == OutputFormat::Json {
+        return Err(BtcVanityError::SerdeDisabled {
+            flag: "--format json",
+        });
+    }
+    // --porcelain is a template with a fixed format.
+    let cli_template = match cli_is_porcelain {
+        true => Some(PORCELAIN_TEMPLATE),
+        false => matches.get_one::<String>("template").map(String::as_str),
+    }
+    .map(OutputTemplate::parse)
+    .transpose()?;
     let cli_output_file_name = match matches.get_one::<String>("output-file") {
         Some(output_file_name) => output_file_name.to_string(),
-        None => String::from(""),
+        None => get_env_var("BTC_VANITY_OUTPUT_FILE")?.unwrap_or_default(),
+    };
+    let cli_output_dir = matches.get_one::<String>("output-dir").cloned();
+    // The private keys must be written somewhere if they aren't printed.
+    if cli_is_no_secrets && cli_output_file_name.is_empty() && cli_output_dir.is_none() {
+        return Err(BtcVanityError::NoSecretsWithoutOutput);
+    }
+    if cli_is_encrypt_output && cli_output_file_name.is_empty() && cli_output_dir.is_none() {
+        return Err(BtcVanityError::EncryptWithoutOutput);
+    }
+    let cli_output_password_file = matches.get_one::<String>("output-password-file").cloned();
+    let cli_max_output_size = matches.get_one::<u64>("max-output-size").copied();
+    let cli_checkpoint = matches.get_one::<String>("checkpoint").cloned();
+    let cli_summary_file = matches.get_one::<String>("summary-file").cloned();
+    #[cfg(not(feature = "serde"))]
+    if cli_summary_file.is_some() {
+        return Err(BtcVanityError::SerdeDisabled {
+            flag: "--summary-file",
+        });
+    }
+    let cli_name_template = match matches.get_one::<String>("name-template") {
+        Some(name_template) => name_template.to_string(),
+        None => match cli_output_format {
+            OutputFormat::Text => String::from("{chain}-{pattern}-{n}.txt"),
+            OutputFormat::Json => String::from("{chain}-{pattern}-{n}.json"),
+        },
     };
 
     // Sets vanity_mode for searching and mode to predefined decoration strings.
@@ -62,15 +500,77 @@ pub fn get_cli_flags(matches: ArgMatches) -> CliFlags {
         VanityMode::Prefix
     };
 
-    CliFlags {
+    Ok(CliFlags {
         threads,
+        count,
+        timeout,
+        max_attempts,
+        max_rate,
+        seed,
+        extra_entropy,
+        excluded_addresses,
         strings,
         flags: flags_vec,
+        watch_file: cli_watch_file,
+        is_ignore_unknown_flags: cli_is_ignore_unknown_flags,
         force_flags: cli_force_flags,
         is_case_sensitive: cli_is_case_sensitive,
         is_fast_disabled: cli_is_fast_disabled,
+        max_len: cli_max_len,
+        is_quiet: cli_is_quiet,
+        is_stats: cli_is_stats,
+        is_no_secrets: cli_is_no_secrets,
+        is_porcelain: cli_is_porcelain,
+        is_encrypt_output: cli_is_encrypt_output,
+        is_no_color: cli_is_no_color,
+        is_estimate: cli_is_estimate,
+        is_recalibrate: cli_is_recalibrate,
+        is_dry_run: cli_is_dry_run,
+        is_benchmark: cli_is_benchmark,
+        is_list_chains: cli_is_list_chains,
+        is_random: cli_is_random,
+        is_keep_leading_one: cli_is_keep_leading_one,
+        is_unique_matches: cli_is_unique_matches,
+        is_pin_threads: cli_is_pin_threads,
+        is_overwrite: cli_is_overwrite,
+        is_no_dedup: cli_is_no_dedup,
+        is_yes: cli_is_yes,
+        difficulty_warn: cli_difficulty_warn,
+        log_level: cli_log_level,
+        output_format: cli_output_format,
+        template: cli_template,
         output_file_name: cli_output_file_name,
+        max_output_size: cli_max_output_size,
+        output_dir: cli_output_dir,
+        checkpoint: cli_checkpoint,
+        summary_file: cli_summary_file,
+        output_password_file: cli_output_password_file,
+        name_template: cli_name_template,
         vanity_mode: cli_vanity_mode,
+    })
+}
+
+/// Parses a duration like "30s", "10m" or "2h". A number without a unit is taken as seconds.
+pub fn parse_duration(duration: &str) -> Option<Duration> {
+    let duration = duration.trim();
+    let (number, seconds_per_unit) = match duration.char_indices().last()? {
+        (i, 's') => (&duration[..i], 1),
+        (i, 'm') => (&duration[..i], 60),
+        (i, 'h') => (&duration[..i], 60 * 60),
+        _ => (duration, 1),
+    };
+    let number = number.parse::<u64>().ok()?;
+
+    Some(Duration::from_secs(number.checked_mul(seconds_per_unit)?))
+}
+
+/// Returns the longest string accepted: everything an address can match if fast mode is disabled,
+/// otherwise the --max-len override or the fast mode limit of the chain.
+fn resolve_max_pattern_len(is_fast_disabled: bool, max_len: Option<usize>) -> usize {
+    match (is_fast_disabled, max_len) {
+        (true, _) => BITCOIN.max_pattern_len(false),
+        (false, Some(max_len)) => max_len,
+        (false, None) => BITCOIN.max_pattern_len(true),
     }
 }
 
@@ -79,8 +579,11 @@ pub fn get_cli_flags(matches: ArgMatches) -> CliFlags {
 pub struct StringsFlags {
     is_case_sensitive: bool,
     is_fast_disabled: bool,
+    max_pattern_len: usize,
     output_file_name: String,
     vanity_mode: VanityMode,
+    threads: u64,
+    count: u64,
 }
 
 impl StringsFlags {
@@ -88,14 +591,20 @@ impl StringsFlags {
     fn from(
         is_case_sensitive: bool,
         is_fast_disabled: bool,
+        max_len: Option<usize>,
         output_file_name: String,
         vanity_mode: VanityMode,
+        threads: u64,
+        count: u64,
     ) -> Self {
         StringsFlags {
             is_case_sensitive,
             is_fast_disabled,
+            max_pattern_len: resolve_max_pattern_len(is_fast_disabled, max_len),
             output_file_name,
             vanity_mode,
+            threads,
+            count,
         }
     }
 
@@ -105,8 +614,11 @@ impl StringsFlags {
         StringsFlags {
             is_case_sensitive: cli_args.is_case_sensitive,
             is_fast_disabled: cli_args.is_fast_disabled,
+            max_pattern_len: resolve_max_pattern_len(cli_args.is_fast_disabled, cli_args.max_len),
             output_file_name: cli_args.output_file_name.to_string(),
             vanity_mode: cli_args.vanity_mode,
+            threads: cli_args.threads,
+            count: cli_args.count,
         }
     }
 
@@ -125,6 +637,33 @@ impl StringsFlags {
     pub fn get_is_fast_mode_disabled(&self) -> bool {
         self.is_fast_disabled
     }
+
+    /// Longest string accepted, see `resolve_max_pattern_len`.
+    pub fn get_max_pattern_len(&self) -> usize {
+        self.max_pattern_len
+    }
+
+    pub fn get_threads(&self) -> u64 {
+        self.threads
+    }
+
+    pub fn get_count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl From<&StringsFlags> for SearchOptions {
+    /// The search options of a string set by its flags, the others have their defaults.
+    fn from(string_flags: &StringsFlags) -> Self {
+        SearchOptions {
+            vanity_mode: string_flags.vanity_mode,
+            case_sensitive: string_flags.is_case_sensitive,
+            max_pattern_len: Some(string_flags.max_pattern_len),
+            count: string_flags.count,
+            threads: string_flags.threads,
+            ..SearchOptions::default()
+        }
+    }
 }
 
 /// Returns A StringFlags depending on string's flags that we get from the input file.
@@ -156,14 +695,80 @@ pub fn get_strings_flags(cli_args: &CliFlags, index: usize) -> StringsFlags {
             } else {
                 cli_args.is_fast_disabled || flags.disable_fast_mode
             };
+            // Thread count and count of the string are used if set, like its vanity mode
+            let string_threads = match flags.threads {
+                Some(threads) => resolve_threads(Some(threads)),
+                None => cli_args.threads,
+            };
+            let string_count = flags.count.unwrap_or(cli_args.count);
 
             // Construct and return the StringsArgs struct
             StringsFlags::from(
                 string_is_case_sensitive,
                 string_is_fast_disabled,
+                cli_args.max_len,
                 string_output_file_name.to_string(),
                 string_vanity_mode,
+                string_threads,
+                string_count,
             )
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("10m"), Some(Duration::from_secs(600)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("45"), Some(Duration::from_secs(45)));
+    }
+
+    #[test]
+    fn test_parse_invalid_duration() {
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("s"), None);
+        assert_eq!(parse_duration("ten minutes"), None);
+        assert_eq!(parse_duration("1d"), None);
+    }
+
+    #[test]
+    fn test_resolve_max_pattern_len() {
+        assert_eq!(resolve_max_pattern_len(false, None), 4);
+        assert_eq!(resolve_max_pattern_len(false, Some(6)), 6);
+        assert_eq!(resolve_max_pattern_len(true, None), 33);
+        assert_eq!(resolve_max_pattern_len(true, Some(6)), 33);
+    }
+
+    #[test]
+    fn test_resolve_threads() {
+        assert_eq!(resolve_threads(Some(8)), 8);
+        assert!(resolve_threads(Some(0)) > 0);
+        assert_eq!(resolve_threads(Some(0)), resolve_threads(None));
+    }
+
+    #[test]
+    fn test_search_options_from_strings_flags() {
+        let string_flags = StringsFlags::from(
+            true,
+            false,
+            Some(6),
+            String::new(),
+            VanityMode::Suffix,
+            3,
+            2,
+        );
+        let options = SearchOptions::from(&string_flags);
+
+        assert_eq!(options.vanity_mode, VanityMode::Suffix);
+        assert!(options.case_sensitive);
+        assert_eq!(options.max_pattern_len, Some(6));
+        assert_eq!(options.count, 2);
+        assert_eq!(options.threads, 3);
+        assert!(options.timeout.is_none());
+    }
+}
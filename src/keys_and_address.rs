@@ -20,12 +20,212 @@
 //!                 random_address.get_comp_address())
 //! ```
 
+use crate::error::BtcVanityError;
+use bitcoin::hashes::{sha256, Hash};
 use bitcoin::key::{PrivateKey, PublicKey};
-use bitcoin::secp256k1::{rand, All, Secp256k1};
-use bitcoin::Address;
+use bitcoin::secp256k1::constants::{GENERATOR_X, GENERATOR_Y};
+use bitcoin::secp256k1::rand::{Rng, RngCore, SeedableRng};
+use bitcoin::secp256k1::{self, rand, All, Scalar, Secp256k1, SecretKey};
 use bitcoin::Network::Bitcoin;
+use bitcoin::{Address, NetworkKind, PubkeyHash};
+use rand_chacha::ChaCha20Rng;
+use std::fmt::Write;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+/// Returns a deterministic random number generator for the given seed and search thread.
+/// Every thread gets its own ChaCha20 stream, so threads never generate the same keys.
+/// Keys generated with it are predictable and must never be used with real funds.
+pub fn seeded_rng(seed: u64, thread_index: u64) -> ChaCha20Rng {
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+    rng.set_stream(thread_index);
+    rng
+}
+
+/// A source of the secret material of the generated keys, ex: a hardware random number generator.
+pub trait KeySource {
+    /// Fills the buffer with 32 bytes of secret material.
+    fn fill_secret(&mut self, secret: &mut [u8; 32]);
+}
+
+impl<S: KeySource + ?Sized> KeySource for Box<S> {
+    fn fill_secret(&mut self, secret: &mut [u8; 32]) {
+        (**self).fill_secret(secret)
+    }
+}
+
+/// Uses a random number generator as a key source.
+/// `RngKeySource(rand::thread_rng())` is the default source, it is seeded from the OS.
+pub struct RngKeySource<R>(pub R);
+
+impl<R: RngCore> KeySource for RngKeySource<R> {
+    fn fill_secret(&mut self, secret: &mut [u8; 32]) {
+        self.0.fill_bytes(secret);
+    }
+}
+
+/// XORs the SHA-256 hash of extra entropy, ex: dice rolls, into every secret of the inner source.
+/// The keys stay as random as the inner source even if the extra entropy is weak or known.
+pub struct EntropyMixer<S> {
+    source: S,
+    entropy: [u8; 32],
+}
+
+impl<S: KeySource> EntropyMixer<S> {
+    pub fn new(source: S, entropy: &[u8]) -> Self {
+        EntropyMixer {
+            source,
+            entropy: sha256::Hash::hash(entropy).to_byte_array(),
+        }
+    }
+}
+
+impl<S: KeySource> KeySource for EntropyMixer<S> {
+    fn fill_secret(&mut self, secret: &mut [u8; 32]) {
+        self.source.fill_secret(secret);
+        for (byte, entropy) in secret.iter_mut().zip(self.entropy) {
+            *byte ^= entropy;
+        }
+    }
+}
+
+/// Takes secrets from the key source until one of them is a valid secp256k1 key.
+fn secret_key_from(source: &mut dyn KeySource) -> SecretKey {
+    let mut secret = [0u8; 32];
+    let secret_key = loop {
+        source.fill_secret(&mut secret);
+        if let Ok(secret_key) = SecretKey::from_slice(&secret) {
+            break secret_key;
+        }
+    };
+    erase_bytes(&mut secret);
+    secret_key
+}
+
+/// Overwrites the secret bytes with zeros. The write is volatile, so it isn't removed as a dead
+/// store when the bytes go out of scope right after it.
+fn erase_bytes(secret: &mut [u8; 32]) {
+    // SAFETY: the pointer comes from a mutable reference, so it is valid and aligned.
+    unsafe { std::ptr::write_volatile(secret, [0; 32]) };
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Number of consecutive keys generated from a starting key before a new one is taken from the source.
+const STEPS_PER_START: u32 = 1 << 16;
+
+/// Generates consecutive key pairs k, k+1, k+2, ... from a starting key k taken from the key source.
+/// The public key of k+1 is the public key of k plus the generator point, which is a lot cheaper
+/// than the scalar multiplication of a new key pair, and the secret key is tracked alongside it.
+/// The keys of a walk are related, so `restart` must be called after a key is handed out,
+/// ex: after a vanity address is found, to keep the found keys independent of each other.
+pub struct IncrementalKeys<S> {
+    source: S,
+    generator: secp256k1::PublicKey,
+    secret_key: SecretKey,
+    public_key: secp256k1::PublicKey,
+    /// The hash160 of the current public key once it is computed.
+    pubkey_hash: Option<PubkeyHash>,
+    remaining: u32,
+}
+
+impl<S: KeySource> IncrementalKeys<S> {
+    pub fn new(secp256k1: &Secp256k1<All>, mut source: S) -> Self {
+        let mut generator = [0u8; 65];
+        generator[0] = 0x04;
+        generator[1..33].copy_from_slice(&GENERATOR_X);
+        generator[33..].copy_from_slice(&GENERATOR_Y);
+        let generator = secp256k1::PublicKey::from_slice(&generator)
+            .expect("the secp256k1 generator is a valid public key");
+
+        let secret_key = secret_key_from(&mut source);
+        IncrementalKeys {
+            source,
+            generator,
+            secret_key,
+            public_key: secret_key.public_key(secp256k1),
+            pubkey_hash: None,
+            remaining: STEPS_PER_START,
+        }
+    }
+
+    /// Takes a new starting key from the source.
+    pub fn restart(&mut self, secp256k1: &Secp256k1<All>) {
+        self.secret_key.non_secure_erase();
+        self.secret_key = secret_key_from(&mut self.source);
+        self.public_key = self.secret_key.public_key(secp256k1);
+        self.pubkey_hash = None;
+        self.remaining = STEPS_PER_START;
+    }
+
+    /// Returns the current key pair and its address and steps to the next key.
+    pub fn next_keys(&mut self, secp256k1: &Secp256k1<All>) -> KeysAndAddress {
+        let pubkey_hash = self.pubkey_hash();
+        let keys_and_address =
+            KeysAndAddress::from_key_pair(self.secret_key, self.public_key, pubkey_hash);
+        self.skip(secp256k1);
+        keys_and_address
+    }
+
+    /// Same as `next_keys` but reuses the candidate in the slot, so the search loops don't
+    /// allocate a new address for every candidate. Take the candidate out of the slot to keep it.
+    pub fn next_keys_into<'a>(
+        &mut self,
+        secp256k1: &Secp256k1<All>,
+        slot: &'a mut Option<KeysAndAddress>,
+    ) -> &'a KeysAndAddress {
+        let pubkey_hash = self.pubkey_hash();
+        let keys_and_address = match slot.take() {
+            Some(mut keys_and_address) => {
+                keys_and_address.set_key_pair(self.secret_key, self.public_key, pubkey_hash);
+                keys_and_address
+            }
+            None => KeysAndAddress::from_key_pair(self.secret_key, self.public_key, pubkey_hash),
+        };
+        self.skip(secp256k1);
+        slot.insert(keys_and_address)
+    }
+
+    /// Returns the public key of the key pair `next_keys` returns next.
+    pub fn public_key(&self) -> &secp256k1::PublicKey {
+        &self.public_key
+    }
+
+    /// Returns the hash160 of the compressed public key `next_keys` returns next. It is computed
+    /// once per key, so a prefix check on the hash160 and the address of the key share it.
+    pub fn pubkey_hash(&mut self) -> PubkeyHash {
+        *self
+            .pubkey_hash
+            .get_or_insert_with(|| PublicKey::new(self.public_key).pubkey_hash())
+    }
+
+    /// Steps to the next key without building the address of the current one.
+    pub fn skip(&mut self, secp256k1: &Secp256k1<All>) {
+        self.remaining -= 1;
+        // Stepping fails only if the next secret key would be 0, which is the end of the curve order.
+        match (
+            self.secret_key.add_tweak(&Scalar::ONE),
+            self.public_key.combine(&self.generator),
+        ) {
+            (Ok(secret_key), Ok(public_key)) if self.remaining > 0 => {
+                self.secret_key.non_secure_erase();
+                self.secret_key = secret_key;
+                self.public_key = public_key;
+                self.pubkey_hash = None;
+            }
+            _ => self.restart(secp256k1),
+        }
+    }
+}
+
+impl<S> Drop for IncrementalKeys<S> {
+    fn drop(&mut self) {
+        self.secret_key.non_secure_erase();
+    }
+}
 
 /// A struct to hold bitcoin::secp256k1::SecretKey bitcoin::Key::PublicKey and a string address
+/// The secret key is overwritten when the struct is dropped, so the secrets of the discarded
+/// candidates of a search don't stay in the freed memory. Copies of the private key taken with
+/// `get_private_key` are not erased.
 pub struct KeysAndAddress {
     private_key: PrivateKey,
     public_key: PublicKey,
@@ -36,7 +236,12 @@ impl KeysAndAddress {
     /// Generates a randomly generated key pair and their compressed addresses with using given Secp256k1.
     /// and Returns them in a KeysAndAddress struct.
     pub fn generate_random(secp256k1: &Secp256k1<All>) -> Self {
-        let (secret_key, pk) = secp256k1.generate_keypair(&mut rand::thread_rng());
+        Self::generate_with_rng(secp256k1, &mut rand::thread_rng())
+    }
+
+    /// Same as `generate_random` but the private key is generated with the given random number generator.
+    pub fn generate_with_rng<R: Rng + ?Sized>(secp256k1: &Secp256k1<All>, rng: &mut R) -> Self {
+        let (secret_key, pk) = secp256k1.generate_keypair(rng);
         let private_key = PrivateKey::new(secret_key, Bitcoin);
         let public_key = PublicKey::new(pk);
 
@@ -47,6 +252,12 @@ impl KeysAndAddress {
         }
     }
 
+    /// Same as `generate_random` but the secret key is taken from the key source.
+    /// Secrets which aren't valid secp256k1 keys are skipped, like `generate_random` does.
+    pub fn generate_random_with(secp256k1: &Secp256k1<All>, source: &mut dyn KeySource) -> Self {
+        Self::from_private_key(secp256k1, PrivateKey::new(secret_key_from(source), Bitcoin))
+    }
+
     /// Generates a randomly generated key pair and their compressed addresses with generating a new Secp256k1.
     /// and Returns them in a KeysAndAddress struct.
     pub fn generate_random_heavy() -> Self {
@@ -62,6 +273,72 @@ impl KeysAndAddress {
         }
     }
 
+    /// Builds the struct from a secret key and its already derived public key and hash160.
+    fn from_key_pair(
+        secret_key: SecretKey,
+        public_key: secp256k1::PublicKey,
+        pubkey_hash: PubkeyHash,
+    ) -> Self {
+        KeysAndAddress {
+            private_key: PrivateKey::new(secret_key, Bitcoin),
+            public_key: PublicKey::new(public_key),
+            comp_address: Address::p2pkh(pubkey_hash, Bitcoin).to_string(),
+        }
+    }
+
+    /// Replaces the key pair and writes its address into the existing address buffer.
+    fn set_key_pair(
+        &mut self,
+        secret_key: SecretKey,
+        public_key: secp256k1::PublicKey,
+        pubkey_hash: PubkeyHash,
+    ) {
+        self.erase_secret();
+        self.private_key = PrivateKey::new(secret_key, Bitcoin);
+        self.public_key = PublicKey::new(public_key);
+        self.comp_address.clear();
+        write!(
+            self.comp_address,
+            "{}",
+            Address::p2pkh(pubkey_hash, Bitcoin)
+        )
+        .expect("writing to a String doesn't fail");
+    }
+
+    /// Derives the public key and the address of the given private key.
+    pub fn from_private_key(secp256k1: &Secp256k1<All>, private_key: PrivateKey) -> Self {
+        let public_key = PublicKey::from_private_key(secp256k1, &private_key);
+
+        KeysAndAddress {
+            private_key,
+            public_key,
+            comp_address: Address::p2pkh(public_key, Bitcoin).to_string(),
+        }
+    }
+
+    /// Same as `from_private_key` but parses the private key from WIF.
+    /// Returns Err if the WIF is invalid or isn't a mainnet private key.
+    pub fn from_wif(secp256k1: &Secp256k1<All>, wif: &str) -> Result<Self, BtcVanityError> {
+        let private_key = PrivateKey::from_wif(wif).map_err(|_| BtcVanityError::InvalidWif)?;
+        if private_key.network != NetworkKind::Main {
+            return Err(BtcVanityError::NotMainnetKey);
+        }
+
+        Ok(Self::from_private_key(secp256k1, private_key))
+    }
+
+    /// Same as `from_private_key` but builds a mainnet private key from its 32 raw bytes.
+    /// Returns Err if the bytes are zero or not below the secp256k1 curve order.
+    pub fn from_secret_bytes(
+        secp256k1: &Secp256k1<All>,
+        secret_bytes: &[u8; 32],
+    ) -> Result<Self, BtcVanityError> {
+        let private_key = PrivateKey::from_slice(secret_bytes, Bitcoin)
+            .map_err(|_| BtcVanityError::InvalidPrivateKey)?;
+
+        Ok(Self::from_private_key(secp256k1, private_key))
+    }
+
     pub fn get_private_key(&self) -> &PrivateKey {
         &self.private_key
     }
@@ -83,6 +360,29 @@ impl KeysAndAddress {
     }
 }
 
+impl KeysAndAddress {
+    /// Overwrites the secret key with a constant valid key.
+    fn erase_secret(&mut self) {
+        self.private_key.inner.non_secure_erase();
+    }
+}
+
+impl Drop for KeysAndAddress {
+    fn drop(&mut self) {
+        self.erase_secret();
+    }
+}
+
+#[cfg(test)]
+impl KeysAndAddress {
+    /// Replaces the address without changing the keys, so the checks of the found key pairs
+    /// can be tested with a key pair which doesn't derive its address.
+    pub(crate) fn with_corrupted_address(mut self, address: &str) -> Self {
+        self.comp_address = address.to_string();
+        self
+    }
+}
+
 #[cfg(feature = "test_only")]
 mod test_only_features {
     use super::*;
@@ -111,23 +411,21 @@ mod test_only_features {
             if safe_mode {
                 // Ensure range_max is greater than range_min
                 if range_max < range_min {
-                    return Err(BtcVanityError::KeysAndAddressError(
-                        "range_max must be greater than range_min",
-                    ));
+                    return Err(BtcVanityError::InvalidRange {
+                        reason: "range_max must be greater than range_min",
+                    });
                 }
 
                 let secp256k1_order = BigUint::from_str_radix(
                     "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141",
                     16,
                 )
-                .map_err(|_| {
-                    BtcVanityError::KeysAndAddressError("Failed to parse hexadecimal string")
-                })?;
+                .expect("the secp256k1 order is valid hexadecimal");
 
                 if range_max > &secp256k1_order {
-                    return Err(BtcVanityError::KeysAndAddressError(
-                        "range_max must be within the valid range for Secp256k1",
-                    ));
+                    return Err(BtcVanityError::InvalidRange {
+                        reason: "range_max must be within the valid range for Secp256k1",
+                    });
                 }
             }
 
@@ -145,7 +443,7 @@ mod test_only_features {
             };
 
             let private_key = PrivateKey::from_slice(&private_key_bytes, Bitcoin)
-                .map_err(|_| BtcVanityError::KeysAndAddressError("Invalid private key"))?;
+                .map_err(|_| BtcVanityError::InvalidPrivateKey)?;
             let public_key = PublicKey::from_private_key(s, &private_key);
 
             Ok(KeysAndAddress {
@@ -170,21 +468,21 @@ mod test_only_features {
         ) -> Result<Self, BtcVanityError> {
             if safe_mode {
                 if private_key_biguint == &BigUint::ZERO {
-                    return Err(BtcVanityError::KeysAndAddressError("renge_min can't be 0"));
+                    return Err(BtcVanityError::InvalidRange {
+                        reason: "range_min can't be 0",
+                    });
                 }
 
                 let secp256k1_order = BigUint::from_str_radix(
                     "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141",
                     16,
                 )
-                .map_err(|_| {
-                    BtcVanityError::KeysAndAddressError("Failed to parse hexadecimal string")
-                })?;
+                .expect("the secp256k1 order is valid hexadecimal");
 
                 if private_key_biguint > &secp256k1_order {
-                    return Err(BtcVanityError::KeysAndAddressError(
-                        "range_max must be within the valid range for Secp256k1",
-                    ));
+                    return Err(BtcVanityError::InvalidRange {
+                        reason: "range_max must be within the valid range for Secp256k1",
+                    });
                 }
             }
 
@@ -198,7 +496,7 @@ mod test_only_features {
             };
 
             let private_key = PrivateKey::from_slice(&private_key_bytes, Bitcoin)
-                .map_err(|_| BtcVanityError::KeysAndAddressError("Invalid private key"))?;
+                .map_err(|_| BtcVanityError::InvalidPrivateKey)?;
             let public_key = PublicKey::from_private_key(s, &private_key);
 
             Ok(KeysAndAddress {
@@ -210,10 +508,103 @@ mod test_only_features {
     }
 }
 
+/// Serializes a KeysAndAddress as `{ "wif", "public_key", "address" }`. Deserializing parses the
+/// private key from "wif", derives the keys again and fails if "public_key" or "address" don't match.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::*;
+    use serde::de::Error;
+    use serde::ser::SerializeStruct;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Serializes the public key and the address of a KeysAndAddress without its private key.
+    pub struct WithoutSecrets<'a>(&'a KeysAndAddress);
+
+    impl KeysAndAddress {
+        /// Returns a view of the key pair that serializes without the private key,
+        /// ex: for logging the found addresses.
+        pub fn without_secrets(&self) -> WithoutSecrets<'_> {
+            WithoutSecrets(self)
+        }
+    }
+
+    impl Serialize for KeysAndAddress {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut state = serializer.serialize_struct("KeysAndAddress", 3)?;
+            state.serialize_field("wif", &self.get_wif_private_key())?;
+            state.serialize_field("public_key", &self.get_comp_public_key())?;
+            state.serialize_field("address", &self.comp_address)?;
+            state.end()
+        }
+    }
+
+    impl Serialize for WithoutSecrets<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut state = serializer.serialize_struct("KeysAndAddress", 2)?;
+            state.serialize_field("public_key", &self.0.get_comp_public_key())?;
+            state.serialize_field("address", &self.0.comp_address)?;
+            state.end()
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct SerializedKeysAndAddress {
+        wif: String,
+        public_key: Option<String>,
+        address: Option<String>,
+    }
+
+    impl<'de> Deserialize<'de> for KeysAndAddress {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let serialized = SerializedKeysAndAddress::deserialize(deserializer)?;
+            let keys_and_address = KeysAndAddress::from_wif(&Secp256k1::new(), &serialized.wif)
+                .map_err(D::Error::custom)?;
+
+            if serialized
+                .public_key
+                .is_some_and(|public_key| public_key != keys_and_address.get_comp_public_key())
+            {
+                return Err(D::Error::custom(
+                    "The public key doesn't belong to the private key",
+                ));
+            }
+            if let Some(address) = serialized.address {
+                if address != keys_and_address.comp_address {
+                    return Err(D::Error::custom(BtcVanityError::AddressMismatch {
+                        address,
+                        derived: keys_and_address.comp_address.clone(),
+                    }));
+                }
+            }
+
+            Ok(keys_and_address)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+pub use serde_impls::WithoutSecrets;
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use bitcoin::secp256k1::Secp256k1;
+    use std::mem::ManuallyDrop;
+    use std::ptr;
+
+    #[test]
+    fn test_seeded_rng() {
+        let secp = Secp256k1::new();
+        let generate = |seed, thread_index| {
+            KeysAndAddress::generate_with_rng(&secp, &mut seeded_rng(seed, thread_index))
+                .get_comp_address()
+                .to_string()
+        };
+
+        assert_eq!(generate(42, 0), generate(42, 0));
+        assert_ne!(generate(42, 0), generate(42, 1));
+        assert_ne!(generate(42, 0), generate(43, 0));
+    }
 
     #[test]
     fn test_generate_random() {
@@ -227,7 +618,7 @@ mod tests {
         assert_eq!(keys_and_address.public_key, derived_public_key);
 
         // Check if the derived public key generates the same address
-        let derived_address = Address::p2pkh(&derived_public_key, Bitcoin).to_string();
+        let derived_address = Address::p2pkh(derived_public_key, Bitcoin).to_string();
         assert_eq!(keys_and_address.comp_address, derived_address);
     }
 
@@ -242,9 +633,244 @@ mod tests {
         assert_eq!(keys_and_address.public_key, derived_public_key);
 
         // Check if the derived public key generates the same address
-        let derived_address = Address::p2pkh(&derived_public_key, Bitcoin).to_string();
+        let derived_address = Address::p2pkh(derived_public_key, Bitcoin).to_string();
         assert_eq!(keys_and_address.comp_address, derived_address);
     }
+
+    #[test]
+    fn test_incremental_keys() {
+        let secp = Secp256k1::new();
+        let mut keys = IncrementalKeys::new(&secp, RngKeySource(seeded_rng(42, 0)));
+        let first = keys.next_keys(&secp);
+        let start =
+            KeysAndAddress::generate_random_with(&secp, &mut RngKeySource(seeded_rng(42, 0)));
+        assert_eq!(first.get_comp_address(), start.get_comp_address());
+
+        // Every stepped key pair still derives its own public key and address.
+        let mut previous = first.private_key.inner;
+        for _ in 0..100 {
+            let keys_and_address = keys.next_keys(&secp);
+            let derived = KeysAndAddress::from_private_key(&secp, keys_and_address.private_key);
+            assert_eq!(keys_and_address.public_key, derived.public_key);
+            assert_eq!(keys_and_address.comp_address, derived.comp_address);
+
+            assert_eq!(
+                previous.add_tweak(&Scalar::ONE).unwrap(),
+                keys_and_address.private_key.inner
+            );
+            previous = keys_and_address.private_key.inner;
+        }
+
+        // Restarting takes a new, unrelated starting key from the source.
+        keys.restart(&secp);
+        let restarted = keys.next_keys(&secp);
+        assert_ne!(
+            previous.add_tweak(&Scalar::ONE).unwrap(),
+            restarted.private_key.inner
+        );
+    }
+
+    #[test]
+    fn test_pubkey_hash_is_shared_with_the_address() {
+        let secp = Secp256k1::new();
+        let mut keys = IncrementalKeys::new(&secp, RngKeySource(seeded_rng(1375, 0)));
+        for _ in 0..100 {
+            let pubkey_hash = keys.pubkey_hash();
+            assert_eq!(keys.pubkey_hash(), pubkey_hash);
+
+            let keys_and_address = keys.next_keys(&secp);
+            assert_eq!(keys_and_address.public_key.pubkey_hash(), pubkey_hash);
+            let derived = KeysAndAddress::from_private_key(&secp, keys_and_address.private_key);
+            assert_eq!(keys_and_address.comp_address, derived.comp_address);
+            // The cached hash160 is dropped when the keys step.
+            assert_ne!(keys.pubkey_hash(), pubkey_hash);
+        }
+    }
+
+    #[test]
+    fn test_next_keys_into_reuses_the_candidate() {
+        let secp = Secp256k1::new();
+        let mut keys = IncrementalKeys::new(&secp, RngKeySource(seeded_rng(1366, 0)));
+        let mut reused_keys = IncrementalKeys::new(&secp, RngKeySource(seeded_rng(1366, 0)));
+
+        let mut slot = None;
+        let buffer = reused_keys
+            .next_keys_into(&secp, &mut slot)
+            .comp_address
+            .as_ptr();
+        let _ = keys.next_keys(&secp);
+        for _ in 0..100 {
+            let expected = keys.next_keys(&secp);
+            let keys_and_address = reused_keys.next_keys_into(&secp, &mut slot);
+            assert_eq!(keys_and_address.private_key, expected.private_key);
+            assert_eq!(keys_and_address.public_key, expected.public_key);
+            assert_eq!(keys_and_address.comp_address, expected.comp_address);
+            assert_eq!(keys_and_address.comp_address.as_ptr(), buffer);
+        }
+
+        // A candidate taken out of the slot is kept and the next one is built from scratch.
+        let kept = slot.take().unwrap();
+        let next = reused_keys.next_keys_into(&secp, &mut slot);
+        assert_eq!(next.comp_address, keys.next_keys(&secp).comp_address);
+        assert_ne!(kept.comp_address, next.comp_address);
+    }
+
+    #[test]
+    fn test_incremental_keys_wrap_at_curve_order() {
+        // The last valid secret key is n - 1, so the walk has to restart after it.
+        let last_key = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364140";
+        let mut secret = [0u8; 32];
+        for (byte, hex) in secret.iter_mut().zip(last_key.as_bytes().chunks(2)) {
+            *byte = u8::from_str_radix(std::str::from_utf8(hex).unwrap(), 16).unwrap();
+        }
+
+        struct FixedThenRandom(Option<[u8; 32]>);
+        impl KeySource for FixedThenRandom {
+            fn fill_secret(&mut self, secret: &mut [u8; 32]) {
+                match self.0.take() {
+                    Some(fixed) => *secret = fixed,
+                    None => rand::thread_rng().fill_bytes(secret),
+                }
+            }
+        }
+
+        let secp = Secp256k1::new();
+        let mut keys = IncrementalKeys::new(&secp, FixedThenRandom(Some(secret)));
+        assert_eq!(
+            keys.next_keys(&secp).private_key.inner.secret_bytes(),
+            secret
+        );
+        let next = keys.next_keys(&secp);
+        let derived = KeysAndAddress::from_private_key(&secp, next.private_key);
+        assert_eq!(next.comp_address, derived.comp_address);
+    }
+
+    #[test]
+    fn test_generate_random_with_key_source() {
+        let secp = Secp256k1::new();
+        let from_rng = KeysAndAddress::generate_with_rng(&secp, &mut seeded_rng(42, 0));
+        let from_source =
+            KeysAndAddress::generate_random_with(&secp, &mut RngKeySource(seeded_rng(42, 0)));
+        assert_eq!(from_source.get_comp_address(), from_rng.get_comp_address());
+
+        // Mixing entropy changes the keys, but the same entropy always gives the same keys.
+        let mixed = |entropy: &[u8]| {
+            let mut source = EntropyMixer::new(RngKeySource(seeded_rng(42, 0)), entropy);
+            KeysAndAddress::generate_random_with(&secp, &mut source)
+                .get_comp_address()
+                .to_string()
+        };
+        assert_ne!(&mixed(b"1 6 3 2 5"), from_rng.get_comp_address());
+        assert_eq!(mixed(b"1 6 3 2 5"), mixed(b"1 6 3 2 5"));
+        assert_ne!(mixed(b"1 6 3 2 5"), mixed(b"4 4 1 2 6"));
+    }
+
+    #[test]
+    fn test_keys_and_address_is_send_and_sync() {
+        // Fails to compile if a field ever stops being Send or Sync.
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<KeysAndAddress>();
+        assert_send_sync::<crate::vanity_addr_generator::SearchResult>();
+    }
+
+    #[test]
+    fn test_drop_erases_secret_key() {
+        let secp = Secp256k1::new();
+        let mut keys_and_address = KeysAndAddress::generate_random(&secp);
+        let secret_bytes = keys_and_address.private_key.inner.secret_bytes();
+
+        keys_and_address.erase_secret();
+        assert_ne!(
+            keys_and_address.private_key.inner.secret_bytes(),
+            secret_bytes
+        );
+    }
+
+    /// non_secure_erase overwrites a secret key with ones.
+    const ERASED_SECRET: [u8; 32] = [1; 32];
+
+    #[test]
+    fn test_drop_of_keys_and_address() {
+        let secp = Secp256k1::new();
+        let mut keys_and_address = ManuallyDrop::new(KeysAndAddress::generate_random(&secp));
+
+        // The secret key is plain bytes inside the struct, so it can still be read after the drop
+        // frees the address string.
+        unsafe { ptr::drop_in_place(&mut *keys_and_address) };
+        let secret_key = unsafe { ptr::read(&keys_and_address.private_key.inner) };
+        assert_eq!(secret_key.secret_bytes(), ERASED_SECRET);
+    }
+
+    #[test]
+    fn test_drop_of_incremental_keys() {
+        let secp = Secp256k1::new();
+        let mut keys = ManuallyDrop::new(IncrementalKeys::new(
+            &secp,
+            RngKeySource(seeded_rng(1349, 0)),
+        ));
+        keys.skip(&secp);
+
+        unsafe { ptr::drop_in_place(&mut *keys) };
+        let secret_key = unsafe { ptr::read(&keys.secret_key) };
+        assert_eq!(secret_key.secret_bytes(), ERASED_SECRET);
+    }
+
+    #[test]
+    fn test_erase_bytes() {
+        let mut secret = [0xab; 32];
+        erase_bytes(&mut secret);
+        assert_eq!(secret, [0; 32]);
+    }
+
+    #[test]
+    fn test_from_wif_round_trip() {
+        let secp = Secp256k1::new();
+        let keys_and_address = KeysAndAddress::generate_random(&secp);
+
+        let imported =
+            KeysAndAddress::from_wif(&secp, &keys_and_address.get_wif_private_key()).unwrap();
+        assert_eq!(
+            imported.get_comp_address(),
+            keys_and_address.get_comp_address()
+        );
+        assert_eq!(imported.public_key, keys_and_address.public_key);
+    }
+
+    #[test]
+    fn test_from_secret_bytes_round_trip() {
+        let secp = Secp256k1::new();
+        let keys_and_address = KeysAndAddress::generate_random(&secp);
+
+        let secret_bytes = keys_and_address.private_key.inner.secret_bytes();
+        let imported = KeysAndAddress::from_secret_bytes(&secp, &secret_bytes).unwrap();
+        assert_eq!(
+            imported.get_comp_address(),
+            keys_and_address.get_comp_address()
+        );
+    }
+
+    #[test]
+    fn test_from_wif_invalid() {
+        let secp = Secp256k1::new();
+        assert!(matches!(
+            KeysAndAddress::from_wif(&secp, "not a wif"),
+            Err(BtcVanityError::InvalidWif)
+        ));
+
+        let testnet_wif = PrivateKey::new(
+            KeysAndAddress::generate_random(&secp).private_key.inner,
+            bitcoin::Network::Testnet,
+        )
+        .to_wif();
+        assert!(matches!(
+            KeysAndAddress::from_wif(&secp, &testnet_wif),
+            Err(BtcVanityError::NotMainnetKey)
+        ));
+        assert!(matches!(
+            KeysAndAddress::from_secret_bytes(&secp, &[0; 32]),
+            Err(BtcVanityError::InvalidPrivateKey)
+        ));
+    }
 }
 
 #[cfg(test)]
@@ -286,7 +912,6 @@ mod test_only_tests {
     }
 
     #[test]
-    #[should_panic(expected = "range_max must be greater than range_min")]
     fn test_generate_with_invalid_range() {
         let secp = Secp256k1::new();
 
@@ -294,11 +919,15 @@ mod test_only_tests {
         let range_min = BigUint::from(100u32);
         let range_max = BigUint::from(10u32);
 
-        let _ = KeysAndAddress::generate_within_range(&secp, &range_min, &range_max, true).unwrap();
+        assert!(matches!(
+            KeysAndAddress::generate_within_range(&secp, &range_min, &range_max, true),
+            Err(BtcVanityError::InvalidRange {
+                reason: "range_max must be greater than range_min"
+            })
+        ));
     }
 
     #[test]
-    #[should_panic(expected = "range_max must be within the valid range for Secp256k1")]
     fn test_generate_with_out_of_bounds_range() {
         let secp = Secp256k1::new();
 
@@ -310,6 +939,57 @@ mod test_only_tests {
         )
         .unwrap();
 
-        let _ = KeysAndAddress::generate_within_range(&secp, &range_min, &range_max, true).unwrap();
+        assert!(matches!(
+            KeysAndAddress::generate_within_range(&secp, &range_min, &range_max, true),
+            Err(BtcVanityError::InvalidRange {
+                reason: "range_max must be within the valid range for Secp256k1"
+            })
+        ));
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde")]
+mod serde_tests {
+    use super::*;
+
+    #[test]
+    fn test_serde_round_trip() {
+        let keys_and_address = KeysAndAddress::generate_random_heavy();
+        let json = serde_json::to_string(&keys_and_address).unwrap();
+        let deserialized: KeysAndAddress = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            deserialized.get_wif_private_key(),
+            keys_and_address.get_wif_private_key()
+        );
+        assert_eq!(
+            deserialized.get_comp_address(),
+            keys_and_address.get_comp_address()
+        );
+    }
+
+    #[test]
+    fn test_serde_without_secrets() {
+        let keys_and_address = KeysAndAddress::generate_random_heavy();
+        let json = serde_json::to_value(keys_and_address.without_secrets()).unwrap();
+
+        assert!(json.get("wif").is_none());
+        assert_eq!(
+            json["address"],
+            keys_and_address.get_comp_address().as_str()
+        );
+    }
+
+    #[test]
+    fn test_serde_rejects_wrong_address() {
+        let keys_and_address = KeysAndAddress::generate_random_heavy();
+        let other = KeysAndAddress::generate_random_heavy();
+        let json = serde_json::json!({
+            "wif": keys_and_address.get_wif_private_key(),
+            "address": other.get_comp_address(),
+        });
+
+        assert!(serde_json::from_value::<KeysAndAddress>(json).is_err());
     }
 }
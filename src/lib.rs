@@ -27,6 +27,25 @@
 //!                 vanity_address.get_comp_address())
 //! ```
 //!
+//! The same search with the builder, which names every option and has defaults for the rest.
+//! ```rust
+//! use btc_vanity::vanity_addr_generator::{VanityAddrBuilder, VanityMode};
+//!
+//! let vanity_address = VanityAddrBuilder::new("Test")
+//!     .threads(16)
+//!     .case_insensitive()
+//!     .mode(VanityMode::Anywhere)
+//!     .generate()
+//!     .unwrap();
+//! ```
+//!
+//! Or in one call with the defaults of bitcoin, importing everything from the prelude.
+//! ```rust
+//! use btc_vanity::prelude::*;
+//!
+//! let vanity_address = bitcoin_vanity("Te", VanityMode::Anywhere).unwrap();
+//! ```
+//!
 //! Creating a keypair
 //! ```rust
 //! use btc_vanity::keys_and_address::KeysAndAddress;
@@ -57,6 +76,12 @@
 //! A bitcoin vanity address generator written with the Rust programming language.
 //!
 //! Usage: btc-vanity [OPTIONS] [string]
+//!        btc-vanity [OPTIONS] [string] <COMMAND>
+//!
+//! Commands:
+//! verify          Checks that a private key derives the given address and the address has the pattern.
+//! decrypt-output  Decrypts an output file written with --encrypt-output and prints it.
+//! help            Print this message or the help of the given subcommand(s)
 //!
 //! Arguments:
 //! [string]  String used to match addresses.
@@ -64,18 +89,95 @@
 //! Options:
 //! -i, --input-file <input-file>    File with strings to match addresses with.
 //! Important: Write every string in a separate line.
+//! Use - to read the strings from stdin.
+//!     --input-format <input-format>
+//!                                  Format of the input file. toml reads every string from an [[entry]] table.
+//!                                  [default: toml for .toml files, lines for the rest] [possible values: lines, toml]
+//!     --ignore-unknown-flags       Warns about the unknown flags in the lines of the input file instead of exiting.
+//!     --watch                      Keeps running and searches the lines appended to the input file until Ctrl-C.
+//!                                  Only the lines format is supported.
 //! -f, --force-flags                Use this flag to override the flags in the input file
 //! or use in file to override cli flags for only that string.
 //! Note: Cli -f is stronger than input-file -f.
 //! -o, --output-file <output-file>  Crates a file that contains found wallet/s.
+//!     --max-output-size <max-output-size>
+//!                                  Rotates the output file before a write would make it larger than the given bytes.
+//!                                  The full file is renamed to <name>.1, then <name>.2, ...
+//!     --output-dir <output-dir>    Writes every found wallet to its own file in the given directory.
+//!     --name-template <name-template>
+//!                                  File name template used with --output-dir. Placeholders: {chain}, {pattern}, {n}
+//!                                  [default: {chain}-{pattern}-{n}.txt, or .json with --format json]
+//!     --checkpoint <checkpoint>    Records every completed string of the input file to the given file
+//!                                  and skips the strings already found in it on the next run.
+//!     --summary-file <summary-file>
+//!                                  Writes the summary of the input file run to the given file as JSON.
+//!     --no-dedup                   Searches every line of the input file for its own wallet/s. By default a line with
+//!                                  the same string, mode, case sensitivity and count as an earlier line gets its wallet/s.
+//!     --append                     Appends the found wallet/s to the output file. [default]
+//!     --overwrite                  Empties the output file before the first write of the run.
+//!     --format <format>            Output format of the found wallet/s. json writes one JSON object per line.
+//!                                  [default: text] [possible values: text, json]
+//!     --template <template>        Formats every found wallet as a single line, ex: "{address},{private_key}".
+//!                                  Placeholders: {address}, {private_key}, {private_key_hex}, {public_key}, {pattern},
+//!                                  {mode}, {chain}, {timestamp}, {attempts}, {duration}, {keys_per_second}
+//!     --porcelain                  Prints every found wallet as a line of address<TAB>private_key<TAB>public_key and nothing
+//!                                  else to stdout, the rest goes to stderr. private_key is in WIF, public_key is the compressed
+//!                                  public key in hex. With --no-secrets private_key is empty.
+//!     --stats                      Adds when and how the wallet is found to the output.
+//!     --no-secrets                 Prints only the address and the public key, the private keys are only written
+//!                                  to the output file. Needs --output-file or --output-dir.
+//!     --encrypt-output             Encrypts everything written to the output files with a passphrase.
+//!                                  Needs --output-file or --output-dir. Read it back with decrypt-output.
+//!     --output-password-file <output-password-file>
+//!                                  Reads the passphrase of --encrypt-output from the first line of the file
+//!                                  instead of asking it.
 //! -p, --prefix                     Finds a vanity address which has 'string' prefix. [default]
 //! -s, --suffix                     Finds a vanity address which has 'string' suffix.
 //! -a, --anywhere                   Finds a vanity address which includes 'string' at any part of the address.
-//! -t, --threads <threads>          Number of threads to be used. [default: 16]
-//! -c, --case-sensitive             Use case sensitive comparison to match addresses.
+//! -t, --threads <threads>          Number of threads to be used. 0 uses all the CPU cores.
+//! [default: number of CPU cores]
+//!     --pin-threads                Pins every search thread to its own CPU core. Logs the cores with --log-level debug.
+//! -n, --count <count>              Number of vanity addresses to be found for each string. [default: 1]
+//!     --unique-matches             Skips the wallets whose matched part is already found, so with -n every wallet has
+//!                                  a different match, ex: the distinct capitalizations of the string. Remembers the last 65536 matches.
+//!     --exclude-addresses <exclude-addresses>
+//!                                  Skips the matching addresses listed in the file, one address per line, ex: the ones already found.
+//!     --keep-leading-one           Searches a prefix starting with '1' as it is. By default the '1' is dropped,
+//!                                  because every address already starts with it.
+//!     --random                     Generates -n random wallet/s without searching for a string.
+//!     --timeout <timeout>          Gives up searching a string after the given duration. ex: 30s, 10m, 2h
+//!     --max-attempts <max-attempts>
+//!                                  Gives up searching a string after trying the given number of candidates.
+//!     --max-rate <max-rate>        Tries at most the given number of candidates per second, so the search leaves CPU time
+//!                                  to other work. The threads sleep to keep to it.
+//!     --seed <seed>                Generates the keys deterministically from the given number. Only for tests and demos,
+//!                                  never use the found wallet/s with real funds.
+//!     --entropy-file <entropy-file>
+//!                                  Mixes the content of the file, ex: dice rolls, into every generated key as extra entropy.
+//! -c, --case-sensitive             Use case sensitive comparison to match addresses. Without it any case matches.
 //! -d, --disable-fast               Disables fast mode to find a prefix more than 4 characters.
+//!     --max-len <max-len>          Accepts strings up to the given length in fast mode instead of 4 characters. -d removes the limit.
+//! -q, --quiet                      Disables the progress line printed to stderr while searching.
+//!     --no-color                   Disables the colors in the output. Setting the NO_COLOR environment variable does the same.
+//!     --log-level <log-level>      Logs what the search is doing to stderr. debug also logs the speed every second.
+//!                                  [default: off] [possible values: off, error, warn, info, debug, trace]
+//!     --difficulty-warn <difficulty-warn>
+//!                                  Asks for confirmation before searching a string which needs more attempts than this.
+//!                                  [default: 10000000000]
+//! -y, --yes                        Starts searching without asking for confirmation for hard strings.
+//!     --dry-run                    Validates every string and prints the resolved search and difficulty without searching, as JSON lines with --format json.
+//!     --estimate                   Prints the expected attempts and time to find each string without searching.
+//!     --recalibrate                Measures the speed of this machine again instead of using the cached speed.
+//!     --benchmark                  Measures key generation and match speed with 1, 2, 4, ... up to -t threads.
+//!     --list-chains                Prints the supported chains with their alphabet, length limits and capabilities.
 //! -h, --help                       Print help
 //! -V, --version                    Print version
+//!
+//! Environment variables:
+//! BTC_VANITY_THREADS      Used as -t, --threads when it is not given.
+//! BTC_VANITY_OUTPUT_FILE  Used as -o, --output-file when it is not given.
+//! BTC_VANITY_CACHE_DIR    Directory of the cached speed of this machine, used by --estimate and
+//!                         the difficulty warning.
 //! ```
 //!
 //! # Some Cli Usage Examples
@@ -101,10 +203,76 @@
 //! $ btc-vanity -f -s -i inputs.txt
 //! ```
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bench;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod calibration;
+pub mod chain;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod cli;
 pub mod decoration;
+pub mod encryption;
 pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod file;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod flags;
 pub mod keys_and_address;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod logger;
+pub mod output;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod progress;
+#[cfg(all(feature = "serve", not(target_arch = "wasm32")))]
+pub mod serve;
 pub mod vanity_addr_generator;
+pub mod verify;
+
+pub use vanity_addr_generator::estimate;
+
+use crate::chain::BITCOIN;
+use crate::error::BtcVanityError;
+use crate::keys_and_address::KeysAndAddress;
+use crate::vanity_addr_generator::{VanityAddrBuilder, VanityMode};
+
+/// Finds a bitcoin vanity address with the pattern at the position of the vanity mode, with one
+/// thread per CPU core, fast mode and the default case sensitivity of bitcoin: any case matches.
+/// Use `VanityAddrBuilder` for the other options.
+/// Returns Err for the same reasons as `VanityAddrBuilder::generate`.
+///
+/// ```rust
+/// use btc_vanity::prelude::*;
+///
+/// let vanity_address = bitcoin_vanity("ab", VanityMode::Prefix).unwrap();
+/// assert!(vanity_address.get_comp_address()[1..].to_lowercase().starts_with("ab"));
+/// ```
+pub fn bitcoin_vanity(
+    pattern: &str,
+    vanity_mode: VanityMode,
+) -> Result<KeysAndAddress, BtcVanityError> {
+    VanityAddrBuilder::new(pattern)
+        .case_sensitive(BITCOIN.default_case_sensitive)
+        .mode(vanity_mode)
+        .generate()
+}
+
+/// The functions and types most searches need, so a single import is enough.
+///
+/// ```rust
+/// use btc_vanity::prelude::*;
+///
+/// let vanity_address = VanityAddrBuilder::new("ab").case_insensitive().generate().unwrap();
+/// println!("address (compressed): {}", vanity_address.get_comp_address());
+/// ```
+pub mod prelude {
+    pub use crate::bitcoin_vanity;
+    pub use crate::error::BtcVanityError;
+    pub use crate::keys_and_address::KeysAndAddress;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub use crate::vanity_addr_generator::VanityEngine;
+    pub use crate::vanity_addr_generator::{
+        CancellationToken, SearchResult, VanityAddr, VanityAddrBuilder, VanityMode,
+    };
+}
@@ -0,0 +1,42 @@
+//! # Logger Module
+//!
+//! This module is used for printing the log records of the library to stderr,
+//! so the cli can show what the search is doing with --log-level.
+
+use log::{LevelFilter, Log, Metadata, Record};
+use std::io::Write;
+
+/// Writes every enabled log record to stderr as a single line.
+struct StderrLogger;
+
+static LOGGER: StderrLogger = StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let _ = writeln!(
+                std::io::stderr(),
+                "[{:<5} {}] {}",
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+/// Installs the stderr logger with the given level. Does nothing if the level is off
+/// or a logger is already installed.
+pub fn init_logger(level: LevelFilter) {
+    if level != LevelFilter::Off && log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
@@ -1,19 +1,157 @@
+use anstream::adapter::strip_str;
+use btc_vanity::bench::{run_benchmark, BenchmarkConfig};
+use btc_vanity::calibration::{calibration_cache_path, CalibrationCache};
+use btc_vanity::chain::{supported_chains, BITCOIN};
 use btc_vanity::cli::cli;
-use btc_vanity::decoration::get_decoration_strings;
-use btc_vanity::file::write_output_file;
-use btc_vanity::flags::{get_cli_flags, get_strings_flags};
-use btc_vanity::vanity_addr_generator::VanityAddr;
+use btc_vanity::decoration::{
+    get_decoration_strings, get_invalid_character_hint, paint, DIM, ERROR,
+};
+use btc_vanity::encryption::{decrypt_output, OutputCipher};
+use btc_vanity::error::BtcVanityError;
+use btc_vanity::file::{
+    append_checkpoint, expand_name_template, get_checkpoint_hash, get_unique_output_path,
+    read_checkpoint, write_output_file, CheckpointRecord, InputFileWatcher, JsonlWriter,
+    OutputFileOptions, OutputWriter,
+};
+use btc_vanity::flags::{get_cli_flags, get_strings_flags, CliFlags, StringsFlags};
+use btc_vanity::logger::init_logger;
+#[cfg(feature = "serde")]
+use btc_vanity::output::{error_to_json, resolved_search_to_json};
+use btc_vanity::output::{
+    format_entry_header, format_text_entry, vanity_mode_name, LineSummary, OutputFormat,
+    RunSummary, WalletOutput,
+};
+use btc_vanity::progress::ProgressLine;
+use btc_vanity::vanity_addr_generator::estimate::{
+    case_sensitivity_penalty, estimate_attempts, expected_attempts, format_seconds,
+    measure_keys_per_second,
+};
+use btc_vanity::vanity_addr_generator::{
+    CancellationToken, SearchOptions, SearchResult, SearchStats, VanityAddr, VanityAddrBuilder,
+    VanityMode,
+};
+use btc_vanity::verify::verify_wallet;
 use clap::error::ErrorKind;
-use std::fmt::Write;
-use std::time::Instant;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long key pairs are generated to measure the speed of this machine for --estimate.
+/// Searches at least this long are cached as the speed of this machine too.
+const CALIBRATION_DURATION: Duration = Duration::from_secs(2);
+/// How often the input file is checked for new lines with --watch.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Exit codes of the process. If the strings of an input file end with different results, the
+/// code of the first failed string is used, or Partial if some of them are found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ExitCode {
+    /// Every string is found, or the subcommand succeeded.
+    Success = 0,
+    /// Invalid arguments, input file or string.
+    InvalidInput = 2,
+    /// The string couldn't be found before the timeout or the attempt limit.
+    TimedOut = 3,
+    /// The found wallet couldn't be written to the output file.
+    OutputError = 4,
+    /// The search is interrupted with Ctrl-C or a hard string is declined at the confirmation.
+    Interrupted = 5,
+    /// Some of the strings are found and some of them failed.
+    Partial = 6,
+    /// The verified private key derives another address or the address doesn't have the pattern,
+    /// or a found key pair failed the self-check.
+    VerifyFailed = 7,
+}
+
+impl ExitCode {
+    /// Returns the exit code of a search which ended with the error.
+    fn from_error(err: &BtcVanityError) -> Self {
+        match err {
+            BtcVanityError::Cancelled { .. } => ExitCode::Interrupted,
+            BtcVanityError::TimedOut { .. } | BtcVanityError::AttemptLimitReached { .. } => {
+                ExitCode::TimedOut
+            }
+            BtcVanityError::SelfCheckFailed { .. } => ExitCode::VerifyFailed,
+            _ => ExitCode::InvalidInput,
+        }
+    }
+
+    /// Returns the status of a string with this exit code, used in the checkpoint file and the run summary.
+    fn status_name(self) -> &'static str {
+        match self {
+            ExitCode::Success => "found",
+            ExitCode::TimedOut => "not-found",
+            ExitCode::OutputError => "output-error",
+            ExitCode::Interrupted => "interrupted",
+            ExitCode::VerifyFailed => "self-check-failed",
+            ExitCode::InvalidInput | ExitCode::Partial => "invalid",
+        }
+    }
+
+    /// Exits the process with this code.
+    fn exit(self) -> ! {
+        std::process::exit(self as i32)
+    }
+}
+
+/// A search by its string, vanity mode name, case sensitivity and count.
+type DedupKey = (String, &'static str, bool, u64);
+
+/// The state of a run shared by its strings.
+struct Run {
+    /// Writes the output files, encrypts them with --encrypt-output.
+    output_writer: OutputWriter,
+    /// Set by the first Ctrl-C, it cancels the running search.
+    interrupted: Arc<AtomicBool>,
+    /// Output files already written in this run. With --overwrite only their first write truncates them.
+    written_files: HashSet<String>,
+    /// Patterns that finished searching before an interrupt, listed in the interrupt summary.
+    completed: Vec<String>,
+    /// Measured once for every thread count on the first --estimate or hard string
+    /// and reused for the others. The speeds are cached on the disk for the next runs too.
+    keys_per_second: HashMap<u64, (f64, bool)>,
+    calibration_cache: CalibrationCache,
+    /// Wallets found in this run by (string, vanity mode, case sensitivity, count),
+    /// with the line they are found for.
+    found_results: HashMap<DedupKey, (usize, Vec<SearchResult>)>,
+    /// Strings completed by the previous runs with the same --checkpoint file.
+    checkpoint_records: Vec<CheckpointRecord>,
+    /// Outcome of every string for the run summary.
+    summaries: Vec<LineSummary>,
+    /// Errors of the invalid lines appended to the watched input file, by their index.
+    invalid_lines: HashMap<usize, BtcVanityError>,
+}
 
 fn main() {
     // Sets the cli app.
     let app = cli();
 
     // Try to parse the arguments and catch errors
-    let cli_flags = match app.try_get_matches() {
-        Ok(matches) => get_cli_flags(matches),
+    let mut cli_flags = match app.try_get_matches() {
+        #[cfg(feature = "serve")]
+        Ok(matches) if matches.subcommand_matches("serve").is_some() => {
+            run_server(&matches).exit();
+        }
+        Ok(matches) if matches.subcommand_matches("verify").is_some() => {
+            run_verify(matches.subcommand_matches("verify").unwrap()).exit();
+        }
+        Ok(matches) if matches.subcommand_matches("decrypt-output").is_some() => {
+            run_decrypt_output(matches.subcommand_matches("decrypt-output").unwrap()).exit();
+        }
+        Ok(matches) => match get_cli_flags(matches) {
+            Ok(cli_flags) => cli_flags,
+            Err(err) => {
+                eprint_styled(&paint(ERROR, &format!("error: {}", err)));
+                ExitCode::InvalidInput.exit();
+            }
+        },
+        // --help and --version
+        Err(err) if !err.use_stderr() => err.exit(),
         Err(err) => {
             // Check if it's a missing argument error
             if err.kind() == ErrorKind::MissingRequiredArgument {
@@ -27,85 +165,1366 @@ fn main() {
                 // Otherwise, print the default error
                 eprintln!("{}", err);
             }
-            std::process::exit(1); // Exit with an error code
+            ExitCode::InvalidInput.exit();
         }
     };
 
+    if cli_flags.get_is_no_color() {
+        anstream::ColorChoice::Never.write_global();
+    }
+    init_logger(cli_flags.get_log_level());
+
+    if cli_flags.get_is_list_chains() {
+        print_chains();
+        return;
+    }
+
+    // Only set with --ignore-unknown-flags, otherwise get_cli_flags returns an error for them.
+    for (line, flag) in cli_flags.get_unknown_input_flags() {
+        warn_unknown_input_flag(line, flag);
+    }
+
+    if cli_flags.get_is_dry_run() {
+        dry_run(&cli_flags).exit();
+    }
+
+    if cli_flags.get_is_benchmark() {
+        run_benchmark_and_print(cli_flags.get_threads());
+        return;
+    }
+
+    if let Some(seed) = cli_flags.get_seed() {
+        let message = format!(
+            "WARNING: --seed {} makes the private keys predictable! \
+            Anyone who knows the seed can generate the same keys. \
+            Never send real funds to these addresses.",
+            seed
+        );
+        eprint_styled(&format!("{}\n", paint(ERROR, &message)));
+    }
+
+    // Private keys printed to a terminal can end up in the scrollback, screen shares or recordings.
+    // With --watch the lines aren't read yet, so only the cli output file is checked.
+    let prints_private_keys = cli_flags.get_output_dir().is_none()
+        && ((cli_flags.get_watch_file().is_some() && cli_flags.get_output_file_name().is_empty())
+            || (0..cli_flags.get_strings().len()).any(|i| {
+                get_strings_flags(&cli_flags, i)
+                    .get_output_file_name()
+                    .is_empty()
+            }));
+    if prints_private_keys && !cli_flags.get_is_estimate() && std::io::stdout().is_terminal() {
+        eprintln!(
+            "Warning: the private keys will be printed to this terminal. \
+            Use --output-file with --no-secrets to keep them out of it.\n"
+        );
+    }
+
+    // With --encrypt-output everything written to the output files is encrypted with the passphrase.
+    let output_writer = match cli_flags.get_is_encrypt_output() && !cli_flags.get_is_estimate() {
+        true => match read_passphrase(cli_flags.get_output_password_file(), true)
+            .and_then(|passphrase| OutputCipher::new(&passphrase))
+        {
+            Ok(cipher) => OutputWriter::Encrypted(Box::new(cipher)),
+            Err(err) => {
+                eprint_styled(&paint(ERROR, &format!("error: {}", err)));
+                ExitCode::InvalidInput.exit();
+            }
+        },
+        false => OutputWriter::Plain,
+    };
+
+    // The first Ctrl-C stops the running search so its statistics can be printed,
+    // a second one exits immediately.
+    let interrupted = Arc::new(AtomicBool::new(false));
+    if !cli_flags.get_is_estimate() {
+        let interrupted = Arc::clone(&interrupted);
+        let _ = ctrlc::set_handler(move || {
+            if interrupted.swap(true, Ordering::Relaxed) {
+                ExitCode::Interrupted.exit();
+            }
+        });
+    }
+
+    let checkpoint_records = match cli_flags.get_checkpoint() {
+        Some(checkpoint) if !cli_flags.get_is_estimate() => match read_checkpoint(checkpoint) {
+            Ok(records) => records,
+            Err(err) => {
+                let message = format!("Couldn't read the checkpoint file: {}", err);
+                eprint_styled(&format!("{}\n", paint(ERROR, &message)));
+                ExitCode::InvalidInput.exit();
+            }
+        },
+        _ => Vec::new(),
+    };
+
+    let mut run = Run {
+        output_writer,
+        interrupted,
+        written_files: HashSet::new(),
+        completed: Vec::new(),
+        keys_per_second: HashMap::new(),
+        calibration_cache: CalibrationCache::load(calibration_cache_path()),
+        found_results: HashMap::new(),
+        checkpoint_records,
+        summaries: Vec::new(),
+        invalid_lines: HashMap::new(),
+    };
+    let run_start = Instant::now();
+    // Exit code of each string in order.
+    let mut exit_codes = Vec::new();
+
+    // Follows the input file with --watch.
+    let mut watcher = cli_flags
+        .get_watch_file()
+        .map(|file| InputFileWatcher::new(file));
+    let mut next = 0;
+
     // Loop for multiple wallet inputs from text file.
-    for (i, string) in cli_flags.get_strings().iter().enumerate() {
-        let string_flags = get_strings_flags(&cli_flags, i);
+    loop {
+        let i = match next_string(
+            &mut cli_flags,
+            &mut next,
+            &mut watcher,
+            &mut run.invalid_lines,
+            &run.interrupted,
+        ) {
+            Ok(Some(i)) => i,
+            Ok(None) => break,
+            Err(err) => {
+                let message = format!("Couldn't read the input file: {}", err);
+                eprint_styled(&format!("{}\n", paint(ERROR, &message)));
+                exit_codes.push(ExitCode::InvalidInput);
+                break;
+            }
+        };
+        match search_string(&mut run, &cli_flags, i) {
+            ControlFlow::Continue(exit_code) => exit_codes.push(exit_code),
+            ControlFlow::Break(exit_code) => {
+                exit_codes.push(exit_code);
+                break;
+            }
+        }
+    }
 
-        let (vanity_mode_str, case_sensitive_str) = get_decoration_strings(
-            string_flags.get_vanity_mode(),
-            string_flags.get_case_sensitivity(),
+    // The cache only saves the next runs some time, so it doesn't fail the run.
+    if let Err(err) = run.calibration_cache.save() {
+        log::warn!("couldn't save the calibration cache: {}", err);
+    }
+
+    if !cli_flags.get_is_estimate() {
+        let summary = RunSummary {
+            lines: run.summaries,
+            wall_secs: run_start.elapsed().as_secs_f64(),
+        };
+        // A single string has nothing to sum up.
+        if summary.lines.len() > 1 {
+            let single_line = cli_flags.get_output_format() == OutputFormat::Json
+                || cli_flags.get_template().is_some();
+            info(single_line, &format!("Summary:\n{}\n", summary.to_table()));
+        }
+        // --summary-file is rejected by get_cli_flags without the serde feature.
+        #[cfg(feature = "serde")]
+        if let Some(summary_file) = cli_flags.get_summary_file() {
+            if let Err(err) = fs::write(summary_file, format!("{}\n", summary.to_json())) {
+                let message = format!("Couldn't write the summary file: {}", err);
+                eprint_styled(&format!("{}\n", paint(ERROR, &message)));
+                exit_codes.push(ExitCode::OutputError);
+            }
+        }
+    }
+
+    combine_exit_codes(&exit_codes).exit();
+}
+
+/// Searches the string at index i, prints or writes its wallets and the search result, and
+/// records the result in the checkpoint file and the run summary.
+/// Returns the exit code of the string, Break if the run is interrupted and stops after it.
+fn search_string(run: &mut Run, cli_flags: &CliFlags, i: usize) -> ControlFlow<ExitCode, ExitCode> {
+    let string = &cli_flags.get_strings()[i];
+    let string_flags = get_strings_flags(cli_flags, i);
+    let to_stderr =
+        cli_flags.get_output_format() == OutputFormat::Json || cli_flags.get_template().is_some();
+    let string = adjust_string(cli_flags, string, string_flags.get_vanity_mode(), to_stderr);
+    if let Some(note) = case_sensitivity_note(
+        string,
+        string_flags.get_vanity_mode(),
+        string_flags.get_case_sensitivity(),
+    ) {
+        info(to_stderr, &note);
+    }
+    if let Ok(Some(rarity)) = VanityAddr::validate_feasibility(
+        string,
+        string_flags.get_vanity_mode(),
+        string_flags.get_case_sensitivity(),
+    ) {
+        info(to_stderr, &rarity_note(string, rarity));
+    }
+
+    // Prints the difficulty of the string instead of searching it.
+    if cli_flags.get_is_estimate() {
+        return ControlFlow::Continue(estimate_string(run, cli_flags, string, &string_flags));
+    }
+
+    let vanity_mode = string_flags.get_vanity_mode();
+    let output_dir = cli_flags.get_output_dir();
+    let line_output = LineOutput {
+        cli_flags,
+        output_writer: &run.output_writer,
+        // With --output-dir every found wallet gets its own file and the rest is printed to stdout.
+        output_file_name: match output_dir {
+            Some(_) => "",
+            None => string_flags.get_output_file_name(),
+        },
+        string,
+        vanity_mode,
+        case_sensitive: string_flags.get_case_sensitivity(),
+    };
+    // With --format json, --template or --porcelain stdout only has a line per wallet, so the
+    // progress messages go to stderr.
+    let single_line = line_output.is_single_line();
+    let output_location = match output_dir {
+        Some(output_dir) => output_dir.as_str(),
+        None if line_output.output_file_name.is_empty() => "stdout",
+        None => line_output.output_file_name,
+    };
+    let line_summary = |status, found, attempts, duration_secs| LineSummary {
+        line: i + 1,
+        pattern: string.to_string(),
+        status,
+        found,
+        attempts,
+        duration_secs,
+        output: output_location.to_string(),
+    };
+
+    if let Some(err) = run.invalid_lines.remove(&i) {
+        eprint_styled(&paint(ERROR, &format!("error: {}", err)));
+        run.summaries.push(line_summary("invalid", 0, 0, 0.0));
+        return ControlFlow::Continue(ExitCode::InvalidInput);
+    }
+
+    // Skips the string if it is found by a previous run and neither it nor its flags are edited.
+    let checkpoint_hash = get_checkpoint_hash(&format!(
+        "{}\t{}\t{}\t{}\t{}\t{}",
+        string,
+        vanity_mode_name(vanity_mode),
+        string_flags.get_case_sensitivity(),
+        string_flags.get_is_fast_mode_disabled(),
+        string_flags.get_count(),
+        output_location
+    ));
+    if let Some(record) = run.checkpoint_records.iter().find(|record| {
+        record.line == i + 1 && record.hash == checkpoint_hash && record.status == "found"
+    }) {
+        info(
+            single_line,
+            &format!(
+                "Skipping line {}: '{}' is already found, output: {}\n",
+                record.line, string, record.output
+            ),
         );
+        run.completed.push(string.to_string());
+        run.summaries.push(line_summary("skipped", 0, 0, 0.0));
+        return ControlFlow::Continue(ExitCode::Success);
+    }
 
-        // First buffer/print before starting calculation
-        let mut buffer1 = String::new();
-        println!(
-            "Searching key pair which their address {}: '{}' {} with {} threads.\n",
-            vanity_mode_str,
+    // A line with the same search as an earlier line of this run gets the wallets found for it
+    // instead of searching again. --no-dedup searches every line for its own wallets.
+    let count = string_flags.get_count();
+    let is_random = cli_flags.get_is_random();
+    let dedup_key = (!is_random && !cli_flags.get_is_no_dedup()).then(|| {
+        (
+            string.to_string(),
+            vanity_mode_name(vanity_mode),
+            string_flags.get_case_sensitivity(),
+            count,
+        )
+    });
+    let reused = dedup_key
+        .as_ref()
+        .and_then(|key| run.found_results.get(key));
+    let is_reused = reused.is_some();
+
+    // Warns before starting a search which is expected to take long and asks to continue.
+    let expected_attempts =
+        estimate_attempts(string, vanity_mode, string_flags.get_case_sensitivity()) * count as f64;
+    let is_valid_input =
+        VanityAddr::validate_input_len(string, string_flags.get_max_pattern_len()).is_ok();
+    if reused.is_none() && is_valid_input && expected_attempts > cli_flags.get_difficulty_warn() {
+        let (keys_per_second, _) = calibrated_keys_per_second(
+            &mut run.keys_per_second,
+            &mut run.calibration_cache,
+            string_flags.get_threads(),
+            cli_flags.get_is_recalibrate(),
+        );
+        eprintln!(
+            "Warning: '{}' needs {:.0} attempts on average, expected time at {:.0} keys/sec: {}",
             string,
-            case_sensitive_str,
-            cli_flags.get_threads()
+            expected_attempts,
+            keys_per_second,
+            format_seconds(expected_attempts / keys_per_second)
         );
-        if !string_flags.get_output_file_name().is_empty() {
-            buffer1 = format!(
-                "Key pair which their address {}: '{}' {}\n",
-                vanity_mode_str, string, case_sensitive_str
-            )
+        // Without a terminal there is no one to answer, so it just continues after the warning.
+        if !cli_flags.get_is_yes() && std::io::stdin().is_terminal() && !confirm("continue? [y/N] ")
+        {
+            eprintln!("Skipping '{}'.\n", string);
+            run.summaries.push(line_summary("declined", 0, 0, 0.0));
+            return ControlFlow::Continue(ExitCode::Interrupted);
         }
+    }
 
-        // Generates the vanity address and measures the time elapsed while finding the address.
-        let start = Instant::now();
-        let result = VanityAddr::generate(
+    // First print before starting calculation
+    let (vanity_mode_str, case_sensitive_str) =
+        get_decoration_strings(vanity_mode, string_flags.get_case_sensitivity());
+    match (is_random, reused) {
+        (true, _) => info(single_line, "Generating random key pair/s.\n"),
+        (false, Some((line, _))) => info(
+            single_line,
+            &format!(
+                "Using the key pair/s found for line {}, the address {}: '{}' {}.\n",
+                line, vanity_mode_str, string, case_sensitive_str
+            ),
+        ),
+        (false, None) => info(
+            single_line,
+            &format!(
+                "Searching key pair which their address {}: '{}' {} with {} threads.\n",
+                vanity_mode_str,
+                string,
+                case_sensitive_str,
+                string_flags.get_threads()
+            ),
+        ),
+    }
+    if !is_random && reused.is_none() {
+        let resolved = VanityAddr::plan(
             string,
-            cli_flags.get_threads(),
+            vanity_mode,
             string_flags.get_case_sensitivity(),
-            !string_flags.get_is_fast_mode_disabled(),
-            string_flags.get_vanity_mode(),
+            string_flags.get_max_pattern_len(),
+            true,
         );
-        let seconds = start.elapsed().as_secs_f64();
-
-        // Second buffer/print after the vanity address found
-        let buffer2 = match result {
-            Ok(res) => {
-                println!("FOUND IN {:.4} SECONDS!\n", seconds);
-
-                // Format the private key hex value
-                let formatted_private_key_hex =
-                    res.get_private_key()
-                        .to_bytes()
-                        .iter()
-                        .fold(String::new(), |mut acc, byte| {
-                            write!(&mut acc, "{:02X}", byte).unwrap();
-                            acc
-                        });
-
-                // Prints the found key pair and the address which has the string.
-                format!(
-                    "private_key (hex): {}\n\
-                    private_key (wif): {}\n\
-                    public_key (compressed): {}\n\
-                    address (compressed): {}\n\n",
-                    formatted_private_key_hex,
-                    res.get_wif_private_key(),
-                    res.get_comp_public_key(),
-                    res.get_comp_address()
-                )
+        if let Ok(resolved) = resolved {
+            info(
+                single_line,
+                &format!(
+                    "Matching addresses look like: {}\n",
+                    resolved.address_shape()
+                ),
+            );
+        }
+    }
+
+    // Generates the vanity addresses and measures the time elapsed while finding them.
+    // Every found address is printed or written right after it is found.
+    let mut found = 0;
+    // Candidates tried until the last found wallet.
+    let mut last_attempts = 0;
+    // The first error while writing the found wallets.
+    let mut output_error = None;
+    let progress_line =
+        (reused.is_none() && !cli_flags.get_is_quiet() && std::io::stderr().is_terminal())
+            .then(|| Arc::new(ProgressLine::new(expected_attempts)));
+    let mut builder = search_builder(cli_flags, string, &string_flags, &run.interrupted);
+    let stats = SearchStats::new();
+    builder = builder.stats(stats.clone());
+    if let Some(progress_line) = &progress_line {
+        let progress_line = Arc::clone(progress_line);
+        let stats = stats.clone();
+        builder = builder.on_progress(move |_| progress_line.update(&stats.snapshot()));
+    }
+    let start = Instant::now();
+    let mut on_found = |res: &SearchResult| {
+        // Keeps the progress line cleared while the found wallet is printed.
+        let _progress_guard = progress_line.as_ref().map(|line| line.clear());
+        found += 1;
+        last_attempts = res.attempts;
+        let wallet_output = WalletOutput {
+            stats: cli_flags.get_is_stats(),
+            ..WalletOutput::new(string, vanity_mode, res)
+        };
+        if count > 1 {
+            info(
+                single_line,
+                &format!(
+                    "FOUND {}/{} IN {}\n",
+                    found,
+                    count,
+                    wallet_output.found_summary()
+                ),
+            );
+        } else {
+            info(
+                single_line,
+                &format!("FOUND IN {}\n", wallet_output.found_summary()),
+            );
+        }
+
+        if let Err(err) = line_output.write_wallet(&mut run.written_files, wallet_output, found) {
+            output_error.get_or_insert(err);
+        }
+    };
+    // The found wallets are kept for the later lines with the same search.
+    let mut results = Vec::new();
+    let result = match reused {
+        Some((_, reused_results)) => {
+            reused_results.iter().for_each(&mut on_found);
+            Ok(())
+        }
+        None => builder.generate_with(|res| {
+            on_found(&res);
+            if dedup_key.is_some() {
+                results.push(res);
+            }
+        }),
+    };
+
+    if let Some(progress_line) = progress_line {
+        progress_line.finish();
+    }
+    let snapshot = stats.snapshot();
+    if !is_reused && !is_random && snapshot.elapsed >= CALIBRATION_DURATION {
+        run.calibration_cache.record(
+            BITCOIN.name,
+            string_flags.get_threads(),
+            snapshot.average_keys_per_second,
+        );
+    }
+    // A reused line doesn't search, its wallets are counted for the earlier line.
+    if is_reused {
+        last_attempts = 0;
+    }
+
+    if let Some(err) = output_error {
+        let message = format!("Couldn't write the found wallet: {}", err);
+        eprint_styled(&format!("{}\n", paint(ERROR, &message)));
+        record_checkpoint(
+            cli_flags,
+            i + 1,
+            checkpoint_hash,
+            ExitCode::OutputError,
+            output_location,
+        );
+        run.summaries.push(line_summary(
+            "output-error",
+            found,
+            last_attempts,
+            start.elapsed().as_secs_f64(),
+        ));
+        return ControlFlow::Continue(ExitCode::OutputError);
+    }
+
+    let err = match result {
+        Ok(()) => {
+            if let Some(dedup_key) = dedup_key {
+                run.found_results
+                    .entry(dedup_key)
+                    .or_insert((i + 1, results));
+            }
+            run.completed.push(string.to_string());
+            record_checkpoint(
+                cli_flags,
+                i + 1,
+                checkpoint_hash,
+                ExitCode::Success,
+                output_location,
+            );
+            run.summaries.push(line_summary(
+                "found",
+                found,
+                last_attempts,
+                start.elapsed().as_secs_f64(),
+            ));
+            return ControlFlow::Continue(ExitCode::Success);
+        }
+        Err(BtcVanityError::Cancelled { attempts }) => {
+            run.summaries.push(line_summary(
+                "interrupted",
+                found,
+                attempts,
+                start.elapsed().as_secs_f64(),
+            ));
+            eprintln!(
+                "\nInterrupted after searching {} candidates in {:.4} seconds.",
+                attempts,
+                start.elapsed().as_secs_f64()
+            );
+            if cli_flags.get_strings().len() > 1 {
+                eprintln!("Completed patterns: {}", run.completed.join(", "));
+            }
+            return ControlFlow::Break(ExitCode::Interrupted);
+        }
+        Err(err) => err,
+    };
+
+    let (buffer2, error, attempts) = match &err {
+        BtcVanityError::TimedOut { attempts }
+        | BtcVanityError::AttemptLimitReached { attempts } => {
+            let summary = format!(
+                "Searched {} candidates in {:.4} seconds, no match.",
+                attempts,
+                start.elapsed().as_secs_f64()
+            );
+            (summary.clone(), summary, *attempts)
+        }
+        BtcVanityError::SelfCheckFailed { .. } => {
+            let message = err.to_string();
+            (paint(ERROR, &message), message, last_attempts)
+        }
+        _ => (
+            skipping_message(string, &err),
+            err.to_string(),
+            last_attempts,
+        ),
+    };
+    let exit_code = match line_output.write_result(&mut run.written_files, &buffer2, &error) {
+        Ok(()) => ExitCode::from_error(&err),
+        Err(err) => {
+            let message = format!("Couldn't write the search result: {}", err);
+            eprint_styled(&format!("{}\n", paint(ERROR, &message)));
+            ExitCode::OutputError
+        }
+    };
+    record_checkpoint(
+        cli_flags,
+        i + 1,
+        checkpoint_hash,
+        exit_code,
+        output_location,
+    );
+    run.summaries.push(line_summary(
+        exit_code.status_name(),
+        found,
+        attempts,
+        start.elapsed().as_secs_f64(),
+    ));
+    ControlFlow::Continue(exit_code)
+}
+
+/// Prints the expected attempts and time of the string for --estimate.
+/// Returns InvalidInput if the string can't be searched.
+fn estimate_string(
+    run: &mut Run,
+    cli_flags: &CliFlags,
+    string: &str,
+    string_flags: &StringsFlags,
+) -> ExitCode {
+    let (vanity_mode_str, case_sensitive_str) = get_decoration_strings(
+        string_flags.get_vanity_mode(),
+        string_flags.get_case_sensitivity(),
+    );
+    println!(
+        "Estimating key pair which their address {}: '{}' {} with {} threads.\n",
+        vanity_mode_str,
+        string,
+        case_sensitive_str,
+        string_flags.get_threads()
+    );
+    if let Err(err) = VanityAddr::validate_input_len(string, string_flags.get_max_pattern_len())
+        .and_then(|()| {
+            VanityAddr::validate_feasibility(
+                string,
+                string_flags.get_vanity_mode(),
+                string_flags.get_case_sensitivity(),
+            )
+        })
+    {
+        print_styled(&format!("{}\n", skipping_message(string, &err)));
+        return ExitCode::InvalidInput;
+    }
+
+    let (keys_per_second, cached) = calibrated_keys_per_second(
+        &mut run.keys_per_second,
+        &mut run.calibration_cache,
+        string_flags.get_threads(),
+        cli_flags.get_is_recalibrate(),
+    );
+    let attempts = estimate_attempts(
+        string,
+        string_flags.get_vanity_mode(),
+        string_flags.get_case_sensitivity(),
+    );
+    println!(
+        "expected attempts: {:.0}\n\
+        measured speed: {:.0} keys/sec{}\n\
+        expected time: {}\n",
+        attempts,
+        keys_per_second,
+        match cached {
+            true => " (cached, --recalibrate measures it again)",
+            false => "",
+        },
+        format_seconds(attempts / keys_per_second)
+    );
+    ExitCode::Success
+}
+
+/// Returns the builder of the search of the string with the flags of the run.
+/// The string is already adjusted, so its leading '1' is kept.
+fn search_builder(
+    cli_flags: &CliFlags,
+    string: &str,
+    string_flags: &StringsFlags,
+    interrupted: &Arc<AtomicBool>,
+) -> VanityAddrBuilder {
+    let builder = match cli_flags.get_is_random() {
+        true => VanityAddrBuilder::random(),
+        false => VanityAddrBuilder::new(string),
+    };
+    let mut builder = builder.keep_leading_one(true).options(SearchOptions {
+        unique_matches: cli_flags.get_is_unique_matches(),
+        pin_threads: cli_flags.get_is_pin_threads(),
+        timeout: cli_flags.get_timeout(),
+        max_attempts: cli_flags.get_max_attempts(),
+        max_rate: cli_flags.get_max_rate().map(|max_rate| max_rate as f64),
+        cancellation_token: Some(CancellationToken::from(Arc::clone(interrupted))),
+        ..SearchOptions::from(string_flags)
+    });
+    if let Some(seed) = cli_flags.get_seed() {
+        builder = builder.seed(seed);
+    }
+    if let Some(excluded_addresses) = cli_flags.get_excluded_addresses() {
+        builder = builder.exclude_addresses(excluded_addresses);
+    }
+    if let Some(entropy) = cli_flags.get_extra_entropy() {
+        builder = builder.extra_entropy(entropy);
+    }
+    builder
+}
+
+/// Where and how the found wallets and the search result of a string are printed or written.
+struct LineOutput<'a> {
+    cli_flags: &'a CliFlags,
+    output_writer: &'a OutputWriter,
+    /// Empty for stdout and with --output-dir.
+    output_file_name: &'a str,
+    string: &'a str,
+    vanity_mode: VanityMode,
+    case_sensitive: bool,
+}
+
+impl LineOutput<'_> {
+    /// With --format json or --template the wallets are written as a line each.
+    fn is_single_line(&self) -> bool {
+        self.cli_flags.get_output_format() == OutputFormat::Json
+            || self.cli_flags.get_template().is_some()
+    }
+
+    /// The single line format of a found wallet, None for the text format.
+    fn wallet_line(&self, wallet_output: &WalletOutput) -> Option<String> {
+        match (
+            self.cli_flags.get_template(),
+            self.cli_flags.get_output_format(),
+        ) {
+            (Some(template), _) => Some(template.render(wallet_output)),
+            #[cfg(feature = "serde")]
+            (None, OutputFormat::Json) => Some(wallet_output.to_json()),
+            (None, _) => None,
+        }
+    }
+
+    /// Every entry of a text output file starts with a header line,
+    /// --template and json write only their lines.
+    fn entry_header(&self, time: SystemTime) -> String {
+        let is_text_file = (!self.output_file_name.is_empty()
+            || self.cli_flags.get_output_dir().is_some())
+            && !self.is_single_line();
+        match is_text_file {
+            true => format_entry_header(
+                time,
+                "bitcoin",
+                self.string,
+                vanity_mode_name(self.vanity_mode),
+                self.case_sensitive,
+            ),
+            false => String::new(),
+        }
+    }
+
+    fn file_options<'b>(&'b self, written_files: &mut HashSet<String>) -> OutputFileOptions<'b> {
+        OutputFileOptions {
+            overwrite: is_first_overwrite(self.cli_flags, written_files, self.output_file_name),
+            writer: self.output_writer,
+            max_size: self.cli_flags.get_max_output_size(),
+        }
+    }
+
+    /// Prints or writes the n-th wallet found for the string.
+    /// With --no-secrets only the public part of a written wallet is printed.
+    fn write_wallet(
+        &self,
+        written_files: &mut HashSet<String>,
+        wallet_output: WalletOutput,
+        n: u64,
+    ) -> Result<(), BtcVanityError> {
+        match self.cli_flags.get_output_dir() {
+            Some(output_dir) => output_to_dir(
+                output_dir,
+                self.cli_flags.get_name_template(),
+                &wallet_output,
+                n,
+                &match self.wallet_line(&wallet_output) {
+                    Some(line) => format!("{}\n", line),
+                    None => format_text_entry(
+                        &self.entry_header(wallet_output.found_at),
+                        &wallet_output.to_text(),
+                    ),
+                },
+                self.output_writer,
+            )?,
+            None => {
+                let options = self.file_options(written_files);
+                match self.wallet_line(&wallet_output) {
+                    Some(line) => output_line(self.output_file_name, &line, &options)?,
+                    None => output(
+                        self.output_file_name,
+                        &self.entry_header(wallet_output.found_at),
+                        &wallet_output.to_styled_text(),
+                        &options,
+                    )?,
+                }
+            }
+        }
+
+        if self.cli_flags.get_is_no_secrets() {
+            let public_output = WalletOutput {
+                hide_secrets: true,
+                ..wallet_output
+            };
+            match self.wallet_line(&public_output) {
+                Some(line) => println!("{}", line),
+                None => print_styled(&public_output.to_styled_text()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints or writes the result of a search which didn't find the wallets, buffer2 for the text
+    /// output and the error for json.
+    #[cfg_attr(not(feature = "serde"), allow(unused_variables))]
+    fn write_result(
+        &self,
+        written_files: &mut HashSet<String>,
+        buffer2: &str,
+        error: &str,
+    ) -> Result<(), BtcVanityError> {
+        let options = self.file_options(written_files);
+        match self.cli_flags.get_output_format() {
+            #[cfg(feature = "serde")]
+            OutputFormat::Json => output_line(
+                self.output_file_name,
+                &error_to_json(self.string, self.vanity_mode, error),
+                &options,
+            ),
+            // --porcelain keeps stdout for the found wallets.
+            _ if self.cli_flags.get_is_porcelain() && self.output_file_name.is_empty() => {
+                eprint_styled(&format!("{}\n", buffer2));
+                Ok(())
+            }
+            _ => output(
+                self.output_file_name,
+                &self.entry_header(SystemTime::now()),
+                &format!("{}\n\n", buffer2),
+                &options,
+            ),
+        }
+    }
+}
+
+/// Appends the result of the string at the given line to the --checkpoint file if it is set.
+/// A failed write is only reported, the search results are already printed or written.
+fn record_checkpoint(
+    cli_flags: &CliFlags,
+    line: usize,
+    hash: u64,
+    exit_code: ExitCode,
+    output: &str,
+) {
+    let Some(checkpoint) = cli_flags.get_checkpoint() else {
+        return;
+    };
+    let record = CheckpointRecord {
+        line,
+        hash,
+        status: exit_code.status_name().to_string(),
+        output: output.to_string(),
+    };
+    if let Err(err) = append_checkpoint(checkpoint, &record) {
+        let message = format!("Couldn't write the checkpoint file: {}", err);
+        eprint_styled(&format!("{}\n", paint(ERROR, &message)));
+    }
+}
+
+/// Returns the index of the next string to search, or None when every string is searched.
+/// With --watch it waits for the lines appended to the input file instead, until Ctrl-C.
+fn next_string(
+    cli_flags: &mut CliFlags,
+    next: &mut usize,
+    watcher: &mut Option<InputFileWatcher>,
+    invalid_lines: &mut HashMap<usize, BtcVanityError>,
+    interrupted: &AtomicBool,
+) -> Result<Option<usize>, BtcVanityError> {
+    while *next == cli_flags.get_strings().len() {
+        let Some(watcher) = watcher.as_mut() else {
+            return Ok(None);
+        };
+        if interrupted.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        let lines = watcher.read_new_lines()?;
+        if lines.is_empty() {
+            std::thread::sleep(WATCH_INTERVAL);
+        }
+        for line in lines {
+            let index = cli_flags.get_strings().len();
+            if let Err(err) = cli_flags.push_input_line(&line) {
+                invalid_lines.insert(index, err);
+            }
+            for (line, flag) in cli_flags.get_unknown_input_flags() {
+                if line == index + 1 {
+                    warn_unknown_input_flag(line, flag);
+                }
             }
-            Err(err) => format!("Skipping because of error: {}\n\n", err),
+        }
+    }
+
+    *next += 1;
+    Ok(Some(*next - 1))
+}
+
+/// Prints the warning of an unknown flag in the input file, used with --ignore-unknown-flags.
+fn warn_unknown_input_flag(line: usize, flag: &str) {
+    eprintln!(
+        "Warning: ignoring the unknown flag '{}' in line {} of the input file.",
+        flag, line
+    );
+}
+
+/// Returns Success if every string succeeded, Interrupted if the run is interrupted,
+/// Partial if some strings succeeded and some failed, otherwise the first failed exit code.
+fn combine_exit_codes(exit_codes: &[ExitCode]) -> ExitCode {
+    let first_failure = exit_codes.iter().find(|&&code| code != ExitCode::Success);
+    match first_failure {
+        None => ExitCode::Success,
+        Some(_) if exit_codes.contains(&ExitCode::Interrupted) => ExitCode::Interrupted,
+        Some(_) if exit_codes.contains(&ExitCode::Success) => ExitCode::Partial,
+        Some(&code) => code,
+    }
+}
+
+/// Prints every supported chain with its alphabet, length limits and capabilities.
+fn print_chains() {
+    for chain in supported_chains() {
+        println!(
+            "{}\n  \
+            alphabet: {}\n  \
+            max length (fast mode): {}\n  \
+            max length: {}\n  \
+            address length: {}-{}\n  \
+            fixed prefix: {}\n  \
+            case sensitive matching: {}\n  \
+            case insensitive matching: {}\n  \
+            default: {}\n  \
+            compiled in: {}\n",
+            chain.name,
+            chain.alphabet,
+            chain.max_pattern_len(true),
+            chain.max_pattern_len(false),
+            chain.address_len.start(),
+            chain.address_len.end(),
+            chain.fixed_prefix.unwrap_or("none"),
+            yes_no(chain.supports_case_sensitive),
+            yes_no(chain.supports_case_insensitive),
+            match chain.default_case_sensitive {
+                true => "case sensitive",
+                false => "case insensitive, -c for case sensitive",
+            },
+            yes_no(chain.compiled_in)
+        );
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    match value {
+        true => "yes",
+        false => "no",
+    }
+}
+
+/// Drops the implicit leading '1' of a prefix string unless --keep-leading-one is given
+/// and tells the user about it.
+fn adjust_string<'a>(
+    cli_flags: &CliFlags,
+    string: &'a str,
+    vanity_mode: VanityMode,
+    to_stderr: bool,
+) -> &'a str {
+    if cli_flags.get_is_keep_leading_one() {
+        return string;
+    }
+
+    let adjusted = VanityAddr::adjust_input(string, vanity_mode);
+    if adjusted.len() != string.len() {
+        info(
+            to_stderr,
+            &format!(
+                "Note: every address starts with '1', so '{}' is searched as '{}'. \
+                Use --keep-leading-one to search for '1{}'.\n",
+                string, adjusted, string
+            ),
+        );
+    }
+    adjusted
+}
+
+/// Compares the expected attempts of a case sensitive string with its case insensitive ones, so
+/// a mixed case string searched with -c isn't harder than intended by accident. Returns None if
+/// the string is case insensitive or case sensitivity doesn't make it harder.
+fn case_sensitivity_note(
+    string: &str,
+    vanity_mode: VanityMode,
+    case_sensitive: bool,
+) -> Option<String> {
+    if !case_sensitive || case_sensitivity_penalty(string, &BITCOIN) <= 1.0 {
+        return None;
+    }
+    let sensitive = expected_attempts(&BITCOIN, string, vanity_mode, true)?;
+    let insensitive = expected_attempts(&BITCOIN, string, vanity_mode, false)?;
+    Some(format!(
+        "Note: case-sensitive: ~{:.1e} expected attempts; case-insensitive: ~{:.1e} ({:.0}x easier). \
+        Drop -c if any case will do.\n",
+        sensitive,
+        insensitive,
+        sensitive / insensitive
+    ))
+}
+
+/// Returns the speed of this machine with the given threads. It is measured once per run, and
+/// cached on the disk between the runs unless `recalibrate` is true.
+/// The returned bool is true if the speed is from the disk cache.
+fn calibrated_keys_per_second(
+    keys_per_second: &mut HashMap<u64, (f64, bool)>,
+    calibration_cache: &mut CalibrationCache,
+    threads: u64,
+    recalibrate: bool,
+) -> (f64, bool) {
+    *keys_per_second.entry(threads).or_insert_with(|| {
+        calibration_cache.keys_per_second(BITCOIN.name, threads, recalibrate, || {
+            measure_keys_per_second(threads, CALIBRATION_DURATION)
+        })
+    })
+}
+
+/// Tells a prefix string is harder than its estimate because few addresses start with its first
+/// characters, ex: a lowercase letter right after the leading '1'.
+fn rarity_note(string: &str, rarity: f64) -> String {
+    format!(
+        "Note: few addresses start with '1{}', it is about {:.0}x harder than the estimate.\n",
+        string, rarity
+    )
+}
+
+/// Validates every string with its resolved flags and prints a report line for each of them
+/// without searching, or a JSON line of the resolved search with --format json.
+/// Returns InvalidInput if any of the strings is invalid.
+fn dry_run(cli_flags: &CliFlags) -> ExitCode {
+    let mut exit_code = ExitCode::Success;
+
+    for (i, string) in cli_flags.get_strings().iter().enumerate() {
+        let string_flags = get_strings_flags(cli_flags, i);
+        let fast_mode = !string_flags.get_is_fast_mode_disabled();
+        let max_len = string_flags.get_max_pattern_len();
+        let output_file_name = match string_flags.get_output_file_name().as_str() {
+            "" => "stdout",
+            output_file_name => output_file_name,
         };
 
-        // If string_output_file_name is empty it just prints the buffer2 to stdout else writes the wallet to the output file.
-        if !string_flags.get_output_file_name().is_empty() {
-            write_output_file(
-                string_flags.get_output_file_name(),
-                &format!("{}\n{}", buffer1, buffer2),
+        #[cfg(feature = "serde")]
+        if cli_flags.get_output_format() == OutputFormat::Json {
+            let resolved = VanityAddr::plan(
+                string,
+                string_flags.get_vanity_mode(),
+                string_flags.get_case_sensitivity(),
+                max_len,
+                cli_flags.get_is_keep_leading_one(),
             )
-            .unwrap()
-        } else {
-            println!("{}", buffer2)
+            .map_err(|err| err.to_string());
+            if resolved.is_err() {
+                exit_code = ExitCode::InvalidInput;
+            }
+            println!(
+                "{}",
+                resolved_search_to_json(
+                    i + 1,
+                    string,
+                    string_flags.get_vanity_mode(),
+                    &resolved,
+                    string_flags.get_count(),
+                    output_file_name
+                )
+            );
+            continue;
+        }
+
+        let string = adjust_string(cli_flags, string, string_flags.get_vanity_mode(), false);
+        println!(
+            "line {}: '{}' {}, {}, {}, output: {}",
+            i + 1,
+            string,
+            vanity_mode_name(string_flags.get_vanity_mode()),
+            match string_flags.get_case_sensitivity() {
+                true => "case sensitive",
+                false => "case insensitive",
+            },
+            match fast_mode {
+                true => "fast mode",
+                false => "fast mode disabled",
+            },
+            output_file_name
+        );
+
+        let feasibility = VanityAddr::validate_input_len(string, max_len).and_then(|()| {
+            VanityAddr::validate_feasibility(
+                string,
+                string_flags.get_vanity_mode(),
+                string_flags.get_case_sensitivity(),
+            )
+        });
+        match feasibility {
+            Ok(rarity) => {
+                println!(
+                    "  expected attempts: {:.0}",
+                    estimate_attempts(
+                        string,
+                        string_flags.get_vanity_mode(),
+                        string_flags.get_case_sensitivity()
+                    ) * string_flags.get_count() as f64
+                );
+                if let Some(note) = case_sensitivity_note(
+                    string,
+                    string_flags.get_vanity_mode(),
+                    string_flags.get_case_sensitivity(),
+                ) {
+                    print_styled(&format!("  {}", paint(DIM, &note)));
+                }
+                if let Some(rarity) = rarity {
+                    print_styled(&format!("  {}", paint(DIM, &rarity_note(string, rarity))));
+                }
+                println!();
+            }
+            Err(err) => {
+                exit_code = ExitCode::InvalidInput;
+                let message = format!("error: {}", error_message(string, &err));
+                print_styled(&format!("  {}\n", paint(ERROR, &message)));
+            }
         }
     }
+
+    exit_code
+}
+
+/// Verifies the wallet given to the verify subcommand and prints the result.
+fn run_verify(matches: &clap::ArgMatches) -> ExitCode {
+    let private_key = matches.get_one::<String>("private-key").unwrap();
+    let address = matches.get_one::<String>("address").unwrap();
+    let pattern = matches.get_one::<String>("pattern");
+    let vanity_mode = match matches.get_one::<String>("mode").map(String::as_str) {
+        Some("suffix") => VanityMode::Suffix,
+        Some("anywhere") => VanityMode::Anywhere,
+        _ => VanityMode::Prefix,
+    };
+    let case_sensitive = matches.get_flag("case-sensitive");
+
+    match verify_wallet(
+        private_key,
+        address,
+        pattern.map(String::as_str),
+        vanity_mode,
+        case_sensitive,
+    ) {
+        Ok(keys_and_address) => {
+            println!(
+                "OK: the private key derives {}",
+                keys_and_address.get_comp_address()
+            );
+            if let Some(pattern) = pattern {
+                let (vanity_mode_str, case_sensitive_str) =
+                    get_decoration_strings(vanity_mode, case_sensitive);
+                println!(
+                    "OK: the address {}: '{}' {}",
+                    vanity_mode_str, pattern, case_sensitive_str
+                );
+            }
+            ExitCode::Success
+        }
+        Err(
+            err @ (BtcVanityError::AddressMismatch { .. } | BtcVanityError::PatternMismatch { .. }),
+        ) => {
+            eprint_styled(&paint(ERROR, &format!("FAILED: {}", err)));
+            ExitCode::VerifyFailed
+        }
+        Err(err) => {
+            eprint_styled(&paint(ERROR, &format!("error: {}", err)));
+            ExitCode::InvalidInput
+        }
+    }
+}
+
+/// Reads the passphrase from the first line of the password file, or asks it in the terminal.
+/// With confirm it is asked twice, so a typo doesn't make the output file unreadable.
+fn read_passphrase(
+    password_file: Option<&String>,
+    confirm: bool,
+) -> Result<String, BtcVanityError> {
+    let passphrase = match password_file {
+        Some(password_file) => fs::read_to_string(password_file)?
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        None => {
+            let passphrase = rpassword::prompt_password("Passphrase: ")?;
+            if confirm && rpassword::prompt_password("Repeat the passphrase: ")? != passphrase {
+                return Err(BtcVanityError::InvalidPassphrase {
+                    reason: "the passphrases don't match",
+                });
+            }
+            passphrase
+        }
+    };
+    match passphrase.is_empty() {
+        true => Err(BtcVanityError::InvalidPassphrase {
+            reason: "it is empty",
+        }),
+        false => Ok(passphrase),
+    }
+}
+
+/// Decrypts the file given to the decrypt-output subcommand and prints it.
+fn run_decrypt_output(matches: &clap::ArgMatches) -> ExitCode {
+    let file_name = matches.get_one::<String>("file").unwrap();
+    let decrypted = fs::read_to_string(file_name)
+        .map_err(BtcVanityError::from)
+        .and_then(|data| {
+            let passphrase =
+                read_passphrase(matches.get_one::<String>("output-password-file"), false)?;
+            decrypt_output(&data, &passphrase)
+        });
+
+    match decrypted {
+        Ok(text) => {
+            print!("{}", text);
+            ExitCode::Success
+        }
+        Err(err) => {
+            eprint_styled(&paint(ERROR, &format!("error: {}", err)));
+            ExitCode::InvalidInput
+        }
+    }
+}
+
+/// Serves the HTTP API on the --address of the serve subcommand with -t threads.
+#[cfg(feature = "serve")]
+fn run_server(matches: &clap::ArgMatches) -> ExitCode {
+    let serve_matches = matches.subcommand_matches("serve").unwrap();
+    let address = serve_matches.get_one::<String>("address").unwrap();
+    let threads = btc_vanity::flags::resolve_threads(matches.get_one::<u64>("threads").copied());
+
+    let listener = match std::net::TcpListener::bind(address) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprint_styled(&paint(
+                ERROR,
+                &format!("error: couldn't listen on {}: {}", address, err),
+            ));
+            return ExitCode::InvalidInput;
+        }
+    };
+    println!(
+        "Serving vanity address requests on http://{} with {} threads.",
+        address, threads
+    );
+    match btc_vanity::serve::serve(listener, threads) {
+        Ok(()) => ExitCode::Success,
+        Err(err) => {
+            eprint_styled(&paint(ERROR, &format!("error: {}", err)));
+            ExitCode::OutputError
+        }
+    }
+}
+
+/// Runs the benchmark with 1, 2, 4, ... up to the given threads and prints the results.
+fn run_benchmark_and_print(threads: u64) {
+    let config = BenchmarkConfig {
+        threads,
+        ..BenchmarkConfig::default()
+    };
+    println!(
+        "Benchmarking key generation and matching for {} seconds per run with up to {} threads.\n",
+        config.duration.as_secs(),
+        threads
+    );
+
+    let report = run_benchmark(&config);
+    println!(
+        "{:>8} {:>20} {:>25} {:>20}",
+        "threads", "keygen (keys/sec)", "incremental (keys/sec)", "match (keys/sec)"
+    );
+    for run in report.runs {
+        println!(
+            "{:>8} {:>20.0} {:>25.0} {:>20.0}",
+            run.threads,
+            run.keygen_keys_per_second,
+            run.incremental_keys_per_second,
+            run.match_keys_per_second
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    {
+        use btc_vanity::bench::measure_search;
+        use btc_vanity::vanity_addr_generator::Backend;
+
+        println!(
+            "\n{:>8} {:>20} {:>20}",
+            "threads", "threads (keys/sec)", "rayon (keys/sec)"
+        );
+        println!(
+            "{:>8} {:>20.0} {:>20.0}",
+            threads,
+            measure_search(Backend::Threads, threads, config.duration, &config.pattern),
+            measure_search(Backend::Rayon, threads, config.duration, &config.pattern)
+        );
+    }
+}
+
+/// Returns the message printed for a string skipped because of the error.
+fn skipping_message(string: &str, err: &BtcVanityError) -> String {
+    let message = format!("Skipping because of error: {}", error_message(string, err));
+    paint(ERROR, &message)
+}
+
+/// Returns the error message. Invalid characters are pointed at with a caret line under the string.
+fn error_message(string: &str, err: &BtcVanityError) -> String {
+    match err {
+        BtcVanityError::InvalidCharacter { ch, index } => format!(
+            "{}\n{}",
+            err,
+            get_invalid_character_hint(string, *ch, *index)
+        ),
+        _ => err.to_string(),
+    }
+}
+
+/// Prints a progress message to stdout, or to stderr if stdout is reserved for JSON output.
+fn info(to_stderr: bool, message: &str) {
+    match to_stderr {
+        true => eprint_styled(&paint(DIM, message)),
+        false => print_styled(&paint(DIM, message)),
+    }
+}
+
+/// Prints the styled text to stdout. The colors are removed if stdout isn't a terminal,
+/// --no-color is given or NO_COLOR is set.
+fn print_styled(text: &str) {
+    let _ = writeln!(anstream::stdout(), "{}", text);
+}
+
+/// Same as `print_styled` but prints to stderr.
+fn eprint_styled(text: &str) {
+    let _ = writeln!(anstream::stderr(), "{}", text);
+}
+
+/// Prints the question to stderr and returns true if the answer read from stdin is y or yes.
+fn confirm(question: &str) -> bool {
+    eprint!("{}", question);
+    let _ = std::io::stderr().flush();
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Returns true if --overwrite is set and this is the first write to the output file in this run,
+/// so the patterns after the first one append to the same file instead of truncating it.
+fn is_first_overwrite(
+    cli_flags: &CliFlags,
+    written_files: &mut HashSet<String>,
+    output_file_name: &str,
+) -> bool {
+    cli_flags.get_is_overwrite() && written_files.insert(output_file_name.to_string())
+}
+
+/// Writes the found wallet to a new file in output_dir named with the name_template.
+/// n is the number of the wallet found for the same string.
+fn output_to_dir(
+    output_dir: &str,
+    name_template: &str,
+    wallet_output: &WalletOutput,
+    n: u64,
+    content: &str,
+    writer: &OutputWriter,
+) -> Result<(), BtcVanityError> {
+    let file_name = expand_name_template(
+        name_template,
+        wallet_output.chain,
+        &wallet_output.pattern,
+        n,
+    );
+    let path = get_unique_output_path(Path::new(output_dir), &file_name)?;
+    write_output_file(
+        &path.to_string_lossy(),
+        content,
+        &OutputFileOptions {
+            overwrite: true,
+            writer,
+            max_size: None,
+        },
+    )
+}
+
+/// If output_file_name is empty it just prints the buffer2 to stdout else writes it to the output file
+/// as an entry with the header line. Without a header, ex: with --template, only buffer2 is written.
+/// The colors of buffer2 are only kept for the terminal.
+/// The output file is written with the options, see write_output_file.
+fn output(
+    output_file_name: &str,
+    header: &str,
+    buffer2: &str,
+    options: &OutputFileOptions,
+) -> Result<(), BtcVanityError> {
+    if !output_file_name.is_empty() {
+        let buffer2 = strip_str(buffer2).to_string();
+        write_output_file(
+            output_file_name,
+            &match header.is_empty() {
+                true => buffer2,
+                false => format_text_entry(header, &buffer2),
+            },
+            options,
+        )
+    } else {
+        print_styled(buffer2);
+        Ok(())
+    }
+}
+
+/// If output_file_name is empty it just prints the line, ex: a JSON object, to stdout else appends it
+/// to the output file as a new line. The lines are appended with JsonlWriter, so a crash doesn't
+/// corrupt the previous lines and other runs can append to the same file.
+fn output_line(
+    output_file_name: &str,
+    line: &str,
+    options: &OutputFileOptions,
+) -> Result<(), BtcVanityError> {
+    if output_file_name.is_empty() {
+        println!("{}", line);
+        Ok(())
+    } else if options.overwrite {
+        write_output_file(output_file_name, &format!("{}\n", line), options)
+    } else {
+        JsonlWriter::open(output_file_name, options.max_size)?.append(&options.writer.encode(line)?)
+    }
 }
@@ -0,0 +1,897 @@
+//! # Wallet Output Module
+//!
+//! This module is used for formatting found wallets the same way for stdout and output files,
+//! either as the classic text block, as a single line JSON object or as a single line
+//! formatted with a --template.
+
+use crate::decoration::{paint, DIM, HIGHLIGHT};
+use crate::error::BtcVanityError;
+#[cfg(feature = "serde")]
+use crate::vanity_addr_generator::{estimate::estimate_attempts, ResolvedSearch};
+use crate::vanity_addr_generator::{SearchResult, VanityMode};
+use std::fmt::Write;
+use std::ops::Range;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Output formats for the found wallets.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Everything printed about a found wallet.
+pub struct WalletOutput {
+    pub chain: &'static str,
+    pub pattern: String,
+    pub mode: &'static str,
+    pub address: String,
+    pub private_key_hex: String,
+    pub private_key_wif: String,
+    pub public_key: String,
+    pub duration_secs: f64,
+    pub attempts: u64,
+    /// Average speed of the search until the wallet was found.
+    pub keys_per_second: f64,
+    /// Byte range of the address which matched the pattern.
+    pub match_range: Range<usize>,
+    pub found_at: SystemTime,
+    /// Appends the search metadata to the outputs if true.
+    pub stats: bool,
+    /// Leaves the private keys out of the outputs if true.
+    pub hide_secrets: bool,
+}
+
+/// Returns the name of the vanity mode used in machine-readable outputs.
+pub fn vanity_mode_name(vanity_mode: VanityMode) -> &'static str {
+    match vanity_mode {
+        VanityMode::Prefix => "prefix",
+        VanityMode::Suffix => "suffix",
+        VanityMode::Anywhere => "anywhere",
+    }
+}
+
+/// Formats the time as an ISO 8601 UTC timestamp like 2024-01-31T12:00:00Z.
+pub fn format_utc_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+
+    // Converts the days since 1970-01-01 to a civil date, see Howard Hinnant's days_from_civil.
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Formats a count with thousands separators. ex: 48,230,912
+fn format_thousands(count: u64) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Formats a speed with a metric prefix. ex: 3.89 Mkeys/s
+fn format_rate(keys_per_second: f64) -> String {
+    const UNITS: [(f64, &str); 3] = [(1e9, "G"), (1e6, "M"), (1e3, "k")];
+
+    for (unit_rate, unit) in UNITS {
+        if keys_per_second >= unit_rate {
+            return format!("{:.2} {}keys/s", keys_per_second / unit_rate, unit);
+        }
+    }
+    format!("{:.0} keys/s", keys_per_second)
+}
+
+/// Formats how long a search took until a wallet was found, used by the "FOUND IN" line of the
+/// CLI, ex: `12.41s after 48,230,912 candidates (3.89 Mkeys/s)`
+pub fn format_found_summary(duration_secs: f64, attempts: u64, keys_per_second: f64) -> String {
+    format!(
+        "{:.2}s after {} candidates ({})",
+        duration_secs,
+        format_thousands(attempts),
+        format_rate(keys_per_second)
+    )
+}
+
+/// Serializes the value as a single line JSON object.
+#[cfg(feature = "serde")]
+fn to_json_line<T: serde::Serialize>(value: &T) -> String {
+    // The JSON objects only have strings, numbers and options, they can't fail to serialize.
+    serde_json::to_string(value).unwrap()
+}
+
+/// The JSON object of a found wallet, see `WalletOutput::to_json`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct WalletJson<'a> {
+    chain: &'a str,
+    pattern: &'a str,
+    mode: &'a str,
+    address: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    private_key_wif: Option<&'a str>,
+    public_key: &'a str,
+    duration_secs: f64,
+    attempts: u64,
+    keys_per_second: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    found_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<&'a str>,
+}
+
+/// The JSON object of a string that couldn't be found, see `error_to_json`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct ErrorJson<'a> {
+    chain: &'a str,
+    pattern: &'a str,
+    mode: &'a str,
+    error: &'a str,
+}
+
+/// The JSON object of a resolved string of the input, see `resolved_search_to_json`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct ResolvedSearchJson<'a> {
+    line: usize,
+    chain: &'a str,
+    input: &'a str,
+    pattern: &'a str,
+    mode: &'a str,
+    case_sensitive: bool,
+    max_len: usize,
+    address: String,
+    expected_attempts: f64,
+    rarity: Option<f64>,
+    output: &'a str,
+}
+
+/// The JSON object of an invalid string of the input, see `resolved_search_to_json`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct InvalidSearchJson<'a> {
+    line: usize,
+    chain: &'a str,
+    input: &'a str,
+    mode: &'a str,
+    output: &'a str,
+    error: &'a str,
+}
+
+/// The JSON object of the run summary, see `RunSummary::to_json`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct RunSummaryJson<'a> {
+    lines: &'a [LineSummary],
+    strings: usize,
+    failures: usize,
+    total_attempts: u64,
+    wall_secs: f64,
+}
+
+/// The line after every entry of the text output file.
+pub const ENTRY_SEPARATOR: &str = "----------------------------------------";
+
+/// Formats the first line of an entry of the text output file, so the entries of a file shared by
+/// many runs can be told apart, ex:
+/// `[2024-01-31T12:00:00Z] chain: bitcoin, pattern: 'Emiv', mode: prefix, case sensitive: true, btc-vanity: 1.3.1`
+pub fn format_entry_header(
+    time: SystemTime,
+    chain: &str,
+    pattern: &str,
+    mode: &str,
+    case_sensitive: bool,
+) -> String {
+    format!(
+        "[{}] chain: {}, pattern: '{}', mode: {}, case sensitive: {}, btc-vanity: {}",
+        format_utc_timestamp(time),
+        chain,
+        pattern,
+        mode,
+        case_sensitive,
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// Formats an entry of the text output file: the header line, the text and the separator line.
+pub fn format_text_entry(header: &str, text: &str) -> String {
+    format!("{}\n{}\n{}\n", header, text.trim_end(), ENTRY_SEPARATOR)
+}
+
+impl WalletOutput {
+    /// Collects the output fields of a found vanity address.
+    pub fn new(pattern: &str, vanity_mode: VanityMode, result: &SearchResult) -> Self {
+        let keys_and_address = &result.keys_and_address;
+
+        // Format the private key hex value
+        let private_key_hex = keys_and_address.get_private_key().to_bytes().iter().fold(
+            String::new(),
+            |mut acc, byte| {
+                write!(&mut acc, "{:02X}", byte).unwrap();
+                acc
+            },
+        );
+
+        let duration_secs = result.duration.as_secs_f64();
+        WalletOutput {
+            chain: "bitcoin",
+            pattern: pattern.to_string(),
+            mode: vanity_mode_name(vanity_mode),
+            address: keys_and_address.get_comp_address().to_string(),
+            private_key_hex,
+            private_key_wif: keys_and_address.get_wif_private_key(),
+            public_key: keys_and_address.get_comp_public_key(),
+            duration_secs,
+            attempts: result.attempts,
+            keys_per_second: match duration_secs > 0.0 {
+                true => result.attempts as f64 / duration_secs,
+                false => 0.0,
+            },
+            match_range: result.match_range.clone(),
+            found_at: SystemTime::now(),
+            stats: false,
+            hide_secrets: false,
+        }
+    }
+
+    /// Formats the duration, the attempts and the speed of the search, see `format_found_summary`.
+    pub fn found_summary(&self) -> String {
+        format_found_summary(self.duration_secs, self.attempts, self.keys_per_second)
+    }
+
+    /// Formats the found key pair and the address which has the string.
+    pub fn to_text(&self) -> String {
+        let secrets = match self.hide_secrets {
+            true => String::new(),
+            false => format!(
+                "private_key (hex): {}\n\
+                private_key (wif): {}\n",
+                self.private_key_hex, self.private_key_wif
+            ),
+        };
+        format!(
+            "{}public_key (compressed): {}\n\
+            address (compressed): {}\n{}\n",
+            secrets,
+            self.public_key,
+            self.address,
+            self.stats_text()
+        )
+    }
+
+    /// Formats the search metadata block if stats are enabled, otherwise returns an empty string.
+    fn stats_text(&self) -> String {
+        if !self.stats {
+            return String::new();
+        }
+        format!(
+            "found at: {}\n\
+            duration: {:.4} seconds\n\
+            attempts: {}\n\
+            speed: {:.0} keys/sec\n\
+            pattern: '{}' ({}, {})\n\
+            btc-vanity version: {}\n",
+            format_utc_timestamp(self.found_at),
+            self.duration_secs,
+            self.attempts,
+            self.keys_per_second,
+            self.pattern,
+            self.mode,
+            self.chain,
+            env!("CARGO_PKG_VERSION")
+        )
+    }
+
+    /// Same as `to_text` but the labels are dimmed and the matched part of the address is highlighted.
+    pub fn to_styled_text(&self) -> String {
+        let address = match self.address.get(self.match_range.clone()) {
+            Some(matched) => format!(
+                "{}{}{}",
+                &self.address[..self.match_range.start],
+                paint(HIGHLIGHT, matched),
+                &self.address[self.match_range.end..]
+            ),
+            None => self.address.clone(),
+        };
+        let secrets = match self.hide_secrets {
+            true => String::new(),
+            false => format!(
+                "{} {}\n\
+                {} {}\n",
+                paint(DIM, "private_key (hex):"),
+                self.private_key_hex,
+                paint(DIM, "private_key (wif):"),
+                self.private_key_wif
+            ),
+        };
+        format!(
+            "{}{} {}\n\
+            {} {}\n{}\n",
+            secrets,
+            paint(DIM, "public_key (compressed):"),
+            self.public_key,
+            paint(DIM, "address (compressed):"),
+            address,
+            match self.stats {
+                true => paint(DIM, &self.stats_text()),
+                false => String::new(),
+            }
+        )
+    }
+
+    /// Formats the wallet as a single line JSON object. With stats the found time
+    /// and the btc-vanity version are added too.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        to_json_line(&WalletJson {
+            chain: self.chain,
+            pattern: &self.pattern,
+            mode: self.mode,
+            address: &self.address,
+            private_key_wif: (!self.hide_secrets).then_some(self.private_key_wif.as_str()),
+            public_key: &self.public_key,
+            duration_secs: self.duration_secs,
+            attempts: self.attempts,
+            keys_per_second: self.keys_per_second.round() as u64,
+            found_at: self.stats.then(|| format_utc_timestamp(self.found_at)),
+            version: self.stats.then_some(env!("CARGO_PKG_VERSION")),
+        })
+    }
+}
+
+/// The fields of a found wallet a template can have.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Placeholder {
+    Address,
+    PrivateKey,
+    PrivateKeyHex,
+    PublicKey,
+    Pattern,
+    Mode,
+    Chain,
+    Timestamp,
+    Attempts,
+    Duration,
+    KeysPerSecond,
+}
+
+/// The names of the placeholders in templates, without the braces.
+const PLACEHOLDERS: [(&str, Placeholder); 11] = [
+    ("address", Placeholder::Address),
+    ("private_key", Placeholder::PrivateKey),
+    ("private_key_hex", Placeholder::PrivateKeyHex),
+    ("public_key", Placeholder::PublicKey),
+    ("pattern", Placeholder::Pattern),
+    ("mode", Placeholder::Mode),
+    ("chain", Placeholder::Chain),
+    ("timestamp", Placeholder::Timestamp),
+    ("attempts", Placeholder::Attempts),
+    ("duration", Placeholder::Duration),
+    ("keys_per_second", Placeholder::KeysPerSecond),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TemplatePart {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// The template of --porcelain. The fields and their order are stable, so scripts can rely on them:
+/// the address, the private key in WIF and the compressed public key in hex, separated with tabs.
+pub const PORCELAIN_TEMPLATE: &str = "{address}\t{private_key}\t{public_key}";
+
+/// A --template like "{address},{private_key}" which formats every found wallet as a single line.
+/// It is parsed before searching, so unknown placeholders are reported before anything is found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+impl OutputTemplate {
+    /// Parses the template. `{{` and `}}` are literal braces.
+    /// Returns Err if a placeholder is unknown or isn't closed.
+    pub fn parse(template: &str) -> Result<Self, BtcVanityError> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut rest = template;
+        while let Some(c) = rest.chars().next() {
+            rest = &rest[c.len_utf8()..];
+            match c {
+                '{' | '}' if rest.starts_with(c) => {
+                    rest = &rest[1..];
+                    text.push(c);
+                }
+                '{' => {
+                    let Some(end) = rest.find('}') else {
+                        return Err(BtcVanityError::InvalidTemplate {
+                            placeholder: format!("{{{}", rest),
+                        });
+                    };
+                    let name = &rest[..end];
+                    let Some((_, placeholder)) = PLACEHOLDERS.iter().find(|(n, _)| *n == name)
+                    else {
+                        return Err(BtcVanityError::InvalidTemplate {
+                            placeholder: format!("{{{}}}", name),
+                        });
+                    };
+                    if !text.is_empty() {
+                        parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(TemplatePart::Placeholder(*placeholder));
+                    rest = &rest[end + 1..];
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(TemplatePart::Text(text));
+        }
+
+        Ok(OutputTemplate { parts })
+    }
+
+    /// Formats the wallet with the template, without a trailing newline.
+    /// The private key placeholders are left empty if the secrets of the wallet are hidden.
+    pub fn render(&self, wallet_output: &WalletOutput) -> String {
+        let mut line = String::new();
+        for part in &self.parts {
+            let placeholder = match part {
+                TemplatePart::Text(text) => {
+                    line.push_str(text);
+                    continue;
+                }
+                TemplatePart::Placeholder(placeholder) => placeholder,
+            };
+            match placeholder {
+                Placeholder::Address => line.push_str(&wallet_output.address),
+                Placeholder::PrivateKey | Placeholder::PrivateKeyHex
+                    if wallet_output.hide_secrets => {}
+                Placeholder::PrivateKey => line.push_str(&wallet_output.private_key_wif),
+                Placeholder::PrivateKeyHex => line.push_str(&wallet_output.private_key_hex),
+                Placeholder::PublicKey => line.push_str(&wallet_output.public_key),
+                Placeholder::Pattern => line.push_str(&wallet_output.pattern),
+                Placeholder::Mode => line.push_str(wallet_output.mode),
+                Placeholder::Chain => line.push_str(wallet_output.chain),
+                Placeholder::Timestamp => {
+                    line.push_str(&format_utc_timestamp(wallet_output.found_at))
+                }
+                Placeholder::Attempts => write!(&mut line, "{}", wallet_output.attempts).unwrap(),
+                Placeholder::Duration => {
+                    write!(&mut line, "{:.4}", wallet_output.duration_secs).unwrap()
+                }
+                Placeholder::KeysPerSecond => {
+                    write!(&mut line, "{:.0}", wallet_output.keys_per_second).unwrap()
+                }
+            }
+        }
+        line
+    }
+}
+
+/// Formats an error of a string that couldn't be found as a single line JSON object.
+#[cfg(feature = "serde")]
+pub fn error_to_json(pattern: &str, vanity_mode: VanityMode, error: &str) -> String {
+    to_json_line(&ErrorJson {
+        chain: "bitcoin",
+        pattern,
+        mode: vanity_mode_name(vanity_mode),
+        error,
+    })
+}
+
+/// Formats the search a string of the input resolves to as a single line JSON object, or the
+/// error of the string if it is invalid. `count` is the number of wallets searched for and
+/// `output` where they are written to.
+#[cfg(feature = "serde")]
+pub fn resolved_search_to_json(
+    line: usize,
+    input: &str,
+    vanity_mode: VanityMode,
+    resolved: &Result<ResolvedSearch, String>,
+    count: u64,
+    output: &str,
+) -> String {
+    let resolved = match resolved {
+        Ok(resolved) => resolved,
+        Err(error) => {
+            return to_json_line(&InvalidSearchJson {
+                line,
+                chain: "bitcoin",
+                input,
+                mode: vanity_mode_name(vanity_mode),
+                output,
+                error,
+            })
+        }
+    };
+
+    to_json_line(&ResolvedSearchJson {
+        line,
+        chain: resolved.chain,
+        input: &resolved.input,
+        pattern: &resolved.pattern,
+        mode: vanity_mode_name(resolved.vanity_mode),
+        case_sensitive: resolved.case_sensitive,
+        max_len: resolved.max_len,
+        address: resolved.address_shape(),
+        expected_attempts: (estimate_attempts(
+            &resolved.pattern,
+            resolved.vanity_mode,
+            resolved.case_sensitive,
+        ) * count as f64)
+            .round(),
+        rarity: resolved.rarity.map(|rarity| (rarity * 10.0).round() / 10.0),
+        output,
+    })
+}
+
+/// Outcome of a string of the run, listed in the run summary.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LineSummary {
+    /// Line number of the string in the input file, starting from 1.
+    pub line: usize,
+    pub pattern: String,
+    /// One of found, skipped, declined, not-found, interrupted, output-error or invalid.
+    pub status: &'static str,
+    /// Number of wallets found.
+    pub found: u64,
+    /// Number of candidates tried, 0 if the string isn't searched in this run.
+    pub attempts: u64,
+    pub duration_secs: f64,
+    /// Where the found wallets are written to.
+    pub output: String,
+}
+
+impl LineSummary {
+    /// Returns false if the string isn't found or skipped.
+    pub fn is_success(&self) -> bool {
+        matches!(self.status, "found" | "skipped")
+    }
+}
+
+/// Outcomes of all the strings of the run with the totals.
+pub struct RunSummary {
+    pub lines: Vec<LineSummary>,
+    /// Time elapsed from the first string until the end of the run.
+    pub wall_secs: f64,
+}
+
+impl RunSummary {
+    pub fn total_attempts(&self) -> u64 {
+        self.lines.iter().map(|line| line.attempts).sum()
+    }
+
+    pub fn failures(&self) -> usize {
+        self.lines.iter().filter(|line| !line.is_success()).count()
+    }
+
+    /// Formats the summary as a table with a line for every string and the totals.
+    pub fn to_table(&self) -> String {
+        let mut table = format!(
+            "{:>5}  {:<12} {:>5} {:>14} {:>12}  {:<20} pattern\n",
+            "line", "status", "found", "attempts", "seconds", "output"
+        );
+        for line in &self.lines {
+            writeln!(
+                &mut table,
+                "{:>5}  {:<12} {:>5} {:>14} {:>12.4}  {:<20} '{}'",
+                line.line,
+                line.status,
+                line.found,
+                line.attempts,
+                line.duration_secs,
+                line.output,
+                line.pattern
+            )
+            .unwrap();
+        }
+        write!(
+            &mut table,
+            "Total: {} strings, {} failed, {} attempts in {:.4} seconds.",
+            self.lines.len(),
+            self.failures(),
+            self.total_attempts(),
+            self.wall_secs
+        )
+        .unwrap();
+        table
+    }
+
+    /// Formats the summary as a JSON object, ex: for --summary-file.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        to_json_line(&RunSummaryJson {
+            lines: &self.lines,
+            strings: self.lines.len(),
+            failures: self.failures(),
+            total_attempts: self.total_attempts(),
+            wall_secs: self.wall_secs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wallet_output() -> WalletOutput {
+        WalletOutput {
+            chain: "bitcoin",
+            pattern: String::from("emiv"),
+            mode: "prefix",
+            address: String::from("1EmivB8uShHdr4Dft4yGRpvDEUbdUDXkxT"),
+            private_key_hex: String::from("00"),
+            private_key_wif: String::from("KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn"),
+            public_key: String::from(
+                "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            ),
+            duration_secs: 1.5,
+            attempts: 1234,
+            keys_per_second: 822.7,
+            match_range: 1..5,
+            found_at: UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000),
+            stats: false,
+            hide_secrets: false,
+        }
+    }
+
+    #[test]
+    fn test_to_styled_text() {
+        let wallet_output = wallet_output();
+        let styled_text = wallet_output.to_styled_text();
+
+        assert!(styled_text.contains(&format!("1{}B8uSh", paint(HIGHLIGHT, "Emiv"))));
+        assert_eq!(
+            anstream::adapter::strip_str(&styled_text).to_string(),
+            wallet_output.to_text()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_to_json() {
+        assert_eq!(
+            wallet_output().to_json(),
+            "{\"chain\":\"bitcoin\",\"pattern\":\"emiv\",\"mode\":\"prefix\",\
+            \"address\":\"1EmivB8uShHdr4Dft4yGRpvDEUbdUDXkxT\",\
+            \"private_key_wif\":\"KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn\",\
+            \"public_key\":\"0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798\",\
+            \"duration_secs\":1.5,\"attempts\":1234,\"keys_per_second\":823}"
+        );
+    }
+
+    #[test]
+    fn test_found_summary() {
+        assert_eq!(
+            format_found_summary(12.41, 48_230_912, 3_886_455.4),
+            "12.41s after 48,230,912 candidates (3.89 Mkeys/s)"
+        );
+        assert_eq!(
+            wallet_output().found_summary(),
+            "1.50s after 1,234 candidates (823 keys/s)"
+        );
+        assert_eq!(format_thousands(0), "0");
+        assert_eq!(format_thousands(999), "999");
+        assert_eq!(format_thousands(1_000), "1,000");
+        assert_eq!(format_rate(1_500.0), "1.50 kkeys/s");
+        assert_eq!(format_rate(2.5e9), "2.50 Gkeys/s");
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats_output = WalletOutput {
+            stats: true,
+            ..wallet_output()
+        };
+        let text = stats_output.to_text();
+
+        assert!(text.ends_with(&format!(
+            "address (compressed): 1EmivB8uShHdr4Dft4yGRpvDEUbdUDXkxT\n\
+            found at: 2023-11-14T22:13:20Z\n\
+            duration: 1.5000 seconds\n\
+            attempts: 1234\n\
+            speed: 823 keys/sec\n\
+            pattern: 'emiv' (prefix, bitcoin)\n\
+            btc-vanity version: {}\n\n",
+            env!("CARGO_PKG_VERSION")
+        )));
+        assert_eq!(
+            anstream::adapter::strip_str(&stats_output.to_styled_text()).to_string(),
+            text
+        );
+        #[cfg(feature = "serde")]
+        assert!(stats_output.to_json().ends_with(&format!(
+            "\"attempts\":1234,\"keys_per_second\":823,\"found_at\":\"2023-11-14T22:13:20Z\",\"version\":\"{}\"}}",
+            env!("CARGO_PKG_VERSION")
+        )));
+        assert!(!wallet_output().to_text().contains("found at: "));
+    }
+
+    #[test]
+    fn test_hide_secrets() {
+        let public_output = WalletOutput {
+            hide_secrets: true,
+            ..wallet_output()
+        };
+        #[allow(unused_mut)]
+        let mut outputs = vec![public_output.to_text(), public_output.to_styled_text()];
+        #[cfg(feature = "serde")]
+        outputs.push(public_output.to_json());
+
+        for output in outputs {
+            assert!(!output.contains("private_key"));
+            assert!(!output.contains(&public_output.private_key_wif));
+            assert!(output.contains("B8uShHdr4Dft4yGRpvDEUbdUDXkxT"));
+        }
+    }
+
+    #[test]
+    fn test_template_render() {
+        let template =
+            OutputTemplate::parse("{address},{private_key} {{{pattern}}} {attempts}/{timestamp}")
+                .unwrap();
+
+        assert_eq!(
+            template.render(&wallet_output()),
+            "1EmivB8uShHdr4Dft4yGRpvDEUbdUDXkxT,\
+            KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn {emiv} 1234/2023-11-14T22:13:20Z"
+        );
+        let public_output = WalletOutput {
+            hide_secrets: true,
+            ..wallet_output()
+        };
+        assert_eq!(
+            template.render(&public_output),
+            "1EmivB8uShHdr4Dft4yGRpvDEUbdUDXkxT, {emiv} 1234/2023-11-14T22:13:20Z"
+        );
+    }
+
+    #[test]
+    fn test_template_invalid_placeholder() {
+        for (template, placeholder) in [("{address},{wif}", "{wif}"), ("{address", "{address")] {
+            assert!(matches!(
+                OutputTemplate::parse(template),
+                Err(BtcVanityError::InvalidTemplate { placeholder: p }) if p == placeholder
+            ));
+        }
+        assert!(OutputTemplate::parse("no placeholders }").is_ok());
+    }
+
+    #[test]
+    fn test_format_utc_timestamp() {
+        assert_eq!(format_utc_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            format_utc_timestamp(UNIX_EPOCH + std::time::Duration::from_secs(951_825_599)),
+            "2000-02-29T11:59:59Z"
+        );
+    }
+
+    #[test]
+    fn test_text_entry() {
+        let header = format_entry_header(UNIX_EPOCH, "bitcoin", "Emiv", "prefix", true);
+        assert_eq!(
+            header,
+            format!(
+                "[1970-01-01T00:00:00Z] chain: bitcoin, pattern: 'Emiv', mode: prefix, \
+                case sensitive: true, btc-vanity: {}",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+        assert_eq!(
+            format_text_entry("header", "address (compressed): 1Emiv\n\n"),
+            format!("header\naddress (compressed): 1Emiv\n{}\n", ENTRY_SEPARATOR)
+        );
+    }
+
+    fn run_summary() -> RunSummary {
+        let line_summary = |line, pattern: &str, status, found, attempts| LineSummary {
+            line,
+            pattern: pattern.to_string(),
+            status,
+            found,
+            attempts,
+            duration_secs: 0.5,
+            output: String::from("stdout"),
+        };
+        RunSummary {
+            lines: vec![
+                line_summary(1, "Emiv", "found", 1, 1000),
+                line_summary(2, "Em0", "invalid", 0, 0),
+                line_summary(3, "TALA", "not-found", 0, 500),
+            ],
+            wall_secs: 1.5,
+        }
+    }
+
+    #[test]
+    fn test_run_summary_table() {
+        let table = run_summary().to_table();
+        let lines = table.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("    1  found"));
+        assert!(lines[2].ends_with("'Em0'"));
+        assert_eq!(
+            lines[4],
+            "Total: 3 strings, 2 failed, 1500 attempts in 1.5000 seconds."
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_run_summary_json() {
+        let json: serde_json::Value = serde_json::from_str(&run_summary().to_json()).unwrap();
+
+        assert_eq!(json["strings"], 3);
+        assert_eq!(json["failures"], 2);
+        assert_eq!(json["total_attempts"], 1500);
+        assert_eq!(json["lines"][2]["status"], "not-found");
+        assert_eq!(json["lines"][0]["output"], "stdout");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_error_to_json_escapes() {
+        assert_eq!(
+            error_to_json("a\"b", VanityMode::Anywhere, "line1\nline2 \\ end"),
+            "{\"chain\":\"bitcoin\",\"pattern\":\"a\\\"b\",\"mode\":\"anywhere\",\
+            \"error\":\"line1\\nline2 \\\\ end\"}"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_resolved_search_to_json() {
+        use crate::vanity_addr_generator::VanityAddr;
+
+        let resolved = VanityAddr::plan("1Emiv", VanityMode::Prefix, false, 4, false)
+            .map_err(|err| err.to_string());
+        let json = resolved_search_to_json(1, "1Emiv", VanityMode::Prefix, &resolved, 2, "out.txt");
+        assert!(json.starts_with(
+            "{\"line\":1,\"chain\":\"bitcoin\",\"input\":\"1Emiv\",\"pattern\":\"Emiv\",\
+            \"mode\":\"prefix\",\"case_sensitive\":false,\"max_len\":4,\"address\":\"1Emiv...\",\
+            \"expected_attempts\":"
+        ));
+        assert!(json.ends_with(",\"rarity\":null,\"output\":\"out.txt\"}"));
+
+        let json = resolved_search_to_json(
+            2,
+            "Em0",
+            VanityMode::Suffix,
+            &Err(String::from("invalid")),
+            1,
+            "stdout",
+        );
+        assert_eq!(
+            json,
+            "{\"line\":2,\"chain\":\"bitcoin\",\"input\":\"Em0\",\"mode\":\"suffix\",\
+            \"output\":\"stdout\",\"error\":\"invalid\"}"
+        );
+    }
+}
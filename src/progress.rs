@@ -0,0 +1,124 @@
+//! # Progress Line Module
+//!
+//! This module is used for drawing a live progress line to stderr while a vanity address
+//! is being searched, showing the attempts, the speed, the elapsed time and an ETA.
+
+use crate::vanity_addr_generator::estimate::format_seconds;
+use crate::vanity_addr_generator::SearchStatsSnapshot;
+
+use std::io::Write;
+use std::sync::{Mutex, MutexGuard};
+
+/// ANSI escape sequence that moves the cursor to the line start and clears the line.
+const CLEAR_LINE: &str = "\r\x1b[2K";
+
+/// Formats big counts with a metric suffix. ex: 412.3M
+pub fn format_count(count: f64) -> String {
+    const UNITS: [(f64, &str); 4] = [(1e12, "T"), (1e9, "G"), (1e6, "M"), (1e3, "K")];
+
+    for (unit_count, unit) in UNITS {
+        if count >= unit_count {
+            return format!("{:.1}{}", count / unit_count, unit);
+        }
+    }
+    format!("{:.0}", count)
+}
+
+/// Formats the progress line of a search with its current speed.
+fn format_progress(stats: &SearchStatsSnapshot, expected_attempts: f64) -> String {
+    let eta = if stats.keys_per_second > 0.0 {
+        let remaining = (expected_attempts - stats.attempts as f64).max(0.0);
+        format_seconds(remaining / stats.keys_per_second)
+    } else {
+        String::from("unknown")
+    };
+
+    format!(
+        "{} attempts | {} keys/sec | elapsed {} | ETA ~{}",
+        format_count(stats.attempts as f64),
+        format_count(stats.keys_per_second),
+        format_seconds(stats.elapsed.as_secs_f64()),
+        eta
+    )
+}
+
+/// The progress line of a search, redrawn by the progress callback of the search.
+pub struct ProgressLine {
+    expected_attempts: f64,
+    line_lock: Mutex<()>,
+}
+
+impl ProgressLine {
+    /// `expected_attempts` is used to compute the ETA.
+    pub fn new(expected_attempts: f64) -> Self {
+        ProgressLine {
+            expected_attempts,
+            line_lock: Mutex::new(()),
+        }
+    }
+
+    /// Redraws the progress line with the statistics of the search.
+    pub fn update(&self, stats: &SearchStatsSnapshot) {
+        let line = format_progress(stats, self.expected_attempts);
+        let _guard = self.line_lock.lock().unwrap_or_else(|err| err.into_inner());
+        let mut stderr = std::io::stderr();
+        let _ = write!(stderr, "{}{}", CLEAR_LINE, line);
+        let _ = stderr.flush();
+    }
+
+    /// Clears the progress line and keeps it from being redrawn until the returned guard is
+    /// dropped, so other output can be printed in between.
+    pub fn clear(&self) -> MutexGuard<'_, ()> {
+        let guard = self.line_lock.lock().unwrap_or_else(|err| err.into_inner());
+        let mut stderr = std::io::stderr();
+        let _ = write!(stderr, "{}", CLEAR_LINE);
+        let _ = stderr.flush();
+        guard
+    }
+
+    /// Clears the progress line after the search is finished.
+    pub fn finish(&self) {
+        drop(self.clear());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(999.0), "999");
+        assert_eq!(format_count(1_500.0), "1.5K");
+        assert_eq!(format_count(412_300_000.0), "412.3M");
+    }
+
+    fn stats(attempts: u64, keys_per_second: f64) -> SearchStatsSnapshot {
+        SearchStatsSnapshot {
+            attempts,
+            elapsed: Duration::from_secs(2),
+            keys_per_second,
+            running: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_format_progress() {
+        assert_eq!(
+            format_progress(&stats(2_000_000, 1_000_000.0), 3_000_000.0),
+            "2.0M attempts | 1.0M keys/sec | elapsed 2.0 seconds | ETA ~1.0 seconds"
+        );
+        // ETA can't go below zero when the search takes longer than expected.
+        assert_eq!(
+            format_progress(&stats(4_000_000, 2_000_000.0), 3_000_000.0),
+            "4.0M attempts | 2.0M keys/sec | elapsed 2.0 seconds | ETA ~0.0 seconds"
+        );
+        // The ETA follows the current speed, not the average one.
+        assert_eq!(
+            format_progress(&stats(2_000_000, 500_000.0), 3_000_000.0),
+            "2.0M attempts | 500.0K keys/sec | elapsed 2.0 seconds | ETA ~2.0 seconds"
+        );
+    }
+}
@@ -0,0 +1,571 @@
+//! # Vanity Address HTTP Server Module
+//!
+//! This module is used for serving vanity address requests over a local HTTP API,
+//! so other services can generate vanity addresses without running the cli.
+//! It is only compiled with the `serve` feature.
+//!
+//! Endpoints:
+//! ```txt
+//! POST /generate  {"pattern": "Emiv", "chain": "bitcoin", "mode": "prefix", "case_sensitive": false, "threads": 8}
+//!                 Returns the found wallet as a JSON object. Only pattern is required.
+//! GET  /status    Returns the number of queued requests and the speed of the running search.
+//! GET  /metrics   Returns the counters of the server in the Prometheus text format.
+//! ```
+//!
+//! Requests are queued and searched one by one with the server's threads, so multiple requests
+//! don't oversubscribe the CPU.
+//! At most 64 connections are served at once, the next ones wait to be accepted, and a
+//! connection is closed if its client doesn't send anything for 10 seconds.
+
+use crate::chain::BITCOIN;
+use crate::error::BtcVanityError;
+use crate::output::{error_to_json, WalletOutput};
+use crate::vanity_addr_generator::{SearchResult, SearchStats, VanityAddrBuilder, VanityMode};
+
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+/// The biggest request body accepted by the server.
+const MAX_BODY_LEN: usize = 64 * 1024;
+
+/// The biggest request line and headers accepted by the server.
+const MAX_HEADER_LEN: usize = 8 * 1024;
+
+/// Limits of the connections, so slow or too many clients can't tie up the server's threads.
+#[derive(Clone, Copy)]
+struct ConnectionLimits {
+    /// Connections over this wait to be accepted until an open one is closed.
+    max_connections: usize,
+    /// A connection is closed if the client doesn't send anything for this long.
+    read_timeout: Duration,
+}
+
+const CONNECTION_LIMITS: ConnectionLimits = ConnectionLimits {
+    max_connections: 64,
+    read_timeout: Duration::from_secs(10),
+};
+
+/// Content type of the Prometheus text format.
+const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// A parsed POST /generate request.
+#[derive(Debug, PartialEq)]
+pub struct GenerateRequest {
+    pub pattern: String,
+    pub vanity_mode: VanityMode,
+    pub case_sensitive: bool,
+    /// Requested thread count. It can't be more than the server's threads. 0 uses all of them.
+    pub threads: u64,
+}
+
+/// A queued request and the channel its JSON response is sent back with.
+struct Job {
+    request: GenerateRequest,
+    response: mpsc::Sender<Result<String, String>>,
+}
+
+/// Search state shared between the worker and the connections for GET /status and /metrics.
+#[derive(Default)]
+struct ServerState {
+    queued: AtomicU64,
+    /// Open connections, see ConnectionLimits.
+    connections: Mutex<usize>,
+    connection_closed: Condvar,
+    metrics: Mutex<ServerMetrics>,
+}
+
+impl ServerState {
+    fn metrics(&self) -> MutexGuard<'_, ServerMetrics> {
+        self.metrics.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// An open connection counted in ServerState::connections until it is dropped.
+struct ConnectionSlot(Arc<ServerState>);
+
+impl ConnectionSlot {
+    /// Waits until less than max_connections connections are open.
+    fn acquire(state: &Arc<ServerState>, max_connections: usize) -> Self {
+        let connections = state
+            .connections
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let mut connections = state
+            .connection_closed
+            .wait_while(connections, |connections| *connections >= max_connections)
+            .unwrap_or_else(|err| err.into_inner());
+        *connections += 1;
+        ConnectionSlot(Arc::clone(state))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        *self
+            .0
+            .connections
+            .lock()
+            .unwrap_or_else(|err| err.into_inner()) -= 1;
+        self.0.connection_closed.notify_one();
+    }
+}
+
+/// Counters of the server since it started. Every search has its own statistics, and they are
+/// added to the counters under the same lock the search is replaced with, so the counters never
+/// go back.
+#[derive(Default)]
+struct ServerMetrics {
+    /// Statistics of the running search, or empty ones between the searches.
+    search: SearchStats,
+    /// Keys generated by the finished searches.
+    keys_generated: u64,
+    jobs_completed: u64,
+    jobs_failed: u64,
+}
+
+impl GenerateRequest {
+    /// Parses the JSON body of a POST /generate request.
+    pub fn from_json(json: &str) -> Result<Self, &'static str> {
+        let object = match serde_json::from_str::<Value>(json) {
+            Ok(Value::Object(object)) => object,
+            _ => return Err("body must be a JSON object"),
+        };
+
+        let pattern = match object.get("pattern") {
+            Some(Value::String(pattern)) => pattern.to_string(),
+            _ => return Err("pattern is required and must be a string"),
+        };
+        match object.get("chain") {
+            None | Some(Value::Null) => {}
+            Some(Value::String(chain)) if chain == "bitcoin" => {}
+            _ => return Err("chain must be bitcoin"),
+        }
+        let vanity_mode = match object.get("mode") {
+            None | Some(Value::Null) => VanityMode::Prefix,
+            Some(Value::String(mode)) => match mode.as_str() {
+                "prefix" => VanityMode::Prefix,
+                "suffix" => VanityMode::Suffix,
+                "anywhere" => VanityMode::Anywhere,
+                _ => return Err("mode must be prefix, suffix or anywhere"),
+            },
+            _ => return Err("mode must be prefix, suffix or anywhere"),
+        };
+        let case_sensitive = match object.get("case_sensitive") {
+            None | Some(Value::Null) => false,
+            Some(Value::Bool(case_sensitive)) => *case_sensitive,
+            _ => return Err("case_sensitive must be a boolean"),
+        };
+        let threads = match object.get("threads") {
+            None | Some(Value::Null) => 0,
+            Some(threads) => threads
+                .as_u64()
+                .ok_or("threads must be a positive integer")?,
+        };
+
+        Ok(GenerateRequest {
+            pattern,
+            vanity_mode,
+            case_sensitive,
+            threads,
+        })
+    }
+}
+
+/// Serves the HTTP API on the listener until the process exits.
+/// Every search uses at most max_threads threads.
+pub fn serve(listener: TcpListener, max_threads: u64) -> Result<(), BtcVanityError> {
+    serve_with_limits(listener, max_threads, CONNECTION_LIMITS)
+}
+
+fn serve_with_limits(
+    listener: TcpListener,
+    max_threads: u64,
+    limits: ConnectionLimits,
+) -> Result<(), BtcVanityError> {
+    let state = Arc::new(ServerState::default());
+    let (sender, receiver) = mpsc::channel::<Job>();
+
+    let worker_state = Arc::clone(&state);
+    thread::spawn(move || {
+        for job in receiver {
+            let stats = SearchStats::new();
+            worker_state.metrics().search = stats.clone();
+            let result = search(&job.request, max_threads, stats.clone());
+            {
+                let mut metrics = worker_state.metrics();
+                metrics.keys_generated += stats.snapshot().attempts;
+                metrics.search = SearchStats::new();
+                match result.is_ok() {
+                    true => metrics.jobs_completed += 1,
+                    false => metrics.jobs_failed += 1,
+                }
+            }
+            worker_state.queued.fetch_sub(1, Ordering::Relaxed);
+            let _ = job.response.send(result);
+        }
+    });
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        // The next connections wait in the listen queue until this one has a slot.
+        let slot = ConnectionSlot::acquire(&state, limits.max_connections);
+        let sender = sender.clone();
+        thread::spawn(move || {
+            let _ = handle_connection(stream, &sender, &slot.0, limits.read_timeout);
+        });
+    }
+    Ok(())
+}
+
+/// Searches the requested vanity address and returns the wallet or the error as a JSON object.
+fn search(
+    request: &GenerateRequest,
+    max_threads: u64,
+    stats: SearchStats,
+) -> Result<String, String> {
+    let threads = match request.threads {
+        0 => max_threads,
+        threads => threads.min(max_threads),
+    };
+
+    let mut found: Option<SearchResult> = None;
+    let result = VanityAddrBuilder::new(&request.pattern)
+        .threads(threads)
+        .case_sensitive(request.case_sensitive)
+        .mode(request.vanity_mode)
+        .stats(stats)
+        .generate_with(|result| found = Some(result));
+
+    match (result, found) {
+        (Ok(()), Some(found)) => {
+            Ok(WalletOutput::new(&request.pattern, request.vanity_mode, &found).to_json())
+        }
+        (Err(err), _) => Err(error_to_json(
+            &request.pattern,
+            request.vanity_mode,
+            &err.to_string(),
+        )),
+        (Ok(()), None) => Err(error_to_json(
+            &request.pattern,
+            request.vanity_mode,
+            "no address found",
+        )),
+    }
+}
+
+/// Reads one HTTP request from the stream, routes it and writes the response.
+/// Returns Err if the client doesn't send anything for read_timeout.
+fn handle_connection(
+    mut stream: TcpStream,
+    jobs: &mpsc::Sender<Job>,
+    state: &ServerState,
+    read_timeout: Duration,
+) -> Result<(), BtcVanityError> {
+    stream.set_read_timeout(Some(read_timeout))?;
+    stream.set_write_timeout(Some(read_timeout))?;
+    let mut reader = BufReader::new(
+        stream
+            .try_clone()?
+            .take((MAX_HEADER_LEN + MAX_BODY_LEN) as u64),
+    );
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    // None if the Content-Length isn't a number.
+    let mut content_length = Some(0);
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let (status, body) = match (method.as_str(), path.as_str(), content_length) {
+        ("GET", "/status", _) => ("200 OK", status_json(state)),
+        ("GET", "/metrics", _) => ("200 OK", metrics_text(state)),
+        ("POST", "/generate", None) => (
+            "400 Bad Request",
+            message_json("error", "invalid Content-Length"),
+        ),
+        ("POST", "/generate", Some(content_length)) if content_length > MAX_BODY_LEN => (
+            "413 Payload Too Large",
+            message_json("error", "body is too large"),
+        ),
+        ("POST", "/generate", Some(content_length)) => {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            let body = String::from_utf8_lossy(&body);
+            match GenerateRequest::from_json(&body) {
+                Ok(request) => {
+                    let (response, receiver) = mpsc::channel();
+                    state.queued.fetch_add(1, Ordering::Relaxed);
+                    let _ = jobs.send(Job { request, response });
+                    match receiver.recv() {
+                        Ok(Ok(wallet)) => ("200 OK", wallet),
+                        Ok(Err(error)) => ("400 Bad Request", error),
+                        Err(_) => (
+                            "500 Internal Server Error",
+                            message_json("error", "search worker stopped"),
+                        ),
+                    }
+                }
+                Err(error) => ("400 Bad Request", message_json("error", error)),
+            }
+        }
+        _ => ("404 Not Found", message_json("error", "not found")),
+    };
+
+    let content_type = match (method.as_str(), path.as_str(), status) {
+        ("GET", "/metrics", "200 OK") => METRICS_CONTENT_TYPE,
+        _ => "application/json",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+/// The response of GET /status.
+#[derive(Serialize)]
+struct StatusJson {
+    queued: u64,
+    keys_per_second: u64,
+}
+
+/// Returns the queued request count and the speed of the running search as a JSON object.
+fn status_json(state: &ServerState) -> String {
+    serde_json::to_string(&StatusJson {
+        queued: state.queued.load(Ordering::Relaxed),
+        keys_per_second: state.metrics().search.snapshot().keys_per_second.round() as u64,
+    })
+    .unwrap()
+}
+
+/// Returns the counters of the server in the Prometheus text format. The keys generated by the
+/// running search are counted too.
+fn metrics_text(state: &ServerState) -> String {
+    let metrics = state.metrics();
+    let search = metrics.search.snapshot();
+    let chain = BITCOIN.name;
+
+    format!(
+        "# HELP btc_vanity_keys_generated_total Candidate keys generated by the searches.\n\
+        # TYPE btc_vanity_keys_generated_total counter\n\
+        btc_vanity_keys_generated_total{{chain=\"{chain}\"}} {}\n\
+        # HELP btc_vanity_keys_per_second Speed of the running search over the last second.\n\
+        # TYPE btc_vanity_keys_per_second gauge\n\
+        btc_vanity_keys_per_second {:.0}\n\
+        # HELP btc_vanity_jobs_completed_total Requests answered with a found wallet.\n\
+        # TYPE btc_vanity_jobs_completed_total counter\n\
+        btc_vanity_jobs_completed_total{{chain=\"{chain}\"}} {}\n\
+        # HELP btc_vanity_jobs_failed_total Requests whose search ended with an error.\n\
+        # TYPE btc_vanity_jobs_failed_total counter\n\
+        btc_vanity_jobs_failed_total{{chain=\"{chain}\"}} {}\n\
+        # HELP btc_vanity_queue_depth Requests waiting for or running their search.\n\
+        # TYPE btc_vanity_queue_depth gauge\n\
+        btc_vanity_queue_depth {}\n",
+        metrics.keys_generated + search.attempts,
+        search.keys_per_second,
+        metrics.jobs_completed,
+        metrics.jobs_failed,
+        state.queued.load(Ordering::Relaxed),
+    )
+}
+
+/// Returns a JSON object with a single string field.
+fn message_json(key: &str, message: &str) -> String {
+    serde_json::json!({ key: message }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_request_from_invalid_json() {
+        for body in ["", "[]", r#"{"pattern": "Em""#, r#"{"pattern": "Em"} x"#] {
+            assert_eq!(
+                GenerateRequest::from_json(body),
+                Err("body must be a JSON object")
+            );
+        }
+        assert!(GenerateRequest::from_json(r#"{"pattern": {"a": 1}}"#).is_err());
+        assert!(GenerateRequest::from_json(r#"{"pattern": "Em", "threads": 1.5}"#).is_err());
+    }
+
+    #[test]
+    fn test_message_json_escapes() {
+        let json: Value = serde_json::from_str(&message_json("error", "a\"b\\c\nd")).unwrap();
+        assert_eq!(json["error"], "a\"b\\c\nd");
+    }
+
+    #[test]
+    fn test_generate_request_from_json() {
+        assert_eq!(
+            GenerateRequest::from_json(r#"{"pattern": "Em", "mode": "suffix", "threads": 2}"#),
+            Ok(GenerateRequest {
+                pattern: String::from("Em"),
+                vanity_mode: VanityMode::Suffix,
+                case_sensitive: false,
+                threads: 2,
+            })
+        );
+        assert!(GenerateRequest::from_json(r#"{"mode": "prefix"}"#).is_err());
+        assert!(GenerateRequest::from_json(r#"{"pattern": "Em", "chain": "solana"}"#).is_err());
+        assert!(GenerateRequest::from_json(r#"{"pattern": "Em", "threads": -1}"#).is_err());
+    }
+
+    /// Sends the raw HTTP request to the server and returns the response.
+    fn send(address: std::net::SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener, 4));
+
+        let body = r#"{"pattern": "a", "mode": "anywhere"}"#;
+        let response = send(
+            address,
+            &format!(
+                "POST /generate HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("\"address\":\"1"));
+
+        let response = send(
+            address,
+            "POST /generate HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}",
+        );
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+
+        let response = send(
+            address,
+            "POST /generate HTTP/1.1\r\nContent-Length: 2x\r\n\r\n{}",
+        );
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(response.contains("invalid Content-Length"));
+
+        let response = send(address, "GET /status HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("{\"queued\":0,\"keys_per_second\":0}"));
+
+        let response = send(address, "GET /missing HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+    }
+
+    #[test]
+    fn test_serve_connection_limits() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let limits = ConnectionLimits {
+            max_connections: 1,
+            read_timeout: Duration::from_millis(200),
+        };
+        thread::spawn(move || serve_with_limits(listener, 1, limits));
+
+        // A client which never sends its request holds the only connection until it is
+        // closed after the read timeout, then the next connection is accepted.
+        let start = std::time::Instant::now();
+        let mut idle = TcpStream::connect(address).unwrap();
+        let response = send(address, "GET /status HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(start.elapsed() >= limits.read_timeout);
+
+        let mut response = String::new();
+        idle.read_to_string(&mut response).unwrap();
+        assert!(response.is_empty());
+    }
+
+    /// Returns the value of the metric line starting with the name and its labels.
+    fn metric(metrics: &str, name: &str) -> u64 {
+        metrics
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+            .unwrap()
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_serve_metrics() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener, 2));
+        let generate = |body: &str| {
+            send(
+                address,
+                &format!(
+                    "POST /generate HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                ),
+            )
+        };
+
+        let response = send(address, "GET /metrics HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+        assert_eq!(
+            metric(
+                &response,
+                "btc_vanity_keys_generated_total{chain=\"bitcoin\"}"
+            ),
+            0
+        );
+        assert!(response.contains("# TYPE btc_vanity_queue_depth gauge"));
+
+        assert!(generate(r#"{"pattern": "ab"}"#).starts_with("HTTP/1.1 200 OK"));
+        assert!(generate(r#"{"pattern": "a0"}"#).starts_with("HTTP/1.1 400 Bad Request"));
+
+        let metrics = send(address, "GET /metrics HTTP/1.1\r\n\r\n");
+        assert!(
+            metric(
+                &metrics,
+                "btc_vanity_keys_generated_total{chain=\"bitcoin\"}"
+            ) > 0
+        );
+        assert_eq!(
+            metric(
+                &metrics,
+                "btc_vanity_jobs_completed_total{chain=\"bitcoin\"}"
+            ),
+            1
+        );
+        assert_eq!(
+            metric(&metrics, "btc_vanity_jobs_failed_total{chain=\"bitcoin\"}"),
+            1
+        );
+        assert_eq!(metric(&metrics, "btc_vanity_queue_depth"), 0);
+        assert_eq!(metric(&metrics, "btc_vanity_keys_per_second"), 0);
+    }
+}
@@ -23,19 +23,552 @@
 //!                 vanity_address.get_comp_address())
 //! ```
 
+mod ascii_case;
+pub mod builder;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod engine;
+pub mod estimate;
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+pub mod future;
+pub mod prefix_range;
+pub mod sink;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod stream;
+
+pub use builder::VanityAddrBuilder;
+#[cfg(not(target_arch = "wasm32"))]
+pub use engine::VanityEngine;
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+pub use future::GenerateFuture;
+#[cfg(not(target_arch = "wasm32"))]
+pub use stream::VanityStream;
+
+use crate::chain::{BASE58_ALPHABET, BITCOIN};
 use crate::error::BtcVanityError;
-use crate::keys_and_address::KeysAndAddress;
+use crate::keys_and_address::{
+    seeded_rng, EntropyMixer, IncrementalKeys, KeySource, KeysAndAddress, RngKeySource,
+};
+use crate::vanity_addr_generator::estimate::estimate_attempts;
+use crate::vanity_addr_generator::prefix_range::PrefixRanges;
+use crate::verify::{self_check, self_check_keys};
 
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::rand;
 use bitcoin::secp256k1::{All, Secp256k1};
-use std::sync::mpsc;
+use log::{debug, error, info, log_enabled, trace, warn, Level};
+use std::any::Any;
+use std::collections::{HashSet, VecDeque};
+use std::ops::{ControlFlow, Range};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
+
+/// A prefix string this many times rarer than its estimate is reported by `validate_feasibility`.
+const RARE_PREFIX_FACTOR: f64 = 4.0;
 
 /// An Empty Struct for a more structured code
 /// implements the only public function generate
 pub struct VanityAddr;
 
+/// A found vanity address with the search statistics at the moment it was found.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchResult {
+    pub keys_and_address: KeysAndAddress,
+    /// Number of candidates tried by all the threads until this address was found.
+    pub attempts: u64,
+    /// Time elapsed from the start of the search until this address was found.
+    pub duration: Duration,
+    /// Byte range of the address which matched the string.
+    pub match_range: Range<usize>,
+    /// Index of the search thread which found this address, starting from 0.
+    pub thread_id: usize,
+}
+
+/// Options of a search, everything but the string, the keys and the callbacks.
+/// `VanityAddrBuilder` keeps its options in it and passes it down to the search threads.
+#[derive(Clone)]
+pub struct SearchOptions {
+    pub vanity_mode: VanityMode,
+    pub case_sensitive: bool,
+    /// Longest string accepted, None is the fast mode limit of the chain.
+    pub max_pattern_len: Option<usize>,
+    /// Number of vanity addresses to find.
+    pub count: u64,
+    pub threads: u64,
+    pub backend: Backend,
+    pub pin_threads: bool,
+    /// An address whose matched substring is already found doesn't count.
+    pub unique_matches: bool,
+    /// The matching addresses in it are skipped.
+    pub excluded_addresses: Option<Arc<HashSet<Box<[u8]>>>>,
+    pub timeout: Option<Duration>,
+    pub max_attempts: Option<u64>,
+    /// Keys per second of all the threads together.
+    pub max_rate: Option<f64>,
+    /// Every tried candidate is added to it.
+    pub attempt_counter: Option<Arc<AtomicU64>>,
+    pub cancellation_token: Option<CancellationToken>,
+    /// The attempts of every thread are counted on it.
+    pub stats: Option<SearchStats>,
+}
+
+impl Default for SearchOptions {
+    /// A case sensitive prefix search for a single address in fast mode, with one thread per
+    /// CPU core and no limits.
+    fn default() -> Self {
+        SearchOptions {
+            vanity_mode: VanityMode::Prefix,
+            case_sensitive: true,
+            max_pattern_len: None,
+            count: 1,
+            threads: thread::available_parallelism().map_or(1, |cores| cores.get() as u64),
+            backend: Backend::default(),
+            pin_threads: false,
+            unique_matches: false,
+            excluded_addresses: None,
+            timeout: None,
+            max_attempts: None,
+            max_rate: None,
+            attempt_counter: None,
+            cancellation_token: None,
+            stats: None,
+        }
+    }
+}
+
+/// A string resolved to the search which is run for it, see `VanityAddr::plan`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedSearch {
+    pub chain: &'static str,
+    /// The string as it is given.
+    pub input: String,
+    /// The string which is matched, ex: "Emiv" for the input "1Emiv" in prefix mode.
+    pub pattern: String,
+    pub vanity_mode: VanityMode,
+    pub case_sensitive: bool,
+    /// Longest pattern accepted, the fast mode limit or an override of it.
+    pub max_len: usize,
+    /// How many times harder the pattern is than its estimate, see `validate_feasibility`.
+    pub rarity: Option<f64>,
+}
+
+impl ResolvedSearch {
+    /// Returns how the addresses matching the search look, ex: "1Emiv..." for a prefix search.
+    pub fn address_shape(&self) -> String {
+        let fixed_prefix = BITCOIN.fixed_prefix.unwrap_or_default();
+        match self.vanity_mode {
+            VanityMode::Prefix => format!("{}{}...", fixed_prefix, self.pattern),
+            VanityMode::Suffix => format!("{}...{}", fixed_prefix, self.pattern),
+            VanityMode::Anywhere => format!("{}...{}...", fixed_prefix, self.pattern),
+        }
+    }
+}
+
+/// Cancels a running search from another thread, ex: when the user closes the window of a GUI.
+/// Clones share the same state, so any clone can cancel the search.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops the search using this token. The search returns Err(BtcVanityError::Cancelled) shortly after.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl From<Arc<AtomicBool>> for CancellationToken {
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        CancellationToken(flag)
+    }
+}
+
+/// How often the progress callback of a search is called.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Progress of a running search passed to the progress callback.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchProgress {
+    /// Number of candidates tried by all the threads so far.
+    pub attempts: u64,
+    /// Time elapsed from the start of the search.
+    pub elapsed: Duration,
+    /// Keys per second of each thread since the previous progress report.
+    pub thread_rates: Vec<f64>,
+}
+
+impl SearchProgress {
+    /// Keys per second of all the threads since the previous progress report.
+    pub fn keys_per_second(&self) -> f64 {
+        self.thread_rates.iter().sum()
+    }
+}
+
+/// Called with the progress of a running search about once per second.
+pub type ProgressCallback = Box<dyn Fn(SearchProgress) + Send + Sync>;
+
+/// The instantaneous speed of `SearchStats::snapshot` is measured over at least this long.
+const SPEED_WINDOW: Duration = Duration::from_secs(1);
+
+/// Statistics of a search at the moment `SearchStats::snapshot` is called.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchStatsSnapshot {
+    /// Number of candidates tried by all the threads so far.
+    pub attempts: u64,
+    /// Number of candidates tried by each thread so far.
+    pub thread_attempts: Vec<u64>,
+    /// Time elapsed from the start of the search, or its duration if it is finished.
+    pub elapsed: Duration,
+    /// Keys per second of all the threads over the last second. 0 if the search isn't running.
+    pub keys_per_second: f64,
+    /// Keys per second of all the threads from the start of the search.
+    pub average_keys_per_second: f64,
+    pub running: bool,
+}
+
+/// Live statistics of a search which can be read from other threads while searching.
+/// Clones share the same counters, so a clone given to the builder can be read from anywhere.
+/// Every search started with it resets the counters.
+#[derive(Clone, Default)]
+pub struct SearchStats(Arc<Mutex<StatsState>>);
+
+#[derive(Default)]
+struct StatsState {
+    /// Attempt counter of every search thread, only written by its thread.
+    thread_attempts: Arc<Vec<CachePadded<AtomicU64>>>,
+    start: Option<Instant>,
+    /// Duration of the search after it is finished.
+    duration: Option<Duration>,
+    /// Time and attempts the instantaneous speed is measured from.
+    last_sample: Option<(Instant, u64)>,
+    /// None until the search has run for a whole SPEED_WINDOW.
+    keys_per_second: Option<f64>,
+}
+
+impl SearchStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resets the statistics for a new search started at `start`
+    /// and returns the attempt counters of its threads.
+    pub(crate) fn start(&self, threads: u64, start: Instant) -> Arc<Vec<CachePadded<AtomicU64>>> {
+        let mut state = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        *state = StatsState {
+            thread_attempts: Arc::new((0..threads).map(|_| CachePadded::default()).collect()),
+            start: Some(start),
+            duration: None,
+            last_sample: Some((start, 0)),
+            keys_per_second: None,
+        };
+        Arc::clone(&state.thread_attempts)
+    }
+
+    /// Stops the clock of the search, the counters keep their final values.
+    pub(crate) fn finish(&self) {
+        let mut state = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        state.duration = state.start.map(|start| start.elapsed());
+    }
+
+    /// Reads the counters of the search. The threads update their counters after every chunk of
+    /// candidates, so the attempts can be behind by a chunk per thread while searching.
+    pub fn snapshot(&self) -> SearchStatsSnapshot {
+        let mut state = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        let thread_attempts: Vec<u64> = state
+            .thread_attempts
+            .iter()
+            .map(|counter| counter.0.load(Ordering::Relaxed))
+            .collect();
+        let attempts = thread_attempts.iter().sum();
+        let running = state.start.is_some() && state.duration.is_none();
+        let elapsed = match (state.duration, state.start) {
+            (Some(duration), _) => duration,
+            (None, Some(start)) => start.elapsed(),
+            (None, None) => Duration::ZERO,
+        };
+        let average_keys_per_second = match elapsed.is_zero() {
+            true => 0.0,
+            false => attempts as f64 / elapsed.as_secs_f64(),
+        };
+
+        if let Some((sampled_at, sampled_attempts)) = state.last_sample {
+            let since_sample = sampled_at.elapsed();
+            if running && since_sample >= SPEED_WINDOW {
+                state.keys_per_second =
+                    Some((attempts - sampled_attempts) as f64 / since_sample.as_secs_f64());
+                state.last_sample = Some((Instant::now(), attempts));
+            }
+        }
+
+        SearchStatsSnapshot {
+            attempts,
+            thread_attempts,
+            elapsed,
+            keys_per_second: match running {
+                true => state.keys_per_second.unwrap_or(average_keys_per_second),
+                false => 0.0,
+            },
+            average_keys_per_second,
+            running,
+        }
+    }
+}
+
+/// Candidates a search thread tries between checks of the stop and cancel flags and updates
+/// of the attempt counters.
+const CHECK_INTERVAL: u64 = 8;
+
+/// Longest sleep of a throttled search thread between checks of the stop and cancel flags.
+const MAX_PACING_SLEEP: Duration = Duration::from_millis(50);
+
+/// The CPU cores the search threads are pinned to with `SearchOptions::pin_threads`.
+#[derive(Clone)]
+#[cfg(not(target_arch = "wasm32"))]
+struct CorePins(Arc<Vec<core_affinity::CoreId>>);
+
+/// wasm32 has no CPU cores to list, so the search threads are never pinned.
+#[derive(Clone)]
+#[cfg(target_arch = "wasm32")]
+struct CorePins;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl CorePins {
+    /// Returns None if the CPU cores can't be listed.
+    fn list() -> Option<Self> {
+        core_affinity::get_core_ids()
+            .filter(|core_ids| !core_ids.is_empty())
+            .map(|core_ids| CorePins(Arc::new(core_ids)))
+    }
+
+    /// Pins the calling search thread to a core, the threads take the cores in turns.
+    fn pin(&self, thread_index: u64) {
+        let core_id = self.0[thread_index as usize % self.0.len()];
+        match core_affinity::set_for_current(core_id) {
+            true => debug!(
+                "Search thread {} pinned to core {}",
+                thread_index, core_id.id
+            ),
+            false => warn!(
+                "Search thread {} couldn't be pinned to core {}",
+                thread_index, core_id.id
+            ),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl CorePins {
+    fn list() -> Option<Self> {
+        None
+    }
+
+    fn pin(&self, _thread_index: u64) {}
+}
+
+/// Keeps a search thread at its share of the max rate of a throttled search.
+struct Pacer {
+    /// Candidates per second of this thread.
+    rate: f64,
+    start: Instant,
+}
+
+impl Pacer {
+    fn new(max_rate: f64, threads: u64, start: Instant) -> Self {
+        Pacer {
+            rate: max_rate / threads as f64,
+            start,
+        }
+    }
+
+    /// Returns how long the thread has to wait after trying `tried` candidates, so it doesn't
+    /// go faster than its rate. A thread which fell behind, ex: while it wasn't scheduled, isn't
+    /// slowed down until it catches up.
+    fn wait(&self, tried: u64, now: Instant) -> Duration {
+        Duration::from_secs_f64(tried as f64 / self.rate)
+            .saturating_sub(now.saturating_duration_since(self.start))
+    }
+}
+
+/// What `find_matching` does with a found key pair after passing it to `on_found`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Verdict {
+    /// The key pair doesn't count.
+    Skip,
+    /// The key pair counts and the search goes on until `count` of them are accepted.
+    Accept,
+    /// The key pair counts and the search stops, ex: the result sink has enough of them.
+    AcceptAndStop,
+}
+
+/// A value on its own cache line, ex: a per-thread attempt counter or the stop flag,
+/// so the threads don't slow each other down.
+#[derive(Default)]
+#[repr(align(64))]
+pub(crate) struct CachePadded<T>(T);
+
+/// Most matched substrings a search with unique matches remembers. A substring takes about
+/// 100 bytes with the set overhead, so it stays under about 7 MB. After that the oldest ones are
+/// forgotten and their matches can be found again.
+const MAX_SEEN_MATCHES: usize = 65_536;
+
+/// The matched substrings already found by a search with unique matches.
+pub(crate) struct SeenMatches {
+    seen: HashSet<String>,
+    /// The seen substrings from the oldest to the newest, to forget the oldest one when full.
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl SeenMatches {
+    pub(crate) fn new() -> Self {
+        Self::with_capacity(MAX_SEEN_MATCHES)
+    }
+
+    fn with_capacity(capacity: usize) -> Self {
+        SeenMatches {
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Remembers the matched substring and returns true if it isn't seen before.
+    pub(crate) fn insert(&mut self, matched: &str) -> bool {
+        if self.seen.contains(matched) {
+            return false;
+        }
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(matched.to_string());
+        self.order.push_back(matched.to_string());
+        true
+    }
+
+    /// Returns true if every distinct match of a string with `unique` of them is seen
+    /// and none of them is forgotten. Only the stream ends when every match is found.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn has_all(&self, unique: u64) -> bool {
+        unique <= self.capacity as u64 && self.seen.len() as u64 == unique
+    }
+}
+
+/// Returns how many distinct substrings can match the string. Every letter with both of its
+/// cases in base58 doubles it when case insensitive, ex: "e" has 2, "E" and "e".
+pub(crate) fn unique_match_count(string: &str, case_sensitive: bool) -> u64 {
+    string
+        .chars()
+        .map(|c| match case_sensitive {
+            true => 1,
+            false => BASE58_ALPHABET
+                .chars()
+                .filter(|a| a.eq_ignore_ascii_case(&c))
+                .count()
+                .max(1) as u64,
+        })
+        .fold(1, u64::saturating_mul)
+}
+
+/// Where the search threads of a search run.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Spawns new threads for every search.
+    #[default]
+    Threads,
+    /// Runs the search threads as tasks on the current rayon thread pool, ex: a pool the
+    /// application already owns. At most as many of them run at once as the pool has threads,
+    /// so don't search from a thread of a pool that has no other free threads.
+    #[cfg(feature = "rayon")]
+    Rayon,
+}
+
+/// The running search threads of a search.
+enum Workers {
+    Threads(Vec<thread::JoinHandle<()>>),
+    /// Every task holds a sender, so the receiver is disconnected when all of them exit.
+    /// Tasks which panic send the panic payload.
+    #[cfg(feature = "rayon")]
+    Rayon(Receiver<Box<dyn Any + Send>>),
+}
+
+impl Workers {
+    /// Starts `threads` search threads on the backend, `worker` returns the loop of each one.
+    fn spawn<W, F>(backend: Backend, threads: u64, worker: W) -> Self
+    where
+        W: Fn(u64) -> F,
+        F: FnOnce() + Send + 'static,
+    {
+        match backend {
+            Backend::Threads => Workers::Threads(
+                (0..threads)
+                    .map(|index| thread::spawn(worker(index)))
+                    .collect(),
+            ),
+            #[cfg(feature = "rayon")]
+            Backend::Rayon => {
+                let (done_sender, done_receiver) = mpsc::channel();
+                for index in 0..threads {
+                    let work = worker(index);
+                    let done_sender: mpsc::Sender<Box<dyn Any + Send>> = done_sender.clone();
+                    // A panic would reach the panic handler of the pool, which aborts by default.
+                    rayon::spawn(move || {
+                        if let Err(payload) =
+                            std::panic::catch_unwind(std::panic::AssertUnwindSafe(work))
+                        {
+                            let _ = done_sender.send(payload);
+                        }
+                    });
+                }
+                Workers::Rayon(done_receiver)
+            }
+        }
+    }
+
+    /// Waits until every search thread exits its loop.
+    /// Returns the panic message of the first search thread which panicked, if any.
+    fn join(self) -> Option<String> {
+        let payloads: Vec<_> = match self {
+            Workers::Threads(handles) => handles
+                .into_iter()
+                .filter_map(|handle| handle.join().err())
+                .collect(),
+            #[cfg(feature = "rayon")]
+            Workers::Rayon(done_receiver) => done_receiver.iter().collect(),
+        };
+
+        payloads.into_iter().map(panic_message).next()
+    }
+}
+
+/// Returns the message of a panic payload. `panic!` with arguments gives a String and without
+/// them a &str, other payloads have no message.
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map_or_else(
+            || String::from("unknown panic payload"),
+            |message| message.to_string(),
+        ),
+    }
+}
+
 /// Vanity mode enum
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum VanityMode {
     Prefix,
     Suffix,
@@ -45,37 +578,160 @@ pub enum VanityMode {
 impl VanityAddr {
     /// Checks all given information's before passing to the vanity address finder function.
     /// Returns Ok if all checks were successful.
+    /// Returns Err if the string is empty.
     /// Returns Err if the string is longer than 4 chars and -d or --disable-fast-mode flags are not given.
+    /// Returns Err if the string is longer than a bitcoin address can match.
     /// Returns Err if the string is not in base58 format.
-    fn validate_input(string: &str, fast_mode: bool) -> Result<(), BtcVanityError> {
+    pub fn validate_input(string: &str, fast_mode: bool) -> Result<(), BtcVanityError> {
+        Self::validate_input_len(string, BITCOIN.max_pattern_len(fast_mode))
+    }
+
+    /// Same as `validate_input` with an explicit limit instead of the fast mode limit of the chain,
+    /// ex: 6 to accept a 6 char prefix without disabling fast mode.
+    /// Returns Err if the string is longer than max_len or than a bitcoin address can match.
+    pub fn validate_input_len(string: &str, max_len: usize) -> Result<(), BtcVanityError> {
         if string.is_empty() {
-            return Ok(());
+            debug!("the string is empty");
+            return Err(BtcVanityError::EmptyPattern);
+        }
+
+        if string.len() > BITCOIN.max_pattern_len(false) {
+            debug!("'{}' is too long for {}", string, BITCOIN.name);
+            return Err(BtcVanityError::PatternTooLong {
+                len: string.len(),
+                max: BITCOIN.max_pattern_len(false),
+                chain: BITCOIN.name,
+            });
         }
 
-        if string.len() > 4 && fast_mode {
-            return Err(BtcVanityError::VanityGeneratorError(
-                    "You're asking for too much!\n\
-                    If you know this will take for a long time and really want to find something longer than 4 characters\n\
-                    disable fast mode with -df or --disable_fast flags.",
-                ));
+        if string.len() > max_len {
+            debug!("'{}' is longer than the limit of {} chars", string, max_len);
+            return Err(BtcVanityError::FastModeEnabled {
+                len: string.len(),
+                max: max_len,
+                chain: BITCOIN.name,
+            });
         }
 
-        let is_base58 = string
+        let invalid_character = string
             .chars()
-            .any(|c| c == '0' || c == 'I' || c == 'O' || c == 'l' || !c.is_alphanumeric());
+            .enumerate()
+            .find(|&(_, c)| !BITCOIN.alphabet.contains(c));
 
-        if is_base58 {
-            return Err(BtcVanityError::VanityGeneratorError(
-                    "Your input is not in base58. Don't include zero: '0', uppercase i: 'I', uppercase o: 'O', lowercase L: 'l' \
-                    or any non-alphanumeric character in your input!",
-                ));
+        if let Some((index, ch)) = invalid_character {
+            debug!(
+                "'{}' has the invalid character '{}' at index {}",
+                string, ch, index
+            );
+            return Err(BtcVanityError::InvalidCharacter { ch, index });
         }
 
         Ok(())
     }
 
+    /// Checks if a bitcoin address can have the string at the position of the vanity mode. The base58
+    /// digits of an address aren't uniform: the 24 bytes after the version byte can only fill the
+    /// 33 digit addresses up to about "1Q", so the rest of the first digits only appear in the
+    /// shorter addresses, and every extra leading '1' needs a zero byte.
+    /// The string should be valid for `validate_input`.
+    /// Returns Err if no address can match the string, ex: a 33 characters prefix or suffix above
+    /// the highest address.
+    /// Returns Ok with the rarity if a prefix string is at least 4 times rarer than
+    /// `estimate_attempts` expects, ex: about 23 times for a case sensitive lowercase first letter
+    /// and about 4 times for a '1'. Returns Ok(None) otherwise.
+    pub fn validate_feasibility(
+        string: &str,
+        vanity_mode: VanityMode,
+        case_sensitive: bool,
+    ) -> Result<Option<f64>, BtcVanityError> {
+        // A suffix as long as the longest address body, not starting with '1', can only be the
+        // whole body of the longest addresses, so it is checked like a prefix.
+        let fills_address = string.len() == BITCOIN.max_length && !string.starts_with('1');
+        if vanity_mode == VanityMode::Prefix || (vanity_mode == VanityMode::Suffix && fills_address)
+        {
+            // Digits come before the uppercase letters in base58, so the lowest spelling of a
+            // case insensitive string fits if any of them does.
+            let lowest = string
+                .chars()
+                .map(|c| match case_sensitive {
+                    true => c,
+                    false => BASE58_ALPHABET
+                        .chars()
+                        .find(|a| a.eq_ignore_ascii_case(&c))
+                        .unwrap_or(c),
+                })
+                .collect::<String>();
+            if PrefixRanges::new(&lowest, true).is_some_and(|ranges| ranges.is_empty()) {
+                debug!("No {} address can match '{}'", BITCOIN.name, string);
+                return Err(BtcVanityError::ImpossiblePattern {
+                    chain: BITCOIN.name,
+                });
+            }
+        }
+        if vanity_mode != VanityMode::Prefix {
+            return Ok(None);
+        }
+
+        let Some(ranges) = PrefixRanges::new(string, case_sensitive) else {
+            return Ok(None);
+        };
+        let expected = 1.0 / estimate_attempts(string, vanity_mode, case_sensitive);
+        let rarity = expected / ranges.probability();
+        if rarity < RARE_PREFIX_FACTOR {
+            return Ok(None);
+        }
+        debug!(
+            "'{}' is {:.1} times rarer than its estimate",
+            string, rarity
+        );
+        Ok(Some(rarity))
+    }
+
+    /// Returns the string without the leading '1' a prefix search can't match.
+    /// Every address starts with '1' and prefix mode matches after it, so "1Emiv" would need an
+    /// address starting with "11Emiv". Only one '1' is removed, so "11x" still searches for "1x".
+    pub fn adjust_input(string: &str, vanity_mode: VanityMode) -> &str {
+        match (vanity_mode, BITCOIN.fixed_prefix) {
+            (VanityMode::Prefix, Some(fixed_prefix)) => {
+                string.strip_prefix(fixed_prefix).unwrap_or(string)
+            }
+            _ => string,
+        }
+    }
+
+    /// Resolves the search of the string: the implicit leading '1' of a prefix is dropped unless
+    /// keep_leading_one is true, and the result is checked with `validate_input_len` and
+    /// `validate_feasibility`. The pattern of the returned search is what the addresses are
+    /// matched against. max_len is the longest accepted pattern, ex:
+    /// `BITCOIN.max_pattern_len(fast_mode)`.
+    /// Returns Err for the same reasons as `validate_input_len` and `validate_feasibility`.
+    pub fn plan(
+        string: &str,
+        vanity_mode: VanityMode,
+        case_sensitive: bool,
+        max_len: usize,
+        keep_leading_one: bool,
+    ) -> Result<ResolvedSearch, BtcVanityError> {
+        let pattern = match keep_leading_one {
+            true => string,
+            false => Self::adjust_input(string, vanity_mode),
+        };
+        Self::validate_input_len(pattern, max_len)?;
+        let rarity = Self::validate_feasibility(pattern, vanity_mode, case_sensitive)?;
+
+        Ok(ResolvedSearch {
+            chain: BITCOIN.name,
+            input: string.to_string(),
+            pattern: pattern.to_string(),
+            vanity_mode,
+            case_sensitive,
+            max_len,
+            rarity,
+        })
+    }
+
     /// Checks all given information's before passing to the vanity address finder function.
-    /// Returns Result<KeysAndAddressString, VanityGeneratorError>
+    /// Returns Result<KeysAndAddress, BtcVanityError>
     /// Returns OK if a vanity address found successfully with keys_and_address::KeysAndAddress struct
     /// Returns Err if the string is longer than 4 chars and -d or --disable-fast-mode flags are not given.
     /// Returns Err if the string is not in base58 format.
@@ -86,21 +742,196 @@ impl VanityAddr {
         fast_mode: bool,
         vanity_mode: VanityMode,
     ) -> Result<KeysAndAddress, BtcVanityError> {
-        let secp256k1 = Secp256k1::new();
+        VanityAddrBuilder::new(string)
+            .threads(threads)
+            .case_sensitive(case_sensitive)
+            .fast_mode(fast_mode)
+            .mode(vanity_mode)
+            .generate()
+    }
 
-        Self::validate_input(string, fast_mode)?;
+    /// Returns a random key pair without searching for a pattern.
+    pub fn random() -> KeysAndAddress {
+        VanityAddrBuilder::random()
+            .generate()
+            .expect("random key pairs are generated without searching")
+    }
 
-        if string.is_empty() {
-            return Ok(KeysAndAddress::generate_random(&secp256k1));
+    /// Same as `generate` but searches on the calling thread without spawning any threads,
+    /// for targets without thread support, ex: wasm32. It blocks until a vanity address is found
+    /// or max_attempts candidates are tried. The string is resolved with `plan` like the other
+    /// searches, and the found key pair is checked with `verify::self_check` before it is returned.
+    /// Returns Err if max_attempts candidates are tried before a vanity address is found.
+    /// Returns Err for the same reasons as `plan` and `verify::self_check`.
+    pub fn generate_blocking_single(
+        string: &str,
+        case_sensitive: bool,
+        fast_mode: bool,
+        vanity_mode: VanityMode,
+        max_attempts: Option<u64>,
+    ) -> Result<KeysAndAddress, BtcVanityError> {
+        let resolved = Self::plan(
+            string,
+            vanity_mode,
+            case_sensitive,
+            BITCOIN.max_pattern_len(fast_mode),
+            false,
+        )?;
+        let string = resolved.pattern.as_str();
+
+        let secp256k1 = Secp256k1::new();
+        let mut keys = IncrementalKeys::new(&secp256k1, default_key_source(None, 0));
+        let mut candidate = None;
+        let mut attempts = 0;
+        while max_attempts.is_none_or(|max_attempts| attempts < max_attempts) {
+            attempts += 1;
+            let matched = SearchEngines::is_match(
+                keys.next_keys_into(&secp256k1, &mut candidate)
+                    .get_comp_address(),
+                string,
+                case_sensitive,
+                vanity_mode,
+            );
+            if let Some(keys_and_address) = candidate.take_if(|_| matched) {
+                self_check(
+                    &secp256k1,
+                    &keys_and_address,
+                    string,
+                    vanity_mode,
+                    case_sensitive,
+                )?;
+                return Ok(keys_and_address);
+            }
         }
 
-        Ok(SearchEngines::find_vanity_address(
+        Err(BtcVanityError::AttemptLimitReached { attempts })
+    }
+
+    /// Same as `generate` but keeps the search threads running until `count` independent
+    /// vanity addresses are found.
+    /// Returns Result<Vec<KeysAndAddress>, BtcVanityError>
+    /// Returns OK with all the found keys_and_address::KeysAndAddress structs in the order they were found.
+    /// Returns Err if count is 0.
+    /// Returns Err if the timeout passes before all the vanity addresses are found.
+    /// Returns Err if max_attempts candidates are tried before all the vanity addresses are found.
+    /// Returns Err if the string is longer than 4 chars and -d or --disable-fast-mode flags are not given.
+    /// Returns Err if the string is not in base58 format.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_n(
+        string: &str,
+        count: u64,
+        threads: u64,
+        case_sensitive: bool,
+        fast_mode: bool,
+        vanity_mode: VanityMode,
+        timeout: Option<Duration>,
+        max_attempts: Option<u64>,
+    ) -> Result<Vec<KeysAndAddress>, BtcVanityError> {
+        let mut found = Vec::new();
+        Self::generate_n_with(
             string,
+            count,
             threads,
             case_sensitive,
+            fast_mode,
             vanity_mode,
-            secp256k1,
-        ))
+            timeout,
+            max_attempts,
+            None,
+            None,
+            None,
+            |result| found.push(result.keys_and_address),
+        )?;
+
+        Ok(found)
+    }
+
+    /// Same as `generate_n` but streams every found vanity address with its search statistics
+    /// to `on_found` right after it is found instead of collecting them. If the timeout passes or the attempt limit is reached,
+    /// the addresses found until then are already passed to `on_found` and Err is returned for the rest.
+    /// If attempt_counter is given, every tried candidate is added to it so the caller can watch
+    /// the progress of the search from another thread. It should start from 0.
+    /// If cancel_flag is given, setting it to true from another thread stops the search and
+    /// Err is returned.
+    /// If seed is given, every thread generates its keys from a random number generator derived
+    /// from the seed and its index instead of the system one. The keys are predictable, so they are
+    /// only for tests and demos. With one thread the same seed always finds the same addresses;
+    /// with more threads which thread finds first depends on the scheduling.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_n_with<F: FnMut(SearchResult)>(
+        string: &str,
+        count: u64,
+        threads: u64,
+        case_sensitive: bool,
+        fast_mode: bool,
+        vanity_mode: VanityMode,
+        timeout: Option<Duration>,
+        max_attempts: Option<u64>,
+        attempt_counter: Option<Arc<AtomicU64>>,
+        cancel_flag: Option<Arc<AtomicBool>>,
+        seed: Option<u64>,
+        on_found: F,
+    ) -> Result<(), BtcVanityError> {
+        let mut builder = VanityAddrBuilder::new(string)
+            .count(count)
+            .threads(threads)
+            .case_sensitive(case_sensitive)
+            .fast_mode(fast_mode)
+            .mode(vanity_mode);
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(max_attempts) = max_attempts {
+            builder = builder.max_attempts(max_attempts);
+        }
+        if let Some(attempt_counter) = attempt_counter {
+            builder = builder.attempt_counter(attempt_counter);
+        }
+        if let Some(cancel_flag) = cancel_flag {
+            builder = builder.cancellation_token(cancel_flag.into());
+        }
+        if let Some(seed) = seed {
+            builder = builder.seed(seed);
+        }
+
+        builder.generate_with(on_found)
+    }
+}
+
+/// Returns the byte index of the first occurrence of the string in the address ignoring ASCII case,
+/// without allocating lowercase copies of them. Addresses and valid strings are base58, so it
+/// finds the same occurrence as comparing their lowercase copies.
+fn find_ignore_ascii_case(address: &str, string: &str) -> Option<usize> {
+    ascii_case::find(address.as_bytes(), string.as_bytes())
+}
+
+/// Creates the key source of a search thread from the index of the thread.
+/// It is called on the search thread, so the key sources don't need to be Send.
+pub type KeySourceFactory = Arc<dyn Fn(u64) -> Box<dyn KeySource> + Send + Sync>;
+
+/// Returns the key source of a search thread, seeded if a seed is given.
+pub(crate) fn default_key_source(seed: Option<u64>, thread_index: u64) -> Box<dyn KeySource> {
+    match seed {
+        Some(seed) => Box::new(RngKeySource(seeded_rng(seed, thread_index))),
+        None => Box::new(RngKeySource(rand::thread_rng())),
+    }
+}
+
+/// Returns the key sources of a search. The given key sources are used instead of the default ones,
+/// and the extra entropy is mixed into every key if it is given.
+fn key_source_factory(
+    seed: Option<u64>,
+    key_source: Option<KeySourceFactory>,
+    extra_entropy: Option<Arc<[u8]>>,
+) -> KeySourceFactory {
+    let key_source = key_source
+        .unwrap_or_else(|| Arc::new(move |thread_index| default_key_source(seed, thread_index)));
+
+    match extra_entropy {
+        Some(entropy) => Arc::new(move |thread_index| {
+            Box::new(EntropyMixer::new(key_source(thread_index), &entropy))
+        }),
+        None => key_source,
     }
 }
 
@@ -108,73 +939,544 @@ impl VanityAddr {
 pub struct SearchEngines;
 
 impl SearchEngines {
-    /// Search for the vanity address with given threads.
-    /// First come served! If a thread finds a vanity address that satisfy all the requirements it sends
-    /// the keys_and_address::KeysAndAddress struct wia std::sync::mpsc channel and find_vanity_address function kills all the other
-    /// threads and closes the channel and returns the found KeysAndAddress struct that includes
-    /// key pair and the desired address.
-    fn find_vanity_address(
+    /// Returns the byte range of the address which matched the string.
+    /// Returns None if the address doesn't have the string at the place vanity mode asks for.
+    pub(crate) fn find_match(
+        address: &str,
         string: &str,
-        threads: u64,
         case_sensitive: bool,
         vanity_mode: VanityMode,
-        secp256k1: Secp256k1<All>,
-    ) -> KeysAndAddress {
+    ) -> Option<Range<usize>> {
         let string_len = string.len();
+        let start = match vanity_mode {
+            VanityMode::Prefix => 1,
+            VanityMode::Suffix => address.len().checked_sub(string_len)?,
+            VanityMode::Anywhere => match case_sensitive {
+                true => address.find(string)?,
+                false => find_ignore_ascii_case(address, string)?,
+            },
+        };
+
+        Self::is_match(address, string, case_sensitive, vanity_mode)
+            .then_some(start..start + string_len)
+    }
+
+    /// Returns true if the address has the string at the place vanity mode asks for.
+    /// Prefix mode skips the leading '1' of the address.
+    /// Returns false if the string is longer than the part of the address it is compared with.
+    pub fn is_match(
+        address: &str,
+        string: &str,
+        case_sensitive: bool,
+        vanity_mode: VanityMode,
+    ) -> bool {
+        let string_len = string.len();
+        let slice = match vanity_mode {
+            VanityMode::Prefix => address.get(1..=string_len),
+            // The tail of the encoded address already has the checksum in it, so a suffix is
+            // compared on it without decoding or hashing anything again.
+            VanityMode::Suffix => address
+                .len()
+                .checked_sub(string_len)
+                .and_then(|start| address.get(start..)),
+            VanityMode::Anywhere => {
+                return match case_sensitive {
+                    true => address.contains(string),
+                    false => find_ignore_ascii_case(address, string).is_some(),
+                }
+            }
+        };
+
+        match (slice, case_sensitive) {
+            (None, _) => false,
+            (Some(slice), true) => slice == string,
+            (Some(slice), false) => ascii_case::eq(slice.as_bytes(), string.as_bytes()),
+        }
+    }
+
+    /// Searches for the first key pair the predicate accepts with given threads, for searches
+    /// the vanity modes can't express. The predicate gets the whole keys_and_address::KeysAndAddress
+    /// struct, so it can check the keys and the address together. The accepted key pair is
+    /// checked with `verify::self_check_keys` before it is returned.
+    /// Returns Err if threads is 0.
+    /// Returns Err if the accepted key pair fails `verify::self_check_keys`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use btc_vanity::vanity_addr_generator::SearchEngines;
+    ///
+    /// // An address with "e" in it whose compressed public key is odd.
+    /// let keys_and_address = SearchEngines::find_custom(
+    ///     |keys_and_address| {
+    ///         keys_and_address.get_comp_address().contains('e')
+    ///             && keys_and_address.get_comp_public_key().starts_with("03")
+    ///     },
+    ///     4,
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn find_custom<P: Fn(&KeysAndAddress) -> bool + Send + Sync + 'static>(
+        predicate: P,
+        threads: u64,
+    ) -> Result<KeysAndAddress, BtcVanityError> {
+        let mut found = None;
+        let secp256k1 = Secp256k1::new();
+        let check_secp256k1 = secp256k1.clone();
+        Self::find_matching(
+            predicate,
+            None,
+            SearchOptions {
+                threads,
+                ..SearchOptions::default()
+            },
+            key_source_factory(None, None, None),
+            None,
+            secp256k1,
+            |result| {
+                self_check_keys(&check_secp256k1, &result.keys_and_address)?;
+                found = Some(result.keys_and_address);
+                Ok(Verdict::Accept)
+            },
+        )?;
+
+        Ok(found.expect("find_matching returns Ok only after a key pair is found"))
+    }
+
+    /// Search for `count` vanity addresses with given threads with `find_matching`
+    /// and passes them to `on_found` with the range of the address which matched the string.
+    /// Every found key pair is checked with `verify::self_check` first, and the search returns
+    /// SelfCheckFailed instead of passing on a key pair which fails it.
+    /// The search stops early if `on_found` returns Break.
+    /// If unique_matches is set, an address whose matched substring is already found is skipped
+    /// and doesn't count, so Err is returned if the string has less than `count` distinct matches.
+    /// The matching addresses in excluded_addresses are skipped by the search threads. The set is
+    /// only looked up after a match, so it doesn't slow down the other candidates.
+    fn find_vanity_addresses<F: FnMut(SearchResult) -> ControlFlow<()>>(
+        string: &str,
+        mut options: SearchOptions,
+        key_source: KeySourceFactory,
+        on_progress: Option<ProgressCallback>,
+        secp256k1: Secp256k1<All>,
+        mut on_found: F,
+    ) -> Result<(), BtcVanityError> {
+        let SearchOptions {
+            count,
+            threads,
+            case_sensitive,
+            vanity_mode,
+            unique_matches,
+            ..
+        } = options;
+        let excluded_addresses = options.excluded_addresses.take();
+        info!(
+            "Searching {} {} for '{}' on {} with {} threads (case sensitive: {})",
+            count,
+            if count == 1 { "address" } else { "addresses" },
+            string,
+            BITCOIN.name,
+            threads,
+            case_sensitive
+        );
+        debug!("Vanity mode: {:?}", vanity_mode);
+
+        let mut seen_matches = None;
+        if unique_matches {
+            let unique = unique_match_count(string, case_sensitive);
+            if count > unique {
+                return Err(BtcVanityError::TooFewUniqueMatches { count, unique });
+            }
+            seen_matches = Some(SeenMatches::new());
+        }
+
+        // Prefix strings are checked on the hash160 first, so most candidates are never encoded.
+        let prefilter = match vanity_mode {
+            VanityMode::Prefix => PrefixRanges::new(string, case_sensitive).map(Arc::new),
+            _ => None,
+        };
+        let pattern = string.to_string();
+        let check_secp256k1 = secp256k1.clone();
+        Self::find_matching(
+            move |keys_and_address: &KeysAndAddress| {
+                let address = keys_and_address.get_comp_address();
+                if !Self::is_match(address, &pattern, case_sensitive, vanity_mode) {
+                    return false;
+                }
+                match &excluded_addresses {
+                    Some(excluded) if excluded.contains(address.as_bytes()) => {
+                        debug!("Skipping {}, it is in the exclusion list", address);
+                        false
+                    }
+                    _ => true,
+                }
+            },
+            prefilter,
+            options,
+            key_source,
+            on_progress,
+            secp256k1,
+            |mut result| {
+                self_check(
+                    &check_secp256k1,
+                    &result.keys_and_address,
+                    string,
+                    vanity_mode,
+                    case_sensitive,
+                )?;
+                result.match_range = Self::find_match(
+                    result.keys_and_address.get_comp_address(),
+                    string,
+                    case_sensitive,
+                    vanity_mode,
+                )
+                .unwrap_or(0..0);
+                if let Some(seen_matches) = &mut seen_matches {
+                    let address = result.keys_and_address.get_comp_address();
+                    if !seen_matches.insert(&address[result.match_range.clone()]) {
+                        debug!("Skipping {}, its match is already found", address);
+                        return Ok(Verdict::Skip);
+                    }
+                }
+                Ok(match on_found(result) {
+                    ControlFlow::Continue(()) => Verdict::Accept,
+                    ControlFlow::Break(()) => Verdict::AcceptAndStop,
+                })
+            },
+        )
+    }
+
+    /// Search for `count` key pairs the predicate accepts with given threads on the backend.
+    /// If prefilter is given, only the candidates whose public key passes it are built
+    /// and passed to the predicate.
+    /// Threads keep searching after a match and send every found keys_and_address::KeysAndAddress
+    /// struct wia std::sync::mpsc channel. Each received struct is passed to `on_found` with the
+    /// attempts and the time elapsed so far as soon as it arrives. `on_found` returns a Verdict to
+    /// skip it, so it doesn't count, or to stop the search early, or Err to stop the search and
+    /// return it. After `count` structs are accepted the stop flag is set, the
+    /// channel is closed and all the threads exit their loops.
+    /// If the timeout passes first, the threads are stopped the same way and Err is returned with
+    /// the number of attempts made.
+    /// Threads try candidates in chunks of CHECK_INTERVAL. Every thread reserves the attempts of a chunk
+    /// on the shared attempt counter before trying it and gives back the ones it doesn't try, so the
+    /// counter can run ahead of the tried candidates by a chunk per thread while searching. Threads
+    /// only try the reserved attempts below max_attempts and exit their loops when the counter
+    /// reaches it. After all the threads exit Err is returned.
+    /// Threads also exit their loops when the cancel flag is set, and Err is returned the same way.
+    /// The flags are checked once per chunk.
+    /// If max_rate is given, every thread tries at most its share of it per second, counted from
+    /// the start of the search, and sleeps between chunks to keep to it.
+    /// If pin_threads is set, every thread is pinned to a CPU core, one thread per core while there
+    /// are enough of them. Rayon tasks are never pinned, the pool threads aren't the search's.
+    /// All the threads have exited when it returns. If one of them panicked and the search failed,
+    /// Err is returned with the panic message.
+    /// Every thread also counts its own candidates on the counters of `stats`, which can be read
+    /// from other threads while searching. If on_progress is given, a monitor thread reads them
+    /// every PROGRESS_INTERVAL and passes them to it, so the threads never run the callback.
+    /// The vanity mode, the case sensitivity, the unique matches and the excluded addresses of
+    /// the options are up to the predicate, they aren't used here.
+    fn find_matching<P, F>(
+        predicate: P,
+        prefilter: Option<Arc<PrefixRanges>>,
+        options: SearchOptions,
+        key_source: KeySourceFactory,
+        on_progress: Option<ProgressCallback>,
+        secp256k1: Secp256k1<All>,
+        mut on_found: F,
+    ) -> Result<(), BtcVanityError>
+    where
+        P: Fn(&KeysAndAddress) -> bool + Send + Sync + 'static,
+        F: FnMut(SearchResult) -> Result<Verdict, BtcVanityError>,
+    {
+        let SearchOptions {
+            count,
+            threads,
+            backend,
+            pin_threads,
+            timeout,
+            max_attempts,
+            max_rate,
+            attempt_counter,
+            cancellation_token,
+            stats,
+            ..
+        } = options;
+        if threads == 0 {
+            return Err(BtcVanityError::InvalidThreadCount);
+        }
+
         let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(CachePadded(AtomicBool::new(false)));
+        let cancel = cancellation_token.map(|token| token.0).unwrap_or_default();
+        let attempts = attempt_counter.unwrap_or_default();
+        let predicate = Arc::new(predicate);
+        let start = Instant::now();
+        let deadline = timeout.and_then(|timeout| start.checked_add(timeout));
+        let stats = stats.unwrap_or_default();
+        let thread_attempts = stats.start(threads, start);
+
+        let mut core_pins = None;
+        if pin_threads && backend == Backend::Threads {
+            core_pins = CorePins::list();
+            if core_pins.is_none() {
+                warn!(
+                    "Can't list the CPU cores to pin the search threads to, the OS schedules them"
+                );
+            }
+        }
 
-        for _ in 0..threads {
+        let workers = Workers::spawn(backend, threads, |thread_index| {
             let sender = sender.clone();
-            let string = string.to_string();
-            let mut anywhere_flag = false;
-            let mut prefix_suffix_flag = false;
+            let predicate = Arc::clone(&predicate);
             let secp256k1 = secp256k1.clone();
+            let stop = Arc::clone(&stop);
+            let cancel = Arc::clone(&cancel);
+            let attempts = Arc::clone(&attempts);
+            let thread_attempts = Arc::clone(&thread_attempts);
+            let key_source = Arc::clone(&key_source);
+            let prefilter = prefilter.clone();
+            let core_pins = core_pins.clone();
 
-            let _ = thread::spawn(move || {
-                loop {
-                    let keys_and_address = KeysAndAddress::generate_random(&secp256k1);
-                    let address = keys_and_address.get_comp_address();
-
-                    match vanity_mode {
-                        VanityMode::Prefix => {
-                            let slice = &address[1..=string_len];
-                            prefix_suffix_flag = match case_sensitive {
-                                true => slice == string,
-                                false => slice.to_lowercase() == string.to_lowercase(),
-                            };
+            move || {
+                trace!("Search thread {} started", thread_index);
+                if let Some(core_pins) = core_pins {
+                    core_pins.pin(thread_index);
+                }
+                let thread_attempts = &thread_attempts[thread_index as usize].0;
+                let mut keys = IncrementalKeys::new(&secp256k1, key_source(thread_index));
+                // Reused for every candidate until one matches, so the loop doesn't allocate.
+                let mut candidate = None;
+                let mut tried = 0;
+                let pacer = max_rate.map(|max_rate| Pacer::new(max_rate, threads, start));
+                while !stop.0.load(Ordering::Relaxed) && !cancel.load(Ordering::Relaxed) {
+                    if let Some(pacer) = &pacer {
+                        let wait = pacer.wait(tried, Instant::now());
+                        if !wait.is_zero() {
+                            // Sleeps in slices, so the flags are still checked while throttled.
+                            thread::sleep(wait.min(MAX_PACING_SLEEP));
+                            continue;
                         }
-                        VanityMode::Suffix => {
-                            let address_len = address.len();
-                            let slice = &address[address_len - string_len..address_len];
-                            prefix_suffix_flag = match case_sensitive {
-                                true => slice == string,
-                                false => slice.to_lowercase() == string.to_lowercase(),
-                            };
+                    }
+                    let reserved = attempts.fetch_add(CHECK_INTERVAL, Ordering::Relaxed);
+                    let chunk = match max_attempts {
+                        Some(max_attempts) => {
+                            max_attempts.saturating_sub(reserved).min(CHECK_INTERVAL)
                         }
-                        VanityMode::Anywhere => {
-                            anywhere_flag = match case_sensitive {
-                                true => address.contains(&string),
-                                false => address.to_lowercase().contains(&string.to_lowercase()),
-                            };
+                        None => CHECK_INTERVAL,
+                    };
+
+                    let mut chunk_tried = 0;
+                    let mut found = None;
+                    while chunk_tried < chunk {
+                        chunk_tried += 1;
+                        let matched = match &prefilter {
+                            Some(prefilter)
+                                if !prefilter
+                                    .may_match_hash160(&keys.pubkey_hash().to_byte_array()) =>
+                            {
+                                keys.skip(&secp256k1);
+                                false
+                            }
+                            _ => predicate(keys.next_keys_into(&secp256k1, &mut candidate)),
+                        };
+                        // A match ends the chunk, so its attempt count doesn't include the
+                        // rest of the chunk.
+                        found = candidate.take_if(|_| matched);
+                        if found.is_some() {
+                            break;
                         }
                     }
-                    // If the channel closed, that means another thread found a keypair and closed it
-                    // so we just return and kill the thread if an error occurs.
-                    if (prefix_suffix_flag || anywhere_flag)
-                        && sender.send(keys_and_address).is_err()
+
+                    if chunk_tried < CHECK_INTERVAL {
+                        attempts.fetch_sub(CHECK_INTERVAL - chunk_tried, Ordering::Relaxed);
+                    }
+                    // Only this thread writes its counter.
+                    tried += chunk_tried;
+                    thread_attempts.store(tried, Ordering::Relaxed);
+
+                    if let Some(keys_and_address) = found {
+                        // If the channel closed, that means enough key pairs are found and the receiver
+                        // is dropped so we just return and kill the thread if an error occurs.
+                        if sender
+                            .send((thread_index as usize, keys_and_address))
+                            .is_err()
+                        {
+                            break;
+                        }
+                        // The next keys of the walk would be related to the found one.
+                        keys.restart(&secp256k1);
+                    }
+                    // Dropping the sender after the last allowed attempt lets the receiver
+                    // know that this thread is done.
+                    if max_attempts
+                        .is_some_and(|max_attempts| reserved + chunk_tried >= max_attempts)
                     {
+                        break;
+                    }
+                }
+                trace!(
+                    "Search thread {} stopped after {} attempts",
+                    thread_index,
+                    thread_attempts.load(Ordering::Relaxed)
+                );
+            }
+        });
+        // Drop the original sender so the receiver doesn't wait for it.
+        drop(sender);
+
+        // The monitor also logs the speed at debug level, so the search threads never log.
+        let monitor = (on_progress.is_some() || log_enabled!(Level::Debug)).then(|| {
+            let stop = Arc::clone(&stop);
+            let cancel = Arc::clone(&cancel);
+            let stats = stats.clone();
+
+            thread::spawn(move || {
+                let mut last_thread_attempts = vec![0; threads as usize];
+                let mut last_report = start;
+                loop {
+                    // Woken up early when the search ends.
+                    thread::park_timeout(PROGRESS_INTERVAL.saturating_sub(last_report.elapsed()));
+                    if stop.0.load(Ordering::Relaxed) || cancel.load(Ordering::Relaxed) {
                         return;
                     }
+                    if last_report.elapsed() < PROGRESS_INTERVAL {
+                        continue;
+                    }
+
+                    let interval = last_report.elapsed().as_secs_f64();
+                    last_report = Instant::now();
+                    let snapshot = stats.snapshot();
+                    let thread_rates = snapshot
+                        .thread_attempts
+                        .iter()
+                        .zip(last_thread_attempts.iter_mut())
+                        .map(|(&current, last)| {
+                            let rate = (current - *last) as f64 / interval;
+                            *last = current;
+                            rate
+                        })
+                        .collect();
+                    let progress = SearchProgress {
+                        attempts: snapshot.attempts,
+                        elapsed: snapshot.elapsed,
+                        thread_rates,
+                    };
+                    debug!(
+                        "{} attempts, {:.0} keys/sec",
+                        progress.attempts,
+                        progress.keys_per_second()
+                    );
+                    if let Some(on_progress) = &on_progress {
+                        on_progress(progress);
+                    }
                 }
-            });
+            })
+        });
+
+        let result = Self::receive_vanity_addresses(
+            count,
+            max_attempts,
+            &receiver,
+            &stop.0,
+            &cancel,
+            &attempts,
+            start,
+            deadline,
+            &mut on_found,
+        );
+
+        // The threads check the stop flag before every chunk of candidates, so they are joined
+        // right away and don't keep running after the search returns.
+        stop.0.store(true, Ordering::Relaxed);
+        let panicked = workers.join();
+        stats.finish();
+        if let Some(monitor) = monitor {
+            monitor.thread().unpark();
+            let _ = monitor.join();
+        }
+
+        // A search which failed because its threads panicked would otherwise look like it
+        // ran out of threads, attempts or time.
+        match panicked {
+            Some(message) if result.is_err() => {
+                error!("A search thread panicked: {}", message);
+                Err(BtcVanityError::WorkerPanicked { message })
+            }
+            _ => result,
         }
+    }
+
+    /// Receives the key pairs sent by the search threads of `find_matching`
+    /// and passes them to `on_found` until it accepts `count` of them.
+    #[allow(clippy::too_many_arguments)]
+    fn receive_vanity_addresses<F: FnMut(SearchResult) -> Result<Verdict, BtcVanityError>>(
+        count: u64,
+        max_attempts: Option<u64>,
+        receiver: &Receiver<(usize, KeysAndAddress)>,
+        stop: &AtomicBool,
+        cancel: &AtomicBool,
+        attempts: &AtomicU64,
+        start: Instant,
+        deadline: Option<Instant>,
+        on_found: &mut F,
+    ) -> Result<(), BtcVanityError> {
+        let mut found = 0;
+        while found < count {
+            let received = match deadline {
+                Some(deadline) => {
+                    receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
 
-        loop {
-            match receiver.try_recv() {
-                Ok(pair) => return pair,
-                Err(_) => continue,
+            match received {
+                Ok((thread_id, pair)) => {
+                    let address = pair.get_comp_address().to_string();
+                    let result = SearchResult {
+                        keys_and_address: pair,
+                        attempts: attempts.load(Ordering::Relaxed),
+                        duration: start.elapsed(),
+                        match_range: 0..0,
+                        thread_id,
+                    };
+                    let (result_attempts, duration) = (result.attempts, result.duration);
+                    let verdict = on_found(result)?;
+                    if verdict == Verdict::Skip {
+                        continue;
+                    }
+                    found += 1;
+                    let last = found == count || verdict == Verdict::AcceptAndStop;
+                    if last {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                    info!(
+                        "Found {} ({}/{}) on thread {} after {} attempts in {:.2?}",
+                        address, found, count, thread_id, result_attempts, duration
+                    );
+                    if last {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    stop.store(true, Ordering::Relaxed);
+                    let attempts = attempts.load(Ordering::Relaxed);
+                    info!("Search timed out after {} attempts", attempts);
+                    return Err(BtcVanityError::TimedOut { attempts });
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    let attempts = attempts.load(Ordering::Relaxed);
+                    info!("All search threads stopped after {} attempts", attempts);
+                    if cancel.load(Ordering::Relaxed) {
+                        return Err(BtcVanityError::Cancelled { attempts });
+                    }
+                    if max_attempts.is_some_and(|max_attempts| attempts >= max_attempts) {
+                        return Err(BtcVanityError::AttemptLimitReached { attempts });
+                    }
+                    return Err(BtcVanityError::SearchThreadsExited);
+                }
             }
         }
+
+        Ok(())
     }
 }
 
@@ -190,7 +1492,7 @@ mod test_only_features {
         /// USE ONLY FOR TESTING. USING THIS FUNCTION FOR CREATING A VALIDITY KEY IS NOT SAFE!!!
         ///
         /// Checks all given information's before passing to the vanity address finder function.
-        /// Returns Result<KeysAndAddressString, VanityGeneratorError>
+        /// Returns Result<KeysAndAddress, BtcVanityError>
         /// Returns OK if a vanity address found successfully with keys_and_address::KeysAndAddress struct
         /// Returns Err if the string is longer than 4 chars and -d or --disable-fast-mode flags are not given.
         /// Returns Err if the string is not in base58 format.
@@ -208,12 +1510,6 @@ mod test_only_features {
 
             Self::validate_input(string, fast_mode)?;
 
-            if string.is_empty() {
-                return KeysAndAddress::generate_within_range(
-                    &secp256k1, &range_min, &range_max, true,
-                );
-            }
-
             SearchEngines::find_vanity_address_within_range(
                 string,
                 range_min,
@@ -247,18 +1543,23 @@ mod test_only_features {
             vanity_mode: VanityMode,
             secp256k1: Secp256k1<All>,
         ) -> Result<KeysAndAddress, BtcVanityError> {
-            let string_len = string.len();
             let (sender, receiver) = mpsc::channel();
 
+            if threads == 0 {
+                return Err(BtcVanityError::InvalidThreadCount);
+            }
+
             // Ensure range_max is greater than range_min
             if range_max <= range_min {
-                return Err(BtcVanityError::VanityGeneratorError(
-                    "range_max must be greater than range_min",
-                ));
+                return Err(BtcVanityError::InvalidRange {
+                    reason: "range_max must be greater than range_min",
+                });
             }
 
             if range_min == BigUint::ZERO {
-                return Err(BtcVanityError::VanityGeneratorError("range_min can't be 0"));
+                return Err(BtcVanityError::InvalidRange {
+                    reason: "range_min can't be 0",
+                });
             }
 
             // Private key range_max must be within the valid range for Secp256k1
@@ -266,14 +1567,12 @@ mod test_only_features {
                 "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141",
                 16,
             )
-            .map_err(|_| {
-                BtcVanityError::VanityGeneratorError("Failed to parse hexadecimal string")
-            })?;
+            .expect("the secp256k1 order is valid hexadecimal");
 
             if range_max > secp256k1_order {
-                return Err(BtcVanityError::VanityGeneratorError(
-                    "range_max must be within the valid range for Secp256k1",
-                ));
+                return Err(BtcVanityError::InvalidRange {
+                    reason: "range_max must be within the valid range for Secp256k1",
+                });
             }
 
             // Calculate the total range size
@@ -288,8 +1587,6 @@ mod test_only_features {
             for i in 0..threads {
                 let sender = sender.clone();
                 let string = string.to_string();
-                let mut anywhere_flag = false;
-                let mut prefix_suffix_flag = false;
                 let secp256k1 = secp256k1.clone();
                 let finished_threads = Arc::clone(&finished_threads);
 
@@ -320,33 +1617,8 @@ mod test_only_features {
 
                         let address = keys_and_address.get_comp_address();
 
-                        match vanity_mode {
-                            VanityMode::Prefix => {
-                                let slice = &address[1..=string_len];
-                                prefix_suffix_flag = match case_sensitive {
-                                    true => slice == string,
-                                    false => slice.to_lowercase() == string.to_lowercase(),
-                                };
-                            }
-                            VanityMode::Suffix => {
-                                let address_len = address.len();
-                                let slice = &address[address_len - string_len..address_len];
-                                prefix_suffix_flag = match case_sensitive {
-                                    true => slice == string,
-                                    false => slice.to_lowercase() == string.to_lowercase(),
-                                };
-                            }
-                            VanityMode::Anywhere => {
-                                anywhere_flag = match case_sensitive {
-                                    true => address.contains(&string),
-                                    false => {
-                                        address.to_lowercase().contains(&string.to_lowercase())
-                                    }
-                                };
-                            }
-                        }
                         // If the channel is closed, another thread found a keypair, so we kill this thread
-                        if (prefix_suffix_flag || anywhere_flag)
+                        if Self::is_match(address, &string, case_sensitive, vanity_mode)
                             && sender.send(keys_and_address).is_err()
                         {
                             return;
@@ -367,9 +1639,7 @@ mod test_only_features {
                     Err(mpsc::TryRecvError::Empty) => {
                         // Check if all threads have finished their work
                         if finished_threads.load(Ordering::SeqCst) == threads as usize {
-                            return Err(BtcVanityError::VanityGeneratorError(
-                                "Vanity address not found within the given range",
-                            ));
+                            return Err(BtcVanityError::NotFoundInRange);
                         }
                     }
                     Err(_) => continue,
@@ -432,31 +1702,768 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "You're asking for too much!")]
-    fn test_generate_vanity_string_too_long_with_fast_mode() {
-        let vanity_string = "12345"; // String longer than 4 characters
-        let _ = VanityAddr::generate(
+    fn test_generate_n_vanity_prefix() {
+        let vanity_string = "et";
+        let found = VanityAddr::generate_n(
             vanity_string,
+            3,                  // Find 3 addresses
+            4,                  // Use 4 threads
+            true,               // Case-insensitivity
+            true,               // Fast mode (limits string size with 4 characters)
+            VanityMode::Prefix, // Vanity mode set to Prefix
+            None,               // No timeout
+            None,               // No attempt limit
+        )
+        .unwrap();
+
+        assert_eq!(found.len(), 3);
+        for keys_and_address in &found {
+            assert!(keys_and_address.get_comp_address().starts_with("1et"));
+        }
+        assert_ne!(found[0].get_comp_address(), found[1].get_comp_address());
+        assert_ne!(found[1].get_comp_address(), found[2].get_comp_address());
+    }
+
+    #[test]
+    fn test_find_match() {
+        let address = "1EmivB8uShHdr4Dft4yGRpvDEUbdUDXkxT";
+
+        assert_eq!(
+            SearchEngines::find_match(address, "emiv", false, VanityMode::Prefix),
+            Some(1..5)
+        );
+        assert_eq!(
+            SearchEngines::find_match(address, "emiv", true, VanityMode::Prefix),
+            None
+        );
+        assert_eq!(
+            SearchEngines::find_match(address, "kxT", true, VanityMode::Suffix),
+            Some(31..34)
+        );
+        assert_eq!(
+            SearchEngines::find_match(address, "dft4", false, VanityMode::Anywhere),
+            Some(14..18)
+        );
+    }
+
+    #[test]
+    fn test_find_custom() {
+        let keys_and_address = SearchEngines::find_custom(
+            |keys_and_address| {
+                keys_and_address.get_comp_address().ends_with('e')
+                    && keys_and_address.get_comp_public_key().starts_with("03")
+            },
+            4,
+        )
+        .unwrap();
+
+        assert!(keys_and_address.get_comp_address().ends_with('e'));
+        assert!(keys_and_address.get_comp_public_key().starts_with("03"));
+
+        assert!(matches!(
+            SearchEngines::find_custom(|_| true, 0),
+            Err(BtcVanityError::InvalidThreadCount)
+        ));
+    }
+
+    #[test]
+    fn test_generate_n_zero_count() {
+        let result = VanityAddr::generate_n(
+            "et",
+            0,                  // Find no addresses
+            4,                  // Use 4 threads
+            true,               // Case-insensitivity
+            true,               // Fast mode (limits string size with 4 characters)
+            VanityMode::Prefix, // Vanity mode set to Prefix
+            None,               // No timeout
+            None,               // No attempt limit
+        );
+
+        assert!(matches!(result, Err(BtcVanityError::InvalidCount)));
+    }
+
+    #[test]
+    fn test_generate_n_timeout() {
+        let start = Instant::now();
+        let result = VanityAddr::generate_n(
+            "zzzz",
+            1,                                // Find 1 address
+            4,                                // Use 4 threads
+            true,                             // Case-insensitivity
+            true,                             // Fast mode (limits string size with 4 characters)
+            VanityMode::Prefix,               // Vanity mode set to Prefix
+            Some(Duration::from_millis(100)), // Give up after 100ms
+            None,                             // No attempt limit
+        );
+
+        assert!(matches!(
+            result,
+            Err(BtcVanityError::TimedOut { attempts }) if attempts > 0
+        ));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_generate_n_max_attempts() {
+        let result = VanityAddr::generate_n(
+            "zzzz",
+            1,                  // Find 1 address
             4,                  // Use 4 threads
+            true,               // Case-insensitivity
+            true,               // Fast mode (limits string size with 4 characters)
+            VanityMode::Prefix, // Vanity mode set to Prefix
+            None,               // No timeout
+            Some(1000),         // Give up after 1000 attempts
+        );
+
+        assert!(matches!(
+            result,
+            Err(BtcVanityError::AttemptLimitReached { attempts }) if (1000..1004).contains(&attempts)
+        ));
+    }
+
+    #[test]
+    fn test_max_attempts_between_chunks() {
+        // Not a multiple of the chunk size, so the last chunks are cut short.
+        let result = VanityAddrBuilder::new("zzzz")
+            .threads(3)
+            .max_attempts(1001)
+            .generate();
+
+        assert!(matches!(
+            result,
+            Err(BtcVanityError::AttemptLimitReached { attempts: 1001 })
+        ));
+    }
+
+    #[test]
+    fn test_search_stats_counts_every_chunk() {
+        let stats = SearchStats::new();
+        let result = VanityAddrBuilder::new("zzzz")
+            .threads(3)
+            .max_attempts(CHECK_INTERVAL * 500)
+            .stats(stats.clone())
+            .generate();
+        assert!(matches!(
+            result,
+            Err(BtcVanityError::AttemptLimitReached { .. })
+        ));
+
+        let snapshot = stats.snapshot();
+        assert!(!snapshot.running);
+        assert_eq!(snapshot.attempts, CHECK_INTERVAL * 500);
+        assert_eq!(snapshot.thread_attempts.len(), 3);
+        assert_eq!(
+            snapshot.thread_attempts.iter().sum::<u64>(),
+            snapshot.attempts
+        );
+        // Every thread flushes its counter after whole chunks only.
+        assert!(snapshot
+            .thread_attempts
+            .iter()
+            .all(|attempts| attempts % CHECK_INTERVAL == 0));
+        assert_eq!(snapshot.keys_per_second, 0.0);
+        assert!(snapshot.average_keys_per_second > 0.0);
+    }
+
+    #[test]
+    fn test_search_stats_are_monotonic() {
+        let stats = SearchStats::new();
+        let search = {
+            let stats = stats.clone();
+            thread::spawn(move || {
+                VanityAddrBuilder::new("zzzzzzzzzzzzzzzzzzzz")
+                    .threads(2)
+                    .fast_mode(false)
+                    .timeout(Duration::from_millis(1500))
+                    .stats(stats)
+                    .generate()
+            })
+        };
+
+        let mut snapshots = Vec::new();
+        while !search.is_finished() {
+            snapshots.push(stats.snapshot());
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(matches!(
+            search.join().unwrap(),
+            Err(BtcVanityError::TimedOut { .. })
+        ));
+        snapshots.push(stats.snapshot());
+
+        let running = snapshots.iter().filter(|snapshot| snapshot.running);
+        assert!(running.clone().count() > 10);
+        for snapshot in running {
+            assert!(snapshot
+                .thread_attempts
+                .iter()
+                .all(|attempts| attempts % CHECK_INTERVAL == 0));
+        }
+        for pair in snapshots.windows(2) {
+            assert!(pair[0].attempts <= pair[1].attempts);
+            assert!(pair[0].elapsed <= pair[1].elapsed);
+            // Skips the snapshots taken before the search started its threads.
+            if pair[0].thread_attempts.len() == pair[1].thread_attempts.len() {
+                assert!(pair[0]
+                    .thread_attempts
+                    .iter()
+                    .zip(&pair[1].thread_attempts)
+                    .all(|(before, after)| before <= after));
+            }
+        }
+        let last = snapshots.last().unwrap();
+        assert!(!last.running);
+        assert!(last.elapsed >= Duration::from_millis(1500));
+        assert!(last.attempts > 0);
+    }
+
+    #[test]
+    fn test_unique_matches() {
+        // "e" has only 2 distinct matches, so duplicates arrive quickly and are skipped.
+        let mut matched = VanityAddrBuilder::new("e")
+            .threads(4)
+            .case_insensitive()
+            .count(2)
+            .unique_matches(true)
+            .generate_n()
+            .unwrap()
+            .iter()
+            .map(|keys_and_address| keys_and_address.get_comp_address()[1..2].to_string())
+            .collect::<Vec<_>>();
+        matched.sort();
+        assert_eq!(matched, ["E", "e"]);
+
+        let result = VanityAddrBuilder::new("e")
+            .case_insensitive()
+            .count(3)
+            .unique_matches(true)
+            .generate_n();
+        assert!(matches!(
+            result,
+            Err(BtcVanityError::TooFewUniqueMatches {
+                count: 3,
+                unique: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn test_unique_match_count() {
+        assert_eq!(unique_match_count("Emiv", true), 1);
+        // 'i' has no uppercase in base58.
+        assert_eq!(unique_match_count("Emiv", false), 8);
+        assert_eq!(unique_match_count("123", false), 1);
+    }
+
+    #[test]
+    fn test_seen_matches_forgets_the_oldest() {
+        let mut seen_matches = SeenMatches::with_capacity(2);
+        assert!(seen_matches.insert("ab"));
+        assert!(!seen_matches.insert("ab"));
+        assert!(seen_matches.insert("Ab"));
+        assert!(seen_matches.has_all(2));
+        assert!(seen_matches.insert("aB"));
+        // "ab" is forgotten to keep the set bounded.
+        assert!(seen_matches.insert("ab"));
+        assert!(!seen_matches.insert("aB"));
+        assert!(!seen_matches.has_all(4));
+    }
+
+    #[test]
+    fn test_find_custom_worker_panic() {
+        let result = SearchEngines::find_custom(|_| panic!("predicate failed"), 2);
+
+        assert!(matches!(
+            result,
+            Err(BtcVanityError::WorkerPanicked { message }) if message == "predicate failed"
+        ));
+    }
+
+    #[test]
+    fn test_generate_pinned_threads() {
+        // Also finds an address on platforms where pinning fails.
+        let keys_and_address = VanityAddrBuilder::new("e")
+            .threads(2)
+            .case_insensitive()
+            .pin_threads(true)
+            .generate()
+            .unwrap();
+
+        assert!(keys_and_address.get_comp_address()[1..]
+            .to_lowercase()
+            .starts_with('e'));
+    }
+
+    #[test]
+    fn test_generate_zero_threads() {
+        let result = VanityAddr::generate(
+            "e",
+            0,                  // Use 0 threads
             false,              // Case-insensitivity
             true,               // Fast mode (limits string size with 4 characters)
             VanityMode::Prefix, // Vanity mode set to Prefix
+        );
+
+        assert!(matches!(result, Err(BtcVanityError::InvalidThreadCount)));
+    }
+
+    #[test]
+    fn test_generate_n_with_seed_is_deterministic() {
+        let generate = |seed| {
+            let mut addresses = Vec::new();
+            VanityAddr::generate_n_with(
+                "e",
+                2,                  // Find 2 addresses
+                1,                  // Use 1 thread so the order of the keys is fixed
+                false,              // Case-insensitivity
+                true,               // Fast mode (limits string size with 4 characters)
+                VanityMode::Prefix, // Vanity mode set to Prefix
+                None,               // No timeout
+                None,               // No attempt limit
+                None,               // No attempt counter
+                None,               // No cancel flag
+                Some(seed),
+                |result| addresses.push(result.keys_and_address.get_comp_address().to_string()),
+            )
+            .unwrap();
+            addresses
+        };
+
+        assert_eq!(generate(42), generate(42));
+        assert_ne!(generate(42), generate(43));
+    }
+
+    #[test]
+    fn test_generate_blocking_single() {
+        let vanity_address =
+            VanityAddr::generate_blocking_single("e", false, true, VanityMode::Suffix, None)
+                .unwrap();
+        assert!(vanity_address
+            .get_comp_address()
+            .to_lowercase()
+            .ends_with('e'));
+
+        assert!(matches!(
+            VanityAddr::generate_blocking_single(
+                "zzzzzzzz",
+                true,
+                false,
+                VanityMode::Prefix,
+                Some(10)
+            ),
+            Err(BtcVanityError::AttemptLimitReached { attempts: 10 })
+        ));
+    }
+
+    #[test]
+    fn test_generate_blocking_single_leading_one() {
+        let vanity_address =
+            VanityAddr::generate_blocking_single("1e", false, true, VanityMode::Prefix, None)
+                .unwrap();
+        assert!(vanity_address.get_comp_address()[1..]
+            .to_lowercase()
+            .starts_with('e'));
+    }
+
+    #[test]
+    fn test_pacer() {
+        let start = Instant::now();
+        // 2 threads share 4000 candidates per second, so a thread tries one every 0.5 ms.
+        let pacer = Pacer::new(4000.0, 2, start);
+
+        assert_eq!(pacer.wait(0, start), Duration::ZERO);
+        assert_eq!(pacer.wait(2000, start), Duration::from_secs(1));
+        assert_eq!(
+            pacer.wait(2000, start + Duration::from_millis(400)),
+            Duration::from_millis(600)
+        );
+        assert_eq!(
+            pacer.wait(2000, start + Duration::from_secs(1)),
+            Duration::ZERO
+        );
+        // A thread which fell behind doesn't wait until it catches up.
+        assert_eq!(
+            pacer.wait(2000, start + Duration::from_secs(3)),
+            Duration::ZERO
+        );
+        assert_eq!(
+            pacer.wait(8000, start + Duration::from_secs(3)),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn test_generate_n_with_joins_threads() {
+        let attempt_counter = Arc::new(AtomicU64::new(0));
+        VanityAddr::generate_n_with(
+            "e",
+            2,                                  // Find 2 addresses
+            4,                                  // Use 4 threads
+            false,                              // Case-insensitivity
+            true,                               // Fast mode (limits string size with 4 characters)
+            VanityMode::Prefix,                 // Vanity mode set to Prefix
+            None,                               // No timeout
+            None,                               // No attempt limit
+            Some(Arc::clone(&attempt_counter)), // Watches the threads after the search returns
+            None,                               // No cancel flag
+            None,                               // Not seeded
+            |_| {},
         )
         .unwrap();
+
+        // The threads are joined before the search returns, so the counter doesn't move anymore.
+        let attempts = attempt_counter.load(Ordering::Relaxed);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(attempt_counter.load(Ordering::Relaxed), attempts);
+    }
+
+    #[test]
+    fn test_generate_n_with_cancel() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let canceller = {
+            let cancel = Arc::clone(&cancel);
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(100));
+                cancel.store(true, Ordering::Relaxed);
+            })
+        };
+
+        let start = Instant::now();
+        let result = VanityAddr::generate_n_with(
+            "zzzz",
+            1,                  // Find 1 address
+            4,                  // Use 4 threads
+            true,               // Case-insensitivity
+            true,               // Fast mode (limits string size with 4 characters)
+            VanityMode::Prefix, // Vanity mode set to Prefix
+            None,               // No timeout
+            None,               // No attempt limit
+            None,               // No attempt counter
+            Some(cancel),       // Cancelled after 100ms
+            None,               // Not seeded
+            |_| panic!("no address should be found"),
+        );
+        canceller.join().unwrap();
+
+        assert!(matches!(
+            result,
+            Err(BtcVanityError::Cancelled { attempts }) if attempts > 0
+        ));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_case_insensitive_matching_agrees_with_lowercase() {
+        use crate::chain::BASE58_ALPHABET;
+        use bitcoin::secp256k1::rand::Rng;
+
+        let alphabet = BASE58_ALPHABET.as_bytes();
+        let mut rng = seeded_rng(1361, 0);
+        let mut random_string = |len: usize| -> String {
+            (0..len)
+                .map(|_| alphabet[rng.gen_range(0..alphabet.len())] as char)
+                .collect()
+        };
+
+        for _ in 0..20_000 {
+            let address = format!("1{}", random_string(33));
+            // Short strings, so a good part of them match somewhere.
+            let string_len = 1 + ((address.as_bytes()[1] as usize) % 3);
+            let string = random_string(string_len);
+
+            assert_eq!(
+                find_ignore_ascii_case(&address, &string),
+                address.to_lowercase().find(&string.to_lowercase())
+            );
+            for vanity_mode in [VanityMode::Prefix, VanityMode::Suffix, VanityMode::Anywhere] {
+                let expected = match vanity_mode {
+                    VanityMode::Prefix => address[1..]
+                        .to_lowercase()
+                        .starts_with(&string.to_lowercase()),
+                    VanityMode::Suffix => address.to_lowercase().ends_with(&string.to_lowercase()),
+                    VanityMode::Anywhere => address.to_lowercase().contains(&string.to_lowercase()),
+                };
+                assert_eq!(
+                    SearchEngines::is_match(&address, &string, false, vanity_mode),
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_suffix_matching_case_at_the_boundaries() {
+        let address = "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2";
+        let is_suffix = |string: &str, case_sensitive: bool| {
+            SearchEngines::is_match(address, string, case_sensitive, VanityMode::Suffix)
+        };
+
+        // The first and the last letter of the window in the other case.
+        assert!(is_suffix("jaNVn2", false));
+        assert!(!is_suffix("jaNVN2", true));
+        assert!(!is_suffix("JaNVn2", true));
+        assert!(is_suffix("JaNVN2", true));
+        // The character before the window doesn't match.
+        assert!(!is_suffix("yJaNVN2", false));
+        // The whole address, with the leading '1'.
+        assert!(is_suffix(&address.to_lowercase(), false));
+        assert!(!is_suffix(&address.to_lowercase(), true));
+        assert_eq!(
+            SearchEngines::find_match(address, "jaNVn2", false, VanityMode::Suffix),
+            Some(28..34)
+        );
+    }
+
+    #[test]
+    fn test_matchers_with_strings_longer_than_the_address() {
+        let string = "Emivvvvvvvvvvvvvvvvvvvvvvvvvvvvvv";
+        for address in ["", "1", "1Emiv", "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2"] {
+            for case_sensitive in [true, false] {
+                for vanity_mode in [VanityMode::Prefix, VanityMode::Suffix, VanityMode::Anywhere] {
+                    assert!(!SearchEngines::is_match(
+                        address,
+                        string,
+                        case_sensitive,
+                        vanity_mode
+                    ));
+                    assert_eq!(
+                        SearchEngines::find_match(address, string, case_sensitive, vanity_mode),
+                        None
+                    );
+                }
+            }
+        }
+
+        // A string filling the whole address after the leading '1' still matches.
+        let address = "1Emiv";
+        assert!(SearchEngines::is_match(
+            address,
+            "Emiv",
+            true,
+            VanityMode::Prefix
+        ));
+        assert!(!SearchEngines::is_match(
+            address,
+            "Emivv",
+            true,
+            VanityMode::Prefix
+        ));
+        assert_eq!(
+            SearchEngines::find_match(address, "1emiv", false, VanityMode::Suffix),
+            Some(0..5)
+        );
+    }
+
+    #[test]
+    fn test_adjust_input() {
+        assert_eq!(
+            VanityAddr::adjust_input("1Emiv", VanityMode::Prefix),
+            "Emiv"
+        );
+        assert_eq!(VanityAddr::adjust_input("11x", VanityMode::Prefix), "1x");
+        assert_eq!(VanityAddr::adjust_input("Emiv", VanityMode::Prefix), "Emiv");
+        assert_eq!(
+            VanityAddr::adjust_input("1Emiv", VanityMode::Suffix),
+            "1Emiv"
+        );
+        assert_eq!(
+            VanityAddr::adjust_input("1Emiv", VanityMode::Anywhere),
+            "1Emiv"
+        );
+    }
+
+    #[test]
+    fn test_plan() {
+        let fast_max = BITCOIN.max_pattern_len(true);
+        let resolved =
+            VanityAddr::plan("1Emiv", VanityMode::Prefix, false, fast_max, false).unwrap();
+        assert_eq!(
+            resolved,
+            ResolvedSearch {
+                chain: "bitcoin",
+                input: String::from("1Emiv"),
+                pattern: String::from("Emiv"),
+                vanity_mode: VanityMode::Prefix,
+                case_sensitive: false,
+                max_len: 4,
+                rarity: None,
+            }
+        );
+        assert_eq!(resolved.address_shape(), "1Emiv...");
+
+        let resolved = VanityAddr::plan("1Emiv", VanityMode::Prefix, true, 5, true).unwrap();
+        assert_eq!(resolved.pattern, "1Emiv");
+        assert_eq!(resolved.address_shape(), "11Emiv...");
+        let resolved = VanityAddr::plan("1Emiv", VanityMode::Suffix, true, 5, false).unwrap();
+        assert_eq!(resolved.pattern, "1Emiv");
+        assert_eq!(resolved.address_shape(), "1...1Emiv");
+        let resolved =
+            VanityAddr::plan("Emiv", VanityMode::Anywhere, true, fast_max, false).unwrap();
+        assert_eq!(resolved.address_shape(), "1...Emiv...");
+        assert!(
+            VanityAddr::plan("z", VanityMode::Prefix, true, fast_max, false)
+                .unwrap()
+                .rarity
+                .is_some()
+        );
+
+        assert!(matches!(
+            VanityAddr::plan("1Emiv", VanityMode::Suffix, true, fast_max, false),
+            Err(BtcVanityError::FastModeEnabled { len: 5, max: 4, .. })
+        ));
+        assert!(matches!(
+            VanityAddr::plan("1Em0", VanityMode::Prefix, true, fast_max, false),
+            Err(BtcVanityError::InvalidCharacter { ch: '0', index: 2 })
+        ));
+        assert!(matches!(
+            VanityAddr::plan(&"z".repeat(33), VanityMode::Suffix, true, 33, false),
+            Err(BtcVanityError::ImpossiblePattern { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_input_len() {
+        assert!(VanityAddr::validate_input_len("Emivv", 5).is_ok());
+        assert!(VanityAddr::validate_input_len("Emivvv", 33).is_ok());
+
+        let err = VanityAddr::validate_input_len("Emivvv", 5).unwrap_err();
+        assert!(matches!(
+            err,
+            BtcVanityError::FastModeEnabled {
+                len: 6,
+                max: 5,
+                chain: "bitcoin"
+            }
+        ));
+        assert!(err
+            .to_string()
+            .contains("The string is 6 chars, the limit is 5; pass --disable-fast or --max-len"));
+        // The limit can't go above what an address can match.
+        assert!(matches!(
+            VanityAddr::validate_input_len(&"a".repeat(34), 40),
+            Err(BtcVanityError::PatternTooLong { max: 33, .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_input_longer_than_address() {
+        let vanity_string = "a".repeat(34);
+
+        assert!(matches!(
+            VanityAddr::validate_input(&vanity_string, false),
+            Err(BtcVanityError::PatternTooLong {
+                len: 34,
+                max: 33,
+                chain: "bitcoin"
+            })
+        ));
+        assert!(VanityAddr::validate_input(&vanity_string[..33], false).is_ok());
+    }
+
+    #[test]
+    fn test_validate_feasibility_impossible_patterns() {
+        // 24 bytes fill 33 base58 digits only up to about "Q".
+        let highest = "z".repeat(33);
+        for vanity_mode in [VanityMode::Prefix, VanityMode::Suffix] {
+            assert!(matches!(
+                VanityAddr::validate_feasibility(&highest, vanity_mode, true),
+                Err(BtcVanityError::ImpossiblePattern { chain: "bitcoin" })
+            ));
+        }
+        assert!(matches!(
+            VanityAddr::validate_feasibility(&"r".repeat(33), VanityMode::Prefix, false),
+            Err(BtcVanityError::ImpossiblePattern { .. })
+        ));
+        // Case insensitive 'p' can still be the possible 'P'.
+        assert!(
+            VanityAddr::validate_feasibility(&"p".repeat(33), VanityMode::Prefix, false).is_ok()
+        );
+        assert!(VanityAddr::validate_feasibility(
+            &format!("P{}", "z".repeat(32)),
+            VanityMode::Prefix,
+            true
+        )
+        .is_ok());
+        // A long suffix starting with '1' can end a shorter address, so it isn't checked.
+        let suffix = format!("1{}", "z".repeat(32));
+        assert!(VanityAddr::validate_feasibility(&suffix, VanityMode::Suffix, true).is_ok());
+        assert!(VanityAddr::validate_feasibility(&highest, VanityMode::Anywhere, true).is_ok());
+        assert!(
+            VanityAddr::validate_feasibility(&"1".repeat(25), VanityMode::Prefix, true).is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_feasibility_rarity() {
+        let rarity = |string, vanity_mode, case_sensitive| {
+            VanityAddr::validate_feasibility(string, vanity_mode, case_sensitive).unwrap()
+        };
+
+        let lowercase = rarity("z", VanityMode::Prefix, true).unwrap();
+        assert!((23.0..24.0).contains(&lowercase), "{}", lowercase);
+        let leading_one = rarity("1", VanityMode::Prefix, true).unwrap();
+        assert!((4.0..5.0).contains(&leading_one), "{}", leading_one);
+        assert!(rarity("1a", VanityMode::Prefix, true).unwrap() > 20.0);
+
+        assert_eq!(rarity("Emiv", VanityMode::Prefix, true), None);
+        assert_eq!(rarity("emiv", VanityMode::Prefix, false), None);
+        assert_eq!(rarity("z", VanityMode::Suffix, true), None);
+        assert_eq!(rarity("z", VanityMode::Anywhere, true), None);
+    }
+
+    #[test]
+    fn test_generate_vanity_string_too_long_with_fast_mode() {
+        let vanity_string = "23456"; // String longer than 4 characters, without the leading "1" prefix mode drops
+        let result = VanityAddr::generate(
+            vanity_string,
+            4,                  // Use 4 threads
+            false,              // Case-insensitivity
+            true,               // Fast mode (limits string size with 4 characters)
+            VanityMode::Prefix, // Vanity mode set to Prefix
+        );
+
+        assert!(matches!(
+            result,
+            Err(BtcVanityError::FastModeEnabled {
+                len: 5,
+                max: 4,
+                chain: "bitcoin"
+            })
+        ));
     }
 
     #[test]
-    #[should_panic(expected = "Your input is not in base58.")]
     fn test_generate_vanity_invalid_base58() {
         let vanity_string = "emiO"; // Contains invalid base58 character 'O'
-        let _ = VanityAddr::generate(
+        let result = VanityAddr::generate(
             vanity_string,
             4,                  // Use 4 threads
             false,              // Case-insensitivity
             true,               // Fast mode (limits string size with 4 characters)
             VanityMode::Prefix, // Vanity mode set to Prefix
-        )
-        .unwrap();
+        );
+
+        assert!(matches!(
+            result,
+            Err(BtcVanityError::InvalidCharacter { ch: 'O', index: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_validate_input_invalid_character_position() {
+        assert!(matches!(
+            VanityAddr::validate_input("emiO", true),
+            Err(BtcVanityError::InvalidCharacter { ch: 'O', index: 3 })
+        ));
+        assert!(matches!(
+            VanityAddr::validate_input("a-b", true),
+            Err(BtcVanityError::InvalidCharacter { ch: '-', index: 1 })
+        ));
+        assert!(matches!(
+            VanityAddr::validate_input("éa", true),
+            Err(BtcVanityError::InvalidCharacter { ch: 'é', index: 0 })
+        ));
     }
 }
 
@@ -513,7 +2520,6 @@ mod test_only_tests {
     }
 
     #[test]
-    #[should_panic(expected = "Vanity address not found within the given range")]
     fn test_generate_within_range_loop_proof_not_found() {
         let vanity_string = "abc";
         let range_min = BigUint::from_str_radix(
@@ -527,7 +2533,7 @@ mod test_only_tests {
         )
         .unwrap();
 
-        let _ = VanityAddr::generate_within_range(
+        let result = VanityAddr::generate_within_range(
             vanity_string,
             range_min,
             range_max,
@@ -535,7 +2541,8 @@ mod test_only_tests {
             true,
             true,
             VanityMode::Prefix,
-        )
-        .unwrap();
+        );
+
+        assert!(matches!(result, Err(BtcVanityError::NotFoundInRange)));
     }
 }
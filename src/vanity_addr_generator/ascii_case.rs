@@ -0,0 +1,317 @@
+//! # ASCII Case Insensitive Comparisons
+//!
+//! The comparisons of the case insensitive matchers, vectorized with SSE2 and AVX2 on x86_64 and
+//! NEON on aarch64. A vector lowercases 16 or 32 bytes at once by adding 0x20 to the lanes between
+//! 'A' and 'Z', then compares whole lanes. Other targets, and inputs too short to fill a vector,
+//! use the scalar versions.
+
+/// Returns true if the byte strings are equal ignoring ASCII case.
+pub(crate) fn eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    if a.len() >= x86::SSE2_LANES {
+        // SAFETY: SSE2 is part of the x86_64 baseline and the inputs fill a vector.
+        return unsafe { x86::eq_sse2(a, b) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    if a.len() >= neon::LANES {
+        // SAFETY: NEON is part of the aarch64 baseline and the inputs fill a vector.
+        return unsafe { neon::eq(a, b) };
+    }
+
+    a.eq_ignore_ascii_case(b)
+}
+
+/// Returns the index of the first occurrence of the needle in the haystack ignoring ASCII case.
+pub(crate) fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        let positions = (haystack.len() + 1).saturating_sub(needle.len());
+        if positions >= x86::AVX2_LANES && is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 is detected and there is a position for every lane.
+            return unsafe { x86::find_avx2(haystack, needle) };
+        }
+        if positions >= x86::SSE2_LANES {
+            // SAFETY: SSE2 is part of the x86_64 baseline and there is a position for every lane.
+            return unsafe { x86::find_sse2(haystack, needle) };
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    if (haystack.len() + 1).saturating_sub(needle.len()) >= neon::LANES {
+        // SAFETY: NEON is part of the aarch64 baseline and there is a position for every lane.
+        return unsafe { neon::find(haystack, needle) };
+    }
+
+    find_scalar(haystack, needle)
+}
+
+/// The scalar version of `find`, one window at a time.
+fn find_scalar(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle))
+}
+
+/// Runs a vectorized search of a non-empty needle with at least `lanes` positions in the haystack.
+/// `candidates(start)` returns the mask of the positions `start..start + lanes` whose first and last
+/// bytes match the needle, with `lane_bits` bits per position. Candidates are checked with the
+/// scalar comparison.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+#[inline(always)]
+fn find_candidates(
+    haystack: &[u8],
+    needle: &[u8],
+    lanes: usize,
+    lane_bits: u32,
+    candidates: impl Fn(usize) -> u64,
+) -> Option<usize> {
+    let positions = haystack.len() + 1 - needle.len();
+    let lane_mask = u64::MAX >> (64 - lane_bits);
+    let mut start = 0;
+    loop {
+        // The last block is moved back to end at the last position, the positions it
+        // repeats are already checked and didn't match.
+        let block = start.min(positions - lanes);
+        let mut mask = candidates(block);
+        while mask != 0 {
+            let lane = mask.trailing_zeros() / lane_bits;
+            let position = block + lane as usize;
+            if haystack[position..position + needle.len()].eq_ignore_ascii_case(needle) {
+                return Some(position);
+            }
+            mask &= !(lane_mask << (lane * lane_bits));
+        }
+
+        start = block + lanes;
+        if start >= positions {
+            return None;
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::find_candidates;
+    use std::arch::x86_64::*;
+
+    pub(super) const SSE2_LANES: usize = 16;
+    pub(super) const AVX2_LANES: usize = 32;
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn lowercase_sse2(bytes: __m128i) -> __m128i {
+        // Bytes above 0x7f are negative and never between 'A' and 'Z'.
+        let upper = _mm_and_si128(
+            _mm_cmpgt_epi8(bytes, _mm_set1_epi8(b'A' as i8 - 1)),
+            _mm_cmplt_epi8(bytes, _mm_set1_epi8(b'Z' as i8 + 1)),
+        );
+        _mm_add_epi8(bytes, _mm_and_si128(upper, _mm_set1_epi8(0x20)))
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn lowercase_avx2(bytes: __m256i) -> __m256i {
+        let upper = _mm256_and_si256(
+            _mm256_cmpgt_epi8(bytes, _mm256_set1_epi8(b'A' as i8 - 1)),
+            _mm256_cmpgt_epi8(_mm256_set1_epi8(b'Z' as i8 + 1), bytes),
+        );
+        _mm256_add_epi8(bytes, _mm256_and_si256(upper, _mm256_set1_epi8(0x20)))
+    }
+
+    /// The inputs must have the same length of at least `SSE2_LANES` bytes.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn eq_sse2(a: &[u8], b: &[u8]) -> bool {
+        let len = a.len();
+        let mut start = 0;
+        loop {
+            // The last chunk is moved back to end at the end of the inputs.
+            let chunk = start.min(len - SSE2_LANES);
+            let a_lanes = lowercase_sse2(_mm_loadu_si128(a.as_ptr().add(chunk).cast()));
+            let b_lanes = lowercase_sse2(_mm_loadu_si128(b.as_ptr().add(chunk).cast()));
+            if _mm_movemask_epi8(_mm_cmpeq_epi8(a_lanes, b_lanes)) != 0xffff {
+                return false;
+            }
+
+            start = chunk + SSE2_LANES;
+            if start >= len {
+                return true;
+            }
+        }
+    }
+
+    /// The needle must not be empty and the haystack must have at least `SSE2_LANES` positions for it.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn find_sse2(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        let first = _mm_set1_epi8(needle[0].to_ascii_lowercase() as i8);
+        let last = _mm_set1_epi8(needle[needle.len() - 1].to_ascii_lowercase() as i8);
+        let offset = needle.len() - 1;
+
+        find_candidates(haystack, needle, SSE2_LANES, 1, |start| {
+            let block = haystack.as_ptr().add(start);
+            let first_lanes = lowercase_sse2(_mm_loadu_si128(block.cast()));
+            let last_lanes = lowercase_sse2(_mm_loadu_si128(block.add(offset).cast()));
+            let matches = _mm_and_si128(
+                _mm_cmpeq_epi8(first_lanes, first),
+                _mm_cmpeq_epi8(last_lanes, last),
+            );
+            _mm_movemask_epi8(matches) as u32 as u64
+        })
+    }
+
+    /// The needle must not be empty and the haystack must have at least `AVX2_LANES` positions for it.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn find_avx2(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        let first = _mm256_set1_epi8(needle[0].to_ascii_lowercase() as i8);
+        let last = _mm256_set1_epi8(needle[needle.len() - 1].to_ascii_lowercase() as i8);
+        let offset = needle.len() - 1;
+
+        find_candidates(haystack, needle, AVX2_LANES, 1, |start| {
+            let block = haystack.as_ptr().add(start);
+            let first_lanes = lowercase_avx2(_mm256_loadu_si256(block.cast()));
+            let last_lanes = lowercase_avx2(_mm256_loadu_si256(block.add(offset).cast()));
+            let matches = _mm256_and_si256(
+                _mm256_cmpeq_epi8(first_lanes, first),
+                _mm256_cmpeq_epi8(last_lanes, last),
+            );
+            _mm256_movemask_epi8(matches) as u32 as u64
+        })
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use super::find_candidates;
+    use std::arch::aarch64::*;
+
+    pub(super) const LANES: usize = 16;
+
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn lowercase(bytes: uint8x16_t) -> uint8x16_t {
+        let upper = vandq_u8(
+            vcgeq_u8(bytes, vdupq_n_u8(b'A')),
+            vcleq_u8(bytes, vdupq_n_u8(b'Z')),
+        );
+        vaddq_u8(bytes, vandq_u8(upper, vdupq_n_u8(0x20)))
+    }
+
+    /// The inputs must have the same length of at least `LANES` bytes.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn eq(a: &[u8], b: &[u8]) -> bool {
+        let len = a.len();
+        let mut start = 0;
+        loop {
+            // The last chunk is moved back to end at the end of the inputs.
+            let chunk = start.min(len - LANES);
+            let a_lanes = lowercase(vld1q_u8(a.as_ptr().add(chunk)));
+            let b_lanes = lowercase(vld1q_u8(b.as_ptr().add(chunk)));
+            if vminvq_u8(vceqq_u8(a_lanes, b_lanes)) != 0xff {
+                return false;
+            }
+
+            start = chunk + LANES;
+            if start >= len {
+                return true;
+            }
+        }
+    }
+
+    /// The needle must not be empty and the haystack must have at least `LANES` positions for it.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        let first = vdupq_n_u8(needle[0].to_ascii_lowercase());
+        let last = vdupq_n_u8(needle[needle.len() - 1].to_ascii_lowercase());
+        let offset = needle.len() - 1;
+
+        // NEON has no movemask, narrowing the 0x00/0xff lanes by 4 bits packs them into a
+        // 64 bit mask with 4 bits per lane.
+        find_candidates(haystack, needle, LANES, 4, |start| {
+            let block = haystack.as_ptr().add(start);
+            let first_lanes = lowercase(vld1q_u8(block));
+            let last_lanes = lowercase(vld1q_u8(block.add(offset)));
+            let matches = vandq_u8(vceqq_u8(first_lanes, first), vceqq_u8(last_lanes, last));
+            vget_lane_u64::<0>(vreinterpret_u64_u8(vshrn_n_u16::<4>(vreinterpretq_u16_u8(
+                matches,
+            ))))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys_and_address::seeded_rng;
+    use bitcoin::secp256k1::rand::Rng;
+
+    /// Letters at the edges of the lowercased range, the bytes next to them and bytes above 0x7f.
+    const TRICKY_BYTES: &[u8] = b"aAzZbB@[`{09\x7f\x80\xc1\xda\xe1\xfa\xff";
+
+    #[test]
+    fn test_eq_agrees_with_scalar() {
+        let mut rng = seeded_rng(1368, 0);
+        for _ in 0..20_000 {
+            let len = rng.gen_range(0..70);
+            let a: Vec<u8> = (0..len)
+                .map(|_| TRICKY_BYTES[rng.gen_range(0..TRICKY_BYTES.len())])
+                .collect();
+            // Mostly the same string with flipped case, sometimes with a changed byte.
+            let mut b: Vec<u8> = a
+                .iter()
+                .map(|&byte| match rng.gen_bool(0.5) {
+                    true => byte.to_ascii_uppercase(),
+                    false => byte.to_ascii_lowercase(),
+                })
+                .collect();
+            if len > 0 && rng.gen_bool(0.5) {
+                b[rng.gen_range(0..len)] = rng.gen();
+            }
+
+            assert_eq!(eq(&a, &b), a.eq_ignore_ascii_case(&b), "{a:?} {b:?}");
+        }
+        assert!(!eq(b"abc", b"abcd"));
+    }
+
+    #[test]
+    fn test_find_agrees_with_scalar() {
+        let mut rng = seeded_rng(1368, 1);
+        for _ in 0..20_000 {
+            let haystack: Vec<u8> = (0..rng.gen_range(0..80))
+                .map(|_| match rng.gen_bool(0.1) {
+                    true => rng.gen(),
+                    false => TRICKY_BYTES[rng.gen_range(0..6)],
+                })
+                .collect();
+            let needle: Vec<u8> = match rng.gen_bool(0.5) && !haystack.is_empty() {
+                // A part of the haystack with flipped case, so it matches at least there.
+                true => {
+                    let start = rng.gen_range(0..haystack.len());
+                    let end = rng.gen_range(start..=haystack.len().min(start + 10));
+                    haystack[start..end]
+                        .iter()
+                        .map(|byte| byte.to_ascii_uppercase())
+                        .collect()
+                }
+                false => (0..rng.gen_range(0..10))
+                    .map(|_| TRICKY_BYTES[rng.gen_range(0..6)])
+                    .collect(),
+            };
+
+            let expected = match needle.is_empty() {
+                true => Some(0),
+                false => find_scalar(&haystack, &needle),
+            };
+            assert_eq!(
+                find(&haystack, &needle),
+                expected,
+                "{haystack:?} {needle:?}"
+            );
+        }
+    }
+}
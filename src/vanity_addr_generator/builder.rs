@@ -0,0 +1,653 @@
+//! # Vanity Address Builder Module
+//!
+//! This module provides a builder for the search options, so a vanity address can be generated
+//! without remembering the order of the positional arguments of `VanityAddr::generate`.
+//!
+//! # Example Usage
+//!
+//! ```rust
+//! use btc_vanity::vanity_addr_generator::{VanityAddrBuilder, VanityMode};
+//! use std::time::Duration;
+//!
+//! let vanity_address = VanityAddrBuilder::new("Tst")
+//!     .threads(4)
+//!     .case_insensitive()
+//!     .mode(VanityMode::Anywhere)
+//!     .timeout(Duration::from_secs(600))
+//!     .generate()
+//!     .unwrap();
+//!
+//! println!("address (compressed): {}", vanity_address.get_comp_address())
+//! ```
+
+use crate::chain::BITCOIN;
+use crate::error::BtcVanityError;
+use crate::keys_and_address::{KeySource, KeysAndAddress};
+use crate::vanity_addr_generator::sink::{CallbackSink, ResultSink, VecSink};
+use crate::vanity_addr_generator::{
+    key_source_factory, Backend, CancellationToken, KeySourceFactory, ProgressCallback,
+    ResolvedSearch, SearchEngines, SearchOptions, SearchProgress, SearchResult, SearchStats,
+    VanityAddr, VanityMode,
+};
+
+use bitcoin::secp256k1::Secp256k1;
+use log::warn;
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Search options of a vanity address. Every option has a default, so only the string is required.
+pub struct VanityAddrBuilder {
+    string: String,
+    options: SearchOptions,
+    seed: Option<u64>,
+    key_source: Option<KeySourceFactory>,
+    extra_entropy: Option<Arc<[u8]>>,
+    on_progress: Option<ProgressCallback>,
+    /// Every generated key pair is returned without matching the string.
+    random: bool,
+    keep_leading_one: bool,
+}
+
+impl VanityAddrBuilder {
+    /// Starts a case sensitive prefix search for the string in fast mode,
+    /// with one thread per CPU core and no limits.
+    pub fn new(string: &str) -> Self {
+        VanityAddrBuilder {
+            string: string.to_string(),
+            options: SearchOptions::default(),
+            seed: None,
+            key_source: None,
+            extra_entropy: None,
+            on_progress: None,
+            random: false,
+            keep_leading_one: false,
+        }
+    }
+
+    /// Generates random key pairs without searching for a pattern. The string of `new` can't be
+    /// empty, so this is the explicit way to get key pairs which aren't vanity addresses.
+    pub fn random() -> Self {
+        VanityAddrBuilder {
+            random: true,
+            ..Self::new("")
+        }
+    }
+
+    /// Number of threads to search with.
+    pub fn threads(mut self, threads: u64) -> Self {
+        self.options.threads = threads;
+        self
+    }
+
+    /// Where the search threads run, new threads for every search by default.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.options.backend = backend;
+        self
+    }
+
+    /// Pins every search thread to its own CPU core, so the OS doesn't move them between cores.
+    /// The threads run unpinned where pinning isn't supported. Ignored with `Backend::Rayon`.
+    pub fn pin_threads(mut self, pin_threads: bool) -> Self {
+        self.options.pin_threads = pin_threads;
+        self
+    }
+
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.options.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// Same as `case_sensitive(false)`, ex: "tESt" also matches "Test".
+    pub fn case_insensitive(self) -> Self {
+        self.case_sensitive(false)
+    }
+
+    /// Fast mode limits the string to 4 characters. Disable it to search longer strings.
+    /// Same as setting `max_pattern_len` to the fast mode limit or to what an address can match.
+    pub fn fast_mode(mut self, fast_mode: bool) -> Self {
+        self.options.max_pattern_len = Some(BITCOIN.max_pattern_len(fast_mode));
+        self
+    }
+
+    /// Longest string accepted, instead of the fast mode limit of the chain, ex: 5 to search a
+    /// 5 char prefix while still rejecting longer ones by accident.
+    pub fn max_pattern_len(mut self, max_pattern_len: usize) -> Self {
+        self.options.max_pattern_len = Some(max_pattern_len);
+        self
+    }
+
+    pub fn mode(mut self, vanity_mode: VanityMode) -> Self {
+        self.options.vanity_mode = vanity_mode;
+        self
+    }
+
+    /// A prefix string starting with '1' is searched after the implicit leading '1' of the address
+    /// by default, see `VanityAddr::adjust_input`. Setting this searches for the string as it is,
+    /// so "1x" needs an address starting with "11x".
+    pub fn keep_leading_one(mut self, keep_leading_one: bool) -> Self {
+        self.keep_leading_one = keep_leading_one;
+        self
+    }
+
+    /// Number of vanity addresses `generate_n` and `generate_with` search for.
+    pub fn count(mut self, count: u64) -> Self {
+        self.options.count = count;
+        self
+    }
+
+    /// Skips the addresses whose matched substring is already found, so every found address has
+    /// a different match, ex: the distinct capitalizations of a case insensitive string.
+    /// Only the last 65,536 matches are remembered, so the memory it takes is bounded;
+    /// with a bigger count an older match can be found again.
+    pub fn unique_matches(mut self, unique_matches: bool) -> Self {
+        self.options.unique_matches = unique_matches;
+        self
+    }
+
+    /// Skips the matching addresses in the set, ex: the addresses already found in earlier runs or
+    /// a list of compromised ones, see `file::get_addresses_from_file`. The set is shared by the
+    /// search threads and only looked up after a match.
+    pub fn exclude_addresses(mut self, addresses: impl Into<Arc<HashSet<Box<[u8]>>>>) -> Self {
+        self.options.excluded_addresses = Some(addresses.into());
+        self
+    }
+
+    /// Gives up the search after the given duration.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    /// Gives up the search after trying the given number of candidates.
+    pub fn max_attempts(mut self, max_attempts: u64) -> Self {
+        self.options.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Tries at most the given number of candidates per second with all the threads together,
+    /// so a long search leaves the CPU to other work. The threads sleep between chunks of
+    /// candidates to keep to it.
+    pub fn max_rate(mut self, keys_per_second: f64) -> Self {
+        self.options.max_rate = Some(keys_per_second);
+        self
+    }
+
+    /// Every tried candidate is added to the counter, so the progress of the search can be
+    /// watched from another thread. It should start from 0.
+    pub fn attempt_counter(mut self, attempt_counter: Arc<AtomicU64>) -> Self {
+        self.options.attempt_counter = Some(attempt_counter);
+        self
+    }
+
+    /// Cancelling the token from another thread stops the search.
+    pub fn cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.options.cancellation_token = Some(cancellation_token);
+        self
+    }
+
+    /// Returns the cancellation token of the search, setting a new one if there isn't one.
+    #[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+    pub(crate) fn get_or_insert_cancellation_token(&mut self) -> CancellationToken {
+        self.options
+            .cancellation_token
+            .get_or_insert_with(CancellationToken::new)
+            .clone()
+    }
+
+    /// Generates the keys deterministically from the seed. The keys are predictable,
+    /// so they are only for tests and demos.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Generates the secret keys of every search thread from the key source `key_source`
+    /// returns for the index of the thread, instead of the system random number generator.
+    /// It is called on the search thread, so the key source doesn't need to be Send. Overrides the seed.
+    pub fn key_source<F: Fn(u64) -> Box<dyn KeySource> + Send + Sync + 'static>(
+        mut self,
+        key_source: F,
+    ) -> Self {
+        self.key_source = Some(Arc::new(key_source));
+        self
+    }
+
+    /// XORs the SHA-256 hash of the extra entropy, ex: the content of a file of dice rolls,
+    /// into every generated secret key.
+    pub fn extra_entropy(mut self, entropy: &[u8]) -> Self {
+        self.extra_entropy = Some(entropy.into());
+        self
+    }
+
+    /// Calls `on_progress` with the attempts, the elapsed time and the speed of every thread about
+    /// once per second while searching. It runs on a separate monitor thread, not on the search threads.
+    pub fn on_progress<F: Fn(SearchProgress) + Send + Sync + 'static>(
+        mut self,
+        on_progress: F,
+    ) -> Self {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    /// Counts the attempts of every search thread on `stats`, so the speed of the search
+    /// can be read with `SearchStats::snapshot` from another thread while searching.
+    pub fn stats(mut self, stats: SearchStats) -> Self {
+        self.options.stats = Some(stats);
+        self
+    }
+
+    /// Replaces all the search options with the given ones, ex: the ones a CLI built from its flags.
+    pub fn options(mut self, options: SearchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Returns the search the string resolves to with the options of the builder, without
+    /// searching. See `VanityAddr::plan`.
+    /// Returns Err if the string is invalid, for the same reasons as `generate_with`.
+    pub fn plan(&self) -> Result<ResolvedSearch, BtcVanityError> {
+        VanityAddr::plan(
+            &self.string,
+            self.options.vanity_mode,
+            self.options.case_sensitive,
+            self.options
+                .max_pattern_len
+                .unwrap_or(BITCOIN.max_pattern_len(true)),
+            self.keep_leading_one,
+        )
+    }
+
+    /// Searches for a single vanity address, the count is ignored.
+    /// Returns Err for the same reasons as `generate_with`.
+    pub fn generate(self) -> Result<KeysAndAddress, BtcVanityError> {
+        Ok(self.generate_result()?.keys_and_address)
+    }
+
+    /// Same as `generate` but also returns the attempts, the duration, the thread
+    /// and the matched range of the search.
+    pub fn generate_result(self) -> Result<SearchResult, BtcVanityError> {
+        let mut sink = VecSink::new();
+        self.count(1).generate_into(&mut sink)?;
+
+        Ok(sink
+            .into_results()
+            .pop()
+            .expect("generate_into returns Ok only after a vanity address is found"))
+    }
+
+    /// Searches for `count` vanity addresses and returns them in the order they were found.
+    /// Returns Err for the same reasons as `generate_with`.
+    pub fn generate_n(self) -> Result<Vec<KeysAndAddress>, BtcVanityError> {
+        let mut sink = VecSink::new();
+        self.generate_into(&mut sink)?;
+
+        Ok(sink
+            .into_results()
+            .into_iter()
+            .map(|result| result.keys_and_address)
+            .collect())
+    }
+
+    /// Searches for `count` vanity addresses and streams every found one with its search statistics
+    /// to `on_found` right after it is found.
+    /// Returns Err if count or threads is 0.
+    /// Returns Err if the string is empty and the builder isn't created with `random`.
+    /// Returns Err if the string is longer than 4 chars while fast mode is enabled.
+    /// Returns Err if the string is not in base58 format.
+    /// Returns Err if unique matches are enabled and the string has less distinct matches than count.
+    /// Returns Err if the timeout passes, the attempt limit is reached or the search is cancelled
+    /// before all the vanity addresses are found. The ones found until then are already passed to `on_found`.
+    /// Returns Err with the panic message if a search thread panics and the search fails.
+    pub fn generate_with<F: FnMut(SearchResult)>(
+        self,
+        mut on_found: F,
+    ) -> Result<(), BtcVanityError> {
+        self.generate_into(&mut CallbackSink(|result| {
+            on_found(result);
+            ControlFlow::Continue(())
+        }))
+    }
+
+    /// Same as `generate_with` but passes the found vanity addresses to the sink, which can stop
+    /// the search before `count` of them are found by returning Break. See the `sink` module for
+    /// the built-in sinks.
+    /// Returns Err for the same reasons as `generate_with`.
+    pub fn generate_into<S: ResultSink>(mut self, sink: &mut S) -> Result<(), BtcVanityError> {
+        let secp256k1 = Secp256k1::new();
+        let key_source =
+            key_source_factory(self.seed, self.key_source.take(), self.extra_entropy.take());
+
+        if self.options.count == 0 {
+            return Err(BtcVanityError::InvalidCount);
+        }
+        if self
+            .options
+            .max_rate
+            .is_some_and(|max_rate| !max_rate.is_finite() || max_rate <= 0.0)
+        {
+            return Err(BtcVanityError::InvalidMaxRate);
+        }
+
+        if self.random {
+            let start = Instant::now();
+            let thread_attempts = self
+                .options
+                .stats
+                .as_ref()
+                .map(|stats| stats.start(1, start));
+            let mut key_source = key_source(0);
+            for attempts in 1..=self.options.count {
+                if let Some(thread_attempts) = &thread_attempts {
+                    thread_attempts[0].0.store(attempts, Ordering::Relaxed);
+                }
+                let flow = sink.accept(SearchResult {
+                    keys_and_address: KeysAndAddress::generate_random_with(
+                        &secp256k1,
+                        &mut key_source,
+                    ),
+                    attempts,
+                    duration: start.elapsed(),
+                    match_range: 0..0,
+                    thread_id: 0,
+                });
+                if flow.is_break() {
+                    break;
+                }
+            }
+            if let Some(stats) = &self.options.stats {
+                stats.finish();
+            }
+            return Ok(());
+        }
+
+        let resolved = self.plan()?;
+        if resolved.pattern != resolved.input {
+            warn!(
+                "the leading '1' of '{}' is implicit, searching for '{}'",
+                resolved.input, resolved.pattern
+            );
+        }
+        if let Some(rarity) = resolved.rarity {
+            warn!(
+                "few addresses start with '1{}', it is about {:.0}x harder than the estimate",
+                resolved.pattern, rarity
+            );
+        }
+
+        SearchEngines::find_vanity_addresses(
+            &resolved.pattern,
+            self.options,
+            key_source,
+            self.on_progress,
+            secp256k1,
+            |result| sink.accept(result),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys_and_address::{seeded_rng, RngKeySource};
+    use crate::vanity_addr_generator::CHECK_INTERVAL;
+    use std::thread;
+
+    #[test]
+    fn test_builder_generate() {
+        let vanity_address = VanityAddrBuilder::new("e")
+            .threads(4)
+            .case_insensitive()
+            .mode(VanityMode::Anywhere)
+            .generate()
+            .unwrap();
+
+        assert!(vanity_address
+            .get_comp_address()
+            .to_lowercase()
+            .contains('e'));
+    }
+
+    #[test]
+    fn test_builder_exclude_addresses() {
+        // A single letter is matched within a few candidates, so the exclusion list is hit.
+        let search = || {
+            VanityAddrBuilder::new("a")
+                .threads(1)
+                .seed(7)
+                .case_insensitive()
+        };
+        let first = search().generate().unwrap().get_comp_address().clone();
+
+        let excluded = HashSet::<Box<[u8]>>::from([first.as_bytes().into()]);
+        let vanity_address = search().exclude_addresses(excluded).generate().unwrap();
+        assert_ne!(vanity_address.get_comp_address(), &first);
+        assert!(vanity_address.get_comp_address()[1..]
+            .to_lowercase()
+            .starts_with('a'));
+    }
+
+    #[test]
+    fn test_builder_generate_result() {
+        let result = VanityAddrBuilder::new("e")
+            .threads(4)
+            .case_insensitive()
+            .mode(VanityMode::Anywhere)
+            .generate_result()
+            .unwrap();
+        let address = result.keys_and_address.get_comp_address();
+
+        assert!(result.attempts > 0);
+        assert!(result.thread_id < 4);
+        assert_eq!(address[result.match_range].to_lowercase(), "e");
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_builder_rayon_backend() {
+        let results = std::sync::Mutex::new(Vec::new());
+        VanityAddrBuilder::new("e")
+            .threads(4)
+            .backend(Backend::Rayon)
+            .case_insensitive()
+            .count(3)
+            .generate_with(|result| results.lock().unwrap().push(result))
+            .unwrap();
+
+        let results = results.into_inner().unwrap();
+        assert_eq!(results.len(), 3);
+        for result in results {
+            assert!(result.thread_id < 4);
+            assert!(result.keys_and_address.get_comp_address()[1..]
+                .to_lowercase()
+                .starts_with('e'));
+        }
+    }
+
+    #[test]
+    fn test_builder_generate_n() {
+        let vanity_addresses = VanityAddrBuilder::new("e")
+            .threads(4)
+            .case_insensitive()
+            .count(3)
+            .generate_n()
+            .unwrap();
+
+        assert_eq!(vanity_addresses.len(), 3);
+        for vanity_address in vanity_addresses {
+            assert!(vanity_address.get_comp_address()[1..]
+                .to_lowercase()
+                .starts_with('e'));
+        }
+    }
+
+    #[test]
+    fn test_builder_random() {
+        assert!(matches!(
+            VanityAddrBuilder::new("").generate(),
+            Err(BtcVanityError::EmptyPattern)
+        ));
+
+        let key_pairs = VanityAddrBuilder::random().count(3).generate_n().unwrap();
+        assert_eq!(key_pairs.len(), 3);
+        assert_ne!(
+            key_pairs[0].get_comp_address(),
+            key_pairs[1].get_comp_address()
+        );
+    }
+
+    #[test]
+    fn test_builder_keep_leading_one() {
+        let vanity_address = VanityAddrBuilder::new("1e")
+            .threads(4)
+            .case_insensitive()
+            .generate()
+            .unwrap();
+        assert!(vanity_address.get_comp_address()[1..]
+            .to_lowercase()
+            .starts_with('e'));
+
+        let result = VanityAddrBuilder::new("1zz")
+            .threads(1)
+            .keep_leading_one(true)
+            .max_attempts(10)
+            .generate();
+        assert!(matches!(
+            result,
+            Err(BtcVanityError::AttemptLimitReached { .. })
+        ));
+    }
+
+    #[test]
+    fn test_builder_cancellation_token() {
+        let token = CancellationToken::new();
+        let canceller = {
+            let token = token.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(100));
+                token.cancel();
+            })
+        };
+
+        let start = Instant::now();
+        let result = VanityAddrBuilder::new("zzzzzzzzzzzzzzzzzzzz")
+            .threads(4)
+            .fast_mode(false)
+            .cancellation_token(token)
+            .generate();
+        canceller.join().unwrap();
+
+        assert!(matches!(result, Err(BtcVanityError::Cancelled { .. })));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_builder_on_progress() {
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let result = {
+            let reports = Arc::clone(&reports);
+            VanityAddrBuilder::new("zzzzzzzzzzzzzzzzzzzz")
+                .threads(2)
+                .fast_mode(false)
+                .timeout(Duration::from_millis(2500))
+                .on_progress(move |progress| reports.lock().unwrap().push(progress))
+                .generate()
+        };
+
+        assert!(matches!(result, Err(BtcVanityError::TimedOut { .. })));
+        let reports = reports.lock().unwrap();
+        // About once per second, so 2 reports unless the machine is too busy.
+        assert!((1..=2).contains(&reports.len()));
+        assert!(reports[0].attempts > 0);
+        assert!(reports[0].elapsed >= Duration::from_secs(1));
+        assert_eq!(reports[0].thread_rates.len(), 2);
+        assert!(reports[0].keys_per_second() > 0.0);
+    }
+
+    #[test]
+    fn test_builder_key_source() {
+        let generate = |entropy: &[u8]| {
+            VanityAddrBuilder::new("e")
+                .threads(1)
+                .case_insensitive()
+                .key_source(|thread_index| {
+                    Box::new(RngKeySource(seeded_rng(42, thread_index))) as Box<dyn KeySource>
+                })
+                .extra_entropy(entropy)
+                .generate()
+                .unwrap()
+                .get_comp_address()
+                .to_string()
+        };
+
+        assert_eq!(generate(b"dice"), generate(b"dice"));
+        assert_ne!(generate(b"dice"), generate(b"coin"));
+    }
+
+    #[test]
+    fn test_builder_options() {
+        let vanity_addresses = VanityAddrBuilder::new("e")
+            .options(SearchOptions {
+                vanity_mode: VanityMode::Suffix,
+                case_sensitive: false,
+                count: 2,
+                threads: 2,
+                ..SearchOptions::default()
+            })
+            .generate_n()
+            .unwrap();
+
+        assert_eq!(vanity_addresses.len(), 2);
+        for vanity_address in vanity_addresses {
+            assert!(vanity_address
+                .get_comp_address()
+                .to_lowercase()
+                .ends_with('e'));
+        }
+    }
+
+    #[test]
+    fn test_builder_max_attempts() {
+        let result = VanityAddrBuilder::new("zzzz")
+            .threads(2)
+            .max_attempts(100)
+            .generate();
+
+        assert!(matches!(
+            result,
+            Err(BtcVanityError::AttemptLimitReached { .. })
+        ));
+    }
+
+    #[test]
+    fn test_builder_max_rate() {
+        let start = Instant::now();
+        let result = VanityAddrBuilder::new("zzzzzzzzzzzzzzzzzzzz")
+            .fast_mode(false)
+            .threads(2)
+            .max_rate(4000.0)
+            .timeout(Duration::from_secs(1))
+            .generate();
+        let elapsed = start.elapsed();
+
+        // Only the upper bound is checked, a slow machine may stay under the rate. Every thread
+        // may go over its share by a chunk, and the search may stop a bit after the timeout.
+        let Err(BtcVanityError::TimedOut { attempts }) = result else {
+            panic!("the search didn't time out");
+        };
+        let max_attempts = 4000.0 * elapsed.as_secs_f64() + (2 * CHECK_INTERVAL) as f64;
+        assert!(
+            attempts as f64 <= max_attempts,
+            "{} attempts in {:?}",
+            attempts,
+            elapsed
+        );
+
+        assert!(matches!(
+            VanityAddrBuilder::new("a").max_rate(0.0).generate(),
+            Err(BtcVanityError::InvalidMaxRate)
+        ));
+    }
+}
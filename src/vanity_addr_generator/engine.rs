@@ -0,0 +1,343 @@
+//! # Vanity Engine Module
+//!
+//! This module provides a pool of search threads which is reused between searches.
+//! `VanityAddr::generate` spawns new threads for every call, which adds up when vanity addresses
+//! are generated in a loop, so the engine is the preferred way for repeated searches.
+//!
+//! # Example Usage
+//!
+//! ```rust
+//! use btc_vanity::vanity_addr_generator::{VanityEngine, VanityMode};
+//!
+//! let mut engine = VanityEngine::new(4).unwrap();
+//! for string in ["a", "b", "c"] {
+//!     let vanity_address = engine
+//!         .generate(string, false, true, VanityMode::Prefix)
+//!         .unwrap();
+//!     println!("address (compressed): {}", vanity_address.get_comp_address());
+//! }
+//! ```
+
+use crate::chain::BITCOIN;
+use crate::error::BtcVanityError;
+use crate::keys_and_address::{IncrementalKeys, KeysAndAddress};
+use crate::vanity_addr_generator::{
+    default_key_source, CachePadded, SearchEngines, SearchStats, VanityAddr, VanityMode,
+    CHECK_INTERVAL,
+};
+use crate::verify::self_check;
+
+use bitcoin::secp256k1::{All, Secp256k1};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+/// A search submitted to the pool.
+struct Job {
+    /// Increased for every job, so results of the previous jobs can be told apart.
+    id: u64,
+    string: String,
+    case_sensitive: bool,
+    vanity_mode: VanityMode,
+    /// Set after enough vanity addresses are found, so the threads go back to waiting.
+    stop: AtomicBool,
+    /// Attempt counter of every thread for the statistics of the engine.
+    thread_attempts: Arc<Vec<CachePadded<AtomicU64>>>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    job: Option<Arc<Job>>,
+    shutdown: bool,
+}
+
+/// State shared between the engine and its threads.
+#[derive(Default)]
+struct Pool {
+    state: Mutex<PoolState>,
+    job_submitted: Condvar,
+}
+
+/// A pool of search threads parked between searches.
+/// Dropping it stops and joins all the threads.
+pub struct VanityEngine {
+    pool: Arc<Pool>,
+    receiver: Receiver<(u64, KeysAndAddress)>,
+    workers: Vec<JoinHandle<()>>,
+    next_job_id: u64,
+    stats: SearchStats,
+    /// Used for the self-check of the found key pairs.
+    secp256k1: Secp256k1<All>,
+}
+
+impl VanityEngine {
+    /// Starts the given number of search threads.
+    /// Returns Err if threads is 0.
+    pub fn new(threads: u64) -> Result<Self, BtcVanityError> {
+        if threads == 0 {
+            return Err(BtcVanityError::InvalidThreadCount);
+        }
+
+        let pool = Arc::new(Pool::default());
+        let (sender, receiver) = mpsc::channel();
+        let workers = (0..threads)
+            .map(|thread_index| {
+                let pool = Arc::clone(&pool);
+                let sender = sender.clone();
+                thread::spawn(move || Self::work(&pool, &sender, thread_index))
+            })
+            .collect();
+
+        Ok(VanityEngine {
+            pool,
+            receiver,
+            workers,
+            next_job_id: 0,
+            stats: SearchStats::new(),
+            secp256k1: Secp256k1::new(),
+        })
+    }
+
+    /// Returns the statistics of the engine's searches. Every search resets them,
+    /// so a snapshot is the statistics of the running or the last search.
+    pub fn stats(&self) -> SearchStats {
+        self.stats.clone()
+    }
+
+    /// Loop of a search thread. Waits for a new job, searches it until it is stopped and
+    /// waits for the next one until the engine is dropped.
+    fn work(pool: &Pool, sender: &Sender<(u64, KeysAndAddress)>, thread_index: u64) {
+        let secp256k1 = Secp256k1::new();
+        let mut keys = IncrementalKeys::new(&secp256k1, default_key_source(None, thread_index));
+        let mut last_job_id = None;
+        let mut candidate = None;
+
+        loop {
+            let job = {
+                let mut state = pool.state.lock().unwrap_or_else(|err| err.into_inner());
+                loop {
+                    if state.shutdown {
+                        return;
+                    }
+                    match &state.job {
+                        Some(job) if last_job_id != Some(job.id) => break Arc::clone(job),
+                        _ => {
+                            state = pool
+                                .job_submitted
+                                .wait(state)
+                                .unwrap_or_else(|err| err.into_inner())
+                        }
+                    }
+                }
+            };
+            last_job_id = Some(job.id);
+
+            // Only this thread writes its counter.
+            let thread_attempts = &job.thread_attempts[thread_index as usize].0;
+            let mut tried = 0;
+            while !job.stop.load(Ordering::Relaxed) {
+                let matched = SearchEngines::is_match(
+                    keys.next_keys_into(&secp256k1, &mut candidate)
+                        .get_comp_address(),
+                    &job.string,
+                    job.case_sensitive,
+                    job.vanity_mode,
+                );
+                tried += 1;
+                if matched || tried % CHECK_INTERVAL == 0 {
+                    thread_attempts.store(tried, Ordering::Relaxed);
+                }
+                if let Some(keys_and_address) = candidate.take_if(|_| matched) {
+                    if sender.send((job.id, keys_and_address)).is_err() {
+                        return;
+                    }
+                    keys.restart(&secp256k1);
+                }
+            }
+        }
+    }
+
+    /// Searches for a single vanity address with the threads of the engine.
+    /// Returns Err for the same reasons as `VanityAddr::generate`.
+    pub fn generate(
+        &mut self,
+        string: &str,
+        case_sensitive: bool,
+        fast_mode: bool,
+        vanity_mode: VanityMode,
+    ) -> Result<KeysAndAddress, BtcVanityError> {
+        let mut found = self.generate_n(string, 1, case_sensitive, fast_mode, vanity_mode)?;
+        Ok(found.remove(0))
+    }
+
+    /// Searches for `count` vanity addresses with the threads of the engine and returns them
+    /// in the order they were found. The string is resolved with `VanityAddr::plan`, so the
+    /// leading '1' of a prefix is dropped.
+    /// Returns Err for the same reasons as `VanityAddr::generate_n`.
+    pub fn generate_n(
+        &mut self,
+        string: &str,
+        count: u64,
+        case_sensitive: bool,
+        fast_mode: bool,
+        vanity_mode: VanityMode,
+    ) -> Result<Vec<KeysAndAddress>, BtcVanityError> {
+        if count == 0 {
+            return Err(BtcVanityError::InvalidCount);
+        }
+        let resolved = VanityAddr::plan(
+            string,
+            vanity_mode,
+            case_sensitive,
+            BITCOIN.max_pattern_len(fast_mode),
+            false,
+        )?;
+
+        self.next_job_id += 1;
+        let job = Arc::new(Job {
+            id: self.next_job_id,
+            string: resolved.pattern.clone(),
+            case_sensitive,
+            vanity_mode,
+            stop: AtomicBool::new(false),
+            thread_attempts: self.stats.start(self.workers.len() as u64, Instant::now()),
+        });
+        {
+            let mut state = self
+                .pool
+                .state
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            state.job = Some(Arc::clone(&job));
+        }
+        self.pool.job_submitted.notify_all();
+
+        let mut found = Vec::new();
+        while (found.len() as u64) < count {
+            match self.receiver.recv() {
+                // Matches of the previous jobs may still be in the channel.
+                Ok((job_id, keys_and_address)) if job_id == job.id => {
+                    if let Err(err) = self_check(
+                        &self.secp256k1,
+                        &keys_and_address,
+                        &resolved.pattern,
+                        vanity_mode,
+                        case_sensitive,
+                    ) {
+                        job.stop.store(true, Ordering::Relaxed);
+                        self.stats.finish();
+                        return Err(err);
+                    }
+                    found.push(keys_and_address);
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    self.stats.finish();
+                    return Err(BtcVanityError::SearchThreadsExited);
+                }
+            }
+        }
+        job.stop.store(true, Ordering::Relaxed);
+        self.stats.finish();
+
+        Ok(found)
+    }
+}
+
+impl Drop for VanityEngine {
+    fn drop(&mut self) {
+        {
+            let mut state = self
+                .pool
+                .state
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            state.shutdown = true;
+            if let Some(job) = &state.job {
+                job.stop.store(true, Ordering::Relaxed);
+            }
+        }
+        self.pool.job_submitted.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_engine_reuses_threads() {
+        let mut engine = VanityEngine::new(4).unwrap();
+
+        for string in ["a", "b", "c", "d"] {
+            let vanity_address = engine
+                .generate(string, false, true, VanityMode::Prefix)
+                .unwrap();
+            assert!(vanity_address.get_comp_address()[1..]
+                .to_lowercase()
+                .starts_with(string));
+        }
+
+        let vanity_addresses = engine
+            .generate_n("e", 3, false, true, VanityMode::Suffix)
+            .unwrap();
+        assert_eq!(vanity_addresses.len(), 3);
+        for vanity_address in vanity_addresses {
+            assert!(vanity_address
+                .get_comp_address()
+                .to_lowercase()
+                .ends_with('e'));
+        }
+    }
+
+    #[test]
+    fn test_engine_leading_one() {
+        let mut engine = VanityEngine::new(2).unwrap();
+
+        let vanity_address = engine
+            .generate("1e", false, true, VanityMode::Prefix)
+            .unwrap();
+        assert!(vanity_address.get_comp_address()[1..]
+            .to_lowercase()
+            .starts_with('e'));
+    }
+
+    #[test]
+    fn test_engine_stats() {
+        let mut engine = VanityEngine::new(2).unwrap();
+        let stats = engine.stats();
+        assert_eq!(stats.snapshot().attempts, 0);
+
+        engine
+            .generate_n("a", 2, false, true, VanityMode::Prefix)
+            .unwrap();
+        let snapshot = stats.snapshot();
+        assert!(!snapshot.running);
+        assert!(snapshot.attempts > 0);
+        assert_eq!(snapshot.thread_attempts.len(), 2);
+        assert!(snapshot.elapsed > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_engine_invalid_input() {
+        assert!(matches!(
+            VanityEngine::new(0),
+            Err(BtcVanityError::InvalidThreadCount)
+        ));
+
+        let mut engine = VanityEngine::new(1).unwrap();
+        assert!(matches!(
+            engine.generate("Em0", false, true, VanityMode::Prefix),
+            Err(BtcVanityError::InvalidCharacter { ch: '0', index: 2 })
+        ));
+        assert!(engine
+            .generate_n("a", 0, false, true, VanityMode::Prefix)
+            .is_err());
+    }
+}
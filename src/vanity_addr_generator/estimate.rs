@@ -0,0 +1,421 @@
+//! # Difficulty Estimation Module
+//!
+//! This module is used to estimate how many key pairs have to be generated on average
+//! to find a vanity address and how long that takes on the current machine.
+//! It is also available as `btc_vanity::estimate`.
+//!
+//! # Example Usage
+//!
+//! ```rust
+//! use btc_vanity::vanity_addr_generator::estimate::{estimate_attempts, format_seconds};
+//! use btc_vanity::vanity_addr_generator::VanityMode;
+//!
+//! let attempts = estimate_attempts("Emiv", VanityMode::Prefix, true);
+//! let keys_per_second = 100_000.0;
+//!
+//! println!("expected attempts: {:.0}, expected time: {}",
+//!             attempts,
+//!             format_seconds(attempts / keys_per_second))
+//! ```
+//!
+//! # Example Usage With The Chain And The Chance Of Finding
+//!
+//! ```rust
+//! use btc_vanity::chain::BITCOIN;
+//! use btc_vanity::estimate::{expected_attempts, probability_within};
+//! use btc_vanity::vanity_addr_generator::VanityMode;
+//!
+//! // The leading '1' of a bitcoin prefix doesn't count.
+//! let attempts = expected_attempts(&BITCOIN, "1Emiv", VanityMode::Prefix, true).unwrap();
+//! assert!((attempts - 58f64.powi(4)).abs() < 1.0);
+//!
+//! // About 63% of the searches end within the expected attempts.
+//! let chance = probability_within(attempts, attempts as u64);
+//! assert!((chance - 0.632).abs() < 0.001);
+//! ```
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::bench::measure_incremental_keys;
+use crate::chain::{ChainInfo, BITCOIN};
+use crate::vanity_addr_generator::VanityMode;
+
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+/// Number of characters in the base58 alphabet.
+const BASE58_LEN: f64 = 58.0;
+
+/// Number of characters a compressed address has after the leading '1' in most cases.
+const ADDRESS_BODY_LEN: usize = BITCOIN.max_length;
+
+/// Returns how many base58 characters match the given character.
+/// Case insensitive matching of a letter matches both of its cases except the letters
+/// which have one of their cases excluded from base58 ('I', 'O' and 'l').
+fn matching_chars(c: char, case_sensitive: bool) -> f64 {
+    if case_sensitive || !c.is_ascii_alphabetic() {
+        return 1.0;
+    }
+
+    match c.to_ascii_lowercase() {
+        'i' | 'o' | 'l' => 1.0,
+        _ => 2.0,
+    }
+}
+
+/// Returns the expected number of key pairs that have to be generated to find an address
+/// which satisfies the given pattern, vanity mode and case sensitivity.
+///
+/// Prefix and suffix patterns take 58^len attempts when case sensitive. For case insensitive
+/// patterns every letter matches both of its cases so it is half as hard per letter.
+/// Anywhere patterns can match at any window of the address so they are easier by
+/// about the number of windows.
+pub fn estimate_attempts(pattern: &str, vanity_mode: VanityMode, case_sensitive: bool) -> f64 {
+    if pattern.is_empty() {
+        return 1.0;
+    }
+
+    // Probability of a single position matching the whole pattern.
+    let probability = pattern
+        .chars()
+        .map(|c| matching_chars(c, case_sensitive) / BASE58_LEN)
+        .product::<f64>();
+
+    attempts_from_probability(probability, pattern.len(), vanity_mode, ADDRESS_BODY_LEN)
+}
+
+/// Returns the expected number of key pairs that have to be generated to find an address of the
+/// chain which satisfies the pattern, like the builder searches it by default.
+///
+/// - The fixed prefix of the chain doesn't count, so "1Emiv" is as hard as "Emiv" for bitcoin.
+/// - Every character is as hard as the number of alphabet characters it matches. Case insensitive
+///   letters match both of their cases, except the letters with one of their cases missing from
+///   the alphabet, like 'I', 'O' and 'l' for base58.
+/// - Anywhere patterns can match at every window of the longest address body of the chain.
+///
+/// Returns None if the pattern is not estimable because it can never match: it has a character
+/// which isn't in the alphabet of the chain, ex: '0' or a regex like "^E.*v$", or it is longer
+/// than the address of the chain.
+pub fn expected_attempts(
+    chain: &ChainInfo,
+    pattern: &str,
+    vanity_mode: VanityMode,
+    case_sensitive: bool,
+) -> Option<f64> {
+    let pattern = match (vanity_mode, chain.fixed_prefix) {
+        (VanityMode::Prefix, Some(fixed_prefix)) => {
+            pattern.strip_prefix(fixed_prefix).unwrap_or(pattern)
+        }
+        _ => pattern,
+    };
+    if pattern.len() > chain.max_length {
+        return None;
+    }
+
+    let alphabet_len = chain.alphabet.chars().count() as f64;
+    let mut probability = 1.0;
+    for c in pattern.chars() {
+        let matching = chain
+            .alphabet
+            .chars()
+            .filter(|&a| match case_sensitive {
+                true => a == c,
+                false => a.eq_ignore_ascii_case(&c),
+            })
+            .count();
+        if matching == 0 {
+            return None;
+        }
+        probability *= matching as f64 / alphabet_len;
+    }
+
+    Some(attempts_from_probability(
+        probability,
+        pattern.len(),
+        vanity_mode,
+        chain.max_length,
+    ))
+}
+
+/// Returns the expected number of key pairs that have to be generated to find a bitcoin address
+/// ending with the case sensitive suffix.
+///
+/// The last characters of an address are the lowest base58 digits of its 25 byte payload, which
+/// ends with the 4 byte checksum, so they are as random as any base58 string and a suffix of len
+/// characters takes about 58^len attempts, like `estimate_attempts` assumes. Only the payloads
+/// below 2^192 are addresses, so a long suffix with a low value is a little easier than one with
+/// a high value: one more payload ends with it. The difference is below 58^len / 2^192, about 4%
+/// at 32 characters and negligible below 25.
+///
+/// Returns None if the suffix has a character which isn't base58, or no address can end with it.
+pub fn suffix_difficulty(suffix: &str) -> Option<f64> {
+    if suffix.len() > BITCOIN.max_length {
+        return None;
+    }
+    let mut value = BigUint::ZERO;
+    for c in suffix.chars() {
+        value = value * 58u32 + BITCOIN.alphabet.find(c)?;
+    }
+
+    // The version byte of an address is 0, so its payload is below 2^192.
+    let payloads = BigUint::one() << 192u32;
+    if value >= payloads {
+        return None;
+    }
+    let modulus = BigUint::from(58u32).pow(suffix.len() as u32);
+    // Payloads below 2^192 whose lowest base58 digits are the suffix.
+    let matching = (&payloads - 1u32 - &value) / modulus + 1u32;
+
+    Some(payloads.to_f64()? / matching.to_f64()?)
+}
+
+/// Returns how many times harder the pattern is to find case sensitive than case insensitive
+/// on the chain. Every letter with both of its cases in the alphabet makes it about 2 times
+/// harder, so a pattern without letters, or with only letters like 'i' and 'o' for base58,
+/// has a penalty of 1.0. The penalty of a pattern which isn't estimable is 1.0 too.
+pub fn case_sensitivity_penalty(pattern: &str, chain: &ChainInfo) -> f64 {
+    let sensitive = expected_attempts(chain, pattern, VanityMode::Prefix, true);
+    let insensitive = expected_attempts(chain, pattern, VanityMode::Prefix, false);
+    match (sensitive, insensitive) {
+        (Some(sensitive), Some(insensitive)) => sensitive / insensitive,
+        _ => 1.0,
+    }
+}
+
+/// Returns the expected attempts of a pattern which matches a position with the given probability.
+/// Anywhere patterns have a window at every position of the address body of the given length.
+fn attempts_from_probability(
+    probability: f64,
+    pattern_len: usize,
+    vanity_mode: VanityMode,
+    body_len: usize,
+) -> f64 {
+    match vanity_mode {
+        VanityMode::Prefix | VanityMode::Suffix => 1.0 / probability,
+        VanityMode::Anywhere => {
+            let windows = body_len.saturating_sub(pattern_len) + 1;
+            // 1 - (1 - probability)^windows, without losing the tiny probabilities of long patterns.
+            -1.0 / (windows as f64 * (-probability).ln_1p()).exp_m1()
+        }
+    }
+}
+
+/// Returns the probability of finding the address within the given attempts, for a pattern
+/// with the given expected attempts, ex: from `expected_attempts`. Every attempt is an
+/// independent try, so the chance after the expected attempts is only about 63%.
+pub fn probability_within(expected_attempts: f64, attempts: u64) -> f64 {
+    if attempts == 0 {
+        return 0.0;
+    }
+    let probability = 1.0 / expected_attempts;
+    -(attempts as f64 * (-probability).ln_1p()).exp_m1()
+}
+
+/// Generates key pairs on the given number of threads for the given duration and
+/// returns the measured key pairs per second.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn measure_keys_per_second(threads: u64, duration: Duration) -> f64 {
+    measure_incremental_keys(threads, duration)
+}
+
+/// Formats the given seconds in the most readable unit.
+pub fn format_seconds(seconds: f64) -> String {
+    const UNITS: [(f64, &str); 5] = [
+        (60.0 * 60.0 * 24.0 * 365.0, "years"),
+        (60.0 * 60.0 * 24.0, "days"),
+        (60.0 * 60.0, "hours"),
+        (60.0, "minutes"),
+        (1.0, "seconds"),
+    ];
+
+    for (unit_seconds, unit) in UNITS {
+        if seconds >= unit_seconds {
+            return format!("{:.1} {}", seconds / unit_seconds, unit);
+        }
+    }
+    format!("{:.1} seconds", seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(left: f64, right: f64) {
+        assert!(
+            (left - right).abs() <= right * 1e-9,
+            "{} is not close to {}",
+            left,
+            right
+        );
+    }
+
+    #[test]
+    fn test_estimate_prefix_case_sensitive() {
+        assert_close(
+            estimate_attempts("Emiv", VanityMode::Prefix, true),
+            58f64.powi(4),
+        );
+        assert_close(
+            estimate_attempts("12", VanityMode::Suffix, true),
+            58f64.powi(2),
+        );
+    }
+
+    #[test]
+    fn test_estimate_prefix_case_insensitive() {
+        // Digits match only themselves, 'E', 'm' and 'v' match both of their cases but 'i'
+        // only matches itself because 'I' is not in base58.
+        assert_close(
+            estimate_attempts("Emiv", VanityMode::Prefix, false),
+            58f64.powi(4) / 8.0,
+        );
+        assert_close(
+            estimate_attempts("3169", VanityMode::Prefix, false),
+            58f64.powi(4),
+        );
+    }
+
+    #[test]
+    fn test_estimate_anywhere() {
+        let p = 1.0 / 58f64.powi(2);
+        assert_close(
+            estimate_attempts("12", VanityMode::Anywhere, true),
+            1.0 / (1.0 - (1.0 - p).powi(32)),
+        );
+        assert!(
+            estimate_attempts("12", VanityMode::Anywhere, true)
+                < estimate_attempts("12", VanityMode::Prefix, true)
+        );
+    }
+
+    #[test]
+    fn test_expected_attempts() {
+        let attempts = |pattern, vanity_mode, case_sensitive| {
+            expected_attempts(&BITCOIN, pattern, vanity_mode, case_sensitive)
+        };
+
+        // 58^4 = 11316496, the leading '1' of a prefix doesn't count.
+        assert_close(
+            attempts("Emiv", VanityMode::Prefix, true).unwrap(),
+            11_316_496.0,
+        );
+        assert_close(
+            attempts("1Emiv", VanityMode::Prefix, true).unwrap(),
+            11_316_496.0,
+        );
+        // 58^5 = 656356768, the '1' of a suffix counts.
+        assert_close(
+            attempts("1Emiv", VanityMode::Suffix, true).unwrap(),
+            656_356_768.0,
+        );
+        // 'E', 'm' and 'v' match 2 characters each, 'i' only 1: 58^4 / 8 = 1414562.
+        assert_close(
+            attempts("Emiv", VanityMode::Prefix, false).unwrap(),
+            1_414_562.0,
+        );
+        // 'I' only matches 'i', 'L' only 'L' and 'o' only 'o': 58^3 = 195112.
+        assert_close(
+            attempts("ILo", VanityMode::Prefix, false).unwrap(),
+            195_112.0,
+        );
+        // Digits have no other case: 58^2 = 3364.
+        assert_close(attempts("12", VanityMode::Suffix, false).unwrap(), 3364.0);
+        assert_eq!(attempts("", VanityMode::Prefix, true), Some(1.0));
+    }
+
+    #[test]
+    fn test_suffix_difficulty() {
+        assert_close(suffix_difficulty("Emiv").unwrap(), 58f64.powi(4));
+        assert_close(suffix_difficulty("").unwrap(), 1.0);
+        assert_close(
+            suffix_difficulty("Emiv").unwrap(),
+            estimate_attempts("Emiv", VanityMode::Suffix, true),
+        );
+
+        // 2^192 / 58^32 is about 23.3, so the lowest 32 char suffix ends 24 payloads and
+        // the highest one 23.
+        let low = suffix_difficulty(&"1".repeat(32)).unwrap();
+        let high = suffix_difficulty(&"z".repeat(32)).unwrap();
+        assert_close(high / low, 24.0 / 23.0);
+
+        assert_eq!(suffix_difficulty("Em0"), None);
+        assert_eq!(suffix_difficulty(&"z".repeat(33)), None);
+        assert_eq!(suffix_difficulty(&"z".repeat(34)), None);
+        assert!(suffix_difficulty(&"1".repeat(33)).is_some());
+    }
+
+    #[test]
+    fn test_expected_attempts_anywhere() {
+        // "abc" has 33 - 3 + 1 = 31 windows of probability 1 / 58^3 = 1 / 195112.
+        let p = 1.0 / 195_112f64;
+        assert_close(
+            expected_attempts(&BITCOIN, "abc", VanityMode::Anywhere, true).unwrap(),
+            1.0 / (1.0 - (1.0 - p).powi(31)),
+        );
+        // A pattern as long as the address body has a single window.
+        let pattern = "z".repeat(33);
+        assert_close(
+            expected_attempts(&BITCOIN, &pattern, VanityMode::Anywhere, true).unwrap(),
+            58f64.powi(33),
+        );
+    }
+
+    #[test]
+    fn test_expected_attempts_not_estimable() {
+        for pattern in ["0", "Em0", "^E.*v$"] {
+            assert_eq!(
+                expected_attempts(&BITCOIN, pattern, VanityMode::Prefix, false),
+                None
+            );
+        }
+        // 'I' and 'l' aren't in base58, only their other cases.
+        assert_eq!(
+            expected_attempts(&BITCOIN, "I", VanityMode::Prefix, true),
+            None
+        );
+        assert_eq!(
+            expected_attempts(&BITCOIN, "l", VanityMode::Anywhere, true),
+            None
+        );
+        let pattern = "z".repeat(34);
+        assert_eq!(
+            expected_attempts(&BITCOIN, &pattern, VanityMode::Suffix, true),
+            None
+        );
+    }
+
+    #[test]
+    fn test_case_sensitivity_penalty() {
+        // Digits and the letters with one case in base58 match the same chars either way.
+        assert_eq!(case_sensitivity_penalty("1234", &BITCOIN), 1.0);
+        assert_eq!(case_sensitivity_penalty("1io", &BITCOIN), 1.0);
+        assert_close(case_sensitivity_penalty("Emiv", &BITCOIN), 8.0);
+        assert_close(case_sensitivity_penalty("abcdef", &BITCOIN), 64.0);
+        // Not estimable.
+        assert_eq!(case_sensitivity_penalty("Em0", &BITCOIN), 1.0);
+    }
+
+    #[test]
+    fn test_probability_within() {
+        assert_eq!(probability_within(2.0, 0), 0.0);
+        assert_close(probability_within(2.0, 1), 0.5);
+        assert_close(probability_within(2.0, 2), 0.75);
+        assert_close(probability_within(1.0, 1), 1.0);
+        // 1 - (1 - 1/58)^58
+        assert_close(
+            probability_within(58.0, 58),
+            1.0 - (57.0f64 / 58.0).powi(58),
+        );
+        // About 1 - 1/e after the expected attempts of a hard pattern.
+        let attempts = 58f64.powi(6);
+        assert!((probability_within(attempts, attempts as u64) - 0.6321).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_format_seconds() {
+        assert_eq!(format_seconds(0.5), "0.5 seconds");
+        assert_eq!(format_seconds(90.0), "1.5 minutes");
+        assert_eq!(format_seconds(60.0 * 60.0 * 24.0 * 730.0), "2.0 years");
+    }
+}
@@ -0,0 +1,152 @@
+//! # Vanity Address Future Module
+//!
+//! This module runs a search on its own threads and exposes its completion as a future,
+//! so async services can wait for a vanity address without blocking their runtime.
+//! It only uses `std::future`, so it works with any async runtime.
+//!
+//! # Example Usage
+//!
+//! ```rust,ignore
+//! use btc_vanity::vanity_addr_generator::VanityAddrBuilder;
+//!
+//! let vanity_address = VanityAddrBuilder::new("Emiv")
+//!     .case_insensitive()
+//!     .generate_async()
+//!     .await
+//!     .unwrap();
+//! ```
+
+use crate::error::BtcVanityError;
+use crate::keys_and_address::KeysAndAddress;
+use crate::vanity_addr_generator::{CancellationToken, VanityAddrBuilder};
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+/// State shared between the future and the thread running the search.
+#[derive(Default)]
+struct Shared {
+    result: Option<Result<KeysAndAddress, BtcVanityError>>,
+    waker: Option<Waker>,
+}
+
+/// A vanity address search running on its own threads.
+/// Dropping the future before it completes cancels the search, ex: when it loses a `tokio::select!`.
+pub struct GenerateFuture {
+    shared: Arc<Mutex<Shared>>,
+    cancellation_token: CancellationToken,
+}
+
+impl VanityAddrBuilder {
+    /// Same as `generate` but returns a future which completes when the search ends.
+    /// If a cancellation token is set, dropping the future cancels it too.
+    pub fn generate_async(mut self) -> GenerateFuture {
+        let cancellation_token = self.get_or_insert_cancellation_token();
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                let result = self.generate();
+                let mut shared = shared.lock().unwrap_or_else(|err| err.into_inner());
+                shared.result = Some(result);
+                if let Some(waker) = shared.waker.take() {
+                    waker.wake();
+                }
+            });
+        }
+
+        GenerateFuture {
+            shared,
+            cancellation_token,
+        }
+    }
+}
+
+impl Future for GenerateFuture {
+    type Output = Result<KeysAndAddress, BtcVanityError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.lock().unwrap_or_else(|err| err.into_inner());
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for GenerateFuture {
+    fn drop(&mut self) {
+        // Cancelling a finished search does nothing.
+        self.cancellation_token.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vanity_addr_generator::VanityMode;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::task::Wake;
+    use std::thread::Thread;
+    use std::time::{Duration, Instant};
+
+    /// Wakes up the thread blocked on the future.
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Polls the future on the current thread until it completes or the deadline passes.
+    fn block_on<F: Future>(future: F, deadline: Duration) -> Option<F::Output> {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let start = Instant::now();
+
+        while start.elapsed() < deadline {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return Some(output);
+            }
+            thread::park_timeout(deadline.saturating_sub(start.elapsed()));
+        }
+        None
+    }
+
+    #[test]
+    fn test_generate_async() {
+        let future = VanityAddrBuilder::new("e")
+            .threads(4)
+            .case_insensitive()
+            .mode(VanityMode::Anywhere)
+            .generate_async();
+        let vanity_address = block_on(future, Duration::from_secs(30)).unwrap().unwrap();
+
+        assert!(vanity_address
+            .get_comp_address()
+            .to_lowercase()
+            .contains('e'));
+    }
+
+    #[test]
+    fn test_generate_async_drop_cancels_search() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let future = VanityAddrBuilder::new("zzzzzzzzzzzzzzzzzzzz")
+            .threads(2)
+            .fast_mode(false)
+            .cancellation_token(CancellationToken::from(Arc::clone(&cancel)))
+            .generate_async();
+
+        assert!(block_on(future, Duration::from_millis(100)).is_none());
+        assert!(cancel.load(Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,252 @@
+//! # Prefix Range Module
+//!
+//! This module matches prefix strings on the hash160 of a public key, before the address is
+//! base58check encoded. An address is '1' followed by a '1' for every leading zero byte of the
+//! 24 bytes after the version byte, and the base58 digits of those 24 bytes as a number.
+//! So a prefix is a set of ranges of that number, and since the last 4 bytes are the checksum,
+//! the ranges are widened to the hash160 values which can end up in them with any checksum.
+//!
+//! The check can have false positives at the edges of the ranges but never false negatives,
+//! so the candidates which pass it are still matched on their address.
+
+use crate::chain::BASE58_ALPHABET;
+
+use bitcoin::hashes::{hash160, Hash};
+use bitcoin::secp256k1::PublicKey;
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive};
+
+/// Number of bytes after the version byte: the hash160 and the checksum.
+const PAYLOAD_LEN: u32 = 24;
+
+/// Longest base58 number 24 bytes can have.
+const MAX_DIGITS: u32 = 33;
+
+/// Case insensitive strings with more case variants than this are matched on the address only.
+const MAX_VARIANTS: usize = 256;
+
+/// Hash160 ranges of the addresses which can start with a prefix string.
+#[derive(Debug)]
+pub struct PrefixRanges {
+    /// Inclusive hash160 ranges, as big endian numbers.
+    ranges: Vec<([u8; 20], [u8; 20])>,
+}
+
+impl PrefixRanges {
+    /// Returns the ranges of the string, which is matched after the leading '1' of the address.
+    /// Returns None if the string isn't base58 or case insensitive matching has too many variants.
+    pub fn new(string: &str, case_sensitive: bool) -> Option<Self> {
+        let mut ranges = Vec::new();
+        for variant in case_variants(string, case_sensitive)? {
+            for (low, high) in payload_ranges(&variant)? {
+                ranges.push((hash160_floor(&low), hash160_ceil(&high)?));
+            }
+        }
+
+        Some(PrefixRanges { ranges })
+    }
+
+    /// Returns false if the address of the compressed public key can't start with the string.
+    pub fn may_match(&self, public_key: &PublicKey) -> bool {
+        let hash = hash160::Hash::hash(&public_key.serialize());
+        self.may_match_hash160(&hash.to_byte_array())
+    }
+
+    /// Returns true if no address can start with the string.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns the probability of a random hash160 being in the ranges, which is about the
+    /// probability of an address starting with the string.
+    pub fn probability(&self) -> f64 {
+        let hashes: BigUint = self
+            .ranges
+            .iter()
+            .map(|(low, high)| BigUint::from_bytes_be(high) - BigUint::from_bytes_be(low) + 1u32)
+            .sum();
+        hashes.to_f64().unwrap_or(f64::MAX) / 2f64.powi(160)
+    }
+
+    /// Same as `may_match` but for an already computed hash160.
+    pub fn may_match_hash160(&self, hash: &[u8; 20]) -> bool {
+        self.ranges
+            .iter()
+            .any(|(low, high)| low <= hash && hash <= high)
+    }
+}
+
+/// Returns every spelling of the string a case insensitive match accepts, or only the string
+/// if the match is case sensitive. Returns None if there are more than MAX_VARIANTS
+/// or a character has no base58 spelling.
+fn case_variants(string: &str, case_sensitive: bool) -> Option<Vec<String>> {
+    let mut variants = vec![String::new()];
+    for c in string.chars() {
+        let mut options = vec![c];
+        if !case_sensitive {
+            options = [c.to_ascii_lowercase(), c.to_ascii_uppercase()]
+                .into_iter()
+                .filter(|c| BASE58_ALPHABET.contains(*c))
+                .collect();
+            options.dedup();
+        }
+
+        if options.is_empty() || variants.len() * options.len() > MAX_VARIANTS {
+            return None;
+        }
+        variants = variants
+            .iter()
+            .flat_map(|variant| {
+                options.iter().map(move |c| {
+                    let mut variant = variant.clone();
+                    variant.push(*c);
+                    variant
+                })
+            })
+            .collect();
+    }
+
+    Some(variants)
+}
+
+/// Returns the half open ranges of the 24 byte payload numbers whose address starts with
+/// '1' and the string. Returns None if the string isn't base58.
+fn payload_ranges(string: &str) -> Option<Vec<(BigUint, BigUint)>> {
+    // Every leading '1' of the string is a leading zero byte of the payload.
+    let zeros = string.chars().take_while(|&c| c == '1').count() as u32;
+    let digits = &string[zeros as usize..];
+    if zeros > PAYLOAD_LEN {
+        return Some(Vec::new());
+    }
+
+    let zeros_high = BigUint::from(256u32).pow(PAYLOAD_LEN - zeros);
+    if digits.is_empty() {
+        return Some(vec![(BigUint::ZERO, zeros_high)]);
+    }
+    if zeros == PAYLOAD_LEN {
+        return Some(Vec::new());
+    }
+    // The digits must start right after the zero bytes, so the next byte isn't zero.
+    let zeros_low = BigUint::from(256u32).pow(PAYLOAD_LEN - zeros - 1);
+
+    let mut value = BigUint::ZERO;
+    for c in digits.chars() {
+        value = value * 58u32 + BASE58_ALPHABET.find(c)?;
+    }
+
+    let len = digits.len() as u32;
+    let ranges = (len..=MAX_DIGITS)
+        .filter_map(|total_digits| {
+            let scale = BigUint::from(58u32).pow(total_digits - len);
+            let low = (&value * &scale).max(zeros_low.clone());
+            let high = ((&value + 1u32) * &scale).min(zeros_high.clone());
+            (low < high).then_some((low, high))
+        })
+        .collect();
+
+    Some(ranges)
+}
+
+/// Returns the lowest hash160 whose payload can be at least `low` with some checksum.
+fn hash160_floor(low: &BigUint) -> [u8; 20] {
+    to_hash160_bytes(&(low >> 32u32))
+}
+
+/// Returns the highest hash160 whose payload can be below `high` with some checksum.
+/// Returns None if no payload is below `high`.
+fn hash160_ceil(high: &BigUint) -> Option<[u8; 20]> {
+    let checksums = BigUint::one() << 32u32;
+    let ceil = (high + &checksums - 1u32) >> 32u32;
+    (ceil > BigUint::ZERO).then(|| to_hash160_bytes(&(ceil - 1u32)))
+}
+
+fn to_hash160_bytes(value: &BigUint) -> [u8; 20] {
+    let bytes = value.to_bytes_be();
+    let mut hash = [0u8; 20];
+    hash[20 - bytes.len()..].copy_from_slice(&bytes);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys_and_address::seeded_rng;
+    use crate::vanity_addr_generator::{SearchEngines, VanityMode};
+    use bitcoin::secp256k1::rand::RngCore;
+    use bitcoin::{Address, Network, PubkeyHash};
+
+    fn address_of(hash: [u8; 20]) -> String {
+        Address::p2pkh(PubkeyHash::from_byte_array(hash), Network::Bitcoin).to_string()
+    }
+
+    /// Checks the prefix ranges never reject an address the string matcher accepts.
+    fn assert_agrees(string: &str, case_sensitive: bool, hashes: &[[u8; 20]]) {
+        let ranges = PrefixRanges::new(string, case_sensitive).unwrap();
+        for hash in hashes {
+            let address = address_of(*hash);
+            if SearchEngines::is_match(&address, string, case_sensitive, VanityMode::Prefix) {
+                assert!(
+                    ranges.may_match_hash160(hash),
+                    "'{}' matches {} but its hash160 is rejected",
+                    string,
+                    address
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_prefix_ranges_agree_with_string_matching() {
+        let mut rng = seeded_rng(1360, 0);
+        let mut hashes = vec![[0u8; 20]; 200_000];
+        for (i, hash) in hashes.iter_mut().enumerate() {
+            rng.fill_bytes(hash);
+            // Some hashes with leading zero bytes, so their addresses have extra leading '1's.
+            hash[..i % 4].fill(0);
+        }
+
+        for string in ["E", "e", "z", "2", "Ab", "1", "1A", "11", "1z", "zz"] {
+            assert_agrees(string, true, &hashes);
+            assert_agrees(string, false, &hashes);
+        }
+    }
+
+    #[test]
+    fn test_prefix_ranges_reject_most_addresses() {
+        let ranges = PrefixRanges::new("Emiv", true).unwrap();
+        let mut rng = seeded_rng(1360, 1);
+        let mut hash = [0u8; 20];
+        let passed = (0..100_000)
+            .filter(|_| {
+                rng.fill_bytes(&mut hash);
+                ranges.may_match_hash160(&hash)
+            })
+            .count();
+
+        // 58^4 is about 11 million, so almost every random hash160 is rejected.
+        assert!(passed < 10);
+    }
+
+    #[test]
+    fn test_prefix_ranges_boundaries() {
+        let ranges = PrefixRanges::new("1", true).unwrap();
+        assert!(ranges.may_match_hash160(&[0u8; 20]));
+        assert!(!ranges.may_match_hash160(&[0xff; 20]));
+
+        // The second '1' of an address is a zero byte after the version byte.
+        let mut hash = [0xff; 20];
+        hash[0] = 0;
+        assert!(address_of(hash)[1..].starts_with('1'));
+        assert!(ranges.may_match_hash160(&hash));
+        hash[0] = 1;
+        assert!(!address_of(hash)[1..].starts_with('1'));
+        assert!(!ranges.may_match_hash160(&hash));
+    }
+
+    #[test]
+    fn test_prefix_ranges_invalid_input() {
+        assert!(PrefixRanges::new("Em0", true).is_none());
+        assert!(PrefixRanges::new("Em0", false).is_none());
+        assert!(PrefixRanges::new("abcdefghijk", false).is_none());
+    }
+}
@@ -0,0 +1,260 @@
+//! # Result Sink Module
+//!
+//! This module provides the sinks `VanityAddrBuilder::generate_into` passes the found vanity
+//! addresses to. A sink decides what is done with every result and can stop the search early,
+//! so the same search can collect its results, send them to another thread or write them to a file.
+//!
+//! # Example Usage
+//!
+//! ```rust
+//! use btc_vanity::vanity_addr_generator::sink::VecSink;
+//! use btc_vanity::vanity_addr_generator::VanityAddrBuilder;
+//!
+//! // Stops after 3 addresses, before the count is reached.
+//! let mut sink = VecSink::with_limit(3);
+//! VanityAddrBuilder::new("e")
+//!     .case_insensitive()
+//!     .count(10)
+//!     .generate_into(&mut sink)
+//!     .unwrap();
+//!
+//! for result in sink.into_results() {
+//!     println!("address (compressed): {}", result.keys_and_address.get_comp_address());
+//! }
+//! ```
+
+#[cfg(feature = "serde")]
+use crate::output::WalletOutput;
+use crate::vanity_addr_generator::SearchResult;
+#[cfg(feature = "serde")]
+use crate::vanity_addr_generator::VanityMode;
+
+#[cfg(feature = "serde")]
+use std::fs::File;
+#[cfg(feature = "serde")]
+use std::io::{self, BufWriter, Write};
+use std::ops::ControlFlow;
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::sync::mpsc::Sender;
+
+/// Takes the vanity addresses found by `VanityAddrBuilder::generate_into`.
+pub trait ResultSink {
+    /// Takes a found vanity address right after it is found. Returning Break stops the search,
+    /// this result is the last one.
+    fn accept(&mut self, result: SearchResult) -> ControlFlow<()>;
+}
+
+/// Passes every result to the closure, which returns Break to stop the search.
+pub struct CallbackSink<F>(pub F);
+
+impl<F: FnMut(SearchResult) -> ControlFlow<()>> ResultSink for CallbackSink<F> {
+    fn accept(&mut self, result: SearchResult) -> ControlFlow<()> {
+        (self.0)(result)
+    }
+}
+
+/// Collects the results in the order they were found. With a limit the search stops after
+/// that many results.
+#[derive(Default)]
+pub struct VecSink {
+    results: Vec<SearchResult>,
+    limit: Option<usize>,
+}
+
+impl VecSink {
+    /// Collects every result until the search ends.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collects the first `limit` results and stops the search.
+    pub fn with_limit(limit: usize) -> Self {
+        VecSink {
+            results: Vec::new(),
+            limit: Some(limit),
+        }
+    }
+
+    pub fn results(&self) -> &[SearchResult] {
+        &self.results
+    }
+
+    pub fn into_results(self) -> Vec<SearchResult> {
+        self.results
+    }
+
+    fn is_full(&self) -> bool {
+        self.limit.is_some_and(|limit| self.results.len() >= limit)
+    }
+}
+
+impl ResultSink for VecSink {
+    fn accept(&mut self, result: SearchResult) -> ControlFlow<()> {
+        if !self.is_full() {
+            self.results.push(result);
+        }
+        match self.is_full() {
+            true => ControlFlow::Break(()),
+            false => ControlFlow::Continue(()),
+        }
+    }
+}
+
+/// Sends every result to the channel. The search stops when the receiver is dropped.
+pub struct ChannelSink(pub Sender<SearchResult>);
+
+impl ResultSink for ChannelSink {
+    fn accept(&mut self, result: SearchResult) -> ControlFlow<()> {
+        match self.0.send(result) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(_) => ControlFlow::Break(()),
+        }
+    }
+}
+
+/// Writes every result as a line of JSON, the same as `--format json`. The search stops at the
+/// first write error, which is returned by `finish`. Needs the `serde` feature.
+#[cfg(feature = "serde")]
+pub struct FileSink<W: Write> {
+    writer: W,
+    pattern: String,
+    vanity_mode: VanityMode,
+    error: Option<io::Error>,
+}
+
+#[cfg(feature = "serde")]
+impl FileSink<BufWriter<File>> {
+    /// Creates the file, or truncates it if it exists, and writes the results to it.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        pattern: &str,
+        vanity_mode: VanityMode,
+    ) -> io::Result<Self> {
+        Ok(Self::new(
+            BufWriter::new(File::create(path)?),
+            pattern,
+            vanity_mode,
+        ))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<W: Write> FileSink<W> {
+    /// Writes the results to the writer. The pattern and the vanity mode are the ones of
+    /// the search, they are written to every line.
+    pub fn new(writer: W, pattern: &str, vanity_mode: VanityMode) -> Self {
+        FileSink {
+            writer,
+            pattern: pattern.to_string(),
+            vanity_mode,
+            error: None,
+        }
+    }
+
+    /// Flushes the writer and returns it.
+    /// Returns Err if a result or the flush couldn't be written.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<W: Write> ResultSink for FileSink<W> {
+    fn accept(&mut self, result: SearchResult) -> ControlFlow<()> {
+        let line = WalletOutput::new(&self.pattern, self.vanity_mode, &result).to_json();
+        match writeln!(self.writer, "{}", line) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(err) => {
+                self.error = Some(err);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vanity_addr_generator::{VanityAddrBuilder, VanityMode};
+    use std::sync::mpsc;
+
+    fn builder() -> VanityAddrBuilder {
+        VanityAddrBuilder::new("e")
+            .case_insensitive()
+            .mode(VanityMode::Anywhere)
+            .threads(2)
+            .count(10)
+    }
+
+    #[test]
+    fn test_vec_sink_stops_search() {
+        let mut sink = VecSink::with_limit(3);
+        builder().generate_into(&mut sink).unwrap();
+
+        let results = sink.into_results();
+        assert_eq!(results.len(), 3);
+        for result in results {
+            assert!(result
+                .keys_and_address
+                .get_comp_address()
+                .to_lowercase()
+                .contains('e'));
+        }
+
+        let mut sink = VecSink::new();
+        builder().count(4).generate_into(&mut sink).unwrap();
+        assert_eq!(sink.results().len(), 4);
+    }
+
+    #[test]
+    fn test_callback_sink_stops_search() {
+        let mut accepted = 0;
+        builder()
+            .generate_into(&mut CallbackSink(|_| {
+                accepted += 1;
+                match accepted {
+                    3 => ControlFlow::Break(()),
+                    _ => ControlFlow::Continue(()),
+                }
+            }))
+            .unwrap();
+
+        assert_eq!(accepted, 3);
+    }
+
+    #[test]
+    fn test_channel_sink() {
+        let (sender, receiver) = mpsc::channel();
+        builder()
+            .count(3)
+            .generate_into(&mut ChannelSink(sender))
+            .unwrap();
+
+        assert_eq!(receiver.iter().count(), 3);
+
+        // The search stops when nobody receives the results.
+        let (sender, receiver) = mpsc::channel();
+        drop(receiver);
+        let mut sink = ChannelSink(sender);
+        builder().generate_into(&mut sink).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_file_sink_writes_json_lines() {
+        let mut sink = FileSink::new(Vec::new(), "e", VanityMode::Anywhere);
+        builder().count(3).generate_into(&mut sink).unwrap();
+
+        let data = String::from_utf8(sink.finish().unwrap()).unwrap();
+        assert_eq!(data.lines().count(), 3);
+        for line in data.lines() {
+            let json: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(json["pattern"], "e");
+        }
+    }
+}
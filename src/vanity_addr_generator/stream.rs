@@ -0,0 +1,313 @@
+//! # Vanity Address Stream Module
+//!
+//! This module provides an iterator which keeps finding vanity addresses until it is dropped,
+//! so the caller decides how many to take and can filter them with its own predicates.
+//!
+//! # Example Usage
+//!
+//! ```rust
+//! use btc_vanity::vanity_addr_generator::{VanityAddr, VanityMode};
+//!
+//! let addresses: Vec<_> = VanityAddr::stream("e", 4, false, VanityMode::Anywhere)
+//!     .unwrap()
+//!     .filter(|keys_and_address| keys_and_address.get_comp_address().ends_with('a'))
+//!     .take(2)
+//!     .collect();
+//!
+//! for keys_and_address in addresses {
+//!     println!("address (compressed): {}", keys_and_address.get_comp_address());
+//! }
+//! ```
+
+use crate::chain::BITCOIN;
+use crate::error::BtcVanityError;
+use crate::keys_and_address::{IncrementalKeys, KeysAndAddress};
+use crate::vanity_addr_generator::{
+    default_key_source, unique_match_count, SearchEngines, SeenMatches, VanityAddr, VanityMode,
+};
+use crate::verify::self_check;
+
+use bitcoin::secp256k1::{All, Secp256k1};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Number of found addresses each thread can send before it waits for the iterator to take them.
+const BUFFERED_PER_THREAD: usize = 4;
+
+/// Iterator over the vanity addresses found by the search threads, in the order they are found.
+/// Every address is checked with `verify::self_check` before it is yielded, and the stream ends
+/// at the first one which fails it, see `error`.
+/// Dropping it stops and joins all the search threads.
+pub struct VanityStream {
+    receiver: Option<Receiver<KeysAndAddress>>,
+    stop: Arc<AtomicBool>,
+    workers: Vec<JoinHandle<()>>,
+    string: Arc<str>,
+    case_sensitive: bool,
+    vanity_mode: VanityMode,
+    /// Set by `unique_matches`.
+    seen_matches: Option<SeenMatches>,
+    secp256k1: Secp256k1<All>,
+    /// The self check failure which ended the stream.
+    error: Option<BtcVanityError>,
+}
+
+impl VanityAddr {
+    /// Starts searching the string with the given threads and returns an endless iterator of
+    /// the found vanity addresses. The string isn't limited by fast mode, and it is resolved with
+    /// `plan`, so the leading '1' of a prefix is dropped.
+    /// Returns Err if threads is 0, the string is not in base58 format or no address can match it.
+    pub fn stream(
+        string: &str,
+        threads: u64,
+        case_sensitive: bool,
+        vanity_mode: VanityMode,
+    ) -> Result<VanityStream, BtcVanityError> {
+        let resolved = Self::plan(
+            string,
+            vanity_mode,
+            case_sensitive,
+            BITCOIN.max_pattern_len(false),
+            false,
+        )?;
+        if threads == 0 {
+            return Err(BtcVanityError::InvalidThreadCount);
+        }
+
+        let (sender, receiver) = mpsc::sync_channel(threads as usize * BUFFERED_PER_THREAD);
+        let stop = Arc::new(AtomicBool::new(false));
+        let secp256k1 = Secp256k1::new();
+        let string: Arc<str> = Arc::from(resolved.pattern);
+
+        let workers = (0..threads)
+            .map(|thread_index| {
+                let sender = sender.clone();
+                let string = Arc::clone(&string);
+                let secp256k1 = secp256k1.clone();
+                let stop = Arc::clone(&stop);
+
+                thread::spawn(move || {
+                    let mut keys =
+                        IncrementalKeys::new(&secp256k1, default_key_source(None, thread_index));
+                    let mut candidate = None;
+                    while !stop.load(Ordering::Relaxed) {
+                        let matched = SearchEngines::is_match(
+                            keys.next_keys_into(&secp256k1, &mut candidate)
+                                .get_comp_address(),
+                            &string,
+                            case_sensitive,
+                            vanity_mode,
+                        );
+
+                        if let Some(keys_and_address) = candidate.take_if(|_| matched) {
+                            // Sending fails after the stream is dropped, so the thread just returns.
+                            if sender.send(keys_and_address).is_err() {
+                                return;
+                            }
+                            keys.restart(&secp256k1);
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        Ok(VanityStream {
+            receiver: Some(receiver),
+            stop,
+            workers,
+            string,
+            case_sensitive,
+            vanity_mode,
+            seen_matches: None,
+            secp256k1,
+            error: None,
+        })
+    }
+}
+
+impl VanityStream {
+    /// Skips the addresses whose matched substring is already found, so every address has
+    /// a different match, ex: the distinct capitalizations of a case insensitive string.
+    /// The stream ends after every distinct match is found. Only the last 65,536 matches are
+    /// remembered, so the memory it takes is bounded; after that an older match can be found again.
+    pub fn unique_matches(mut self) -> Self {
+        self.seen_matches = Some(SeenMatches::new());
+        self
+    }
+
+    /// Returns the SelfCheckFailed error if the stream ended because a found key pair failed
+    /// `verify::self_check`, None if it is still running or ended normally.
+    pub fn error(&self) -> Option<&BtcVanityError> {
+        self.error.as_ref()
+    }
+}
+
+impl Iterator for VanityStream {
+    type Item = KeysAndAddress;
+
+    /// Waits until the next vanity address is found.
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(seen_matches) = &self.seen_matches {
+            if seen_matches.has_all(unique_match_count(&self.string, self.case_sensitive)) {
+                return None;
+            }
+        }
+
+        loop {
+            let keys_and_address = self.receiver.as_ref()?.recv().ok()?;
+            if let Err(err) = self_check(
+                &self.secp256k1,
+                &keys_and_address,
+                &self.string,
+                self.vanity_mode,
+                self.case_sensitive,
+            ) {
+                self.stop.store(true, Ordering::Relaxed);
+                self.receiver.take();
+                self.error = Some(err);
+                return None;
+            }
+            let Some(seen_matches) = &mut self.seen_matches else {
+                return Some(keys_and_address);
+            };
+            let address = keys_and_address.get_comp_address();
+            let match_range = SearchEngines::find_match(
+                address,
+                &self.string,
+                self.case_sensitive,
+                self.vanity_mode,
+            )
+            .unwrap_or(0..0);
+            if seen_matches.insert(&address[match_range]) {
+                return Some(keys_and_address);
+            }
+        }
+    }
+}
+
+impl Drop for VanityStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Dropping the receiver wakes up the threads waiting for space in the channel.
+        self.receiver.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_stream_yields_matches() {
+        let addresses: Vec<_> = VanityAddr::stream("e", 4, false, VanityMode::Prefix)
+            .unwrap()
+            .take(5)
+            .collect();
+
+        assert_eq!(addresses.len(), 5);
+        for keys_and_address in addresses {
+            assert!(keys_and_address.get_comp_address()[1..]
+                .to_lowercase()
+                .starts_with('e'));
+        }
+    }
+
+    #[test]
+    fn test_stream_leading_one() {
+        let addresses: Vec<_> = VanityAddr::stream("1e", 2, false, VanityMode::Prefix)
+            .unwrap()
+            .take(2)
+            .collect();
+
+        for keys_and_address in addresses {
+            assert!(keys_and_address.get_comp_address()[1..]
+                .to_lowercase()
+                .starts_with('e'));
+        }
+    }
+
+    #[test]
+    fn test_stream_drop_stops_threads() {
+        let start = Instant::now();
+        for _ in 0..10 {
+            // Every address has its leading '1', so every key pair matches.
+            let mut stream = VanityAddr::stream("1", 2, false, VanityMode::Anywhere).unwrap();
+            // Lets the threads fill the channel and wait for space before it is dropped.
+            assert!(stream.next().is_some());
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let stream = VanityAddr::stream("zzzzzzzzzzzzzzzzzzzz", 2, true, VanityMode::Prefix);
+        drop(stream.unwrap());
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_stream_unique_matches() {
+        // "e" has 2 distinct matches, "e" and "E", and duplicates of them arrive quickly.
+        let addresses: Vec<_> = VanityAddr::stream("e", 2, false, VanityMode::Prefix)
+            .unwrap()
+            .unique_matches()
+            .take(3)
+            .map(|keys_and_address| keys_and_address.get_comp_address()[1..2].to_string())
+            .collect();
+
+        assert_eq!(addresses.len(), 2);
+        assert_ne!(addresses[0], addresses[1]);
+    }
+
+    #[test]
+    fn test_stream_self_check() {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let secp256k1 = Secp256k1::new();
+        let mut stream = VanityStream {
+            receiver: Some(receiver),
+            stop: Arc::new(AtomicBool::new(false)),
+            workers: Vec::new(),
+            string: Arc::from("Emiv"),
+            case_sensitive: true,
+            vanity_mode: VanityMode::Prefix,
+            seen_matches: None,
+            secp256k1: secp256k1.clone(),
+            error: None,
+        };
+        // The private key 1 derives 1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH, not the matching address.
+        let corrupted = KeysAndAddress::from_wif(
+            &secp256k1,
+            "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn",
+        )
+        .unwrap()
+        .with_corrupted_address("1EmivB8uShHdr4Dft4yGRpvDEUbdUDXkxT");
+        sender.send(corrupted).unwrap();
+
+        assert!(stream.error().is_none());
+        assert!(stream.next().is_none());
+        assert!(matches!(
+            stream.error(),
+            Some(BtcVanityError::SelfCheckFailed { address, .. })
+                if address == "1EmivB8uShHdr4Dft4yGRpvDEUbdUDXkxT"
+        ));
+        // The stream stays ended after the failure, the search threads can't send anymore.
+        assert!(stream.next().is_none());
+        assert!(stream.stop.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_stream_invalid_input() {
+        assert!(matches!(
+            VanityAddr::stream("Em0", 1, false, VanityMode::Prefix),
+            Err(BtcVanityError::InvalidCharacter { ch: '0', index: 2 })
+        ));
+        assert!(matches!(
+            VanityAddr::stream("e", 0, false, VanityMode::Prefix),
+            Err(BtcVanityError::InvalidThreadCount)
+        ));
+    }
+}
@@ -0,0 +1,223 @@
+//! # Wallet Verification Module
+//!
+//! This module is used to check a previously generated wallet, so a corrupted output file or a
+//! mistyped key is noticed before any funds are sent to the address. The searches also check every
+//! key pair they find with `self_check` before returning it.
+//!
+//! # Example Usage At Your Code
+//! ```rust
+//! use btc_vanity::verify::verify_wallet;
+//! use btc_vanity::vanity_addr_generator::VanityMode;
+//!
+//! let result = verify_wallet(
+//!     "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn",
+//!     "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+//!     Some("Bg"),
+//!     VanityMode::Prefix,
+//!     true,
+//! );
+//! assert!(result.is_ok());
+//! ```
+
+use crate::error::BtcVanityError;
+use crate::keys_and_address::KeysAndAddress;
+use crate::vanity_addr_generator::{SearchEngines, VanityMode};
+use bitcoin::secp256k1::{All, Secp256k1};
+
+/// Derives the address of the WIF private key and checks that it is the given address.
+/// If a pattern is given, also checks that the address has it with the given mode and case sensitivity.
+/// Returns Ok with the derived key pair and address if everything matches.
+/// Returns Err if the private key is invalid, derives another address or the pattern doesn't match.
+pub fn verify_wallet(
+    private_key_wif: &str,
+    address: &str,
+    pattern: Option<&str>,
+    vanity_mode: VanityMode,
+    case_sensitive: bool,
+) -> Result<KeysAndAddress, BtcVanityError> {
+    let keys_and_address = KeysAndAddress::from_wif(&Secp256k1::new(), private_key_wif.trim())?;
+    let address = address.trim();
+
+    if keys_and_address.get_comp_address() != address {
+        return Err(BtcVanityError::AddressMismatch {
+            address: address.to_string(),
+            derived: keys_and_address.get_comp_address().to_string(),
+        });
+    }
+
+    if let Some(pattern) = pattern {
+        // The matcher slices the address by the pattern length, so a longer or non-ASCII
+        // pattern can't match and is rejected before it.
+        let is_match = pattern.is_ascii()
+            && pattern.len() < address.len()
+            && SearchEngines::is_match(address, pattern, case_sensitive, vanity_mode);
+        if !is_match {
+            return Err(BtcVanityError::PatternMismatch {
+                address: address.to_string(),
+                pattern: pattern.to_string(),
+            });
+        }
+    }
+
+    Ok(keys_and_address)
+}
+
+/// Checks a key pair found by a search before it is returned. The public key and the address are
+/// derived again from the WIF private key, without the incremental key math and the batching of the
+/// search threads, and the pattern is matched against the address again.
+/// Returns SelfCheckFailed if the keys don't derive the address or the address doesn't match, so a
+/// corrupted key pair is never printed or written.
+pub fn self_check(
+    secp256k1: &Secp256k1<All>,
+    keys_and_address: &KeysAndAddress,
+    pattern: &str,
+    vanity_mode: VanityMode,
+    case_sensitive: bool,
+) -> Result<(), BtcVanityError> {
+    self_check_keys(secp256k1, keys_and_address)?;
+    let address = keys_and_address.get_comp_address();
+    if !SearchEngines::is_match(address, pattern, case_sensitive, vanity_mode) {
+        return Err(BtcVanityError::SelfCheckFailed {
+            address: address.to_string(),
+            reason: format!("it doesn't match '{}'", pattern),
+        });
+    }
+
+    Ok(())
+}
+
+/// Same as `self_check` without a pattern, for the searches whose matching is up to the caller,
+/// ex: `SearchEngines::find_custom`. Only the public key and the address are derived again.
+/// Returns SelfCheckFailed if the keys don't derive the address.
+pub fn self_check_keys(
+    secp256k1: &Secp256k1<All>,
+    keys_and_address: &KeysAndAddress,
+) -> Result<(), BtcVanityError> {
+    let address = keys_and_address.get_comp_address();
+    let failed = |reason: String| BtcVanityError::SelfCheckFailed {
+        address: address.to_string(),
+        reason,
+    };
+
+    let derived = KeysAndAddress::from_wif(secp256k1, &keys_and_address.get_wif_private_key())
+        .map_err(|err| failed(err.to_string()))?;
+    if derived.get_public_key() != keys_and_address.get_public_key() {
+        return Err(failed(format!(
+            "the private key derives the public key {}",
+            derived.get_comp_public_key()
+        )));
+    }
+    if derived.get_comp_address() != address {
+        return Err(failed(format!(
+            "the private key derives {}",
+            derived.get_comp_address()
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The private key 1 and its compressed address.
+    const WIF: &str = "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn";
+    const ADDRESS: &str = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+
+    #[test]
+    fn test_verify_wallet() {
+        assert!(verify_wallet(WIF, ADDRESS, None, VanityMode::Prefix, false).is_ok());
+        assert!(verify_wallet(WIF, ADDRESS, Some("bgg"), VanityMode::Prefix, false).is_ok());
+        assert!(verify_wallet(WIF, ADDRESS, Some("SAMH"), VanityMode::Suffix, true).is_ok());
+    }
+
+    #[test]
+    fn test_verify_wallet_address_mismatch() {
+        let result = verify_wallet(
+            WIF,
+            "1EmivB8uShHdr4Dft4yGRpvDEUbdUDXkxT",
+            None,
+            VanityMode::Prefix,
+            false,
+        );
+
+        assert!(matches!(
+            result,
+            Err(BtcVanityError::AddressMismatch { derived, .. }) if derived == ADDRESS
+        ));
+    }
+
+    #[test]
+    fn test_verify_wallet_pattern_mismatch() {
+        let result = verify_wallet(WIF, ADDRESS, Some("bgg"), VanityMode::Prefix, true);
+
+        assert!(matches!(
+            result,
+            Err(BtcVanityError::PatternMismatch { .. })
+        ));
+        let too_long = format!("{}1", ADDRESS);
+        let result = verify_wallet(WIF, ADDRESS, Some(&too_long), VanityMode::Suffix, false);
+        assert!(matches!(
+            result,
+            Err(BtcVanityError::PatternMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_verify_wallet_invalid_wif() {
+        let result = verify_wallet("not a key", ADDRESS, None, VanityMode::Prefix, false);
+
+        assert!(matches!(result, Err(BtcVanityError::InvalidWif)));
+    }
+
+    #[test]
+    fn test_self_check() {
+        let secp256k1 = Secp256k1::new();
+        let keys_and_address = KeysAndAddress::from_wif(&secp256k1, WIF).unwrap();
+
+        assert!(self_check(
+            &secp256k1,
+            &keys_and_address,
+            "bgg",
+            VanityMode::Prefix,
+            false
+        )
+        .is_ok());
+        assert!(matches!(
+            self_check(&secp256k1, &keys_and_address, "bgg", VanityMode::Prefix, true),
+            Err(BtcVanityError::SelfCheckFailed { reason, .. }) if reason == "it doesn't match 'bgg'"
+        ));
+    }
+
+    #[test]
+    fn test_self_check_corrupted_key_pair() {
+        let secp256k1 = Secp256k1::new();
+        let corrupted = KeysAndAddress::from_wif(&secp256k1, WIF)
+            .unwrap()
+            .with_corrupted_address("1EmivB8uShHdr4Dft4yGRpvDEUbdUDXkxT");
+
+        // The corrupted address still matches, only the derivation catches it.
+        assert!(matches!(
+            self_check(&secp256k1, &corrupted, "Emiv", VanityMode::Prefix, true),
+            Err(BtcVanityError::SelfCheckFailed { address, reason })
+                if address == "1EmivB8uShHdr4Dft4yGRpvDEUbdUDXkxT"
+                    && reason == format!("the private key derives {}", ADDRESS)
+        ));
+    }
+
+    #[test]
+    fn test_self_check_keys() {
+        let secp256k1 = Secp256k1::new();
+        let keys_and_address = KeysAndAddress::from_wif(&secp256k1, WIF).unwrap();
+        assert!(self_check_keys(&secp256k1, &keys_and_address).is_ok());
+
+        let corrupted =
+            keys_and_address.with_corrupted_address("1EmivB8uShHdr4Dft4yGRpvDEUbdUDXkxT");
+        assert!(matches!(
+            self_check_keys(&secp256k1, &corrupted),
+            Err(BtcVanityError::SelfCheckFailed { reason, .. })
+                if reason == format!("the private key derives {}", ADDRESS)
+        ));
+    }
+}
@@ -0,0 +1,1060 @@
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Runs the compiled binary with the given arguments and stdin and returns its exit code and stdout.
+fn run_cli(args: &[&str], stdin: &str) -> (Option<i32>, String) {
+    let (code, stdout, _) = run_cli_with_env(args, stdin, &[]);
+    (code, stdout)
+}
+
+/// Same as `run_cli` but sets the given environment variables and also returns stderr.
+fn run_cli_with_env(
+    args: &[&str],
+    stdin: &str,
+    envs: &[(&str, &str)],
+) -> (Option<i32>, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_btc-vanity"))
+        .args(args)
+        .envs(envs.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn test_input_file_from_stdin() {
+    let (code, stdout) = run_cli(&["-q", "-i", "-"], "a\nb -s\n");
+
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout.matches("address (compressed): ").count(), 2);
+    assert!(stdout.contains("has the prefix: 'a'"));
+    assert!(stdout.contains("has the suffix: 'b'"));
+}
+
+#[test]
+fn test_input_line_threads_and_count() {
+    // The cli doesn't set -t, so the line's thread count is used instead of the CPU cores.
+    let (code, stdout) = run_cli(&["-i", "-"], "a -t 3 -n 3\nb\n");
+
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("'a' (case sensitivity disabled) with 3 threads."));
+    assert_eq!(stdout.matches("address (compressed): ").count(), 4);
+}
+
+#[test]
+fn test_unknown_input_flag_is_rejected() {
+    let (code, stdout, stderr) = run_cli_with_env(&["-q", "-i", "-"], "a\nb --sufix\n", &[]);
+
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("line 2: unknown flag '--sufix', did you mean '--suffix'?"));
+    assert!(!stdout.contains("address (compressed): "));
+}
+
+#[test]
+fn test_ignore_unknown_flags() {
+    let (code, stdout, stderr) =
+        run_cli_with_env(&["-q", "--ignore-unknown-flags", "-i", "-"], "a -x\n", &[]);
+
+    assert_eq!(code, Some(0));
+    assert!(stderr.contains("ignoring the unknown flag '-x' in line 1"));
+    assert_eq!(stdout.matches("address (compressed): ").count(), 1);
+}
+
+#[test]
+fn test_duplicate_lines_are_searched_once() {
+    let output_files = ["a", "b"].map(|name| {
+        std::env::temp_dir().join(format!(
+            "btc-vanity-dedup-{}-{}.txt",
+            name,
+            std::process::id()
+        ))
+    });
+    let input = format!(
+        "ab -o {}\nab -o {}\n",
+        output_files[0].display(),
+        output_files[1].display()
+    );
+    let addresses = |args: &[&str]| {
+        let (code, _) = run_cli(args, &input);
+        assert_eq!(code, Some(0));
+        output_files.clone().map(|output_file| {
+            let data = fs::read_to_string(&output_file).unwrap();
+            fs::remove_file(&output_file).unwrap();
+            data.lines()
+                .find_map(|line| line.strip_prefix("address (compressed): "))
+                .unwrap()
+                .to_string()
+        })
+    };
+
+    let [first, second] = addresses(&["-q", "-i", "-"]);
+    assert_eq!(first, second);
+
+    let [first, second] = addresses(&["-q", "--no-dedup", "-i", "-"]);
+    assert_ne!(first, second);
+}
+
+#[test]
+fn test_toml_input_file() {
+    let input_file =
+        std::env::temp_dir().join(format!("btc-vanity-input-{}.toml", std::process::id()));
+    fs::write(
+        &input_file,
+        "[[entry]]\npattern = \"a\"\n\n[[entry]]\npattern = \"b\"\nmode = \"suffix\"\ncount = 2\n",
+    )
+    .unwrap();
+
+    let (code, stdout) = run_cli(&["-q", "-i", input_file.to_str().unwrap()], "");
+    fs::remove_file(&input_file).unwrap();
+
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout.matches("address (compressed): ").count(), 3);
+    assert!(stdout.contains("has the prefix: 'a'"));
+    assert!(stdout.contains("has the suffix: 'b'"));
+}
+
+#[test]
+fn test_invalid_toml_entry_is_rejected() {
+    let (code, _, stderr) = run_cli_with_env(
+        &["-q", "--input-format", "toml", "-i", "-"],
+        "[[entry]]\npattern = \"a\"\n\n[[entry]]\npattern = \"b\"\nthreads = -1\n",
+        &[],
+    );
+
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("entry 2: 'threads' must be a non-negative integer"));
+}
+
+#[test]
+fn test_zero_threads_uses_cpu_cores() {
+    let (code, stdout) = run_cli(&["-q", "-t", "0", "a"], "");
+
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout.matches("address (compressed): ").count(), 1);
+}
+
+#[test]
+fn test_pin_threads() {
+    let (code, stdout) = run_cli(&["-q", "--pin-threads", "-t", "2", "a"], "");
+
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout.matches("address (compressed): ").count(), 1);
+}
+
+#[test]
+fn test_template() {
+    let (code, stdout) = run_cli(&["-q", "--template", "{address},{private_key}", "a"], "");
+
+    assert_eq!(code, Some(0));
+    let (address, private_key) = stdout.trim_end().split_once(',').unwrap();
+    assert!(address.starts_with("1a") || address.starts_with("1A"));
+    assert!(private_key.starts_with('K') || private_key.starts_with('L'));
+    assert_eq!(stdout.lines().count(), 1);
+}
+
+#[test]
+fn test_porcelain() {
+    let (code, stdout, stderr) = run_cli_with_env(&["--porcelain", "-i", "-"], "a\n1b\nEm0\n", &[]);
+
+    assert_eq!(code, Some(6));
+    let lines = stdout.split_terminator('\n').collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    for (line, prefix) in lines.iter().zip(["1a", "1b"]) {
+        let fields = line.split('\t').collect::<Vec<_>>();
+        let [address, private_key, public_key] = fields[..] else {
+            panic!("not a porcelain line: {:?}", line);
+        };
+        assert!(address.to_lowercase().starts_with(prefix));
+        assert_eq!(private_key.len(), 52);
+        assert!(private_key.starts_with('K') || private_key.starts_with('L'));
+        assert_eq!(public_key.len(), 66);
+        assert!(public_key.starts_with("02") || public_key.starts_with("03"));
+    }
+    // The human readable text only goes to stderr.
+    assert!(stderr.contains("FOUND IN"));
+    assert!(stderr.contains("searched as 'b'"));
+    assert!(stderr.contains("'Em0'"));
+}
+
+#[test]
+fn test_found_line_reports_attempts_and_speed() {
+    let (code, stdout, stderr) = run_cli_with_env(&["--format", "json", "ab"], "", &[]);
+
+    assert_eq!(code, Some(0));
+    let wallet: serde_json::Value = serde_json::from_str(stdout.trim_end()).unwrap();
+    let attempts = wallet["attempts"].as_u64().unwrap();
+    let duration_secs = wallet["duration_secs"].as_f64().unwrap();
+    let keys_per_second = wallet["keys_per_second"].as_f64().unwrap();
+    assert!(attempts > 0 && duration_secs > 0.0);
+    assert!(
+        (attempts as f64 / duration_secs - keys_per_second).abs() <= keys_per_second * 0.01 + 1.0
+    );
+
+    // ex: FOUND IN 0.01s after 3,412 candidates (341.20 kkeys/s)
+    let found_line = stderr
+        .lines()
+        .find(|line| line.starts_with("FOUND IN "))
+        .unwrap();
+    let candidates = found_line
+        .split(" after ")
+        .nth(1)
+        .and_then(|rest| rest.split(' ').next())
+        .unwrap();
+    assert_eq!(candidates.replace(',', ""), attempts.to_string());
+    assert!(found_line.ends_with("keys/s)"));
+}
+
+#[test]
+fn test_unique_matches() {
+    // 'a' and 'A' are the only distinct matches, and duplicates of them arrive quickly.
+    let (code, stdout, _) = run_cli_with_env(
+        &["--porcelain", "-n", "2", "--unique-matches", "a"],
+        "",
+        &[],
+    );
+
+    assert_eq!(code, Some(0));
+    let mut matched = stdout
+        .lines()
+        .map(|line| line[1..2].to_string())
+        .collect::<Vec<_>>();
+    matched.sort();
+    assert_eq!(matched, ["A", "a"]);
+
+    let (code, _, stderr) = run_cli_with_env(
+        &["--porcelain", "-n", "3", "--unique-matches", "a"],
+        "",
+        &[],
+    );
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("only 2 distinct matches"));
+}
+
+#[test]
+fn test_porcelain_conflicts_with_format() {
+    let (code, stdout) = run_cli(&["--porcelain", "--format", "json", "a"], "");
+
+    assert_eq!(code, Some(2));
+    assert!(stdout.is_empty());
+}
+
+#[test]
+fn test_template_unknown_placeholder() {
+    let (code, stdout) = run_cli(&["-q", "--template", "{address},{wif}", "a"], "");
+
+    assert_eq!(code, Some(2));
+    assert!(stdout.is_empty());
+}
+
+#[test]
+fn test_too_many_threads_is_rejected() {
+    let (code, stdout) = run_cli(&["-q", "-t", "100000", "a"], "");
+
+    assert_ne!(code, Some(0));
+    assert!(stdout.is_empty());
+}
+
+#[test]
+fn test_input_line_case_sensitivity_in_output() {
+    // Only the first line sets -c, the cli doesn't.
+    let output_file =
+        std::env::temp_dir().join(format!("btc-vanity-case-{}.txt", std::process::id()));
+    let output_file_name = output_file.to_str().unwrap();
+
+    let (code, stdout) = run_cli(&["-o", output_file_name, "-i", "-"], "ab -c\ncd\n");
+    let written = fs::read_to_string(&output_file).unwrap();
+    fs::remove_file(&output_file).unwrap();
+
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("has the prefix: 'ab' (case sensitive)"));
+    assert!(stdout.contains("has the prefix: 'cd' (case sensitivity disabled)"));
+    assert!(written.contains("pattern: 'ab', mode: prefix, case sensitive: true,"));
+    assert!(written.contains("pattern: 'cd', mode: prefix, case sensitive: false,"));
+}
+
+#[test]
+fn test_overwrite_truncates_output_file_once_per_run() {
+    let output_file = std::env::temp_dir().join(format!("btc-vanity-{}.txt", std::process::id()));
+    let output_file_name = output_file.to_str().unwrap();
+    fs::write(&output_file, "stale wallet\n").unwrap();
+
+    let (code, _) = run_cli(
+        &["-q", "--overwrite", "-o", output_file_name, "-i", "-"],
+        "a\nb\n",
+    );
+    let written = fs::read_to_string(&output_file).unwrap();
+    fs::remove_file(&output_file).unwrap();
+
+    assert_eq!(code, Some(0));
+    assert!(!written.contains("stale wallet"));
+    assert_eq!(written.matches("address (compressed): ").count(), 2);
+    // Every entry starts with its header line and ends with the separator line.
+    let entries = written
+        .split_terminator("----------------------------------------\n")
+        .collect::<Vec<_>>();
+    assert_eq!(entries.len(), 2);
+    for (entry, pattern) in entries.iter().zip(["a", "b"]) {
+        let header = entry.lines().next().unwrap();
+        assert!(header.starts_with('['));
+        assert!(header.contains(&format!(
+            "] chain: bitcoin, pattern: '{}', mode: prefix, case sensitive: false, btc-vanity: {}",
+            pattern,
+            env!("CARGO_PKG_VERSION")
+        )));
+    }
+}
+
+#[test]
+fn test_template_output_file_has_no_entry_header() {
+    let output_file =
+        std::env::temp_dir().join(format!("btc-vanity-template-{}.txt", std::process::id()));
+    let output_file_name = output_file.to_str().unwrap();
+    let _ = fs::remove_file(&output_file);
+
+    let (code, _) = run_cli(
+        &["-q", "--template", "{address}", "-o", output_file_name, "a"],
+        "",
+    );
+    let written = fs::read_to_string(&output_file).unwrap();
+    fs::remove_file(&output_file).unwrap();
+
+    assert_eq!(code, Some(0));
+    assert_eq!(written.lines().count(), 1);
+    assert!(written.to_lowercase().starts_with("1a"));
+}
+
+#[test]
+fn test_max_output_size_rotates_output_file() {
+    let output_file =
+        std::env::temp_dir().join(format!("btc-vanity-rotation-{}.jsonl", std::process::id()));
+    let output_file_name = output_file.to_str().unwrap();
+    let rotated_file = format!("{}.1", output_file_name);
+    let _ = fs::remove_file(&output_file);
+    let _ = fs::remove_file(&rotated_file);
+
+    // Every wallet is larger than the limit, so every wallet gets its own file.
+    let (code, _) = run_cli(
+        &[
+            "-q",
+            "--format",
+            "json",
+            "--max-output-size",
+            "100",
+            "-o",
+            output_file_name,
+            "-i",
+            "-",
+        ],
+        "a\nb\n",
+    );
+    let rotated = fs::read_to_string(&rotated_file).unwrap();
+    let written = fs::read_to_string(&output_file).unwrap();
+    fs::remove_file(&rotated_file).unwrap();
+    fs::remove_file(&output_file).unwrap();
+
+    assert_eq!(code, Some(0));
+    for (data, pattern) in [(rotated, "a"), (written, "b")] {
+        assert_eq!(data.lines().count(), 1);
+        let wallet: serde_json::Value = serde_json::from_str(data.trim_end()).unwrap();
+        assert_eq!(wallet["pattern"], pattern);
+    }
+}
+
+#[test]
+fn test_encrypt_output_and_decrypt_it() {
+    let output_file =
+        std::env::temp_dir().join(format!("btc-vanity-encrypted-{}.txt", std::process::id()));
+    let password_file =
+        std::env::temp_dir().join(format!("btc-vanity-password-{}.txt", std::process::id()));
+    let output_file_name = output_file.to_str().unwrap();
+    let password_file_name = password_file.to_str().unwrap();
+    let _ = fs::remove_file(&output_file);
+    fs::write(&password_file, "correct horse\n").unwrap();
+
+    let (code, _) = run_cli(
+        &[
+            "-q",
+            "--encrypt-output",
+            "--output-password-file",
+            password_file_name,
+            "-o",
+            output_file_name,
+            "a",
+        ],
+        "",
+    );
+    assert_eq!(code, Some(0));
+    assert!(!fs::read_to_string(&output_file)
+        .unwrap()
+        .contains("private_key"));
+
+    let (code, stdout) = run_cli(
+        &[
+            "decrypt-output",
+            "--output-password-file",
+            password_file_name,
+            output_file_name,
+        ],
+        "",
+    );
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("pattern: 'a', mode: prefix"));
+    assert_eq!(stdout.matches("private_key (wif): ").count(), 1);
+
+    // A wrong passphrase fails instead of printing garbage.
+    fs::write(&password_file, "battery staple\n").unwrap();
+    let (code, stdout) = run_cli(
+        &[
+            "decrypt-output",
+            "--output-password-file",
+            password_file_name,
+            output_file_name,
+        ],
+        "",
+    );
+    fs::remove_file(&output_file).unwrap();
+    fs::remove_file(&password_file).unwrap();
+
+    assert_eq!(code, Some(2));
+    assert!(stdout.is_empty());
+}
+
+#[test]
+fn test_encrypt_output_needs_output_file() {
+    let (code, stdout) = run_cli(&["-q", "--encrypt-output", "a"], "");
+
+    assert_eq!(code, Some(2));
+    assert!(!stdout.contains("address (compressed): "));
+}
+
+#[test]
+fn test_output_file_from_env() {
+    let output_file =
+        std::env::temp_dir().join(format!("btc-vanity-env-{}.txt", std::process::id()));
+    let output_file_name = output_file.to_str().unwrap();
+
+    let (code, stdout, _) = run_cli_with_env(
+        &["-q", "a"],
+        "",
+        &[("BTC_VANITY_OUTPUT_FILE", output_file_name)],
+    );
+    let written = fs::read_to_string(&output_file).unwrap();
+    fs::remove_file(&output_file).unwrap();
+
+    assert_eq!(code, Some(0));
+    assert!(!stdout.contains("address (compressed): "));
+    assert_eq!(written.matches("address (compressed): ").count(), 1);
+}
+
+#[test]
+fn test_invalid_threads_env_is_rejected() {
+    let (code, stdout, stderr) =
+        run_cli_with_env(&["-q", "a"], "", &[("BTC_VANITY_THREADS", "many")]);
+
+    assert_ne!(code, Some(0));
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("BTC_VANITY_THREADS"));
+}
+
+#[test]
+fn test_cli_threads_overrides_env() {
+    let (code, stdout, _) = run_cli_with_env(
+        &["-q", "-t", "2", "a"],
+        "",
+        &[("BTC_VANITY_THREADS", "many")],
+    );
+
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout.matches("address (compressed): ").count(), 1);
+}
+
+#[test]
+fn test_exit_code_invalid_input() {
+    let (code, stdout) = run_cli(&["-q", "Em0"], "");
+
+    assert_eq!(code, Some(2));
+    assert!(stdout.contains("  Em0\n    ^ did you mean 'o'?"));
+}
+
+#[test]
+fn test_exit_code_timed_out() {
+    let (code, _) = run_cli(&["-q", "-c", "--timeout", "0s", "zzzz"], "");
+
+    assert_eq!(code, Some(3));
+}
+
+#[test]
+fn test_max_rate_slows_down_search() {
+    let start = std::time::Instant::now();
+    let (code, _) = run_cli(
+        &[
+            "-q",
+            "-c",
+            "-t",
+            "1",
+            "--max-rate",
+            "100",
+            "--max-attempts",
+            "150",
+            "zzzz",
+        ],
+        "",
+    );
+
+    assert_eq!(code, Some(3));
+    assert!(start.elapsed() >= std::time::Duration::from_millis(1400));
+}
+
+#[test]
+fn test_exit_code_output_error() {
+    let (code, _) = run_cli(&["-q", "-o", "missing-dir/wallets.txt", "a"], "");
+
+    assert_eq!(code, Some(4));
+}
+
+#[test]
+fn test_exit_code_partial_success() {
+    let (code, stdout) = run_cli(&["-q", "-i", "-"], "a\n0\n");
+
+    assert_eq!(code, Some(6));
+    assert_eq!(stdout.matches("address (compressed): ").count(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exit_code_interrupted() {
+    let child = Command::new(env!("CARGO_BIN_EXE_btc-vanity"))
+        .args(["-q", "-c", "zzzz"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Interrupted after searching"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_watch_searches_appended_lines() {
+    use std::io::{BufRead, BufReader};
+
+    let input_file =
+        std::env::temp_dir().join(format!("btc-vanity-watch-{}.txt", std::process::id()));
+    fs::write(&input_file, "a\n").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_btc-vanity"))
+        .args(["--porcelain", "--watch", "-i", input_file.to_str().unwrap()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut read_address = || {
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        line.split('\t').next().unwrap().to_lowercase()
+    };
+
+    assert!(read_address().starts_with("1a"));
+    // The last line isn't complete yet, so it isn't searched.
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .open(&input_file)
+        .unwrap();
+    file.write_all(b"b\nc").unwrap();
+    assert!(read_address().starts_with("1b"));
+
+    // Ctrl-C while waiting for new lines stops the run cleanly.
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    fs::remove_file(&input_file).unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(read_address().is_empty());
+}
+
+#[test]
+fn test_output_dir_writes_a_file_per_wallet() {
+    let output_dir = std::env::temp_dir().join(format!("btc-vanity-dir-{}", std::process::id()));
+    let output_dir_name = output_dir.to_str().unwrap();
+
+    let (code, stdout) = run_cli(
+        &["-q", "-n", "2", "--output-dir", output_dir_name, "-i", "-"],
+        "a\nb\n",
+    );
+    let mut file_names: Vec<_> = fs::read_dir(&output_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    file_names.sort();
+    let written = fs::read_to_string(output_dir.join("bitcoin-a-1.txt")).unwrap();
+    fs::remove_dir_all(&output_dir).unwrap();
+
+    assert_eq!(code, Some(0));
+    assert!(!stdout.contains("address (compressed): "));
+    assert_eq!(
+        file_names,
+        vec![
+            "bitcoin-a-1.txt",
+            "bitcoin-a-2.txt",
+            "bitcoin-b-1.txt",
+            "bitcoin-b-2.txt"
+        ]
+    );
+    assert_eq!(written.matches("address (compressed): ").count(), 1);
+}
+
+#[test]
+fn test_difficulty_warning_without_terminal_continues() {
+    let (code, stdout, stderr) = run_cli_with_env(&["-q", "--difficulty-warn", "1", "a"], "", &[]);
+
+    assert_eq!(code, Some(0));
+    assert!(stderr.contains("Warning: 'a' needs"));
+    assert_eq!(stdout.matches("address (compressed): ").count(), 1);
+}
+
+#[test]
+fn test_list_chains() {
+    let (code, stdout) = run_cli(&["--list-chains"], "");
+
+    assert_eq!(code, Some(0));
+    assert!(stdout.starts_with("bitcoin\n"));
+    assert!(stdout.contains("max length (fast mode): 4"));
+    assert!(stdout.contains("address length: 26-34"));
+    assert!(stdout.contains("fixed prefix: 1"));
+    assert!(stdout.contains("case insensitive matching: yes"));
+    assert!(stdout.contains("default: case insensitive, -c for case sensitive"));
+    assert!(stdout.contains("compiled in: yes"));
+}
+
+#[test]
+fn test_dry_run_reports_every_line() {
+    let (code, stdout) = run_cli(&["--dry-run", "-i", "-"], "Emiv -c\nab -s -o out.txt\n");
+
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("line 1: 'Emiv' prefix, case sensitive, fast mode, output: stdout"));
+    assert!(stdout.contains("line 2: 'ab' suffix, case insensitive, fast mode, output: out.txt"));
+    assert_eq!(stdout.matches("expected attempts: ").count(), 2);
+    assert!(!stdout.contains("address (compressed): "));
+}
+
+#[test]
+fn test_dry_run_notes_case_sensitivity_penalty() {
+    let (code, stdout) = run_cli(&["--dry-run", "-i", "-"], "Emiv -c\n1234 -c\nEmiv\n");
+
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains(
+        "case-sensitive: ~1.1e7 expected attempts; case-insensitive: ~1.4e6 (8x easier)"
+    ));
+    // Digits and case insensitive strings don't get the note.
+    assert_eq!(stdout.matches("case-sensitive: ~").count(), 1);
+}
+
+#[test]
+fn test_dry_run_checks_feasibility() {
+    let input = format!("z -c\nEmiv -c\n{} -c -d\n", "z".repeat(33));
+    let (code, stdout) = run_cli(&["--dry-run", "-i", "-"], &input);
+
+    assert_eq!(code, Some(2));
+    assert!(stdout.contains("few addresses start with '1z', it is about 23x harder"));
+    assert_eq!(stdout.matches("few addresses start with").count(), 1);
+    assert!(stdout.contains("no bitcoin address can match the string"));
+}
+
+#[test]
+fn test_dry_run_json_reports_resolved_search() {
+    let (code, stdout) = run_cli(
+        &["--dry-run", "--format", "json", "-i", "-"],
+        "1Emiv -c\nab -s -o out.txt\nEm0\n",
+    );
+
+    assert_eq!(code, Some(2));
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with(
+        "{\"line\":1,\"chain\":\"bitcoin\",\"input\":\"1Emiv\",\"pattern\":\"Emiv\",\
+        \"mode\":\"prefix\",\"case_sensitive\":true,\"max_len\":4,\"address\":\"1Emiv...\","
+    ));
+    assert!(lines[1].contains("\"pattern\":\"ab\",\"mode\":\"suffix\",\"case_sensitive\":false"));
+    assert!(lines[1].ends_with("\"rarity\":null,\"output\":\"out.txt\"}"));
+    assert!(lines[2].starts_with("{\"line\":3,\"chain\":\"bitcoin\",\"input\":\"Em0\""));
+    assert!(lines[2].contains("\"error\":"));
+}
+
+#[test]
+fn test_max_len_overrides_fast_mode_limit() {
+    let (code, stdout) = run_cli(&["--dry-run", "Emivvv"], "");
+    assert_eq!(code, Some(2));
+    assert!(
+        stdout.contains("The string is 6 chars, the limit is 4; pass --disable-fast or --max-len")
+    );
+
+    let (code, stdout) = run_cli(&["--dry-run", "--max-len", "6", "Emivvv"], "");
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("expected attempts: "));
+
+    let (code, stdout) = run_cli(&["--dry-run", "--max-len", "5", "Emivvv"], "");
+    assert_eq!(code, Some(2));
+    assert!(stdout.contains("The string is 6 chars, the limit is 5;"));
+}
+
+#[test]
+fn test_estimate_caches_the_speed() {
+    let cache_dir = std::env::temp_dir().join(format!("btc-vanity-cache-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&cache_dir);
+    let envs = [("BTC_VANITY_CACHE_DIR", cache_dir.to_str().unwrap())];
+    let speed = |stdout: &str| {
+        stdout
+            .lines()
+            .find_map(|line| line.strip_prefix("measured speed: "))
+            .unwrap()
+            .to_string()
+    };
+
+    let (code, first, _) = run_cli_with_env(&["--estimate", "-t", "1", "Emiv"], "", &envs);
+    assert_eq!(code, Some(0));
+    assert!(!speed(&first).contains("cached"));
+    assert!(cache_dir.join("calibration.tsv").exists());
+
+    let (code, second, _) = run_cli_with_env(&["--estimate", "-t", "1", "Emiv"], "", &envs);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        speed(&second),
+        format!(
+            "{} (cached, --recalibrate measures it again)",
+            speed(&first)
+        )
+    );
+
+    let (code, third, _) = run_cli_with_env(
+        &["--estimate", "--recalibrate", "-t", "1", "Emiv"],
+        "",
+        &envs,
+    );
+    assert_eq!(code, Some(0));
+    assert!(!speed(&third).contains("cached"));
+
+    let _ = fs::remove_dir_all(&cache_dir);
+}
+
+#[test]
+fn test_dry_run_fails_on_invalid_line() {
+    let (code, stdout) = run_cli(&["--dry-run", "-i", "-"], "Emiv\nEm0\n");
+
+    assert_eq!(code, Some(2));
+    assert!(stdout.contains("line 2: 'Em0'"));
+    assert!(stdout.contains("  error: "));
+}
+
+#[test]
+fn test_checkpoint_skips_found_lines() {
+    let checkpoint =
+        std::env::temp_dir().join(format!("btc-vanity-checkpoint-{}.tsv", std::process::id()));
+    let checkpoint_name = checkpoint.to_str().unwrap();
+    let _ = fs::remove_file(&checkpoint);
+
+    let (code, stdout) = run_cli(
+        &["-q", "--checkpoint", checkpoint_name, "-i", "-"],
+        "a\nb\n",
+    );
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout.matches("address (compressed): ").count(), 2);
+
+    // Simulates a run killed while writing the record of the second line.
+    let records = fs::read_to_string(&checkpoint).unwrap();
+    let first_record = records.lines().next().unwrap();
+    fs::write(&checkpoint, format!("{}\n2\t", first_record)).unwrap();
+
+    let (code, stdout) = run_cli(
+        &["-q", "--checkpoint", checkpoint_name, "-i", "-"],
+        "a\nb\n",
+    );
+    fs::remove_file(&checkpoint).unwrap();
+
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("Skipping line 1: 'a' is already found, output: stdout"));
+    assert!(!stdout.contains("Skipping line 2"));
+    assert_eq!(stdout.matches("address (compressed): ").count(), 1);
+}
+
+#[test]
+fn test_summary_file() {
+    let summary_file =
+        std::env::temp_dir().join(format!("btc-vanity-summary-{}.json", std::process::id()));
+    let summary_name = summary_file.to_str().unwrap();
+
+    let (code, stdout) = run_cli(&["--summary-file", summary_name, "-i", "-"], "a\nEm0\n");
+    let summary = fs::read_to_string(&summary_file).unwrap();
+    fs::remove_file(&summary_file).unwrap();
+
+    assert_eq!(code, Some(6));
+    assert!(stdout.contains("Summary:"));
+    assert!(stdout.contains("Total: 2 strings, 1 failed"));
+    assert!(summary.contains("\"line\":1,\"pattern\":\"a\",\"status\":\"found\",\"found\":1"));
+    assert!(summary.contains("\"line\":2,\"pattern\":\"Em0\",\"status\":\"invalid\""));
+    assert!(summary.contains("\"strings\":2,\"failures\":1"));
+}
+
+#[test]
+fn test_checkpoint_searches_edited_lines_again() {
+    let checkpoint = std::env::temp_dir().join(format!(
+        "btc-vanity-checkpoint-edited-{}.tsv",
+        std::process::id()
+    ));
+    let checkpoint_name = checkpoint.to_str().unwrap();
+    let _ = fs::remove_file(&checkpoint);
+
+    run_cli(&["-q", "--checkpoint", checkpoint_name, "-i", "-"], "a\n");
+    let (code, stdout) = run_cli(
+        &["-q", "--checkpoint", checkpoint_name, "-i", "-"],
+        "a -s\n",
+    );
+    fs::remove_file(&checkpoint).unwrap();
+
+    assert_eq!(code, Some(0));
+    assert!(!stdout.contains("Skipping line 1"));
+    assert_eq!(stdout.matches("address (compressed): ").count(), 1);
+}
+
+#[test]
+fn test_stats_adds_metadata_to_output_file() {
+    let output_file =
+        std::env::temp_dir().join(format!("btc-vanity-stats-{}.txt", std::process::id()));
+    let output_file_name = output_file.to_str().unwrap();
+
+    let (code, _) = run_cli(&["-q", "--stats", "-o", output_file_name, "a"], "");
+    let written = fs::read_to_string(&output_file).unwrap();
+    fs::remove_file(&output_file).unwrap();
+
+    assert_eq!(code, Some(0));
+    assert!(written.contains("found at: "));
+    assert!(written.contains("pattern: 'a' (prefix, bitcoin)\n"));
+    assert!(written.contains(" keys/sec\n"));
+    assert!(written.contains(&format!(
+        "btc-vanity version: {}\n",
+        env!("CARGO_PKG_VERSION")
+    )));
+}
+
+#[test]
+fn test_seed_is_reproducible() {
+    let args = ["-q", "-t", "1", "-n", "2", "--seed", "42", "ab"];
+    let (code, stdout, stderr) = run_cli_with_env(&args, "", &[]);
+    let (_, stdout_again, _) = run_cli_with_env(&args, "", &[]);
+
+    assert_eq!(code, Some(0));
+    assert!(stderr.contains("WARNING: --seed 42 makes the private keys predictable!"));
+    let addresses = |stdout: &str| {
+        stdout
+            .lines()
+            .filter(|line| line.starts_with("address (compressed): "))
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(addresses(&stdout).len(), 2);
+    assert_eq!(addresses(&stdout), addresses(&stdout_again));
+}
+
+#[test]
+fn test_exclude_addresses_skips_listed_address() {
+    let exclude_file =
+        std::env::temp_dir().join(format!("btc-vanity-exclude-{}.txt", std::process::id()));
+    let exclude_path = exclude_file.to_str().unwrap();
+    let address = |args: &[&str]| {
+        let (code, stdout) = run_cli(args, "");
+        assert_eq!(code, Some(0));
+        stdout
+            .lines()
+            .find_map(|line| line.strip_prefix("address (compressed): "))
+            .unwrap()
+            .to_string()
+    };
+
+    // A single letter is found within a few candidates, so the seeded search finds it again.
+    let first = address(&["-q", "-t", "1", "--seed", "42", "a"]);
+    fs::write(&exclude_file, format!("{}\n", first)).unwrap();
+    let second = address(&[
+        "-q",
+        "-t",
+        "1",
+        "--seed",
+        "42",
+        "--exclude-addresses",
+        exclude_path,
+        "a",
+    ]);
+    assert_ne!(first, second);
+    assert!(second.to_lowercase().starts_with("1a"));
+
+    fs::write(&exclude_file, "not-an-address\n").unwrap();
+    let (code, _) = run_cli(&["-q", "--exclude-addresses", exclude_path, "a"], "");
+    assert_eq!(code, Some(2));
+    fs::remove_file(&exclude_file).unwrap();
+}
+
+#[test]
+fn test_verify_wallet() {
+    let (code, stdout) = run_cli(
+        &[
+            "verify",
+            "--private-key",
+            "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn",
+            "--address",
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+            "--pattern",
+            "SAMH",
+            "--mode",
+            "suffix",
+        ],
+        "",
+    );
+
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("OK: the private key derives 1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"));
+    assert!(stdout.contains("OK: the address has the suffix: 'SAMH'"));
+}
+
+#[test]
+fn test_verify_wallet_mismatch() {
+    let (code, _, stderr) = run_cli_with_env(
+        &[
+            "verify",
+            "--private-key",
+            "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn",
+            "--address",
+            "1EmivB8uShHdr4Dft4yGRpvDEUbdUDXkxT",
+        ],
+        "",
+        &[],
+    );
+
+    assert_eq!(code, Some(7));
+    assert!(stderr.contains("the private key derives 1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"));
+}
+
+#[test]
+fn test_no_secrets_keeps_private_keys_out_of_stdout() {
+    let output_file =
+        std::env::temp_dir().join(format!("btc-vanity-no-secrets-{}.txt", std::process::id()));
+    let output_file_name = output_file.to_str().unwrap();
+
+    let (code, stdout) = run_cli(&["-q", "--no-secrets", "-o", output_file_name, "a"], "");
+    let written = fs::read_to_string(&output_file).unwrap();
+    fs::remove_file(&output_file).unwrap();
+
+    assert_eq!(code, Some(0));
+    assert!(!stdout.contains("private_key"));
+    assert!(stdout.contains("address (compressed): "));
+    let wif = written
+        .lines()
+        .find_map(|line| line.strip_prefix("private_key (wif): "))
+        .unwrap();
+    assert!(!stdout.contains(wif));
+}
+
+#[test]
+fn test_no_secrets_needs_output_file() {
+    let (code, stdout, stderr) = run_cli_with_env(&["-q", "--no-secrets", "a"], "", &[]);
+
+    assert_eq!(code, Some(2));
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("--no-secrets needs --output-file or --output-dir"));
+}
+
+#[test]
+fn test_log_level() {
+    let (code, _, stderr) = run_cli_with_env(&["-q", "--log-level", "info", "a"], "", &[]);
+
+    assert_eq!(code, Some(0));
+    assert!(stderr.contains("Searching 1 address for 'a' on bitcoin"));
+    assert!(stderr.contains("] Found 1"));
+
+    // Nothing is logged by default.
+    let (code, _, stderr) = run_cli_with_env(&["-q", "a"], "", &[]);
+    assert_eq!(code, Some(0));
+    assert!(!stderr.contains("Searching"));
+}
+
+#[test]
+fn test_entropy_file() {
+    let entropy_file =
+        std::env::temp_dir().join(format!("btc-vanity-entropy-{}.txt", std::process::id()));
+    let entropy_path = entropy_file.to_str().unwrap();
+    let address = |entropy: &str| {
+        fs::write(&entropy_file, entropy).unwrap();
+        let args = [
+            "-q",
+            "-t",
+            "1",
+            "--seed",
+            "42",
+            "--entropy-file",
+            entropy_path,
+            "ab",
+        ];
+        let (code, stdout) = run_cli(&args, "");
+        assert_eq!(code, Some(0));
+        stdout
+            .lines()
+            .find(|line| line.starts_with("address (compressed): "))
+            .unwrap()
+            .to_string()
+    };
+
+    assert_eq!(address("3 1 4 1 5"), address("3 1 4 1 5"));
+    assert_ne!(address("3 1 4 1 5"), address("2 7 1 8 2"));
+    fs::remove_file(&entropy_file).unwrap();
+
+    let (code, _) = run_cli(&["-q", "--entropy-file", entropy_path, "ab"], "");
+    assert_eq!(code, Some(2));
+}
+
+#[test]
+fn test_random_and_empty_string() {
+    let (code, stdout) = run_cli(&["-q", "--random", "-n", "2"], "");
+
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout.matches("address (compressed): ").count(), 2);
+
+    let (code, stdout) = run_cli(&["-q", ""], "");
+
+    assert_eq!(code, Some(2));
+    assert_eq!(stdout.matches("address (compressed): ").count(), 0);
+}
+
+#[test]
+fn test_leading_one_is_dropped_from_prefix() {
+    let (code, stdout) = run_cli(&["-q", "1a"], "");
+
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("'1a' is searched as 'a'"));
+    assert!(stdout.contains("has the prefix: 'a'"));
+}